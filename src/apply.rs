@@ -0,0 +1,419 @@
+//! Applying a declarative description of how the lights should look, for config-as-code setups.
+//!
+//! The desired states are a [States]: one State per selector, plus optional defaults, the same
+//! shape set_states takes. Instead of sending them as they are, [apply] lists the lights, works
+//! out which of the desired fields each light doesn't already have, and sends only those. Lights
+//! already in their desired state get no request at all, so applying the same file twice is
+//! cheap and doesn't restart transitions.
+//!
+//! Only compiled with `blocking` or `async`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! extern crate lifx_rs as lifx;
+//!
+//! use std::collections::HashMap;
+//!
+//! fn main() {
+//!     let config = lifx::LifxConfig{
+//!        access_token: "xxx".to_string(),
+//!        api_endpoints: vec![format!("https://api.lifx.com")],
+//!        ..Default::default()
+//!     };
+//!
+//!     let mut desired = HashMap::new();
+//!     desired.insert(lifx::Selector::Group(format!("Kitchen")), lifx::State::builder().power_on().brightness(0.8).build());
+//!     desired.insert(lifx::Selector::Group(format!("Bedroom")), lifx::State::builder().power_off().build());
+//!
+//!     let results = lifx::apply::apply(config, &lifx::States::from_map(desired, None));
+//!     println!("{:?}", results);
+//! }
+//! ```
+
+use std::str::FromStr;
+
+use crate::{Color, LiFxResults, LifxConfig, LifxError, Light, Selector, State, States};
+
+/// How far apart two 0.0-1.0 values (brightness, saturation) can be and still count as equal.
+/// The lights store them with 16 bits and the API rounds what it reports, so exact comparisons
+/// would see changes that aren't there.
+pub const LEVEL_TOLERANCE: f64 = 0.005;
+
+/// How far apart two hues, in degrees, can be and still count as equal, see LEVEL_TOLERANCE
+pub const HUE_TOLERANCE: f64 = 0.5;
+
+/// The color temperatures the lights support, from 1500 to 9000 kelvin, for Tolerance::fraction
+const KELVIN_RANGE: f64 = 7500.0;
+
+/// How far apart reported and desired values can be and still count as equal
+struct Tolerance {
+    level: f64,
+    hue: f64,
+    kelvin: f64,
+}
+
+/// What plan allows: LEVEL_TOLERANCE and HUE_TOLERANCE, and exact color temperatures
+const PLAN_TOLERANCE: Tolerance = Tolerance { level: LEVEL_TOLERANCE, hue: HUE_TOLERANCE, kelvin: 0.0 };
+
+impl Tolerance {
+    /// `fraction` of each component's range: 0.0 to 1.0 for levels, 360 degrees of hue and the
+    /// lights' 1500 to 9000 kelvin
+    fn fraction(fraction: f64) -> Self {
+        Tolerance { level: fraction, hue: fraction * 360.0, kelvin: fraction * KELVIN_RANGE }
+    }
+}
+
+/// Works out the states to send so that `lights` end up as `desired` describes, without sending
+/// anything.
+///
+/// Each desired state applies to the lights its selector matches. A light matched by several states gets their fields merged, with later states
+/// overriding earlier ones as set_states applies them, and fields still unset taken from the
+/// defaults. Only power, color, brightness and infrared are compared; duration and fast are
+/// passed along with whatever else is sent. Lists don't report infrared, so a desired infrared is
+/// always sent. Disconnected lights are skipped, since what they report is stale.
+///
+/// Lights that need the same changes share one state, with a comma separated id selector, ex:
+/// "id:d073d5000001,id:d073d5000002". The result is ordered by the first light of each state, in
+/// the order of `lights`.
+///
+/// Returns LifxError::Unsupported, listing them, if any desired state has a selector plan can't
+/// match against the lights itself: none at all, one that doesn't parse, a comma separated list,
+/// a zone suffix like "label:Strip|0-7", or a scene_id. Split lists into one state per selector.
+///
+/// # Arguments
+///
+/// * `desired` - The State for each selector, and optional defaults
+/// * `lights` - The current lights, ex: from Light::list_all
+///
+/// # Examples
+///
+/// ```
+/// extern crate lifx_rs as lifx;
+///
+/// fn main() {
+///     let mut light = lifx::Light::default();
+///     light.id = format!("d073d5000001");
+///     light.connected = true;
+///     light.power = format!("on");
+///     light.brightness = 0.5;
+///
+///     let mut state = lifx::State::builder().power_on().brightness(1.0).build();
+///     state.selector = Some(format!("all"));
+///     let mut desired = lifx::States::new();
+///     desired.states = Some(vec![state]);
+///
+///     let changes = lifx::apply::plan(&desired, &[light]).unwrap();
+///     assert_eq!(changes[0].selector, Some(format!("id:d073d5000001")));
+///     assert_eq!((changes[0].power.clone(), changes[0].brightness), (None, Some(1.0)));
+/// }
+///  ```
+pub fn plan(desired: &States, lights: &[Light]) -> Result<Vec<State>, LifxError> {
+    let mut states: Vec<(Selector, &State)> = Vec::new();
+    let mut unusable: Vec<String> = Vec::new();
+    for state in desired.states.iter().flatten() {
+        match state.selector.as_deref().and_then(matchable) {
+            Some(selector) => states.push((selector, state)),
+            None => unusable.push(state.selector.as_deref().map_or_else(|| "no selector".to_string(), |selector| format!("`{}`", selector))),
+        }
+    }
+    if !unusable.is_empty() {
+        return Err(LifxError::Unsupported(format!("can't work out which lights these selectors match: {}", unusable.join(", "))));
+    }
+    let mut changes: Vec<(State, Vec<&str>)> = Vec::new();
+    for light in lights.iter().filter(|light| light.connected) {
+        let matched: Vec<&State> = states.iter().filter(|(selector, _)| selector.matches(light)).map(|(_, state)| *state).collect();
+        if matched.is_empty() {
+            continue;
+        }
+        // Latest first, so merge keeps the fields of the last state that sets them
+        let target = matched.into_iter().rev().chain(desired.defaults.iter()).fold(State::new(), merge);
+        let change = match changes_for(&target, light) {
+            Some(change) => change,
+            None => continue,
+        };
+        match changes.iter_mut().find(|(existing, _)| *existing == change) {
+            Some((_, ids)) => ids.push(&light.id),
+            None => changes.push((change, vec![&light.id])),
+        }
+    }
+    Ok(changes
+        .into_iter()
+        .map(|(mut state, ids)| {
+            state.selector = Some(ids.iter().map(|id| format!("id:{}", id)).collect::<Vec<_>>().join(","));
+            state
+        })
+        .collect())
+}
+
+/// Parses a selector Selector::matches can check a light against by itself, or None
+fn matchable(selector: &str) -> Option<Selector> {
+    if selector.contains([',', '|']) {
+        return None;
+    }
+    match selector.parse() {
+        Ok(Selector::SceneId(_)) | Err(_) => None,
+        Ok(selector) => Some(selector),
+    }
+}
+
+/// Lists the lights and sends what plan works out for them: nothing if every light is already as
+/// desired, one set_state if a single state is needed, and set_states (in chunks of 50)
+/// otherwise. The config's default_duration applies as it does to set_state. A selector plan
+/// can't use is an error, and nothing is sent.
+///
+/// When nothing needs to change, no request is sent and an empty LiFxResults is returned. With
+/// more than one chunk, the results of every chunk are returned together; the first error stops
+/// the apply, leaving the chunks before it applied.
+///
+/// # Arguments
+///
+/// * `config` - A LifxConfig object with the access token and api endpoints.
+/// * `desired` - The State for each selector, and optional defaults, see plan
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate lifx_rs as lifx;
+///
+/// fn main() {
+///     let config = lifx::LifxConfig{
+///        access_token: "xxx".to_string(),
+///        api_endpoints: vec![format!("https://api.lifx.com")],
+///        ..Default::default()
+///     };
+///
+///     let mut evening = lifx::State::builder().power_on().color(lifx::Color::kelvin(2700)).build();
+///     evening.selector = Some(format!("location:Home"));
+///     let mut desired = lifx::States::new();
+///     desired.states = Some(vec![evening]);
+///
+///     let results = lifx::apply::apply(config, &desired);
+///     println!("{:?}", results);
+/// }
+///  ```
+#[cfg(feature = "blocking")]
+pub fn apply(config: LifxConfig, desired: &States) -> Result<LiFxResults, LifxError> {
+    let changes = plan(desired, &Light::list_all(config.clone())?)?;
+    match changes.len() {
+        0 => Ok(no_changes()),
+        1 => {
+            let mut state = changes.into_iter().next().expect("one change");
+            let selector = state.selector.take().unwrap_or_default();
+            Light::set_state_by_selector(config, selector, state)
+        }
+        _ => Light::set_states(config, States { states: Some(changes), defaults: None }),
+    }
+}
+
+/// Asynchronously lists the lights and sends what plan works out for them, see apply
+#[cfg(feature = "async")]
+pub async fn async_apply(config: LifxConfig, desired: &States) -> Result<LiFxResults, LifxError> {
+    let changes = plan(desired, &Light::async_list_all(config.clone()).await?)?;
+    match changes.len() {
+        0 => Ok(no_changes()),
+        1 => {
+            let mut state = changes.into_iter().next().expect("one change");
+            let selector = state.selector.take().unwrap_or_default();
+            Light::async_set_state_by_selector(config, selector, state).await
+        }
+        _ => Light::async_set_states(config, States { states: Some(changes), defaults: None }).await,
+    }
+}
+
+/// Fills the fields `merged` leaves unset from `state`
+fn merge(merged: State, state: &State) -> State {
+    State {
+        power: merged.power.or_else(|| state.power.clone()),
+        color: merged.color.or_else(|| state.color.clone()),
+        brightness: merged.brightness.or(state.brightness),
+        duration: merged.duration.or(state.duration),
+        infrared: merged.infrared.or(state.infrared),
+        fast: merged.fast.or(state.fast),
+        ..merged
+    }
+}
+
+/// The part of `target` that `light` doesn't already match, or None if there's nothing to change
+fn changes_for(target: &State, light: &Light) -> Option<State> {
+    let level_differs = |want: f64, have: f64| (want - have).abs() > LEVEL_TOLERANCE;
+    let change = State {
+        power: target.power.clone().filter(|power| *power != light.power),
+        color: target.color.clone().filter(|color| color_differs(color, light)),
+        brightness: target.brightness.filter(|brightness| level_differs(*brightness, light.brightness)),
+        infrared: target.infrared,
+        ..State::new()
+    };
+    if change.power.is_none() && change.color.is_none() && change.brightness.is_none() && change.infrared.is_none() {
+        return None;
+    }
+    Some(State { duration: target.duration, fast: target.fast, ..change })
+}
+
+/// Whether `light` reports the power, color and brightness `target` sets, each within
+/// `tolerance` as a fraction of its range, see Light::apply_verified. Infrared isn't reported, so
+/// it's passed over.
+pub(crate) fn reached(target: &State, light: &Light, tolerance: f64) -> bool {
+    let tolerance = Tolerance::fraction(tolerance);
+    target.power.as_ref().is_none_or(|power| *power == light.power)
+        && target.brightness.is_none_or(|brightness| (brightness - light.brightness).abs() <= tolerance.level)
+        && target.color.as_ref().is_none_or(|color| !color_differs_within(color, light, &tolerance))
+}
+
+/// Whether the light's color differs from a color string in any component the string sets. A
+/// string that doesn't parse counts as different, so the API gets to report what's wrong with it.
+fn color_differs(color: &str, light: &Light) -> bool {
+    color_differs_within(color, light, &PLAN_TOLERANCE)
+}
+
+/// color_differs, with values within `tolerance` counting as equal
+fn color_differs_within(color: &str, light: &Light, tolerance: &Tolerance) -> bool {
+    let want = match Color::from_str(color) {
+        Ok(want) => want,
+        Err(_) => return true,
+    };
+    let have = &light.color;
+    let level_differs = |want: Option<f64>, have: Option<f64>| match (want, have) {
+        (Some(want), Some(have)) => (want - have).abs() > tolerance.level,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    let hue_differs = match (want.hue, have.hue) {
+        (Some(want), Some(have)) => {
+            let apart = (want - have).rem_euclid(360.0);
+            apart.min(360.0 - apart) > tolerance.hue
+        }
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    let kelvin_differs = match (want.kelvin, have.kelvin) {
+        (Some(want), Some(have)) => (want - have).abs() as f64 > tolerance.kelvin,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    hue_differs
+        || kelvin_differs
+        || level_differs(want.saturation, have.saturation)
+        || level_differs(want.brightness, Some(light.brightness))
+}
+
+fn no_changes() -> LiFxResults {
+    LiFxResults { results: Some(Vec::new()), ..Default::default() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::fixtures;
+
+    fn desired(states: Vec<(&str, State)>) -> States {
+        let states = states
+            .into_iter()
+            .map(|(selector, mut state)| {
+                state.selector = Some(selector.to_string());
+                state
+            })
+            .collect();
+        States { states: Some(states), defaults: None }
+    }
+
+    #[test]
+    fn test_plan_skips_matching_fields() {
+        let lights = fixtures::lights();
+        let on = plan(&desired(vec![("all", State::builder().power_on().brightness(1.0).color("white").build())]), &lights).unwrap();
+        // Lamp and Ceiling already match; Bedside only needs powering on
+        assert_eq!(on, vec![State { selector: Some("id:d073d5000003".to_string()), power: Some("on".to_string()), ..State::new() }]);
+
+        let warmer = plan(&desired(vec![("group:Living Room", State::builder().color("kelvin:2700").duration(2.0).build())]), &lights).unwrap();
+        assert_eq!(warmer.len(), 1);
+        assert_eq!(warmer[0].selector.as_deref(), Some("id:d073d5000001,id:d073d5000002"));
+        assert_eq!((warmer[0].color.as_deref(), warmer[0].duration), (Some("kelvin:2700"), Some(2.0)));
+    }
+
+    #[test]
+    fn test_plan_merges_states() {
+        let mut lights = fixtures::lights();
+        lights[1].connected = false;
+        let mut states = desired(vec![
+            ("all", State::builder().power_off().build()),
+            ("label:Lamp", State::builder().power_on().brightness(0.5).build()),
+        ]);
+        states.defaults = Some(State::builder().brightness(0.2).duration(1.0).build());
+        let changes = plan(&states, &lights).unwrap();
+        // Lamp: the later state overrides power, Ceiling: disconnected, Bedside: already off
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].selector.as_deref(), changes[0].power.as_deref(), changes[0].brightness), (Some("id:d073d5000001"), None, Some(0.5)));
+        assert_eq!((changes[1].selector.as_deref(), changes[1].power.as_deref(), changes[1].brightness), (Some("id:d073d5000003"), None, Some(0.2)));
+        assert_eq!(changes[1].duration, Some(1.0));
+    }
+
+    #[test]
+    fn test_plan_rejects_unusable_selectors() {
+        let lights = fixtures::lights();
+        let on = || State::builder().power_on().build();
+        let mut states = desired(vec![
+            ("label:Lamp", on()),
+            ("id:d073d5000001,id:d073d5000002", on()),
+            ("label:Strip|0-7", on()),
+            ("scene_id:1c8de82b81f445e7cfaafae49b259c71", on()),
+            ("lamp", on()),
+        ]);
+        states.states.as_mut().unwrap().push(on());
+        match plan(&states, &lights) {
+            Err(LifxError::Unsupported(message)) => {
+                assert!(!message.contains("label:Lamp`"), "{}", message);
+                for unusable in ["`id:d073d5000001,id:d073d5000002`", "`label:Strip|0-7`", "`scene_id:", "`lamp`", "no selector"] {
+                    assert!(message.contains(unusable), "{} not in {}", unusable, message);
+                }
+            }
+            other => panic!("unexpected plan {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_color_tolerance() {
+        let mut light = fixtures::light("d073d5000001", "Lamp");
+        light.color = Color { hue: Some(359.8), saturation: Some(0.998), kelvin: Some(3500), ..Default::default() };
+        assert!(!color_differs("hue:0.1 saturation:1", &light));
+        assert!(color_differs("hue:5 saturation:1", &light));
+        assert!(color_differs("kelvin:2700", &light));
+        assert!(color_differs("not a color", &light));
+    }
+
+    #[test]
+    fn test_reached() {
+        let mut light = fixtures::light("d073d5000001", "Lamp");
+        light.brightness = 0.52;
+        light.color = Color { hue: Some(118.0), saturation: Some(1.0), kelvin: Some(3560), ..Default::default() };
+        let target = State::builder().power_on().brightness(0.5).color("hue:120 saturation:1 kelvin:3500").build();
+        assert!(reached(&target, &light, 0.05));
+        assert!(!reached(&target, &light, 0.001));
+        assert!(!reached(&State::builder().power_off().build(), &light, 0.05));
+        assert!(reached(&State::new(), &light, 0.0));
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_apply() {
+        use crate::test_util::{MockTransport, Request};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let results = apply(mock.config(), &desired(vec![("group:Living Room", State::builder().power_on().build())])).unwrap();
+        assert_eq!(results, no_changes());
+        assert_eq!(mock.requests(), vec![Request::List("all".to_string())]);
+
+        mock.clear_requests();
+        apply(mock.config(), &desired(vec![("all", State::builder().brightness(0.5).build())])).unwrap();
+        let operations = mock.operations();
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].0, "id:d073d5000001,id:d073d5000002,id:d073d5000003");
+        assert!(matches!(&operations[0].1, Operation::SetState(state) if state.brightness == Some(0.5)));
+
+        mock.clear_requests();
+        let results = apply(mock.config(), &desired(vec![("all", State::builder().power_off().build()), ("label:Bedside", State::builder().power_on().build())])).unwrap();
+        assert_eq!(mock.operations().len(), 2);
+        assert_eq!(results.results.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,1709 @@
+//! A reusable client for the LIFX HTTP API.
+//!
+//! The `Light`, `Scene` and `Color` functions that take a LifxConfig build a new LifxClient, and so
+//! a new connection, on every call. Keeping one LifxClient around instead reuses its pooled
+//! connections and TLS sessions across calls.
+//!
+//! Only compiled with the `client` feature.
+
+use reqwest::Method;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::{
+    combine_results, set_states_chunks, BreatheEffect, Clean, Color, EffectsOff, FlameEffect, HttpOptions, LiFxResults,
+    LifxConfig, Lights, MorphEffect, MoveEffect, PaletteError, PulseEffect, RateLimit, RetryBudget, SceneActivation,
+    Scenes, State, StateDelta, States, Toggle,
+};
+use crate::transport::{Operation, Transport};
+
+/// A LifxConfig together with the HTTP clients used to talk to its api_endpoints.
+///
+/// Every request goes through the same path: the endpoints are tried in the order given by the
+/// config's endpoint_strategy, falling back to the next endpoint while one can't be reached.
+/// Clones share the same connection pools, so a LifxClient can be cloned into tasks freely.
+/// If the config has a transport (see LifxConfig::with_transport), requests go through it instead.
+///
+/// The rate limit the API reports is tracked per access token across every client in the process,
+/// see LifxClient::rate_limit and LifxConfig::wait_on_rate_limit.
+///
+/// The blocking client behind the non-async methods is only created the first time one of them is
+/// used, since reqwest doesn't allow creating or dropping it inside an async runtime.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate lifx_rs as lifx;
+/// 
+/// #[tokio::main]
+/// async fn main() {
+/// 
+///     let key = "xxx".to_string();
+///     let mut api_endpoints: Vec<String> = Vec::new();
+///
+///     api_endpoints.push(format!("https://api.lifx.com"));
+///     api_endpoints.push(format!("http://localhost:8089"));
+///
+///     let config = lifx::LifxConfig{
+///        access_token: key.clone(),
+///        api_endpoints: api_endpoints,
+///        ..Default::default()
+///     };
+/// 
+///     let client = lifx::LifxClient::new(config);
+///     let mut off_state = lifx::State::new();
+///     off_state.power = Some(format!("off"));
+/// 
+///     // Both requests reuse the same connection
+///     if let Ok(lights) = client.async_list_all().await {
+///         for light in lights {
+///             let results = client.async_set_state_by_selector(format!("id:{}", light.id), off_state.clone()).await;
+///             println!("{:?}", results);
+///         }
+///     }
+/// }
+///  ```
+#[derive(Debug, Clone)]
+pub struct LifxClient {
+    config: LifxConfig,
+    #[cfg(feature = "async")]
+    http: Result<reqwest::Client, String>,
+    #[cfg(feature = "blocking")]
+    blocking: Arc<OnceLock<Result<reqwest::blocking::Client, String>>>,
+    retry_tokens: Arc<Mutex<RetryTokens>>,
+    on_request_complete: Option<RequestHook>,
+    warnings: Arc<Mutex<Vec<String>>>,
+    validations: Arc<Mutex<HashMap<String, Color>>>,
+}
+
+/// How an HTTP request went, passed to the callback set with LifxClient::on_request_complete
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestTiming {
+    /// The URL requested, ex: "https://api.lifx.com/v1/lights/all"
+    pub url: String,
+    pub method: Method,
+    /// The HTTP status of the response, None if none came back, ex: the connection was refused
+    pub status: Option<u16>,
+    /// From sending the request until its response's headers arrived, or it failed
+    pub elapsed: Duration,
+    /// The position of the endpoint in the config's api_endpoints
+    pub endpoint_index: usize,
+    /// True if this was a retry on the same endpoint, see RetryPolicy
+    pub retried: bool,
+}
+
+/// The callback set with LifxClient::on_request_complete
+#[derive(Clone)]
+struct RequestHook(Arc<dyn Fn(&RequestTiming) + Send + Sync>);
+
+impl fmt::Debug for RequestHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("RequestHook(..)")
+    }
+}
+
+/// What's left of a client's RetryBudget
+#[derive(Debug)]
+struct RetryTokens {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RetryTokens {
+    fn full(budget: &RetryBudget) -> Self {
+        RetryTokens { tokens: f64::from(budget.capacity), updated: Instant::now() }
+    }
+
+    /// Adds the tokens refilled since the last update, as of `now`
+    fn refill(&mut self, budget: &RetryBudget, now: Instant) {
+        let refill_per_second = if budget.refill_per_second.is_finite() { budget.refill_per_second.max(0.0) } else { 0.0 };
+        let refilled = now.saturating_duration_since(self.updated).as_secs_f64() * refill_per_second;
+        self.tokens = (self.tokens + refilled).min(f64::from(budget.capacity));
+        self.updated = now;
+    }
+
+    /// Takes a token for a retry if there's one left
+    fn take(&mut self, budget: &RetryBudget, now: Instant) -> bool {
+        self.refill(budget, now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// The message of the LifxError::Config returned for a request when api_endpoints is empty
+pub(crate) const NO_ENDPOINTS: &str = "no API endpoints configured";
+
+/// How long an endpoint that couldn't be reached is passed over by EndpointStrategy::Healthiest
+/// before requests try it again
+pub const ENDPOINT_RECHECK_AFTER: Duration = Duration::from_secs(30);
+
+/// What requests have seen of an api_endpoint's health, shared by every client in the process.
+/// See LifxClient::endpoint_health and EndpointStrategy::Healthiest.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EndpointHealth {
+    /// Attempts in a row that couldn't reach the endpoint, reset by any answer
+    pub consecutive_failures: u32,
+    /// When an attempt last couldn't reach the endpoint
+    pub last_failure: Option<Instant>,
+    /// How long the endpoint takes to answer, averaged over recent requests
+    pub latency: Option<Duration>,
+}
+
+impl EndpointHealth {
+    /// Returns true if the last attempt failed less than ENDPOINT_RECHECK_AFTER before `now`
+    pub fn is_down(&self, now: Instant) -> bool {
+        self.consecutive_failures > 0
+            && self.last_failure.is_some_and(|failed| now.saturating_duration_since(failed) < ENDPOINT_RECHECK_AFTER)
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        self.consecutive_failures = 0;
+        // Weight the newest sample by a third, so one slow answer doesn't reorder the endpoints
+        self.latency = Some(self.latency.map_or(latency, |average| (average * 2 + latency) / 3));
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.last_failure = Some(now);
+    }
+}
+
+/// The error returned by the LIFX HTTP API methods
+#[derive(Debug, Error)]
+pub enum LifxError {
+    /// The request couldn't be sent or its response couldn't be read, ex: a dropped connection.
+    /// DNS failures, refused connections and timeouts have variants of their own.
+    #[error("request failed: {0}")]
+    Transport(#[source] reqwest::Error),
+    /// The endpoint's host name couldn't be resolved, ex: no internet connection for a cloud
+    /// endpoint. Found from a "dns error" in the reqwest error's cause chain.
+    #[error("DNS lookup failed: {0}")]
+    Dns(#[source] reqwest::Error),
+    /// Nothing was listening at the endpoint, ex: a local server that isn't running. Found from
+    /// an io::Error of kind ConnectionRefused in the reqwest error's cause chain.
+    #[error("connection refused: {0}")]
+    ConnectionRefused(#[source] reqwest::Error),
+    /// Connecting or the request took longer than the config's timeout, or the OS gave up
+    /// connecting. Found from reqwest's is_timeout, or an io::Error of kind TimedOut in its cause
+    /// chain.
+    #[error("request timed out: {0}")]
+    Timeout(#[source] reqwest::Error),
+    /// The API answered with a non-success HTTP status, with the API's error message if it sent one
+    #[error("HTTP status {status}: {}", message.as_deref().unwrap_or("no error message"))]
+    Status { status: u16, message: Option<String> },
+    /// The API answered with an error payload instead of the expected response, ex: {"error": "Invalid token"}
+    #[error("LIFX API error: {0}")]
+    Api(String),
+    /// The response body wasn't the JSON this crate expected
+    #[error("could not decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// The request can't be carried out, ex: listing scenes over the LAN, a write when every
+    /// api_endpoint is read-only, or a morph effect on a light that isn't a matrix
+    #[error("not supported: {0}")]
+    Unsupported(String),
+    /// The config's HttpOptions can't be used, ex: a proxy that isn't a URL
+    #[error("invalid config: {0}")]
+    Config(String),
+    /// What was asked for didn't turn up in time, ex: Light::wait_until_present timing out
+    #[error("not found: {0}")]
+    NotFound(String),
+    /// A state was sent, but the light didn't report it in time, see Light::apply_verified
+    #[error("verification failed: {0}")]
+    VerificationFailed(String),
+    /// The response had fields this crate doesn't model, with deny_unknown_fields on, ex:
+    /// ["[0].firmware"] for a light list. See LifxConfig::deny_unknown_fields.
+    #[error("response has unexpected fields: {}", .0.join(", "))]
+    UnexpectedFields(Vec<String>),
+    /// A MorphEffect's palette failed MorphEffect::validate, so nothing was sent
+    #[error(transparent)]
+    Palette(#[from] PaletteError),
+}
+
+impl From<reqwest::Error> for LifxError {
+    /// Sorts out DNS failures, refused connections and timeouts, see their variants. Anything
+    /// else is a LifxError::Transport.
+    fn from(err: reqwest::Error) -> Self {
+        let io_kind = error_chain(&err).find_map(|source| source.downcast_ref::<std::io::Error>()).map(std::io::Error::kind);
+        if err.is_timeout() || io_kind == Some(std::io::ErrorKind::TimedOut) {
+            LifxError::Timeout(err)
+        } else if io_kind == Some(std::io::ErrorKind::ConnectionRefused) {
+            LifxError::ConnectionRefused(err)
+        } else if error_chain(&err).any(|source| source.to_string().starts_with("dns error")) {
+            LifxError::Dns(err)
+        } else {
+            LifxError::Transport(err)
+        }
+    }
+}
+
+/// `err` and each of its sources in turn
+fn error_chain<'a>(err: &'a (dyn std::error::Error + 'static)) -> impl Iterator<Item = &'a (dyn std::error::Error + 'static)> {
+    std::iter::successors(Some(err), |err| err.source())
+}
+
+/// The error payload the API sends in place of a response, ex: {"error": "Invalid token"}
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// The body of a request sent by LifxClient
+pub(crate) enum Body {
+    Empty,
+    Json(serde_json::Value),
+}
+
+impl LifxClient {
+    /// Returns a new LifxClient for `config`
+    pub fn new(config: LifxConfig) -> Self {
+        LifxClient {
+            #[cfg(feature = "async")]
+            http: async_http_client(&config.http).map_err(|err| err.to_string()),
+            #[cfg(feature = "blocking")]
+            blocking: Arc::new(OnceLock::new()),
+            retry_tokens: Arc::new(Mutex::new(RetryTokens::full(&config.retry_budget))),
+            on_request_complete: None,
+            warnings: Arc::new(Mutex::new(Vec::new())),
+            validations: Arc::new(Mutex::new(HashMap::new())),
+            config,
+        }
+    }
+
+    /// Returns the config this client was created with
+    pub fn config(&self) -> &LifxConfig {
+        &self.config
+    }
+
+    /// Returns a clone of this client whose requests time out after `timeout` seconds, see
+    /// LifxConfig::timeout. The clone shares this client's connection pools, so it's cheap to
+    /// make for a single call.
+    pub fn with_timeout(&self, timeout: f64) -> LifxClient {
+        LifxClient { config: self.config.with_timeout(timeout), ..self.clone() }
+    }
+
+    /// Returns a clone of this client that calls `callback` after every HTTP request it sends,
+    /// retries and fallbacks included, ex: to feed request latencies to a metrics system. Clients
+    /// without a callback don't time their requests at all. Requests through a transport aren't
+    /// reported.
+    ///
+    /// The callback runs on the task or thread that sent the request, so it should be quick.
+    pub fn on_request_complete(&self, callback: Arc<dyn Fn(&RequestTiming) + Send + Sync>) -> LifxClient {
+        LifxClient { on_request_complete: Some(RequestHook(callback)), ..self.clone() }
+    }
+
+    /// Returns how many retries this client and its clones can still make before their
+    /// RetryBudget refills, see LifxConfig::retry_budget
+    pub fn retry_budget(&self) -> u32 {
+        let mut tokens = self.retry_tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.refill(&self.config.retry_budget, Instant::now());
+        tokens.tokens as u32
+    }
+
+    /// Takes a retry from the client's RetryBudget, returning false if none are left
+    fn take_retry(&self) -> bool {
+        self.retry_tokens.lock().unwrap_or_else(|e| e.into_inner()).take(&self.config.retry_budget, Instant::now())
+    }
+
+    /// Returns the Warning and X-Deprecation headers of the last response this client or one of
+    /// its clones got, ex: ["299 - \"/v1/lights/:selector/effects/move is deprecated\""], as
+    /// the API sent them. Empty if that response had neither, or no response has come back yet.
+    /// Requests through a transport aren't seen.
+    pub fn last_warnings(&self) -> Vec<String> {
+        self.warnings.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Empties the cache used by validate_cached and async_validate_cached, for this client and
+    /// its clones
+    pub fn clear_validation_cache(&self) {
+        self.validations.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    fn cached_validation(&self, color: &str) -> Option<Color> {
+        self.validations.lock().unwrap_or_else(|e| e.into_inner()).get(color).cloned()
+    }
+
+    fn cache_validation(&self, color: String, validated: &Color) {
+        if validated.error.is_none() && validated.errors.is_none() {
+            self.validations.lock().unwrap_or_else(|e| e.into_inner()).insert(color, validated.clone());
+        }
+    }
+
+    /// Keeps the warnings of a response for last_warnings, replacing the previous response's
+    fn record_warnings(&self, headers: &HeaderMap) {
+        *self.warnings.lock().unwrap_or_else(|e| e.into_inner()) = parse_warnings(headers);
+    }
+
+    /// Returns what requests from any client have seen of `endpoint`'s health. None until a
+    /// request has tried it.
+    pub fn endpoint_health(&self, endpoint: &str) -> Option<EndpointHealth> {
+        endpoint_health().lock().unwrap_or_else(|e| e.into_inner()).get(endpoint).copied()
+    }
+
+    /// Returns the rate limit the HTTP API last reported for this client's access token, from a
+    /// request made by any client. None until a response with X-RateLimit headers comes back.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        rate_limits().lock().unwrap_or_else(|e| e.into_inner()).get(&self.config.access_token).copied()
+    }
+
+    /// Asynchronously activates the scene with the given uuid, see Scene::async_activate
+    #[cfg(feature = "async")]
+    pub async fn async_activate_scene(&self, uuid: String, activation: SceneActivation) -> Result<LiFxResults, LifxError> {
+        self.async_operate(format!("scene_id:{}", uuid), Operation::ActivateScene(activation)).await
+    }
+
+    /// Asynchronously starts the breathe animation on the selected lights, see Light::async_breathe_effect_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_breathe_effect_by_selector(&self, selector: String, breathe: BreatheEffect) -> Result<LiFxResults, LifxError> {
+        self.async_operate(selector, Operation::Breathe(breathe)).await
+    }
+
+    /// Asynchronously starts a HEV clean cycle on the selected lights, see Light::async_clean_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_clean_by_selector(&self, selector: String, clean: Clean) -> Result<LiFxResults, LifxError> {
+        self.async_operate(selector, Operation::Clean(clean)).await
+    }
+
+    /// Asynchronously stops any running effect on the selected lights, see Light::async_effects_off_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_effects_off_by_selector(&self, selector: String, effects_off: EffectsOff) -> Result<LiFxResults, LifxError> {
+        self.async_operate(selector, Operation::EffectsOff(effects_off)).await
+    }
+
+    /// Asynchronously starts the flame animation on the selected lights, see Light::async_flame_effect_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_flame_effect_by_selector(&self, selector: String, flame_effect: FlameEffect) -> Result<LiFxResults, LifxError> {
+        self.async_operate(selector, Operation::Flame(flame_effect)).await
+    }
+
+    /// Asynchronously lists every light belonging to the account, see Light::async_list_all
+    #[cfg(feature = "async")]
+    pub async fn async_list_all(&self) -> Result<Lights, LifxError> {
+        self.async_list_by_selector("all".to_string()).await
+    }
+
+    /// Asynchronously lists the lights matching a selector, see Light::async_list_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_list_by_selector(&self, selector: String) -> Result<Lights, LifxError> {
+        match self.transport() {
+            Some(transport) => on_transport(transport, move |transport| transport.list(&selector)).await,
+            None => self.async_send(Method::GET, &format!("/v1/lights/{}", selector), &Body::Empty).await,
+        }
+    }
+
+    /// Asynchronously lists the scenes belonging to the account, see Scene::async_list
+    #[cfg(feature = "async")]
+    pub async fn async_list_scenes(&self) -> Result<Scenes, LifxError> {
+        match self.transport() {
+            Some(transport) => on_transport(transport, move |transport| transport.list_scenes()).await,
+            None => self.async_send(Method::GET, "/v1/scenes", &Body::Empty).await,
+        }
+    }
+
+    /// Asynchronously starts the morph animation on the selected lights, see Light::async_morph_effect_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_morph_effect_by_selector(&self, selector: String, morph_effect: MorphEffect) -> Result<LiFxResults, LifxError> {
+        morph_effect.validate()?;
+        self.async_operate(selector, Operation::Morph(morph_effect)).await
+    }
+
+    /// Asynchronously starts the move animation on the selected lights, see Light::async_move_effect_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_move_effect_by_selector(&self, selector: String, move_effect: MoveEffect) -> Result<LiFxResults, LifxError> {
+        self.async_operate(selector, Operation::Move(move_effect)).await
+    }
+
+    /// Asynchronously starts the pulse animation on the selected lights, see Light::async_pulse_effect_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_pulse_effect_by_selector(&self, selector: String, pulse_effect: PulseEffect) -> Result<LiFxResults, LifxError> {
+        self.async_operate(selector, Operation::Pulse(pulse_effect)).await
+    }
+
+    /// Asynchronously sets the state of the selected lights, see Light::async_set_state_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_set_state_by_selector(&self, selector: String, state: State) -> Result<LiFxResults, LifxError> {
+        let state = state.with_default_duration(self.config.default_duration);
+        self.async_operate(selector, Operation::SetState(state)).await
+    }
+
+    /// Asynchronously sets several states, one request per 50, see Light::async_set_states
+    #[cfg(feature = "async")]
+    pub async fn async_set_states(&self, states: States) -> Result<LiFxResults, LifxError> {
+        let states = states.with_default_duration(self.config.default_duration);
+        let mut all = Vec::new();
+        for states in set_states_chunks(states) {
+            all.push(match self.transport() {
+                Some(transport) => on_transport(transport, move |transport| transport.set_states(states)).await?,
+                None => self.async_send_results(Method::PUT, "/v1/lights/state", &Body::Json(serde_json::to_value(&states).expect("States always serialize"))).await?,
+            });
+        }
+        Ok(single_or_combined(all))
+    }
+
+    /// Asynchronously changes the state of the selected lights relative to their current state, see Light::async_state_delta_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_state_delta_by_selector(&self, selector: String, delta: StateDelta) -> Result<LiFxResults, LifxError> {
+        let delta = delta.with_default_duration(self.config.default_duration);
+        self.async_operate(selector, Operation::StateDelta(delta)).await
+    }
+
+    /// Asynchronously toggles the power of the selected lights, see Light::async_toggle_by_selector
+    #[cfg(feature = "async")]
+    pub async fn async_toggle_by_selector(&self, selector: String, toggle: Toggle) -> Result<LiFxResults, LifxError> {
+        let toggle = toggle.with_default_duration(self.config.default_duration);
+        self.async_operate(selector, Operation::Toggle(toggle)).await
+    }
+
+    /// Asynchronously validates a color string, returning the parsed Color, see Color::async_validate
+    #[cfg(feature = "async")]
+    pub async fn async_validate_color(&self, color: String) -> Result<Color, LifxError> {
+        match self.transport() {
+            Some(transport) => on_transport(transport, move |transport| transport.validate_color(&color)).await,
+            None => self.async_send(Method::GET, &format!("/v1/color?string={}", color), &Body::Empty).await,
+        }
+    }
+
+    /// Asynchronously validates a color, serving repeat lookups of the same string from the
+    /// client's validation cache, see validate_cached
+    #[cfg(feature = "async")]
+    pub async fn async_validate_cached(&self, color: String) -> Result<Color, LifxError> {
+        if let Some(cached) = self.cached_validation(&color) {
+            return Ok(cached);
+        }
+        let validated = self.async_validate_color(color.clone()).await?;
+        self.cache_validation(color, &validated);
+        Ok(validated)
+    }
+
+    // END OF ASYNC FUNCTIONS
+
+    /// Activates the scene with the given uuid, see Scene::activate
+    #[cfg(feature = "blocking")]
+    pub fn activate_scene(&self, uuid: String, activation: SceneActivation) -> Result<LiFxResults, LifxError> {
+        self.operate(&format!("scene_id:{}", uuid), Operation::ActivateScene(activation))
+    }
+
+    /// Starts the breathe animation on the selected lights, see Light::breathe_by_selector_effect
+    #[cfg(feature = "blocking")]
+    pub fn breathe_effect_by_selector(&self, selector: String, breathe: BreatheEffect) -> Result<LiFxResults, LifxError> {
+        self.operate(&selector, Operation::Breathe(breathe))
+    }
+
+    /// Starts a HEV clean cycle on the selected lights, see Light::clean_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn clean_by_selector(&self, selector: String, clean: Clean) -> Result<LiFxResults, LifxError> {
+        self.operate(&selector, Operation::Clean(clean))
+    }
+
+    /// Stops any running effect on the selected lights, see Light::effects_off_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn effects_off_by_selector(&self, selector: String, effects_off: EffectsOff) -> Result<LiFxResults, LifxError> {
+        self.operate(&selector, Operation::EffectsOff(effects_off))
+    }
+
+    /// Starts the flame animation on the selected lights, see Light::flame_effect_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn flame_effect_by_selector(&self, selector: String, flame_effect: FlameEffect) -> Result<LiFxResults, LifxError> {
+        self.operate(&selector, Operation::Flame(flame_effect))
+    }
+
+    /// Lists every light belonging to the account, see Light::list_all
+    #[cfg(feature = "blocking")]
+    pub fn list_all(&self) -> Result<Lights, LifxError> {
+        self.list_by_selector("all".to_string())
+    }
+
+    /// Lists the lights matching a selector, see Light::list_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn list_by_selector(&self, selector: String) -> Result<Lights, LifxError> {
+        match self.transport() {
+            Some(transport) => transport.list(&selector),
+            None => self.send(Method::GET, &format!("/v1/lights/{}", selector), &Body::Empty),
+        }
+    }
+
+    /// Lists the scenes belonging to the account, see Scene::list
+    #[cfg(feature = "blocking")]
+    pub fn list_scenes(&self) -> Result<Scenes, LifxError> {
+        match self.transport() {
+            Some(transport) => transport.list_scenes(),
+            None => self.send(Method::GET, "/v1/scenes", &Body::Empty),
+        }
+    }
+
+    /// Starts the morph animation on the selected lights, see Light::morph_effect_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn morph_effect_by_selector(&self, selector: String, morph_effect: MorphEffect) -> Result<LiFxResults, LifxError> {
+        morph_effect.validate()?;
+        self.operate(&selector, Operation::Morph(morph_effect))
+    }
+
+    /// Starts the move animation on the selected lights, see Light::move_effect_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn move_effect_by_selector(&self, selector: String, move_effect: MoveEffect) -> Result<LiFxResults, LifxError> {
+        self.operate(&selector, Operation::Move(move_effect))
+    }
+
+    /// Starts the pulse animation on the selected lights, see Light::pulse_effect_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn pulse_effect_by_selector(&self, selector: String, pulse_effect: PulseEffect) -> Result<LiFxResults, LifxError> {
+        self.operate(&selector, Operation::Pulse(pulse_effect))
+    }
+
+    /// Sets the state of the selected lights, see Light::set_state_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn set_state_by_selector(&self, selector: String, state: State) -> Result<LiFxResults, LifxError> {
+        let state = state.with_default_duration(self.config.default_duration);
+        self.operate(&selector, Operation::SetState(state))
+    }
+
+    /// Sets several states, one request per 50, see Light::set_states
+    #[cfg(feature = "blocking")]
+    pub fn set_states(&self, states: States) -> Result<LiFxResults, LifxError> {
+        let states = states.with_default_duration(self.config.default_duration);
+        let mut all = Vec::new();
+        for states in set_states_chunks(states) {
+            all.push(match self.transport() {
+                Some(transport) => transport.set_states(states)?,
+                None => self.send_results(Method::PUT, "/v1/lights/state", &Body::Json(serde_json::to_value(&states).expect("States always serialize")))?,
+            });
+        }
+        Ok(single_or_combined(all))
+    }
+
+    /// Changes the state of the selected lights relative to their current state, see Light::state_delta_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn state_delta_by_selector(&self, selector: String, delta: StateDelta) -> Result<LiFxResults, LifxError> {
+        let delta = delta.with_default_duration(self.config.default_duration);
+        self.operate(&selector, Operation::StateDelta(delta))
+    }
+
+    /// Toggles the power of the selected lights, see Light::toggle_by_selector
+    #[cfg(feature = "blocking")]
+    pub fn toggle_by_selector(&self, selector: String, toggle: Toggle) -> Result<LiFxResults, LifxError> {
+        let toggle = toggle.with_default_duration(self.config.default_duration);
+        self.operate(&selector, Operation::Toggle(toggle))
+    }
+
+    /// Validates a color string, returning the parsed Color, see Color::validate
+    #[cfg(feature = "blocking")]
+    pub fn validate_color(&self, color: String) -> Result<Color, LifxError> {
+        match self.transport() {
+            Some(transport) => transport.validate_color(&color),
+            None => self.send(Method::GET, &format!("/v1/color?string={}", color), &Body::Empty),
+        }
+    }
+
+    /// Validates a color, serving repeat lookups of the same string from a cache this client
+    /// shares with its clones, so it lasts as long as the client does.
+    ///
+    /// The cache assumes a color string always resolves to the same Color, which holds for the
+    /// named colors and hue/saturation/kelvin strings the API accepts. Only clean results (no
+    /// error or errors) are cached, so a failed lookup is retried on the next call.
+    #[cfg(feature = "blocking")]
+    pub fn validate_cached(&self, color: String) -> Result<Color, LifxError> {
+        if let Some(cached) = self.cached_validation(&color) {
+            return Ok(cached);
+        }
+        let validated = self.validate_color(color.clone())?;
+        self.cache_validation(color, &validated);
+        Ok(validated)
+    }
+
+    /// The transport this client's config routes operations through, if it isn't the HTTP API
+    fn transport(&self) -> Option<Arc<dyn Transport>> {
+        self.config.transport.as_ref().map(|transport| transport.0.clone())
+    }
+
+    /// Asynchronously carries out `operation` on `selector`, then starts its auto_off timer if it
+    /// is an effect with one, see BreatheEffect::with_auto_off
+    #[cfg(feature = "async")]
+    async fn async_operate(&self, selector: String, operation: Operation) -> Result<LiFxResults, LifxError> {
+        let auto_off = operation.auto_off();
+        let results = self.async_send_operation(selector.clone(), operation).await?;
+        if let Some(after) = auto_off {
+            let client = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(after).await;
+                let _ = client.async_send_operation(selector, Operation::EffectsOff(EffectsOff::new())).await;
+            });
+        }
+        Ok(results)
+    }
+
+    /// Asynchronously sends `operation` to `selector`, through the config's transport if it has one
+    #[cfg(feature = "async")]
+    async fn async_send_operation(&self, selector: String, operation: Operation) -> Result<LiFxResults, LifxError> {
+        match self.transport() {
+            Some(transport) => on_transport(transport, move |transport| transport.send(&selector, operation)).await,
+            None => {
+                let (method, path, body) = operation.to_request(&selector);
+                self.async_send_results(method, &path, &body).await
+            }
+        }
+    }
+
+    /// Carries out `operation` on `selector`, then starts its auto_off timer on a thread if it is an
+    /// effect with one, see BreatheEffect::with_auto_off
+    #[cfg(feature = "blocking")]
+    fn operate(&self, selector: &str, operation: Operation) -> Result<LiFxResults, LifxError> {
+        let auto_off = operation.auto_off();
+        let results = self.send_operation(selector, operation)?;
+        if let Some(after) = auto_off {
+            let (client, selector) = (self.clone(), selector.to_string());
+            std::thread::spawn(move || {
+                std::thread::sleep(after);
+                let _ = client.send_operation(&selector, Operation::EffectsOff(EffectsOff::new()));
+            });
+        }
+        Ok(results)
+    }
+
+    /// Sends `operation` to `selector`, through the config's transport if it has one
+    #[cfg(feature = "blocking")]
+    fn send_operation(&self, selector: &str, operation: Operation) -> Result<LiFxResults, LifxError> {
+        match self.transport() {
+            Some(transport) => transport.send(selector, operation),
+            None => {
+                let (method, path, body) = operation.to_request(selector);
+                self.send_results(method, &path, &body)
+            }
+        }
+    }
+
+    /// Asynchronously sends a request to the first endpoint, falling back to each of the next ones in turn while they can't be reached
+    #[cfg(feature = "async")]
+    pub(crate) async fn async_send<T: Response>(&self, method: Method, path: &str, body: &Body) -> Result<T, LifxError> {
+        self.async_send_limited(method, path, body, decode).await.map(|(value, _)| value)
+    }
+
+    /// Asynchronously sends a request answered with LiFxResults, attaching the rate limit reported with them
+    #[cfg(feature = "async")]
+    async fn async_send_results(&self, method: Method, path: &str, body: &Body) -> Result<LiFxResults, LifxError> {
+        let (results, rate_limit) = self.async_send_limited(method, path, body, decode_results).await?;
+        Ok(LiFxResults { rate_limit, ..results })
+    }
+
+    #[cfg(feature = "async")]
+    async fn async_send_limited<T: Response>(&self, method: Method, path: &str, body: &Body, decode: Decoder<T>) -> Result<(T, Option<RateLimit>), LifxError> {
+        if let Some(wait) = self.rate_limit_wait() {
+            tokio::time::sleep(wait).await;
+        }
+        let client = self.async_client()?;
+        let mut response = Err(LifxError::Config(NO_ENDPOINTS.to_string()));
+        for endpoint in self.endpoints_for(&method, path)? {
+            response = self.async_send_retrying(client, method.clone(), endpoint, path, body).await.map_err(LifxError::from);
+            if response.is_ok() {
+                break;
+            }
+        }
+        let response = response?;
+        let (status, rate_limit) = (response.status(), self.record_rate_limit(response.headers()));
+        self.record_warnings(response.headers());
+        Ok((self.decode_checked(status, &response.bytes().await?, decode)?, rate_limit))
+    }
+
+    /// Sends a request to the first endpoint, falling back to each of the next ones in turn while they can't be reached
+    #[cfg(feature = "blocking")]
+    pub(crate) fn send<T: Response>(&self, method: Method, path: &str, body: &Body) -> Result<T, LifxError> {
+        self.send_limited(method, path, body, decode).map(|(value, _)| value)
+    }
+
+    /// Sends a request answered with LiFxResults, attaching the rate limit reported with them
+    #[cfg(feature = "blocking")]
+    pub(crate) fn send_results(&self, method: Method, path: &str, body: &Body) -> Result<LiFxResults, LifxError> {
+        let (results, rate_limit) = self.send_limited(method, path, body, decode_results)?;
+        Ok(LiFxResults { rate_limit, ..results })
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_limited<T: Response>(&self, method: Method, path: &str, body: &Body, decode: Decoder<T>) -> Result<(T, Option<RateLimit>), LifxError> {
+        if let Some(wait) = self.rate_limit_wait() {
+            std::thread::sleep(wait);
+        }
+        let client = self.blocking_client()?;
+        let mut response = Err(LifxError::Config(NO_ENDPOINTS.to_string()));
+        for endpoint in self.endpoints_for(&method, path)? {
+            response = self.send_retrying(client, method.clone(), endpoint, path, body).map_err(LifxError::from);
+            if response.is_ok() {
+                break;
+            }
+        }
+        let response = response?;
+        let (status, rate_limit) = (response.status(), self.record_rate_limit(response.headers()));
+        self.record_warnings(response.headers());
+        Ok((self.decode_checked(status, &response.bytes()?, decode)?, rate_limit))
+    }
+
+    /// Asynchronously sends a request to one endpoint, retrying per the config's retry_policy
+    /// while the client's retry budget lasts
+    #[cfg(feature = "async")]
+    async fn async_send_retrying(&self, client: &reqwest::Client, method: Method, endpoint: &str, path: &str, body: &Body) -> reqwest::Result<reqwest::Response> {
+        let policy = &self.config.retry_policy;
+        let mut attempt = 1;
+        loop {
+            let started = Instant::now();
+            let result = self.async_request(client, method.clone(), endpoint, path, body).send().await;
+            record_health(endpoint, started, result.is_ok());
+            self.report_timing(&method, endpoint, path, started, result.as_ref().ok().map(|response| response.status().as_u16()), attempt > 1);
+            match result {
+                Err(_) if attempt < policy.max_attempts && self.take_retry() => {
+                    tokio::time::sleep(policy.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a request to one endpoint, retrying per the config's retry_policy while the
+    /// client's retry budget lasts
+    #[cfg(feature = "blocking")]
+    fn send_retrying(&self, client: &reqwest::blocking::Client, method: Method, endpoint: &str, path: &str, body: &Body) -> reqwest::Result<reqwest::blocking::Response> {
+        let policy = &self.config.retry_policy;
+        let mut attempt = 1;
+        loop {
+            let started = Instant::now();
+            let result = self.request(client, method.clone(), endpoint, path, body).send();
+            record_health(endpoint, started, result.is_ok());
+            self.report_timing(&method, endpoint, path, started, result.as_ref().ok().map(|response| response.status().as_u16()), attempt > 1);
+            match result {
+                Err(_) if attempt < policy.max_attempts && self.take_retry() => {
+                    std::thread::sleep(policy.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Calls the on_request_complete callback, if there is one, for a request sent at `started`
+    fn report_timing(&self, method: &Method, endpoint: &str, path: &str, started: Instant, status: Option<u16>, retried: bool) {
+        if let Some(RequestHook(callback)) = &self.on_request_complete {
+            callback(&RequestTiming {
+                url: format!("{}{}", endpoint, path),
+                method: method.clone(),
+                status,
+                elapsed: started.elapsed(),
+                endpoint_index: self.config.api_endpoints.iter().position(|api_endpoint| api_endpoint == endpoint).unwrap_or_default(),
+                retried,
+            });
+        }
+    }
+
+    /// Decodes a response body, then checks it for unexpected fields if the config asks for that
+    fn decode_checked<T: Response>(&self, status: reqwest::StatusCode, body: &[u8], decode: Decoder<T>) -> Result<T, LifxError> {
+        let value = decode(status, body)?;
+        if self.config.deny_unknown_fields {
+            check_fields(body, &value)?;
+        }
+        Ok(value)
+    }
+
+    /// The endpoints to try a request on, in order. LifxError::Config if the config has none, and
+    /// LifxError::Unsupported for a write when every one of them is read-only.
+    fn endpoints_for(&self, method: &Method, path: &str) -> Result<Vec<&str>, LifxError> {
+        if self.config.api_endpoints.is_empty() {
+            return Err(LifxError::Config(NO_ENDPOINTS.to_string()));
+        }
+        let endpoints = self.config.ordered_endpoints(method != Method::GET);
+        if endpoints.is_empty() {
+            return Err(LifxError::Unsupported(format!("{} {}, every api_endpoint is read-only", method, path)));
+        }
+        Ok(endpoints)
+    }
+
+    /// How long to hold off the next request, if the config waits on the rate limit and it's used up
+    fn rate_limit_wait(&self) -> Option<Duration> {
+        if !self.config.wait_on_rate_limit {
+            return None;
+        }
+        self.rate_limit().and_then(|rate_limit| rate_limit.wait_time(SystemTime::now()))
+    }
+
+    /// Remembers the rate limit in a response's headers for this client's access token, and returns it
+    fn record_rate_limit(&self, headers: &HeaderMap) -> Option<RateLimit> {
+        let rate_limit = parse_rate_limit(headers)?;
+        rate_limits().lock().unwrap_or_else(|e| e.into_inner()).insert(self.config.access_token.clone(), rate_limit);
+        Some(rate_limit)
+    }
+
+    /// The config's timeout, if it's set to a valid number of seconds
+    fn timeout(&self) -> Option<Duration> {
+        self.config.timeout.and_then(|timeout| Duration::try_from_secs_f64(timeout).ok())
+    }
+
+    /// The async HTTP client, built from the config's HttpOptions
+    #[cfg(feature = "async")]
+    fn async_client(&self) -> Result<&reqwest::Client, LifxError> {
+        self.http.as_ref().map_err(|err| LifxError::Config(err.clone()))
+    }
+
+    /// The blocking HTTP client, built from the config's HttpOptions the first time it's needed
+    #[cfg(feature = "blocking")]
+    fn blocking_client(&self) -> Result<&reqwest::blocking::Client, LifxError> {
+        self.blocking
+            .get_or_init(|| blocking_http_client(&self.config.http).map_err(|err| err.to_string()))
+            .as_ref()
+            .map_err(|err| LifxError::Config(err.clone()))
+    }
+
+    #[cfg(feature = "async")]
+    fn async_request(&self, client: &reqwest::Client, method: Method, endpoint: &str, path: &str, body: &Body) -> reqwest::RequestBuilder {
+        let mut request = client.request(method, format!("{}{}", endpoint, path))
+            .header("Authorization", format!("Bearer {}", self.config.access_token));
+        if let Some(timeout) = self.timeout() {
+            request = request.timeout(timeout);
+        }
+        match body {
+            Body::Empty => request,
+            Body::Json(json) => request.json(json),
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    fn request(&self, client: &reqwest::blocking::Client, method: Method, endpoint: &str, path: &str, body: &Body) -> reqwest::blocking::RequestBuilder {
+        let mut request = client.request(method, format!("{}{}", endpoint, path))
+            .header("Authorization", format!("Bearer {}", self.config.access_token));
+        if let Some(timeout) = self.timeout() {
+            request = request.timeout(timeout);
+        }
+        match body {
+            Body::Empty => request,
+            Body::Json(json) => request.json(json),
+        }
+    }
+}
+
+/// HttpOptions parsed into what the reqwest client builders take
+struct HttpSettings {
+    proxy: Option<reqwest::Proxy>,
+    headers: HeaderMap,
+    #[cfg(feature = "tls")]
+    certificates: Vec<reqwest::Certificate>,
+}
+
+impl HttpSettings {
+    fn parse(options: &HttpOptions) -> Result<Self, LifxError> {
+        let invalid = |what: &str, err: &dyn std::fmt::Display| LifxError::Config(format!("{}: {}", what, err));
+        let proxy = match &options.proxy {
+            Some(proxy) => Some(reqwest::Proxy::all(proxy.as_str()).map_err(|err| invalid(&format!("proxy {:?}", proxy), &err))?),
+            None => None,
+        };
+        let mut headers = HeaderMap::new();
+        if let Some(user_agent) = &options.user_agent {
+            let value = HeaderValue::from_str(user_agent).map_err(|err| invalid("user agent", &err))?;
+            headers.insert(USER_AGENT, value);
+        }
+        for (name, value) in &options.headers {
+            let header = format!("header {:?}", name);
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| invalid(&header, &err))?;
+            let value = HeaderValue::from_str(value).map_err(|err| invalid(&header, &err))?;
+            headers.append(name, value);
+        }
+        #[cfg(feature = "tls")]
+        let certificates = options
+            .root_certificates
+            .iter()
+            .map(|pem| reqwest::Certificate::from_pem(pem.as_bytes()).map_err(|err| invalid("root certificate", &err)))
+            .collect::<Result<_, _>>()?;
+        #[cfg(not(feature = "tls"))]
+        if !options.root_certificates.is_empty() || options.danger_accept_invalid_certs {
+            return Err(LifxError::Config("TLS settings need the tls feature".to_string()));
+        }
+        Ok(HttpSettings {
+            proxy,
+            headers,
+            #[cfg(feature = "tls")]
+            certificates,
+        })
+    }
+}
+
+/// Checks that `options` can be used to build the HTTP clients, see LifxConfigBuilder::build
+pub(crate) fn check_http_options(options: &HttpOptions) -> Result<(), LifxError> {
+    HttpSettings::parse(options).map(|_| ())
+}
+
+/// Returns the results of a single request as they are, or joins those of several
+fn single_or_combined(mut all: Vec<LiFxResults>) -> LiFxResults {
+    if all.len() == 1 {
+        return all.remove(0);
+    }
+    combine_results(all)
+}
+
+/// Applies HttpSettings to a reqwest client builder; the async and blocking builders have the same methods
+macro_rules! configure_client {
+    ($builder:expr, $options:expr) => {{
+        let settings = HttpSettings::parse($options)?;
+        let mut builder = $builder.default_headers(settings.headers);
+        if let Some(proxy) = settings.proxy {
+            builder = builder.proxy(proxy);
+        }
+        #[cfg(feature = "tls")]
+        {
+            for certificate in settings.certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+            builder = builder.danger_accept_invalid_certs($options.danger_accept_invalid_certs);
+        }
+        builder.build().map_err(|err| LifxError::Config(err.to_string()))
+    }};
+}
+
+#[cfg(feature = "async")]
+fn async_http_client(options: &HttpOptions) -> Result<reqwest::Client, LifxError> {
+    configure_client!(reqwest::Client::builder(), options)
+}
+
+#[cfg(feature = "blocking")]
+fn blocking_http_client(options: &HttpOptions) -> Result<reqwest::blocking::Client, LifxError> {
+    configure_client!(reqwest::blocking::Client::builder(), options)
+}
+
+/// Runs a blocking transport call on tokio's blocking thread pool, so it doesn't stall the runtime
+#[cfg(feature = "async")]
+async fn on_transport<T, F>(transport: Arc<dyn Transport>, call: F) -> Result<T, LifxError>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn Transport) -> Result<T, LifxError> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(move || call(transport.as_ref())).await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// The last rate limit reported for each access token
+fn rate_limits() -> &'static Mutex<HashMap<String, RateLimit>> {
+    static RATE_LIMITS: OnceLock<Mutex<HashMap<String, RateLimit>>> = OnceLock::new();
+    RATE_LIMITS.get_or_init(Default::default)
+}
+
+/// The health of each endpoint requests have tried
+fn endpoint_health() -> &'static Mutex<HashMap<String, EndpointHealth>> {
+    static ENDPOINT_HEALTH: OnceLock<Mutex<HashMap<String, EndpointHealth>>> = OnceLock::new();
+    ENDPOINT_HEALTH.get_or_init(Default::default)
+}
+
+/// Records the outcome of an attempt on `endpoint` that started at `started`
+fn record_health(endpoint: &str, started: Instant, answered: bool) {
+    let mut health = endpoint_health().lock().unwrap_or_else(|e| e.into_inner());
+    let health = health.entry(endpoint.to_string()).or_default();
+    if answered {
+        health.record_success(started.elapsed());
+    } else {
+        health.record_failure(Instant::now());
+    }
+}
+
+/// Sorts endpoints for EndpointStrategy::Healthiest: those that are up before those that are
+/// down, fastest first. Endpoints without a latency yet sort first so they get probed.
+pub(crate) fn healthiest_first(endpoints: &mut [&str], now: Instant) {
+    let health = endpoint_health().lock().unwrap_or_else(|e| e.into_inner());
+    endpoints.sort_by_key(|endpoint| {
+        let health = health.get(*endpoint).copied().unwrap_or_default();
+        (health.is_down(now), health.latency.unwrap_or_default())
+    });
+}
+
+/// Reads the X-RateLimit headers of a response. None unless both Remaining and Reset are there;
+/// Reset is a unix timestamp in seconds.
+fn parse_rate_limit(headers: &HeaderMap) -> Option<RateLimit> {
+    fn header<T: FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+        headers.get(name)?.to_str().ok()?.trim().parse().ok()
+    }
+    Some(RateLimit {
+        limit: header(headers, "x-ratelimit-limit"),
+        remaining: header(headers, "x-ratelimit-remaining")?,
+        reset: UNIX_EPOCH + Duration::from_secs(header(headers, "x-ratelimit-reset")?),
+    })
+}
+
+/// The values of a response's Warning and X-Deprecation headers, in that order, trimmed. Values
+/// that aren't valid strings are passed over.
+fn parse_warnings(headers: &HeaderMap) -> Vec<String> {
+    ["warning", "x-deprecation"]
+        .iter()
+        .flat_map(|name| headers.get_all(*name))
+        .filter_map(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Turns a response status and body into the value a request is answered with
+type Decoder<T> = fn(reqwest::StatusCode, &[u8]) -> Result<T, LifxError>;
+
+/// Parses a response body as LiFxResults. With `fast` set, the API accepts a request with a 202
+/// and no body, which is reported as LiFxResults::fast_accepted rather than a decode error.
+fn decode_results(status: reqwest::StatusCode, body: &[u8]) -> Result<LiFxResults, LifxError> {
+    if status == reqwest::StatusCode::ACCEPTED || (status.is_success() && body.iter().all(u8::is_ascii_whitespace)) {
+        return Ok(LiFxResults { fast_accepted: true, ..Default::default() });
+    }
+    decode(status, body)
+}
+
+/// Parses a response body as T. A non-2xx status is reported as LifxError::Status with the API's
+/// error message, and an error payload in place of T as LifxError::Api.
+fn decode<T: DeserializeOwned>(status: reqwest::StatusCode, body: &[u8]) -> Result<T, LifxError> {
+    if !status.is_success() {
+        return Err(LifxError::Status {
+            status: status.as_u16(),
+            message: serde_json::from_slice::<ErrorBody>(body).ok().map(|payload| payload.error),
+        });
+    }
+    serde_json::from_slice(body).map_err(|err| match serde_json::from_slice::<ErrorBody>(body) {
+        Ok(payload) => LifxError::Api(payload.error),
+        Err(_) => LifxError::Decode(err),
+    })
+}
+
+/// A type HTTP responses are decoded into, see LifxConfig::deny_unknown_fields
+pub(crate) trait Response: DeserializeOwned + Serialize {
+    /// The paths of the fields kept in `extra` maps because this crate doesn't model them
+    fn extra_fields(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl Response for Color {}
+
+impl Response for Lights {
+    fn extra_fields(&self) -> Vec<String> {
+        indexed_extras(self.iter().map(|light| &light.extra))
+    }
+}
+
+impl Response for Scenes {
+    fn extra_fields(&self) -> Vec<String> {
+        indexed_extras(self.iter().map(|scene| &scene.extra))
+    }
+}
+
+impl Response for LiFxResults {
+    fn extra_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = self.extra.keys().cloned().collect();
+        let results = indexed_extras(self.results.iter().flatten().map(|result| &result.extra));
+        fields.extend(results.into_iter().map(|field| format!("results{}", field)));
+        fields
+    }
+}
+
+/// The keys of each map in a list of `extra` maps, prefixed with its index, ex: "[0].firmware"
+fn indexed_extras<'a>(extras: impl Iterator<Item = &'a serde_json::Map<String, Value>>) -> Vec<String> {
+    extras.enumerate().flat_map(|(i, extra)| extra.keys().map(move |key| format!("[{}].{}", i, key))).collect()
+}
+
+/// Returns LifxError::UnexpectedFields if `body` has fields that weren't decoded into `value`:
+/// those kept in an `extra` map, and those dropped because the type doesn't have them, found by
+/// serializing `value` again and comparing. Null fields are passed over, since a None the type
+/// doesn't serialize can't be told apart from a field it doesn't have.
+fn check_fields<T: Response>(body: &[u8], value: &T) -> Result<(), LifxError> {
+    let sent: Value = match serde_json::from_slice(body) {
+        Ok(sent) => sent,
+        // An empty body, ex: a fast request's
+        Err(_) => return Ok(()),
+    };
+    let mut unexpected = value.extra_fields();
+    dropped_fields(&sent, &serde_json::to_value(value)?, "", &mut unexpected);
+    if unexpected.is_empty() {
+        return Ok(());
+    }
+    unexpected.sort();
+    Err(LifxError::UnexpectedFields(unexpected))
+}
+
+/// Adds the path of every non-null field in `sent` that `decoded` doesn't have to `dropped`
+fn dropped_fields(sent: &Value, decoded: &Value, path: &str, dropped: &mut Vec<String>) {
+    match (sent, decoded) {
+        (Value::Object(sent), Value::Object(decoded)) => {
+            for (key, value) in sent {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match decoded.get(key) {
+                    Some(decoded) => dropped_fields(value, decoded, &field, dropped),
+                    None if !value.is_null() => dropped.push(field),
+                    None => {}
+                }
+            }
+        }
+        (Value::Array(sent), Value::Array(decoded)) => {
+            for (i, (sent, decoded)) in sent.iter().zip(decoded).enumerate() {
+                dropped_fields(sent, decoded, &format!("{}[{}]", path, i), dropped);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use crate::RetryPolicy;
+
+    /// Answers one HTTP request on a local port with `status` and `body`, sending the raw request text back on the channel
+    fn serve_once(status: &'static str, body: &'static str) -> (String, mpsc::Receiver<String>) {
+        serve(0, status, "", body)
+    }
+
+    /// serve_once, with `headers` ("Name: value\r\n" lines) added to the response
+    #[cfg(feature = "blocking")]
+    fn serve_once_with_headers(status: &'static str, headers: &'static str, body: &'static str) -> (String, mpsc::Receiver<String>) {
+        serve(0, status, headers, body)
+    }
+
+    /// serve_once, after closing the first `drops` connections without an answer
+    fn serve(drops: usize, status: &'static str, headers: &'static str, body: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..drops {
+                drop(listener.accept().unwrap());
+            }
+            sender.send(answer(&listener, status, headers, body)).unwrap();
+        });
+        (endpoint, receiver)
+    }
+
+    /// Answers the next `count` requests with `status` and `body`, sending each request's text
+    /// down the channel
+    fn serve_many(count: usize, status: &'static str, body: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..count {
+                sender.send(answer(&listener, status, "", body)).unwrap();
+            }
+        });
+        (endpoint, receiver)
+    }
+
+    /// Accepts a connection, reads one request from it and answers it, returning the request
+    fn answer(listener: &TcpListener, status: &str, headers: &str, body: &str) -> String {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // Read the headers, then as much body as Content-Length says
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text.lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + length || n == 0 {
+                    break;
+                }
+            }
+        }
+        let response = format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}", status, body.len(), headers, body);
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&request).to_string()
+    }
+
+    fn config(endpoint: String) -> LifxConfig {
+        // Nothing listens on the discard port, so every request falls back to `endpoint`
+        LifxConfig {
+            access_token: "xxx".to_string(),
+            api_endpoints: vec!["http://127.0.0.1:9".to_string(), endpoint],
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_send_falls_back() {
+        let (endpoint, request) = serve_once("200 OK", r#"{"results":[{"id":"d073d5","label":"Lamp","status":"ok"}]}"#);
+        let client = LifxClient::new(config(endpoint));
+
+        let mut state = State::new();
+        state.power = Some("off".to_string());
+        let results = client.async_set_state_by_selector("id:d073d5".to_string(), state).await.unwrap();
+        assert_eq!(results.results.unwrap()[0].status, "ok");
+
+        let request = request.recv().unwrap();
+        assert!(request.starts_with("PUT /v1/lights/id:d073d5/state HTTP/1.1"));
+        assert!(request.to_lowercase().contains("authorization: bearer xxx"));
+        assert!(request.to_lowercase().contains("content-type: application/json"));
+        assert!(request.ends_with(r#"{"power":"off"}"#));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_send_json_body() {
+        let (endpoint, request) = serve_once("200 OK", r#"{"results":[]}"#);
+        let client = LifxClient::new(config(endpoint));
+
+        let mut states = States::new().set_default_fast(true);
+        states.states = Some(vec![State { selector: Some("id:d073d5".to_string()), ..Default::default() }]);
+        assert!(client.set_states(states).unwrap().results.unwrap().is_empty());
+
+        let request = request.recv().unwrap();
+        assert!(request.starts_with("PUT /v1/lights/state HTTP/1.1"));
+        assert!(request.ends_with(r#"{"defaults":{"fast":true},"states":[{"selector":"id:d073d5"}]}"#));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let ok = reqwest::StatusCode::OK;
+        assert!(matches!(decode::<Lights>(ok, br#"{"error":"Invalid token"}"#), Err(LifxError::Api(message)) if message == "Invalid token"));
+        assert!(matches!(decode::<Lights>(ok, b"<html>Bad Gateway</html>"), Err(LifxError::Decode(_))));
+        assert!(decode::<Lights>(ok, b"[]").unwrap().is_empty());
+        // Set state answers 207 Multi-Status, which is still a success
+        assert!(decode::<LiFxResults>(reqwest::StatusCode::MULTI_STATUS, br#"{"results":[]}"#).is_ok());
+    }
+
+    #[test]
+    fn test_decode_fast_accepted() {
+        assert!(decode_results(reqwest::StatusCode::ACCEPTED, b"").unwrap().fast_accepted);
+        assert!(decode_results(reqwest::StatusCode::OK, b" \n").unwrap().fast_accepted);
+        assert!(!decode_results(reqwest::StatusCode::MULTI_STATUS, br#"{"results":[]}"#).unwrap().fast_accepted);
+        assert!(matches!(decode_results(reqwest::StatusCode::UNAUTHORIZED, b""), Err(LifxError::Status { status: 401, .. })));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_fast_set_state() {
+        let (endpoint, _request) = serve_once("202 Accepted", "");
+        let state = State::builder().power_on().fast(true).build();
+        let results = LifxClient::new(config(endpoint)).set_state_by_selector("all".to_string(), state).unwrap();
+        assert!(results.fast_accepted);
+        assert_eq!(results.results, None);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_transport_error() {
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(LifxClient::new(unreachable).async_list_all().await, Err(LifxError::ConnectionRefused(_))));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_connection_errors() {
+        let client = |endpoint: &str| LifxClient::new(LifxConfig { api_endpoints: vec![endpoint.to_string()], ..Default::default() });
+        // .invalid never resolves
+        assert!(matches!(client("http://lifx.invalid").list_all(), Err(LifxError::Dns(_))));
+        assert!(matches!(client("http://127.0.0.1:9").list_all(), Err(LifxError::ConnectionRefused(_))));
+        assert!(matches!(client(&black_hole()).with_timeout(0.2).list_all(), Err(LifxError::Timeout(_))));
+        // A dropped connection is none of them
+        let (endpoint, _request) = serve(1, "200 OK", "", "[]");
+        assert!(matches!(client(&endpoint).list_all(), Err(LifxError::Transport(_))));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_status_error() {
+        let (endpoint, _request) = serve_once("401 Unauthorized", r#"{"error":"Invalid token"}"#);
+        match LifxClient::new(config(endpoint)).list_all() {
+            Err(LifxError::Status { status, message }) => {
+                assert_eq!(status, 401);
+                assert_eq!(message.as_deref(), Some("Invalid token"));
+            },
+            other => panic!("expected a status error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_rate_limit_headers() {
+        let (endpoint, _request) = serve_once_with_headers(
+            "207 Multi-Status",
+            "X-RateLimit-Limit: 120\r\nX-RateLimit-Remaining: 0\r\nX-RateLimit-Reset: 1456181052\r\n",
+            r#"{"results":[{"id":"d073d5","label":"Lamp","status":"ok"}]}"#,
+        );
+        // Rate limits are tracked per access token, so this one gets a token of its own
+        let client = LifxClient::new(LifxConfig { access_token: "rate-limited".to_string(), ..config(endpoint) });
+        assert_eq!(client.rate_limit(), None);
+
+        let results = client.toggle_by_selector("id:d073d5".to_string(), Toggle::new()).unwrap();
+        let expected = RateLimit { limit: Some(120), remaining: 0, reset: UNIX_EPOCH + Duration::from_secs(1_456_181_052) };
+        assert_eq!(results.rate_limit, Some(expected));
+        assert_eq!(LifxClient::new(client.config().clone()).rate_limit(), Some(expected));
+        // The reset is long past, so there's nothing to wait for even when waiting is on
+        let waiting = LifxClient::new(LifxConfig { wait_on_rate_limit: true, ..client.config().clone() });
+        assert_eq!(waiting.rate_limit_wait(), None);
+    }
+
+    #[test]
+    fn test_wait_on_rate_limit() {
+        let config = LifxConfig { access_token: "exhausted".to_string(), ..Default::default() };
+        let reset = SystemTime::now() + Duration::from_secs(30);
+        rate_limits().lock().unwrap_or_else(|e| e.into_inner()).insert(config.access_token.clone(), RateLimit { limit: None, remaining: 0, reset });
+
+        assert_eq!(LifxClient::new(config.clone()).rate_limit_wait(), None);
+        let wait = LifxClient::new(LifxConfig { wait_on_rate_limit: true, ..config }).rate_limit_wait().unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_parse_rate_limit() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", "119".parse().unwrap());
+        assert_eq!(parse_rate_limit(&headers), None);
+        headers.insert("x-ratelimit-reset", "1456181052".parse().unwrap());
+        assert_eq!(parse_rate_limit(&headers).map(|rate_limit| (rate_limit.limit, rate_limit.remaining)), Some((None, 119)));
+    }
+
+    #[test]
+    fn test_parse_warnings() {
+        let mut headers = HeaderMap::new();
+        assert!(parse_warnings(&headers).is_empty());
+        headers.append("x-deprecation", "selector groups are going away on 2027-01-01".parse().unwrap());
+        headers.append("warning", "299 - \"fast is deprecated\"".parse().unwrap());
+        headers.append("warning", " 199 - \"misc\" ".parse().unwrap());
+        assert_eq!(parse_warnings(&headers), ["299 - \"fast is deprecated\"", "199 - \"misc\"", "selector groups are going away on 2027-01-01"]);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_last_warnings() {
+        let (endpoint, _request) = serve_once_with_headers(
+            "200 OK",
+            "Warning: 299 - \"/v1/lights/:selector/effects/move is deprecated\"\r\nX-Deprecation: use /v2/effects\r\n",
+            "[]",
+        );
+        let client = LifxClient::new(config(endpoint));
+        assert!(client.last_warnings().is_empty());
+        let clone = client.with_timeout(5.0);
+        assert!(clone.list_all().unwrap().is_empty());
+        let expected = ["299 - \"/v1/lights/:selector/effects/move is deprecated\"", "use /v2/effects"];
+        assert_eq!(client.last_warnings(), expected);
+        assert_eq!(clone.last_warnings(), expected);
+        assert!(LifxClient::new(client.config().clone()).last_warnings().is_empty());
+
+        let (endpoint, _request) = serve_once("200 OK", "[]");
+        let client = LifxClient::new(LifxConfig { api_endpoints: vec![endpoint], ..client.config().clone() });
+        client.list_all().unwrap();
+        assert!(client.last_warnings().is_empty());
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_validate_cached() {
+        let (endpoint, request) = serve_once("200 OK", r#"{"hue":null,"saturation":0.0,"brightness":null,"kelvin":2700}"#);
+        let client = LifxClient::new(config(endpoint));
+        let warm = client.validate_cached("warm white".to_string()).unwrap();
+        assert_eq!(warm.kelvin, Some(2700));
+        assert!(request.recv().unwrap().starts_with("GET /v1/color?string=warm"));
+
+        // Nothing answers any more, so these would fail if they went to the network
+        assert_eq!(client.validate_cached("warm white".to_string()).unwrap(), warm);
+        assert_eq!(client.with_timeout(1.0).validate_cached("warm white".to_string()).unwrap(), warm);
+        assert!(client.validate_cached("cool white".to_string()).is_err());
+        assert!(LifxClient::new(client.config().clone()).validate_cached("warm white".to_string()).is_err());
+        client.clear_validation_cache();
+        assert!(client.validate_cached("warm white".to_string()).is_err());
+
+        let invalid = Color { error: Some("Unable to parse color".to_string()), ..Default::default() };
+        client.cache_validation("reddish".to_string(), &invalid);
+        assert_eq!(client.cached_validation("reddish"), None);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy { max_attempts: 4, base_delay: 0.5, jitter: 0.0 };
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+
+        let jittered = RetryPolicy { jitter: 0.5, ..policy };
+        for _ in 0..20 {
+            let delay = jittered.delay(2);
+            assert!(delay > Duration::from_millis(500) && delay <= Duration::from_secs(1), "{:?}", delay);
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_retries_before_falling_back() {
+        let (endpoint, request) = serve(2, "200 OK", "", "[]");
+        let retrying = LifxConfig {
+            api_endpoints: vec![endpoint],
+            retry_policy: RetryPolicy { max_attempts: 3, base_delay: 0.01, jitter: 0.0 },
+            ..Default::default()
+        };
+        assert!(LifxClient::new(retrying).list_all().unwrap().is_empty());
+        assert!(request.recv().unwrap().starts_with("GET /v1/lights/all HTTP/1.1"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_gives_up_after_max_attempts() {
+        let (endpoint, _request) = serve(2, "200 OK", "", "[]");
+        let retrying = LifxConfig {
+            api_endpoints: vec![endpoint],
+            retry_policy: RetryPolicy { max_attempts: 2, base_delay: 0.01, jitter: 0.0 },
+            ..Default::default()
+        };
+        assert!(matches!(LifxClient::new(retrying).async_list_all().await, Err(LifxError::Transport(_))));
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let body = br#"[{
+            "id": "d073d5000001",
+            "label": "Lamp",
+            "connected": true,
+            "power": "on",
+            "color": {"hue": 0, "saturation": 0, "kelvin": 3500, "mode": "white"},
+            "brightness": 1,
+            "group": {"id": "1c8de82b81f445e7cfaafae49b259c71", "name": "Living Room"},
+            "product": {"name": "LIFX Color", "capabilities": {"has_color": true}},
+            "last_seen": "2015-12-03T05:53:18Z",
+            "seconds_since_seen": 0,
+            "effect": "OFF",
+            "firmware": "3.70",
+            "error": null
+        }]"#;
+        let lights: Lights = decode(reqwest::StatusCode::OK, body).unwrap();
+        match check_fields(body, &lights) {
+            Err(LifxError::UnexpectedFields(fields)) => assert_eq!(fields, vec!["[0].color.mode", "[0].firmware"]),
+            other => panic!("expected unexpected fields, got {:?}", other),
+        }
+
+        let expected = br#"{"results": [{"id": "d073d5000001", "label": "Lamp", "status": "ok"}]}"#;
+        let results: LiFxResults = decode(reqwest::StatusCode::MULTI_STATUS, expected).unwrap();
+        assert!(check_fields(expected, &results).is_ok());
+        assert!(check_fields(b"", &LiFxResults::default()).is_ok());
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_deny_unknown_fields() {
+        const BODY: &str = r#"[{"id": "d073d5000001", "label": "Lamp", "firmware": "3.70"}]"#;
+        let (endpoint, _request) = serve_once("200 OK", BODY);
+        let tolerant = LifxClient::new(LifxConfig { api_endpoints: vec![endpoint], ..Default::default() });
+        assert_eq!(tolerant.list_all().unwrap()[0].extra["firmware"], "3.70");
+
+        let (endpoint, _request) = serve_once("200 OK", BODY);
+        let strict = LifxClient::new(LifxConfig { api_endpoints: vec![endpoint], deny_unknown_fields: true, ..Default::default() });
+        assert!(matches!(strict.list_all(), Err(LifxError::UnexpectedFields(fields)) if fields == vec!["[0].firmware"]));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_on_request_complete() {
+        let (endpoint, _request) = serve(1, "200 OK", "", "[]");
+        let client = LifxClient::new(LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string(), endpoint.clone()],
+            retry_policy: RetryPolicy { max_attempts: 2, base_delay: 0.01, jitter: 0.0 },
+            ..Default::default()
+        });
+        let timings = Arc::new(Mutex::new(Vec::new()));
+        let recorded = timings.clone();
+        let timed = client.on_request_complete(Arc::new(move |timing: &RequestTiming| recorded.lock().unwrap().push(timing.clone())));
+        assert!(timed.list_all().unwrap().is_empty());
+
+        let timings = timings.lock().unwrap();
+        let summary: Vec<_> = timings.iter().map(|timing| (timing.endpoint_index, timing.status, timing.retried)).collect();
+        // Refused twice on the first endpoint, then dropped and answered on the retry on the second
+        assert_eq!(summary, vec![(0, None, false), (0, None, true), (1, None, false), (1, Some(200), true)]);
+        assert_eq!(timings[3].url, format!("{}/v1/lights/all", endpoint));
+        assert_eq!(timings[3].method, Method::GET);
+        assert!(timings.iter().all(|timing| timing.elapsed < Duration::from_secs(5)));
+
+        // Clients without the callback, including the one it was set on, don't report
+        assert!(client.on_request_complete.is_none());
+    }
+
+    #[test]
+    fn test_retry_tokens() {
+        let budget = RetryBudget { capacity: 2, refill_per_second: 4.0 };
+        let start = Instant::now();
+        let mut tokens = RetryTokens { tokens: 2.0, updated: start };
+        assert!(tokens.take(&budget, start));
+        assert!(tokens.take(&budget, start));
+        assert!(!tokens.take(&budget, start));
+        // A quarter second refills one
+        assert!(tokens.take(&budget, start + Duration::from_millis(250)));
+        assert!(!tokens.take(&budget, start + Duration::from_millis(250)));
+        // Never beyond capacity
+        tokens.refill(&budget, start + Duration::from_secs(60));
+        assert_eq!(tokens.tokens, 2.0);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_retry_budget_is_shared() {
+        let (endpoint, _request) = serve(3, "200 OK", "", "[]");
+        let client = LifxClient::new(LifxConfig {
+            api_endpoints: vec![endpoint],
+            retry_policy: RetryPolicy { max_attempts: 5, base_delay: 0.01, jitter: 0.0 },
+            retry_budget: RetryBudget { capacity: 1, refill_per_second: 0.0 },
+            ..Default::default()
+        });
+        assert_eq!(client.retry_budget(), 1);
+        // The first attempt and the one retry the budget allows are both dropped
+        assert!(matches!(client.list_all(), Err(LifxError::Transport(_))));
+        let clone = client.clone();
+        assert_eq!(clone.retry_budget(), 0);
+        assert_eq!(LifxClient::new(client.config().clone()).retry_budget(), 1);
+    }
+
+    /// Accepts connections on a local port and never answers them
+    fn black_hole() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let _streams: Vec<_> = listener.incoming().collect();
+        });
+        endpoint
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_set_states_chunks() {
+        let (endpoint, requests) = serve_many(3, "207 Multi-Status", r#"{"results": [{"id": "d073d5000001", "label": "Lamp", "status": "ok"}]}"#);
+        let client = LifxClient::new(LifxConfig { api_endpoints: vec![endpoint], ..Default::default() });
+        let states: Vec<State> = (0..120).map(|i| State { selector: Some(format!("id:d073d5{:06}", i)), ..State::builder().power_on().build() }).collect();
+        let defaults = Some(State::builder().duration(2.0).build());
+        let results = client.set_states(States { states: Some(states), defaults }).unwrap();
+        assert_eq!(results.results.unwrap().len(), 3);
+
+        let estimate = crate::estimated_requests(120, crate::BatchOperation::SetStates);
+        let sent: Vec<String> = (0..estimate).map(|_| requests.recv_timeout(Duration::from_secs(5)).unwrap()).collect();
+        let sizes: Vec<usize> = sent.iter().map(|request| request.matches("\"selector\"").count()).collect();
+        assert_eq!(sizes, vec![50, 50, 20]);
+        assert!(sent.iter().all(|request| request.starts_with("PUT /v1/lights/state") && request.contains("\"duration\":2.0")));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_timeout_falls_back() {
+        // The silent first endpoint times out, and the fallback still gets its own attempt
+        let (endpoint, _request) = serve_once("200 OK", "[]");
+        let client = LifxClient::new(LifxConfig { api_endpoints: vec![black_hole(), endpoint], ..Default::default() });
+        assert!(client.with_timeout(0.2).list_all().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_timeout() {
+        let silent = LifxConfig { api_endpoints: vec![black_hole()], ..Default::default() }.with_timeout(0.2);
+        match LifxClient::new(silent).async_list_all().await {
+            Err(LifxError::Timeout(err)) => assert!(err.is_timeout()),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_healthiest_first() {
+        let earlier = Instant::now();
+        let now = earlier + ENDPOINT_RECHECK_AFTER;
+        let (fast, slow, down, recovered) = ("http://fast.test", "http://slow.test", "http://down.test", "http://recovered.test");
+        {
+            let mut health = endpoint_health().lock().unwrap_or_else(|e| e.into_inner());
+            health.entry(fast.to_string()).or_default().record_success(Duration::from_millis(20));
+            health.entry(slow.to_string()).or_default().record_success(Duration::from_millis(300));
+            health.entry(down.to_string()).or_default().record_failure(now);
+            let recovered = health.entry(recovered.to_string()).or_default();
+            recovered.record_success(Duration::from_millis(500));
+            recovered.record_failure(earlier);
+        }
+        let mut endpoints = vec![down, slow, "http://untried.test", recovered, fast];
+        healthiest_first(&mut endpoints, now);
+        assert_eq!(endpoints, vec!["http://untried.test", fast, slow, recovered, down]);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_healthiest_skips_unreachable() {
+        // The first request finds the primary down, the second goes straight to the fallback
+        let (first, _) = serve_once("200 OK", "[]");
+        let (second, second_request) = serve_once("200 OK", "[]");
+        let unreachable = "http://127.0.0.1:1".to_string();
+        let config = LifxConfig { api_endpoints: vec![unreachable.clone(), first], endpoint_strategy: crate::EndpointStrategy::Healthiest, ..Default::default() };
+        let client = LifxClient::new(config.clone());
+        client.list_all().unwrap();
+        assert!(client.endpoint_health(&unreachable).unwrap().is_down(Instant::now()));
+
+        let client = LifxClient::new(LifxConfig { api_endpoints: vec![unreachable.clone(), second], ..config });
+        client.list_all().unwrap();
+        assert!(second_request.recv().is_ok());
+        assert_eq!(client.endpoint_health(&unreachable).unwrap().consecutive_failures, 1);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_falls_back_past_the_second_endpoint() {
+        let (endpoint, request) = serve_once("200 OK", "[]");
+        let unreachable = |port: u16| format!("http://127.0.0.1:{}", port);
+        let config = LifxConfig { api_endpoints: vec![unreachable(9), unreachable(1), endpoint], ..Default::default() };
+        assert!(LifxClient::new(config).list_all().unwrap().is_empty());
+        assert!(request.recv().unwrap().starts_with("GET /v1/lights/all HTTP/1.1"));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_no_endpoints() {
+        let config = LifxConfig { access_token: "x".to_string(), ..Default::default() };
+        let result = LifxClient::new(config).list_all();
+        assert!(matches!(result, Err(LifxError::Config(message)) if message == NO_ENDPOINTS));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_no_endpoints() {
+        let config = LifxConfig { access_token: "x".to_string(), ..Default::default() };
+        let result = LifxClient::new(config).async_toggle_by_selector("all".to_string(), Toggle::new()).await;
+        assert!(matches!(result, Err(LifxError::Config(message)) if message == NO_ENDPOINTS));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_no_writes_to_read_only_endpoints() {
+        let config = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            endpoint_options: vec![crate::EndpointOptions::new("http://127.0.0.1:9").read_only()],
+            ..Default::default()
+        };
+        let result = LifxClient::new(config).toggle_by_selector("all".to_string(), Toggle::new());
+        assert!(matches!(result, Err(LifxError::Unsupported(message)) if message.contains("read-only")));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_http_options() {
+        let (endpoint, request) = serve_once("200 OK", "[]");
+        let config = LifxConfig::builder()
+            .endpoint(endpoint)
+            .user_agent("porch-lights/1.0")
+            .header("X-Request-Source", "porch")
+            .build()
+            .unwrap();
+        LifxClient::new(config).list_all().unwrap();
+
+        let request = request.recv().unwrap().to_lowercase();
+        assert!(request.contains("user-agent: porch-lights/1.0"));
+        assert!(request.contains("x-request-source: porch"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_invalid_http_options() {
+        // Configs made without the builder are checked when the client is built
+        let mut config = LifxConfig { api_endpoints: vec!["http://127.0.0.1:9".to_string()], ..Default::default() };
+        config.http.proxy = Some("not a url".to_string());
+        assert!(matches!(LifxClient::new(config).async_list_all().await, Err(LifxError::Config(_))));
+    }
+}
@@ -0,0 +1,492 @@
+//! Loading a LifxConfig from a config file, for daemons that keep their settings on disk.
+//!
+//! Only compiled with the `config` feature (and `blocking` or `async`).
+
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::{LifxConfig, LifxError, RetryPolicy};
+
+/// The settings a config file can hold, see LifxConfig::from_file. Unknown keys are an error,
+/// so a typo doesn't silently fall back to a default.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    token: String,
+    #[serde(default)]
+    endpoints: Option<Vec<String>>,
+    #[serde(default)]
+    default_duration: Option<f64>,
+    #[serde(default)]
+    timeout: Option<f64>,
+    #[serde(default)]
+    retry: Option<RetryFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetryFile {
+    max_attempts: Option<u32>,
+    base_delay: Option<f64>,
+    jitter: Option<f64>,
+}
+
+impl ConfigFile {
+    fn into_config(self) -> LifxConfig {
+        let defaults = RetryPolicy::default();
+        let retry_policy = match self.retry {
+            Some(retry) => RetryPolicy {
+                max_attempts: retry.max_attempts.unwrap_or(defaults.max_attempts),
+                base_delay: retry.base_delay.unwrap_or(defaults.base_delay),
+                jitter: retry.jitter.unwrap_or(defaults.jitter),
+            },
+            None => defaults,
+        };
+        LifxConfig {
+            access_token: self.token,
+            api_endpoints: self.endpoints.unwrap_or_else(|| vec!["https://api.lifx.com".to_string()]),
+            default_duration: self.default_duration,
+            timeout: self.timeout,
+            retry_policy,
+            ..Default::default()
+        }
+    }
+}
+
+impl LifxConfig {
+    /// Reads a config from a JSON or TOML file with the access token, endpoints, timeout and
+    /// retry settings. Without endpoints, requests go to https://api.lifx.com.
+    ///
+    /// ```json
+    /// {
+    ///     "token": "xxx",
+    ///     "endpoints": ["http://localhost:8089", "https://api.lifx.com"],
+    ///     "default_duration": 0.5,
+    ///     "timeout": 5.0,
+    ///     "retry": { "max_attempts": 3, "base_delay": 0.5, "jitter": 0.5 }
+    /// }
+    /// ```
+    ///
+    /// ```toml
+    /// token = "xxx"
+    /// endpoints = ["http://localhost:8089", "https://api.lifx.com"]
+    /// default_duration = 0.5
+    /// timeout = 5.0
+    ///
+    /// [retry]
+    /// max_attempts = 3
+    /// base_delay = 0.5
+    /// jitter = 0.5
+    /// ```
+    ///
+    /// TOML files are read with a small built-in reader that covers strings, numbers, booleans,
+    /// arrays and tables, which is everything a config holds; dates aren't supported.
+    ///
+    /// Returns LifxError::Config if the file can't be read, isn't valid, or doesn't end in .json or .toml,
+    /// or if the config it holds doesn't pass LifxConfig::validate, ex: `"endpoints": []`.
+    ///
+    /// Only compiled with the `config` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The config file, ex: "/etc/lights/lifx.json"
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let config = lifx::LifxConfig::from_file("/etc/lights/lifx.json").unwrap();
+    ///     let lights = lifx::Light::list_all(config);
+    /// }
+    ///  ```
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<LifxConfig, LifxError> {
+        let path = path.as_ref();
+        let invalid = |err: &dyn std::fmt::Display| LifxError::Config(format!("{}: {}", path.display(), err));
+        let toml = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => false,
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => true,
+            _ => return Err(invalid(&"unsupported config file format, expected .json or .toml")),
+        };
+        let text = std::fs::read_to_string(path).map_err(|err| invalid(&err))?;
+        let file: ConfigFile = if toml {
+            let value = toml::parse(&text).map_err(|err| invalid(&err))?;
+            serde_json::from_value(value).map_err(|err| invalid(&err))?
+        } else {
+            serde_json::from_str(&text).map_err(|err| invalid(&err))?
+        };
+        let config = file.into_config();
+        config.validate().map_err(|err| match err {
+            LifxError::Config(message) => invalid(&message),
+            err => err,
+        })?;
+        Ok(config)
+    }
+}
+
+/// Just enough TOML for a config file, read into a serde_json::Value so the JSON and TOML
+/// paths share ConfigFile and its unknown-key checks.
+mod toml {
+    use serde_json::{Map, Number, Value};
+
+    /// Parses a TOML document, errors name the line they're on
+    pub(super) fn parse(text: &str) -> Result<Value, String> {
+        let mut parser = Parser { chars: text.chars().collect(), pos: 0, line: 1 };
+        parser.document().map_err(|err| format!("line {}: {}", parser.line, err))
+    }
+
+    struct Parser {
+        chars: Vec<char>,
+        pos: usize,
+        line: usize,
+    }
+
+    impl Parser {
+        fn peek(&self) -> Option<char> {
+            self.chars.get(self.pos).copied()
+        }
+
+        fn bump(&mut self) -> Option<char> {
+            let c = self.peek()?;
+            self.pos += 1;
+            if c == '\n' {
+                self.line += 1;
+            }
+            Some(c)
+        }
+
+        fn expect(&mut self, expected: char) -> Result<(), String> {
+            match self.bump() {
+                Some(c) if c == expected => Ok(()),
+                Some(c) => Err(format!("expected '{}', found '{}'", expected, c)),
+                None => Err(format!("expected '{}', found end of file", expected)),
+            }
+        }
+
+        /// Skips spaces and tabs, and newlines and comments too if `newlines` is set
+        fn skip(&mut self, newlines: bool) {
+            while let Some(c) = self.peek() {
+                match c {
+                    ' ' | '\t' => {}
+                    '\r' | '\n' if newlines => {}
+                    '#' => {
+                        while !matches!(self.peek(), None | Some('\n')) {
+                            self.pos += 1;
+                        }
+                        continue;
+                    }
+                    _ => return,
+                }
+                self.bump();
+            }
+        }
+
+        fn end_of_line(&mut self) -> Result<(), String> {
+            self.skip(false);
+            match self.peek() {
+                None | Some('\n') => Ok(()),
+                Some('\r') if self.chars.get(self.pos + 1) == Some(&'\n') => Ok(()),
+                Some(c) => Err(format!("expected a new line, found '{}'", c)),
+            }
+        }
+
+        fn document(&mut self) -> Result<Value, String> {
+            let mut root = Map::new();
+            let mut table: Vec<String> = Vec::new();
+            loop {
+                self.skip(true);
+                match self.peek() {
+                    None => return Ok(Value::Object(root)),
+                    Some('[') => {
+                        self.bump();
+                        table = self.key()?;
+                        self.expect(']')?;
+                        let parent = descend(&mut root, &table[..table.len() - 1])?;
+                        let name = &table[table.len() - 1];
+                        if parent.contains_key(name) {
+                            return Err(format!("duplicate table [{}]", table.join(".")));
+                        }
+                        parent.insert(name.clone(), Value::Object(Map::new()));
+                    }
+                    Some(_) => {
+                        let key = self.key()?;
+                        self.expect('=')?;
+                        let value = self.value()?;
+                        let path: Vec<String> = table.iter().chain(&key).cloned().collect();
+                        insert(&mut root, &path, value)?;
+                    }
+                }
+                self.end_of_line()?;
+            }
+        }
+
+        /// A bare, quoted or dotted key
+        fn key(&mut self) -> Result<Vec<String>, String> {
+            let mut path = Vec::new();
+            loop {
+                self.skip(false);
+                let part = match self.peek() {
+                    Some('"') => self.basic_string()?,
+                    Some('\'') => self.literal_string()?,
+                    _ => {
+                        let start = self.pos;
+                        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                            self.pos += 1;
+                        }
+                        if start == self.pos {
+                            return Err("expected a key".to_string());
+                        }
+                        self.chars[start..self.pos].iter().collect()
+                    }
+                };
+                path.push(part);
+                self.skip(false);
+                if self.peek() != Some('.') {
+                    return Ok(path);
+                }
+                self.bump();
+            }
+        }
+
+        fn value(&mut self) -> Result<Value, String> {
+            self.skip(false);
+            match self.peek() {
+                Some('"') => self.basic_string().map(Value::String),
+                Some('\'') => self.literal_string().map(Value::String),
+                Some('[') => self.array(),
+                Some('{') => self.inline_table(),
+                Some(_) => self.scalar(),
+                None => Err("expected a value, found end of file".to_string()),
+            }
+        }
+
+        fn basic_string(&mut self) -> Result<String, String> {
+            self.expect('"')?;
+            let mut string = String::new();
+            loop {
+                match self.string_char()? {
+                    '"' => return Ok(string),
+                    '\\' => {
+                        let c = match self.bump() {
+                            Some('"') => '"',
+                            Some('\\') => '\\',
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some('b') => '\u{8}',
+                            Some('f') => '\u{c}',
+                            Some(u @ ('u' | 'U')) => {
+                                let digits = if u == 'u' { 4 } else { 8 };
+                                let hex: String = (0..digits).filter_map(|_| self.bump()).collect();
+                                u32::from_str_radix(&hex, 16)
+                                    .ok()
+                                    .and_then(char::from_u32)
+                                    .ok_or_else(|| format!("invalid unicode escape \\{}{}", u, hex))?
+                            }
+                            Some(c) => return Err(format!("invalid escape \\{}", c)),
+                            None => return Err("unterminated string".to_string()),
+                        };
+                        string.push(c);
+                    }
+                    c => string.push(c),
+                }
+            }
+        }
+
+        fn literal_string(&mut self) -> Result<String, String> {
+            self.expect('\'')?;
+            let mut string = String::new();
+            loop {
+                match self.string_char()? {
+                    '\'' => return Ok(string),
+                    c => string.push(c),
+                }
+            }
+        }
+
+        /// The next character of a single line string
+        fn string_char(&mut self) -> Result<char, String> {
+            match self.peek() {
+                Some(c) if c != '\n' => {
+                    self.pos += 1;
+                    Ok(c)
+                }
+                _ => Err("unterminated string".to_string()),
+            }
+        }
+
+        fn array(&mut self) -> Result<Value, String> {
+            self.expect('[')?;
+            let mut values = Vec::new();
+            loop {
+                self.skip(true);
+                if self.peek() == Some(']') {
+                    self.bump();
+                    return Ok(Value::Array(values));
+                }
+                values.push(self.value()?);
+                self.skip(true);
+                match self.peek() {
+                    Some(',') => {
+                        self.bump();
+                    }
+                    Some(']') => {}
+                    _ => return Err("expected ',' or ']' in an array".to_string()),
+                }
+            }
+        }
+
+        fn inline_table(&mut self) -> Result<Value, String> {
+            self.expect('{')?;
+            let mut table = Map::new();
+            self.skip(false);
+            if self.peek() == Some('}') {
+                self.bump();
+                return Ok(Value::Object(table));
+            }
+            loop {
+                let key = self.key()?;
+                self.expect('=')?;
+                let value = self.value()?;
+                insert(&mut table, &key, value)?;
+                self.skip(false);
+                match self.bump() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Value::Object(table)),
+                    _ => return Err("expected ',' or '}' in an inline table".to_string()),
+                }
+            }
+        }
+
+        /// A boolean, integer or float
+        fn scalar(&mut self) -> Result<Value, String> {
+            let start = self.pos;
+            while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_')) {
+                self.pos += 1;
+            }
+            let token: String = self.chars[start..self.pos].iter().collect();
+            let number = token.replace('_', "");
+            let value = match token.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ if number.contains(['.', 'e', 'E']) => number
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or_else(|| format!("invalid value '{}'", token))?,
+                _ => number
+                    .parse::<i64>()
+                    .map(|number| Value::Number(number.into()))
+                    .map_err(|_| format!("invalid value '{}'", token))?,
+            };
+            Ok(value)
+        }
+    }
+
+    /// The table at `path`, created if it doesn't exist yet
+    fn descend<'a>(mut table: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>, String> {
+        for key in path {
+            table = match table.entry(key.clone()).or_insert_with(|| Value::Object(Map::new())) {
+                Value::Object(next) => next,
+                _ => return Err(format!("'{}' isn't a table", key)),
+            };
+        }
+        Ok(table)
+    }
+
+    fn insert(root: &mut Map<String, Value>, path: &[String], value: Value) -> Result<(), String> {
+        let (key, parents) = path.split_last().ok_or("expected a key")?;
+        let table = descend(root, parents)?;
+        if table.contains_key(key) {
+            return Err(format!("duplicate key '{}'", path.join(".")));
+        }
+        table.insert(key.clone(), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `text` to a file named `name` in a fresh temp directory
+    fn write(name: &str, text: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("lifx-config-{}-{}", std::process::id(), crate::random_u64()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = write("lifx.json", r#"{"token": "xxx", "endpoints": ["http://localhost:8089"], "timeout": 5.0, "retry": {"max_attempts": 3}}"#);
+        let config = LifxConfig::from_file(&path).unwrap();
+        assert_eq!(config.access_token, "xxx");
+        assert_eq!(config.api_endpoints, vec!["http://localhost:8089"]);
+        assert_eq!(config.timeout, Some(5.0));
+        assert_eq!(config.retry_policy, RetryPolicy { max_attempts: 3, ..Default::default() });
+
+        let minimal = LifxConfig::from_file(write("lifx.json", r#"{"token": "xxx"}"#)).unwrap();
+        assert_eq!(minimal.api_endpoints, vec!["https://api.lifx.com"]);
+    }
+
+    #[test]
+    fn test_from_toml_file() {
+        let text = r#"
+# Kitchen daemon
+token = "xxx"
+endpoints = [
+    "http://localhost:8089", # local proxy first
+    'https://api.lifx.com',
+]
+default_duration = 0.5
+timeout = 5
+
+[retry]
+max_attempts = 3
+jitter = 0.25
+"#;
+        let config = LifxConfig::from_file(write("lifx.toml", text)).unwrap();
+        assert_eq!(config.access_token, "xxx");
+        assert_eq!(config.api_endpoints, vec!["http://localhost:8089", "https://api.lifx.com"]);
+        assert_eq!(config.default_duration, Some(0.5));
+        assert_eq!(config.timeout, Some(5.0));
+        assert_eq!(config.retry_policy, RetryPolicy { max_attempts: 3, jitter: 0.25, ..Default::default() });
+
+        let inline = LifxConfig::from_file(write("lifx.toml", "token = \"x\\u0078x\"\nretry = { base_delay = 1.5 }\n")).unwrap();
+        assert_eq!(inline.access_token, "xxx");
+        assert_eq!(inline.retry_policy, RetryPolicy { base_delay: 1.5, ..Default::default() });
+    }
+
+    #[test]
+    fn test_from_file_errors() {
+        let typo = write("lifx.json", r#"{"token": "xxx", "timeuot": 5.0}"#);
+        assert!(matches!(LifxConfig::from_file(typo), Err(LifxError::Config(message)) if message.contains("timeuot")));
+        assert!(matches!(LifxConfig::from_file(write("lifx.yaml", "token: xxx")), Err(LifxError::Config(_))));
+        let toml_typo = write("lifx.toml", "token = \"xxx\"\n[retry]\nmax_atempts = 3\n");
+        assert!(matches!(LifxConfig::from_file(toml_typo), Err(LifxError::Config(message)) if message.contains("max_atempts")));
+        let unterminated = write("lifx.toml", "token = \"xxx\ntimeout = 5.0\n");
+        assert!(matches!(LifxConfig::from_file(unterminated), Err(LifxError::Config(message)) if message.contains("line 1: unterminated string")));
+        let duplicate = write("lifx.toml", "token = \"xxx\"\ntoken = \"yyy\"\n");
+        assert!(matches!(LifxConfig::from_file(duplicate), Err(LifxError::Config(message)) if message.contains("duplicate key 'token'")));
+        assert!(matches!(LifxConfig::from_file("/nonexistent/lifx.json"), Err(LifxError::Config(_))));
+    }
+
+    #[test]
+    fn test_from_file_validation() {
+        let no_endpoints = write("lifx.json", r#"{"token": "xxx", "endpoints": []}"#);
+        let message = LifxConfig::from_file(&no_endpoints).unwrap_err().to_string();
+        assert_eq!(message, format!("invalid config: {}: no API endpoints configured", no_endpoints.display()));
+        let negative_timeout = write("lifx.json", r#"{"token": "xxx", "timeout": -1.0}"#);
+        assert!(matches!(LifxConfig::from_file(negative_timeout), Err(LifxError::Config(message)) if message.contains("timeout")));
+        let zero_duration = write("lifx.json", r#"{"token": "xxx", "default_duration": 0.0}"#);
+        assert_eq!(LifxConfig::from_file(zero_duration).unwrap().default_duration, Some(0.0));
+        let negative_duration = write("lifx.json", r#"{"token": "xxx", "default_duration": -0.5}"#);
+        assert!(matches!(LifxConfig::from_file(negative_duration), Err(LifxError::Config(message)) if message.contains("default_duration")));
+    }
+}
@@ -0,0 +1,2712 @@
+//! The pure data types of the LIFX HTTP API and the logic that turns them into request params.
+//!
+//! Nothing in this module does any networking or depends on reqwest, so it builds with
+//! `default-features = false` for use over your own transport, ex: on an embedded target. The
+//! `client` feature sends these types to the API as JSON, through their Serialize impls, and
+//! `to_params` gives the same params as strings for form bodies.
+//!
+//! These types still use `String`, `Vec` and `HashMap` from std, so the target needs an allocator
+//! and std collections; what's left out is reqwest, tokio and any sockets.
+//!
+//! Everything here is re-exported from the crate root, so `lifx::State` and `lifx::core::State`
+//! are the same type.
+
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// Represents a LIFX Selector, used to choose which light(s) an operation applies to.
+/// See https://api.developer.lifx.com/docs/selectors
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Selector {
+    /// Every light belonging to the account
+    All,
+    /// A single light by its id (serial number)
+    Id(String),
+    /// Light(s) with the given label
+    Label(String),
+    /// Light(s) in the group with the given id
+    GroupId(String),
+    /// Light(s) in the group with the given name
+    Group(String),
+    /// Light(s) in the location with the given id
+    LocationId(String),
+    /// Light(s) in the location with the given name
+    Location(String),
+    /// Light(s) used by the scene with the given uuid
+    SceneId(String),
+}
+
+impl Selector {
+    /// Checks that a GroupId or LocationId holds a 32 character hex id, the form LIFX uses for
+    /// them, ex: 1c8de82b81f445e7cfaafae49b259c71. This catches passing a light id or a name where
+    /// a group or location id is expected, which the API otherwise rejects with a bare 422.
+    ///
+    /// Validation is opt-in: constructing or parsing a Selector never checks ids. Other selector
+    /// types are always valid.
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let group = lifx::Selector::GroupId(format!("1c8de82b81f445e7cfaafae49b259c71"));
+    ///     assert!(group.validate().is_ok());
+    /// 
+    ///     let light_id = lifx::Selector::GroupId(format!("d073d5000000"));
+    ///     assert!(light_id.validate().is_err());
+    /// }
+    ///  ```
+    pub fn validate(&self) -> Result<(), SelectorParseError> {
+        match self {
+            Selector::GroupId(id) | Selector::LocationId(id) => {
+                if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+                    Ok(())
+                } else {
+                    Err(SelectorParseError::InvalidId(id.clone()))
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selector::All => write!(f, "all"),
+            Selector::Id(id) => write!(f, "id:{}", id),
+            Selector::Label(label) => write!(f, "label:{}", label),
+            Selector::GroupId(id) => write!(f, "group_id:{}", id),
+            Selector::Group(name) => write!(f, "group:{}", name),
+            Selector::LocationId(id) => write!(f, "location_id:{}", id),
+            Selector::Location(name) => write!(f, "location:{}", name),
+            Selector::SceneId(id) => write!(f, "scene_id:{}", id),
+        }
+    }
+}
+
+impl From<Selector> for String {
+    fn from(selector: Selector) -> String {
+        selector.to_string()
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorParseError;
+
+    fn from_str(s: &str) -> Result<Selector, SelectorParseError> {
+        if s == "all" {
+            return Ok(Selector::All);
+        }
+
+        let (kind, value) = match s.split_once(':') {
+            Some((kind, value)) if !value.is_empty() => (kind, value.to_string()),
+            Some(_) => return Err(SelectorParseError::MissingValue(s.to_string())),
+            None => return Err(SelectorParseError::UnknownType(s.to_string())),
+        };
+
+        match kind {
+            "id" => Ok(Selector::Id(value)),
+            "label" => Ok(Selector::Label(value)),
+            "group_id" => Ok(Selector::GroupId(value)),
+            "group" => Ok(Selector::Group(value)),
+            "location_id" => Ok(Selector::LocationId(value)),
+            "location" => Ok(Selector::Location(value)),
+            "scene_id" => Ok(Selector::SceneId(value)),
+            _ => Err(SelectorParseError::UnknownType(kind.to_string())),
+        }
+    }
+}
+
+/// Errors returned when parsing a selector string into a [Selector]
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SelectorParseError {
+    /// The part before the `:` is not a known selector type
+    #[error("unknown selector type: `{0}`")]
+    UnknownType(String),
+    /// The selector type was given without a value, ex: `group_id:`
+    #[error("selector `{0}` is missing a value")]
+    MissingValue(String),
+    /// A group or location id that isn't 32 hex characters, from Selector::validate
+    #[error("`{0}` is not a valid id, expected 32 hex characters")]
+    InvalidId(String),
+}
+
+/// Represents an LIFX Color
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Color {
+    pub hue: Option<f64>,
+    pub saturation: Option<f64>,
+    pub kelvin: Option<i64>,
+    pub brightness: Option<f64>,
+    pub error: Option<String>,
+    pub errors: Option<Vec<Error>>,
+}
+impl Color {
+    /// Renders the set hue, saturation, brightness and kelvin components as a LIFX color string,
+    /// ex: "hue:120 saturation:1 kelvin:3500"
+    pub fn to_param_string(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(hue) = self.hue {
+            parts.push(format!("hue:{}", hue));
+        }
+        if let Some(saturation) = self.saturation {
+            parts.push(format!("saturation:{}", saturation));
+        }
+        if let Some(brightness) = self.brightness {
+            parts.push(format!("brightness:{}", brightness));
+        }
+        if let Some(kelvin) = self.kelvin {
+            parts.push(format!("kelvin:{}", kelvin));
+        }
+        parts.join(" ")
+    }
+
+    /// Returns a color from its hue in degrees (0-360), saturation and brightness (0.0-1.0) and
+    /// kelvin (1500-9000)
+    pub fn from_hsbk(hue: f64, saturation: f64, brightness: f64, kelvin: i64) -> Color {
+        Color { hue: Some(hue), saturation: Some(saturation), brightness: Some(brightness), kelvin: Some(kelvin), ..Default::default() }
+    }
+
+    /// Returns the white at `kelvin` degrees, ex: Color::kelvin(2700) for a warm white.
+    /// Brightness is left unset, so setting it leaves the light's brightness as it is.
+    pub fn kelvin(kelvin: i64) -> Color {
+        Color { saturation: Some(0.0), kelvin: Some(kelvin), ..Default::default() }
+    }
+
+    /// Returns the hue, saturation and brightness of an RGB color
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Color {
+        let (r, g, b) = (red as f64 / 255.0, green as f64 / 255.0, blue as f64 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        // Rounded so the color strings stay short, ex: "hue:30" rather than "hue:30.000000000000004"
+        let round = |value: f64, places: i32| (value * 10f64.powi(places)).round() / 10f64.powi(places);
+        Color { hue: Some(round(hue, 2)), saturation: Some(round(saturation, 4)), brightness: Some(round(max, 4)), ..Default::default() }
+    }
+
+    /// Parses a hex RGB color, ex: "#ff8800", "ff8800" or the short form "#f80"
+    pub fn from_hex(hex: &str) -> Result<Color, ColorParseError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || ColorParseError::InvalidHex(hex.to_string());
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+        match digits.len() {
+            6 => Ok(Color::from_rgb(channel(&digits[0..2])?, channel(&digits[2..4])?, channel(&digits[4..6])?)),
+            3 => {
+                let short = |i: usize| channel(&digits[i..i + 1]).map(|value| value * 17);
+                Ok(Color::from_rgb(short(0)?, short(1)?, short(2)?))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Returns the color as RGB. Unset components count as hue 0, saturation 0 and brightness 1,
+    /// so a kelvin-only white comes out as pure white; kelvin itself is ignored.
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        let hue = self.hue.unwrap_or(0.0).rem_euclid(360.0);
+        let saturation = self.saturation.unwrap_or(0.0).clamp(0.0, 1.0);
+        let value = self.brightness.unwrap_or(1.0).clamp(0.0, 1.0);
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let (r, g, b) = match (hue / 60.0) as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let channel = |c: f64| ((c + value - chroma) * 255.0).round() as u8;
+        (channel(r), channel(g), channel(b))
+    }
+
+    /// Returns the color as a hex RGB string, ex: "#ff8800", see to_rgb
+    pub fn to_hex(&self) -> String {
+        let (red, green, blue) = self.to_rgb();
+        format!("#{:02x}{:02x}{:02x}", red, green, blue)
+    }
+}
+
+/// Renders the color string, see Color::to_param_string
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_param_string())
+    }
+}
+
+impl FromStr for Color {
+    type Err = ColorParseError;
+
+    /// Parses a LIFX color string without asking the API, ex: "red saturation:0.5" or
+    /// "#ff8800 brightness:0.2". Components apply left to right, so later ones override what
+    /// earlier ones set. Values aren't range checked.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(ColorParseError::Empty);
+        }
+        let mut color = Color::default();
+        for part in s.split_whitespace() {
+            let invalid = || ColorParseError::InvalidComponent(part.to_string());
+            let number = |value: &str| value.parse::<f64>().map_err(|_| invalid());
+            let parsed = match part.split_once(':') {
+                Some(("hue", value)) => Color { hue: Some(number(value)?), ..Default::default() },
+                Some(("saturation", value)) => Color { saturation: Some(number(value)?), ..Default::default() },
+                Some(("brightness", value)) => Color { brightness: Some(number(value)?), ..Default::default() },
+                Some(("kelvin", value)) => Color { kelvin: Some(value.parse().map_err(|_| invalid())?), ..Default::default() },
+                Some(("rgb", value)) => {
+                    let channels: Vec<u8> = value.split(',').map(|channel| channel.parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+                    match channels[..] {
+                        [red, green, blue] => Color::from_rgb(red, green, blue),
+                        _ => return Err(invalid()),
+                    }
+                }
+                Some(_) => return Err(invalid()),
+                None if part.starts_with('#') => Color::from_hex(part)?,
+                None => part.parse::<NamedColor>()?.to_color(),
+            };
+            color = Color {
+                hue: parsed.hue.or(color.hue),
+                saturation: parsed.saturation.or(color.saturation),
+                brightness: parsed.brightness.or(color.brightness),
+                kelvin: parsed.kelvin.or(color.kelvin),
+                ..color
+            };
+        }
+        Ok(color)
+    }
+}
+
+/// Lets a Color be used wherever a color string is, ex: `State::builder().color(Color::kelvin(2700))`
+impl From<Color> for String {
+    fn from(color: Color) -> String {
+        color.to_param_string()
+    }
+}
+
+/// Errors returned when building a [Color] from a string
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ColorParseError {
+    /// Not a 3 or 6 digit hex color, ex: "#ff88"
+    #[error("`{0}` is not a hex color, expected #rrggbb or #rgb")]
+    InvalidHex(String),
+    /// Not one of the color names LIFX accepts, see NamedColor
+    #[error("unknown color name: `{0}`")]
+    UnknownName(String),
+    /// A part of a color string that isn't a name, hex color or known `key:value`, ex: "hue:red"
+    #[error("`{0}` is not a color string component")]
+    InvalidComponent(String),
+    /// A color string with nothing in it
+    #[error("empty color string")]
+    Empty,
+}
+
+/// The color names the LIFX HTTP API accepts in color strings.
+/// See https://api.developer.lifx.com/docs/colors
+///
+/// # Examples
+///
+/// ```
+/// extern crate lifx_rs as lifx;
+///
+/// fn main() {
+///     let mut state = lifx::State::new();
+///     state.color = lifx::NamedColor::Red.into();
+///     assert_eq!(state.color.as_deref(), Some("red"));
+///
+///     // The same color, without asking the API to validate "red"
+///     assert_eq!(lifx::NamedColor::Red.to_color().hue, Some(0.0));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NamedColor {
+    White,
+    Red,
+    Orange,
+    Yellow,
+    Cyan,
+    Green,
+    Blue,
+    Purple,
+    Pink,
+}
+
+impl NamedColor {
+    /// Every named color, in the order the API documents them
+    pub const ALL: [NamedColor; 9] = [
+        NamedColor::White,
+        NamedColor::Red,
+        NamedColor::Orange,
+        NamedColor::Yellow,
+        NamedColor::Cyan,
+        NamedColor::Green,
+        NamedColor::Blue,
+        NamedColor::Purple,
+        NamedColor::Pink,
+    ];
+
+    /// The name as it appears in a color string, ex: "cyan"
+    pub fn name(&self) -> &'static str {
+        match self {
+            NamedColor::White => "white",
+            NamedColor::Red => "red",
+            NamedColor::Orange => "orange",
+            NamedColor::Yellow => "yellow",
+            NamedColor::Cyan => "cyan",
+            NamedColor::Green => "green",
+            NamedColor::Blue => "blue",
+            NamedColor::Purple => "purple",
+            NamedColor::Pink => "pink",
+        }
+    }
+
+    /// The hue and saturation the API sets for this name. Like the name, it leaves brightness and
+    /// kelvin unset.
+    pub fn to_color(&self) -> Color {
+        let hue = match self {
+            NamedColor::White => return Color { hue: Some(0.0), saturation: Some(0.0), ..Default::default() },
+            NamedColor::Red => 0.0,
+            NamedColor::Orange => 36.0,
+            NamedColor::Yellow => 60.0,
+            NamedColor::Cyan => 180.0,
+            NamedColor::Green => 120.0,
+            NamedColor::Blue => 250.0,
+            NamedColor::Purple => 280.0,
+            NamedColor::Pink => 325.0,
+        };
+        Color { hue: Some(hue), saturation: Some(1.0), ..Default::default() }
+    }
+}
+
+impl fmt::Display for NamedColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for NamedColor {
+    type Err = ColorParseError;
+
+    /// Parses a color name, ignoring case, ex: "Cyan"
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        NamedColor::ALL
+            .iter()
+            .copied()
+            .find(|color| color.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| ColorParseError::UnknownName(name.to_string()))
+    }
+}
+
+impl From<NamedColor> for String {
+    fn from(color: NamedColor) -> String {
+        color.name().to_string()
+    }
+}
+
+/// For the color fields of the payloads, ex: `state.color = NamedColor::Red.into()`
+impl From<NamedColor> for Option<String> {
+    fn from(color: NamedColor) -> Option<String> {
+        Some(color.into())
+    }
+}
+
+impl From<NamedColor> for Color {
+    fn from(color: NamedColor) -> Color {
+        color.to_color()
+    }
+}
+
+/// Preset white temperatures, named as in the LIFX app
+///
+/// # Examples
+///
+/// ```
+/// extern crate lifx_rs as lifx;
+///
+/// fn main() {
+///     let evening: lifx::State = lifx::Kelvin::Incandescent.into();
+///     assert_eq!(evening.color.as_deref(), Some("kelvin:2700"));
+///
+///     let mut reading = lifx::State::new();
+///     reading.color = lifx::Kelvin::Daylight.into();
+///     reading.brightness = Some(0.8);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kelvin {
+    /// 1500K
+    Candlelight,
+    /// 2000K
+    Sunset,
+    /// 2500K
+    UltraWarm,
+    /// 2700K
+    Incandescent,
+    /// 3000K
+    Warm,
+    /// 3200K
+    NeutralWarm,
+    /// 3500K
+    Neutral,
+    /// 4000K
+    Cool,
+    /// 4500K
+    CoolDaylight,
+    /// 5000K
+    SoftDaylight,
+    /// 5600K
+    Daylight,
+    /// 6000K
+    NoonDaylight,
+    /// 6500K
+    BrightDaylight,
+    /// 7000K
+    CloudyDaylight,
+    /// 7500K
+    BlueDaylight,
+    /// 8000K
+    BlueOvercast,
+    /// 8500K
+    BlueWater,
+    /// 9000K
+    BlueIce,
+}
+
+impl Kelvin {
+    /// Every preset, warmest first
+    pub const ALL: [Kelvin; 18] = [
+        Kelvin::Candlelight,
+        Kelvin::Sunset,
+        Kelvin::UltraWarm,
+        Kelvin::Incandescent,
+        Kelvin::Warm,
+        Kelvin::NeutralWarm,
+        Kelvin::Neutral,
+        Kelvin::Cool,
+        Kelvin::CoolDaylight,
+        Kelvin::SoftDaylight,
+        Kelvin::Daylight,
+        Kelvin::NoonDaylight,
+        Kelvin::BrightDaylight,
+        Kelvin::CloudyDaylight,
+        Kelvin::BlueDaylight,
+        Kelvin::BlueOvercast,
+        Kelvin::BlueWater,
+        Kelvin::BlueIce,
+    ];
+
+    /// The temperature in kelvin, ex: 2700 for Incandescent
+    pub fn kelvin(&self) -> i64 {
+        match self {
+            Kelvin::Candlelight => 1500,
+            Kelvin::Sunset => 2000,
+            Kelvin::UltraWarm => 2500,
+            Kelvin::Incandescent => 2700,
+            Kelvin::Warm => 3000,
+            Kelvin::NeutralWarm => 3200,
+            Kelvin::Neutral => 3500,
+            Kelvin::Cool => 4000,
+            Kelvin::CoolDaylight => 4500,
+            Kelvin::SoftDaylight => 5000,
+            Kelvin::Daylight => 5600,
+            Kelvin::NoonDaylight => 6000,
+            Kelvin::BrightDaylight => 6500,
+            Kelvin::CloudyDaylight => 7000,
+            Kelvin::BlueDaylight => 7500,
+            Kelvin::BlueOvercast => 8000,
+            Kelvin::BlueWater => 8500,
+            Kelvin::BlueIce => 9000,
+        }
+    }
+
+    /// The name shown in the LIFX app, ex: "Cool Daylight"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kelvin::Candlelight => "Candlelight",
+            Kelvin::Sunset => "Sunset",
+            Kelvin::UltraWarm => "Ultra Warm",
+            Kelvin::Incandescent => "Incandescent",
+            Kelvin::Warm => "Warm",
+            Kelvin::NeutralWarm => "Neutral Warm",
+            Kelvin::Neutral => "Neutral",
+            Kelvin::Cool => "Cool",
+            Kelvin::CoolDaylight => "Cool Daylight",
+            Kelvin::SoftDaylight => "Soft Daylight",
+            Kelvin::Daylight => "Daylight",
+            Kelvin::NoonDaylight => "Noon Daylight",
+            Kelvin::BrightDaylight => "Bright Daylight",
+            Kelvin::CloudyDaylight => "Cloudy Daylight",
+            Kelvin::BlueDaylight => "Blue Daylight",
+            Kelvin::BlueOvercast => "Blue Overcast",
+            Kelvin::BlueWater => "Blue Water",
+            Kelvin::BlueIce => "Blue Ice",
+        }
+    }
+
+    /// The color string for this white, ex: "kelvin:2700". The API sets saturation to 0 along
+    /// with the kelvin.
+    pub fn to_param_string(&self) -> String {
+        format!("kelvin:{}", self.kelvin())
+    }
+
+    /// This white as a Color, see Color::kelvin
+    pub fn to_color(&self) -> Color {
+        Color::kelvin(self.kelvin())
+    }
+}
+
+impl fmt::Display for Kelvin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}K)", self.name(), self.kelvin())
+    }
+}
+
+impl From<Kelvin> for String {
+    fn from(kelvin: Kelvin) -> String {
+        kelvin.to_param_string()
+    }
+}
+
+/// For the color fields of the payloads, ex: `state.color = Kelvin::Warm.into()`
+impl From<Kelvin> for Option<String> {
+    fn from(kelvin: Kelvin) -> Option<String> {
+        Some(kelvin.into())
+    }
+}
+
+impl From<Kelvin> for Color {
+    fn from(kelvin: Kelvin) -> Color {
+        kelvin.to_color()
+    }
+}
+
+/// A State that only sets the color to this white
+impl From<Kelvin> for State {
+    fn from(kelvin: Kelvin) -> State {
+        State { color: kelvin.into(), ..Default::default() }
+    }
+}
+
+/// Used to set the duration/state of the HEV Clean array
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Clean {
+    /// Turn the device on / off
+    pub stop: Option<bool>,
+    /// Duration in seconds (leaving blank or 0 sets the default duration for the device)
+    pub duration: Option<i64>
+}
+impl Clean {
+    pub fn new() -> Self {
+        return Clean{
+            stop: None,
+            duration: None
+        };
+    }
+
+    /// Returns the params of this Clean as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.stop{
+            Some(stop) => params.push(("stop".to_string(), stop.to_string())),
+            None => {}
+        }
+        match &self.duration{
+            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
+            None => {}
+        }
+       
+        return params;
+    }
+
+
+}
+
+/// Used to descripe the state of an LIFX Light Source
+///
+/// Unset fields are left out of the JSON body, so in set_states() they fall back to the batch defaults
+/// instead of overriding them with null.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    /// The power state you want to set on the selector. on or off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power: Option<String>,
+    /// The color to set the light to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// The brightness level from 0.0 to 1.0. Overrides any brightness set in color (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<f64>,
+    /// How long in seconds you want the power action to take. Range: 0.0 – 3155760000.0 (100 years)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// The maximum brightness of the infrared channel from 0.0 to 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infrared: Option<f64>,
+    /// The selector to limit which light to use for set_states()
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector:  Option<String>,
+    /// Execute the query fast, without initial state checks and wait for no results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fast: Option<bool>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+}
+impl State {
+
+    /// Returns a new State object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut state = lifx::State::new();
+    ///     state.power = Some(format!("off"));
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return State{
+            power: None,
+            color: None,
+            brightness: None,
+            duration: None,
+            infrared: None,
+            selector: None,
+            fast: None,
+            extra_params: Vec::new()
+        };
+    }
+
+    /// Adds a param that this crate doesn't model yet, sent as a string next to the modeled ones.
+    /// This is an escape hatch for new API params; older servers that don't know a param may reject
+    /// the request. Extra params only apply to single requests: set_states() leaves them out.
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let state = lifx::State::new().raw_param("new_param", "1");
+    /// }
+    ///  ```
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns a State for a single 0-100 slider where 0 means off: 0 gives power "off" with no
+    /// brightness, and 1-100 give power "on" at that brightness. Values above 100 are treated as 100.
+    /// Turning the light off instead of sending brightness 0 avoids the dim glow some bulbs show at 0.
+    /// 
+    /// # Arguments
+    ///
+    /// * `value` - The slider position, 0 to 100
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let state = lifx::State::from_slider(40);
+    ///     assert_eq!(state.brightness, Some(0.4));
+    /// }
+    ///  ```
+    pub fn from_slider(value: u8) -> State {
+        let mut state = State::new();
+        if value == 0 {
+            state.power = Some("off".to_string());
+        } else {
+            state.power = Some("on".to_string());
+            state.brightness = Some(f64::from(value.min(100)) / 100.0);
+        }
+        state
+    }
+
+    /// Sets power to "on" when a color or brightness is set without an explicit power, so the change
+    /// is visible right away instead of being stored on a bulb that is off. An explicit power,
+    /// including "off", is left alone.
+    ///
+    /// The power on shares the state's duration: a bulb that was off fades up to the new color and
+    /// brightness over that duration rather than switching on at its old color first.
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let mut state = lifx::State::new();
+    ///     state.color = Some(format!("blue"));
+    ///     state.duration = Some(1.0);
+    /// 
+    ///     let state = state.ensure_on();
+    ///     assert_eq!(state.power, Some(format!("on")));
+    /// }
+    ///  ```
+    pub fn ensure_on(mut self) -> Self {
+        if self.power.is_none() && (self.color.is_some() || self.brightness.is_some()) {
+            self.power = Some("on".to_string());
+        }
+        self
+    }
+
+    /// Returns a copy of this State with only the listed fields kept and every other field set to None.
+    /// The selector and any extra_params are always kept, so a masked state still targets the same lights.
+    /// 
+    /// # Arguments
+    ///
+    /// * `fields` - The StateFields to keep
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let mut state = lifx::State::new();
+    ///     state.power = Some(format!("on"));
+    ///     state.color = Some(format!("blue"));
+    ///     state.brightness = Some(0.8);
+    /// 
+    ///     // Apply the color only
+    ///     let color_only = state.mask(&[lifx::StateField::Color]);
+    /// }
+    ///  ```
+    pub fn mask(&self, fields: &[StateField]) -> State {
+        let keep = |field: StateField| fields.contains(&field);
+        State {
+            power: if keep(StateField::Power) { self.power.clone() } else { None },
+            color: if keep(StateField::Color) { self.color.clone() } else { None },
+            brightness: if keep(StateField::Brightness) { self.brightness } else { None },
+            duration: if keep(StateField::Duration) { self.duration } else { None },
+            infrared: if keep(StateField::Infrared) { self.infrared } else { None },
+            selector: self.selector.clone(),
+            fast: if keep(StateField::Fast) { self.fast } else { None },
+            extra_params: self.extra_params.clone(),
+        }
+    }
+
+    /// Checks the fields against the ranges the API accepts: power "on" or "off", brightness and
+    /// infrared from 0 to 1, duration up to 100 years, and a color string that parses with
+    /// saturation and brightness from 0 to 1 and kelvin from 1500 to 9000. Every bad field is
+    /// listed in the error. Validation is opt-in: set_state sends whatever it's given.
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let state = lifx::State::builder().power("dim").brightness(1.5).build();
+    ///     let error = state.validate().unwrap_err();
+    ///     assert_eq!(error.fields.len(), 2);
+    ///     assert_eq!(error.fields[0].field, "power");
+    /// }
+    ///  ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut checks = Checks::default();
+        checks.power(&self.power);
+        if let Some(color) = &self.color {
+            match color.parse::<Color>() {
+                Ok(parsed) => {
+                    checks.range("color", "saturation ", parsed.saturation, 0.0, 1.0);
+                    checks.range("color", "brightness ", parsed.brightness, 0.0, 1.0);
+                    checks.range("color", "kelvin ", parsed.kelvin.map(|kelvin| kelvin as f64), 1500.0, 9000.0);
+                }
+                Err(err) => checks.invalid("color", err.to_string()),
+            }
+        }
+        checks.range("brightness", "", self.brightness, 0.0, 1.0);
+        checks.range("duration", "", self.duration, 0.0, MAX_DURATION);
+        checks.range("infrared", "", self.infrared, 0.0, 1.0);
+        checks.finish()
+    }
+
+    /// Fills in a default duration when no duration was set on this State
+    pub fn with_default_duration(mut self, default_duration: Option<f64>) -> Self {
+        if self.duration.is_none() {
+            self.duration = default_duration;
+        }
+        self
+    }
+
+    /// Returns the params of this State as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.power{
+            Some(power) => params.push(("power".to_string(), power.to_string())),
+            None => {}
+        }
+        match &self.color{
+            Some(color) => params.push(("color".to_string(), color.to_string())),
+            None => {}
+        }
+        match &self.brightness{
+            Some(brightness) => params.push(("brightness".to_string(), brightness.to_string())),
+            None => {}
+        }
+        match &self.duration{
+            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
+            None => {}
+        }
+        match &self.infrared{
+            Some(infrared) => params.push(("infrared".to_string(), infrared.to_string())),
+            None => {}
+        }
+        match &self.selector{
+            Some(selector) => params.push(("selector".to_string(), selector.to_string())),
+            None => {}
+        }
+        match &self.fast{
+            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
+            None => {}
+        }
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+
+}
+
+/// The longest duration the API accepts, 100 years in seconds
+const MAX_DURATION: f64 = 3_155_760_000.0;
+
+/// A field that failed State::validate or StateDelta::validate
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidField {
+    /// The param name, ex: "brightness"
+    pub field: &'static str,
+    /// What's wrong with it, ex: "1.5 is not between 0 and 1"
+    pub reason: String,
+}
+
+/// The error returned by State::validate and StateDelta::validate, with every field that is out
+/// of range in the order the fields are declared
+#[derive(Error, Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub fields: Vec<InvalidField>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: Vec<String> = self.fields.iter().map(|invalid| format!("invalid {}: {}", invalid.field, invalid.reason)).collect();
+        f.write_str(&fields.join(", "))
+    }
+}
+
+/// Collects the invalid fields found by a validate method
+#[derive(Default)]
+struct Checks(Vec<InvalidField>);
+
+impl Checks {
+    fn invalid(&mut self, field: &'static str, reason: String) {
+        self.0.push(InvalidField { field, reason });
+    }
+
+    fn power(&mut self, power: &Option<String>) {
+        if let Some(power) = power {
+            if power != "on" && power != "off" {
+                self.invalid("power", format!("`{}` is not on or off", power));
+            }
+        }
+    }
+
+    /// Flags `value` when it's outside min..=max (or NaN). `label` prefixes the reason, for
+    /// checking the components of a color string.
+    fn range(&mut self, field: &'static str, label: &str, value: Option<f64>, min: f64, max: f64) {
+        if let Some(value) = value {
+            if !(min..=max).contains(&value) {
+                self.invalid(field, format!("{}{} is not between {} and {}", label, value, min, max));
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), ValidationError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { fields: self.0 })
+        }
+    }
+}
+
+/// The settable fields of a State, used by State::mask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateField {
+    Power,
+    Color,
+    Brightness,
+    Duration,
+    Infrared,
+    Fast,
+}
+
+/// Used to set the params when posting a Toggle event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Toggle {
+    /// How long in seconds the power action should take. Fractional values (e.g. 0.5) give sub-second fades.
+    pub duration: Option<f64>
+}
+impl Toggle {
+    /// Returns a new Toggle object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut toggle = lifx::Toggle::new();
+    ///     toggle.duration = Some(0.5);
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return Toggle{
+            duration: None
+        };
+    }
+
+    /// Fills in a default duration when no duration was set on this Toggle
+    pub fn with_default_duration(mut self, default_duration: Option<f64>) -> Self {
+        if self.duration.is_none() {
+            self.duration = default_duration;
+        }
+        self
+    }
+
+    /// Returns the params of this Toggle as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.duration{
+            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
+            None => {}
+        }
+        return params;
+    }
+
+
+}
+
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[doc(hidden)]
+pub struct States {
+    pub states: Option<Vec<State>>,
+    pub defaults: Option<State>,
+}
+impl States {
+    /// Returns a new States object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut states = lifx::States::new();
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return States{
+            states: None,
+            defaults: None
+        };
+    }
+
+    /// Builds a States object from a map of selector to State, setting each state's selector from its key.
+    /// Entries are ordered by selector so the request body is stable.
+    /// 
+    /// # Arguments
+    ///
+    /// * `map` - The State to apply for each Selector
+    /// * `defaults` - Default values applied to every state that doesn't set them
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::collections::HashMap;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let mut on = lifx::State::new();
+    ///     on.power = Some(format!("on"));
+    /// 
+    ///     let mut off = lifx::State::new();
+    ///     off.power = Some(format!("off"));
+    /// 
+    ///     let mut map = HashMap::new();
+    ///     map.insert(lifx::Selector::Group(format!("Kitchen")), on);
+    ///     map.insert(lifx::Selector::Group(format!("Bedroom")), off);
+    /// 
+    ///     let states = lifx::States::from_map(map, None);
+    /// }
+    ///  ```
+    pub fn from_map(map: HashMap<Selector, State>, defaults: Option<State>) -> Self {
+        let mut states: Vec<State> = map.into_iter()
+            .map(|(selector, mut state)| {
+                state.selector = Some(selector.to_string());
+                state
+            })
+            .collect();
+        states.sort_by(|a, b| a.selector.cmp(&b.selector));
+
+        States {
+            states: Some(states),
+            defaults,
+        }
+    }
+
+    /// Sets `fast` on the batch defaults, keeping any other defaults already set.
+    /// A state that sets its own `fast` overrides the default; states that leave it unset inherit it.
+    /// 
+    /// # Arguments
+    ///
+    /// * `fast` - Whether entries without their own `fast` skip state checks and results
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let mut confirmed = lifx::State::new();
+    ///     confirmed.selector = Some(format!("id:d073d5"));
+    ///     confirmed.fast = Some(false);
+    /// 
+    ///     let mut states = lifx::States::new().set_default_fast(true);
+    ///     states.states = Some(vec![confirmed]);
+    /// }
+    ///  ```
+    pub fn set_default_fast(mut self, fast: bool) -> Self {
+        let mut defaults = self.defaults.take().unwrap_or_default();
+        defaults.fast = Some(fast);
+        self.defaults = Some(defaults);
+        self
+    }
+
+    /// Fills in a default duration on the defaults when no default duration was set.
+    /// Individual states with their own duration still override it.
+    pub fn with_default_duration(mut self, default_duration: Option<f64>) -> Self {
+        if default_duration.is_some() {
+            let defaults = self.defaults.take().unwrap_or_default();
+            self.defaults = Some(defaults.with_default_duration(default_duration));
+        }
+        self
+    }
+}
+
+/// Used to set the params when posting a StateDelta event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDelta {
+    /// The power state you want to set on the selector. on or off
+    pub power: Option<String>,
+    /// How long in seconds you want the power action to take. Range: 0.0 – 3155760000.0 (100 years)
+    pub duration: Option<f64>,
+    /// The maximum brightness of the infrared channel.
+    pub infrared: Option<f64>,
+    /// Rotate the hue by this angle in degrees. Range: -360.0 – 360.0 degrees
+    pub hue: Option<f64>,
+    /// Change the saturation by this additive amount; the resulting saturation is clipped to [0, 1].
+    pub saturation: Option<f64>,
+    /// Change the brightness by this additive amount; the resulting brightness is clipped to [0, 1].
+    pub brightness: Option<f64>,
+    /// Change the kelvin by this additive amount; the resulting kelvin is clipped to [2500, 9000].
+    pub kelvin: Option<i64>,
+    /// Execute the query fast, without initial state checks and wait for no results.
+    pub fast: Option<bool>,
+}
+impl StateDelta {
+    /// Returns a new StateDelta object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut delta = lifx::StateDelta::new();
+    ///     delta.duration = Some(0);
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return StateDelta{
+            power: None,
+            duration: None,
+            infrared: None,
+            hue: None,
+            saturation: None,
+            brightness: None,
+            kelvin: None,
+            fast: None
+        };
+    }
+
+    /// Checks the fields against the ranges the API accepts: power "on" or "off", duration up to
+    /// 100 years, infrared from 0 to 1, a hue change from -360 to 360, saturation and brightness
+    /// changes from -1 to 1 and a kelvin change from -7500 to 7500. Every bad field is listed in
+    /// the error, see State::validate.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let mut checks = Checks::default();
+        checks.power(&self.power);
+        checks.range("duration", "", self.duration, 0.0, MAX_DURATION);
+        checks.range("infrared", "", self.infrared, 0.0, 1.0);
+        checks.range("hue", "", self.hue, -360.0, 360.0);
+        checks.range("saturation", "", self.saturation, -1.0, 1.0);
+        checks.range("brightness", "", self.brightness, -1.0, 1.0);
+        checks.range("kelvin", "", self.kelvin.map(|kelvin| kelvin as f64), -7500.0, 7500.0);
+        checks.finish()
+    }
+
+    /// Fills in a default duration when no duration was set on this StateDelta
+    pub fn with_default_duration(mut self, default_duration: Option<f64>) -> Self {
+        if self.duration.is_none() {
+            self.duration = default_duration;
+        }
+        self
+    }
+
+    /// Returns the params of this StateDelta as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.power{
+            Some(power) => params.push(("power".to_string(), power.to_string())),
+            None => {}
+        }
+
+        match &self.duration{
+            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
+            None => {}
+        }
+
+        match &self.infrared{
+            Some(infrared) => params.push(("infrared".to_string(), infrared.to_string())),
+            None => {}
+        }
+
+        match &self.hue{
+            Some(hue) => params.push(("hue".to_string(), hue.to_string())),
+            None => {}
+        }
+
+        match &self.saturation{
+            Some(saturation) => params.push(("saturation".to_string(), saturation.to_string())),
+            None => {}
+        }
+
+        match &self.brightness{
+            Some(brightness) => params.push(("brightness".to_string(), brightness.to_string())),
+            None => {}
+        }
+
+        match &self.kelvin{
+            Some(kelvin) => params.push(("kelvin".to_string(), kelvin.to_string())),
+            None => {}
+        }
+
+        match &self.fast{
+            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
+            None => {}
+        }
+
+        return params;
+    }
+
+}
+
+/// Used to set the params when posting a BreatheEffect event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct BreatheEffect {
+    /// The color to use for the breathe effect.
+    pub color: Option<String>,
+    /// The color to start the effect from. If this parameter is omitted then the color the bulb is currently set to is used instead.
+    pub from_color: Option<String>,
+    /// The time in seconds for one cycle of the effect.
+    pub period: Option<f64>,
+    /// The number of times to repeat the effect.
+    pub cycles: Option<f64>,
+    /// If false set the light back to its previous value when effect ends, if true leave the last effect color.
+    pub persist: Option<bool>,
+    /// If true, turn the bulb on if it is not already on.
+    pub power_on: Option<bool>,
+    /// Defines where in a period the target color is at its maximum. Minimum 0.0, maximum 1.0.
+    pub peak: Option<f64>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+    /// Stops the effect with effects_off this long after it starts, see with_auto_off
+    #[serde(skip)]
+    pub auto_off: Option<Duration>,
+}
+impl BreatheEffect {
+    /// Returns a new BreatheEffect object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut breathe = lifx::BreatheEffect::new();
+    ///     breathe.color = Some(format!("red"));
+    ///     breathe.from_color = Some(format!("green"));
+    ///     breathe.period = Some(10);
+    ///     breathe.persist = Some(true);
+    ///     breathe.power_on = Some(true);
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return BreatheEffect{
+            color: None,
+            from_color: None,
+            period: None,
+            cycles: None,
+            persist: None,
+            power_on: None,
+            peak: None,
+            extra_params: Vec::new(),
+            auto_off: None
+        };
+    }
+
+    /// Adds a param that this crate doesn't model yet, see State::raw_param
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns a BreatheEffect that breathes continuously between two colors: each cycle goes from
+    /// `color_a` to `color_b` and back to `color_a` over `period` seconds.
+    ///
+    /// The API has no infinite cycle count, so cycles is set to OSCILLATE_CYCLES, which at one second
+    /// per cycle runs for over 30 years. Stop it with an effects_off call. persist is false so the bulb
+    /// returns to its previous color once the effect is stopped.
+    /// 
+    /// # Arguments
+    ///
+    /// * `color_a` - The color each cycle starts and ends on
+    /// * `color_b` - The color each cycle breathes to
+    /// * `period` - The time in seconds for one A to B and back cycle
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let breathe = lifx::BreatheEffect::oscillate("blue", "purple", 4.0);
+    /// }
+    ///  ```
+    pub fn oscillate(color_a: &str, color_b: &str, period: f64) -> Self {
+        BreatheEffect {
+            color: Some(color_b.to_string()),
+            from_color: Some(color_a.to_string()),
+            period: Some(period),
+            cycles: Some(Self::OSCILLATE_CYCLES),
+            persist: Some(false),
+            ..Self::new()
+        }
+    }
+
+    /// The cycle count BreatheEffect::oscillate uses in place of an unbounded effect
+    pub const OSCILLATE_CYCLES: f64 = 1_000_000_000.0;
+
+    /// Returns the params of this BreatheEffect as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.color{
+            Some(color) => params.push(("color".to_string(), color.to_string())),
+            None => {}
+        }
+
+        match &self.from_color{
+            Some(from_color) => params.push(("from_color".to_string(), from_color.to_string())),
+            None => {}
+        }
+
+        match &self.period{
+            Some(period) => params.push(("period".to_string(), period.to_string())),
+            None => {}
+        }
+
+        match &self.cycles{
+            Some(cycles) => params.push(("cycles".to_string(), cycles.to_string())),
+            None => {}
+        }
+
+        match &self.persist{
+            Some(persist) => params.push(("persist".to_string(), persist.to_string())),
+            None => {}
+        }
+
+        match &self.power_on{
+            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
+            None => {}
+        }
+
+        match &self.peak{
+            Some(peak) => params.push(("peak".to_string(), peak.to_string())),
+            None => {}
+        }
+
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+}
+
+/// Used to set the params when posting a MoveEffect event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MoveEffect {
+    /// Move direction, forward or backward. See Direction.
+    pub direction: Option<String>,
+    /// The time in seconds for one cycle of the effect. Fractional values are allowed.
+    pub period: Option<f64>,
+    /// The number of times to repeat the effect.
+    pub cycles: Option<f64>,
+    /// If true, turn the bulb on if it is not already on.
+    pub power_on: Option<bool>,
+    /// Execute the query fast, without initial state checks and wait for no results.
+    pub fast: Option<bool>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+    /// Stops the effect with effects_off this long after it starts, see with_auto_off
+    #[serde(skip)]
+    pub auto_off: Option<Duration>,
+}
+impl MoveEffect {
+    /// Returns a new MoveEffect object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut move_effect = lifx::MoveEffect::new();
+    ///     move_effect.direction = Some(format!("forward")); // or backward
+    ///     move_effect.period = Some(10.0);
+    ///     move_effect.cycles = Some(0.9);
+    ///     move_effect.power_on = Some(true);
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return MoveEffect{
+            direction: None,
+            period: None,
+            cycles: None,
+            power_on: None,
+            fast: None,
+            extra_params: Vec::new(),
+            auto_off: None
+        };
+    }
+
+    /// Returns a MoveEffect that sweeps across the strip exactly once over `duration` seconds.
+    ///
+    /// One cycle of the move effect shifts the pattern along the whole strip once and takes `period`
+    /// seconds, so a single pass is cycles = 1 with period = duration. power_on is set so the sweep is
+    /// visible on a strip that is off.
+    /// 
+    /// # Arguments
+    ///
+    /// * `direction` - Which way to sweep
+    /// * `duration` - How long the sweep takes in seconds
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let wipe = lifx::MoveEffect::single_pass(lifx::Direction::Forward, 2.5);
+    /// }
+    ///  ```
+    pub fn single_pass(direction: Direction, duration: f64) -> Self {
+        MoveEffect {
+            direction: Some(direction.to_string()),
+            period: Some(duration),
+            cycles: Some(1.0),
+            power_on: Some(true),
+            ..Self::new()
+        }
+    }
+
+    /// Adds a param that this crate doesn't model yet, see State::raw_param
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the params of this MoveEffect as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.direction{
+            Some(direction) => params.push(("direction".to_string(), direction.to_string())),
+            None => {}
+        }
+
+        match &self.period{
+            Some(period) => params.push(("period".to_string(), period.to_string())),
+            None => {}
+        }
+
+        match &self.cycles{
+            Some(cycles) => params.push(("cycles".to_string(), cycles.to_string())),
+            None => {}
+        }
+
+        match &self.power_on{
+            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
+            None => {}
+        }
+
+        match &self.fast{
+            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
+            None => {}
+        }
+
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+}
+
+/// The direction of a MoveEffect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Forward => write!(f, "forward"),
+            Direction::Backward => write!(f, "backward"),
+        }
+    }
+}
+
+/// The most colors a morph effect palette can hold
+pub(crate) const MAX_MORPH_PALETTE: usize = 16;
+
+/// Errors returned by MorphEffect::validate
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PaletteError {
+    /// A palette with no colors. Leave the palette unset to use the device's own.
+    #[error("the morph palette is empty")]
+    Empty,
+    /// More colors than a palette can hold
+    #[error("the morph palette has {0} colors, it can hold at most 16")]
+    TooManyColors(usize),
+    /// A palette entry that isn't a color string
+    #[error("morph palette color {index} is invalid: {error}")]
+    InvalidColor { index: usize, error: ColorParseError },
+}
+
+/// Used to set the params when posting a MorphEffect event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MorphEffect {
+    /// The time in seconds for one cycle of the effect.
+    pub period: Option<f64>,
+    /// How long the animation lasts for in seconds. Not specifying a duration makes the animation never stop. Specifying 0 makes the animation stop. Note that there is a known bug where the tile remains in the animation once it has completed if duration is nonzero.
+    pub duration: Option<f64>,
+    /// You can control the colors in the animation by specifying a list of color specifiers. For example ["red", "hue:100 saturation:1"]. See https://api.developer.lifx.com/docs/colors
+    pub palette: Option<Vec<String>>,
+    /// If true, turn the bulb on if it is not already on.
+    pub power_on: Option<bool>,
+    /// Execute the query fast, without initial state checks and wait for no results.
+    pub fast: Option<bool>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+    /// Stops the effect with effects_off this long after it starts, see with_auto_off
+    #[serde(skip)]
+    pub auto_off: Option<Duration>,
+}
+impl MorphEffect {
+    /// Returns a new MorphEffect object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut morph_effect = lifx::MorphEffect::new();
+    ///     morph_effect.period = Some(10);
+    ///     morph_effect.duration = Some(0);
+    /// 
+    ///     let mut palette: Vec<String> = Vec::new();
+    ///     palette.push("red");
+    ///     palette.push("green");
+    /// 
+    ///     morph_effect.palette = Some(palette);
+    ///     morph_effect.power_on = Some(true);
+    /// 
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return MorphEffect{
+            period: None,
+            duration: None,
+            palette: None,
+            power_on: None,
+            fast: None,
+            extra_params: Vec::new(),
+            auto_off: None
+        };
+    }
+
+    /// Returns a MorphEffect whose palette repeats each color by its weight, so heavier colors
+    /// take up more of the animation. The API only takes a flat list, so repetition is how the
+    /// weighting reaches the device.
+    /// If the weights add up to more than the 16 colors a palette can hold, they are scaled down
+    /// proportionally (every color with a nonzero weight keeps at least one slot) and the palette is
+    /// cut at 16. Colors with weight 0 are left out.
+    /// 
+    /// # Arguments
+    ///
+    /// * `colors` - Each Color with its weight
+    /// * `period` - The time in seconds for one cycle of the effect
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let blue = lifx::Color { hue: Some(240.0), saturation: Some(1.0), ..Default::default() };
+    ///     let white = lifx::Color { saturation: Some(0.0), kelvin: Some(6500), ..Default::default() };
+    /// 
+    ///     // Mostly blue with the occasional white flash
+    ///     let morph_effect = lifx::MorphEffect::from_weighted(&[(blue, 7), (white, 1)], 5.0);
+    /// }
+    ///  ```
+    pub fn from_weighted(colors: &[(Color, u8)], period: f64) -> Self {
+        let total: usize = colors.iter().map(|(_, weight)| *weight as usize).sum();
+        let mut palette: Vec<String> = Vec::new();
+        for (color, weight) in colors.iter().filter(|(_, weight)| *weight > 0) {
+            let count = if total > MAX_MORPH_PALETTE {
+                (*weight as usize * MAX_MORPH_PALETTE / total).max(1)
+            } else {
+                *weight as usize
+            };
+            palette.extend(std::iter::repeat_n(color.to_param_string(), count));
+        }
+        palette.truncate(MAX_MORPH_PALETTE);
+
+        let mut morph_effect = MorphEffect::new();
+        morph_effect.period = Some(period);
+        morph_effect.palette = Some(palette);
+        morph_effect
+    }
+
+    /// Checks the palette before it's sent: it must hold between 1 and 16 colors, each a color
+    /// string Color::from_str can parse. An unset palette is valid. Light::morph_effect and
+    /// friends run this check and return LifxError::Palette without sending anything.
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let mut morph_effect = lifx::MorphEffect::new();
+    ///     morph_effect.palette = Some(vec!["red".to_string(), "hue:120 saturation:1".to_string()]);
+    ///     assert!(morph_effect.validate().is_ok());
+    /// 
+    ///     morph_effect.palette = Some(vec!["red".to_string(), "reddish".to_string()]);
+    ///     assert!(morph_effect.validate().is_err());
+    /// }
+    ///  ```
+    pub fn validate(&self) -> Result<(), PaletteError> {
+        let palette = match &self.palette {
+            Some(palette) => palette,
+            None => return Ok(()),
+        };
+        if palette.is_empty() {
+            return Err(PaletteError::Empty);
+        }
+        if palette.len() > MAX_MORPH_PALETTE {
+            return Err(PaletteError::TooManyColors(palette.len()));
+        }
+        for (index, color) in palette.iter().enumerate() {
+            color.parse::<Color>().map_err(|error| PaletteError::InvalidColor { index, error })?;
+        }
+        Ok(())
+    }
+
+    /// Adds a param that this crate doesn't model yet, see State::raw_param
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the params of this MorphEffect as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.period{
+            Some(period) => params.push(("period".to_string(), period.to_string())),
+            None => {}
+        }
+
+        match &self.duration{
+            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
+            None => {}
+        }
+
+        match &self.palette{
+            Some(palette) => params.push(("palette".to_string(), string_vec_to_params(palette.to_vec()))),
+            None => {}
+        }
+
+        match &self.power_on{
+            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
+            None => {}
+        }
+
+        match &self.fast{
+            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
+            None => {}
+        }
+
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+}
+
+
+
+/// Used to set the params when posting a PulseEffect event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PulseEffect {
+    /// The color to use for the breathe effect.
+    pub color: Option<String>,
+    /// The color to start the effect from. If this parameter is omitted then the color the bulb is currently set to is used instead.
+    pub from_color: Option<String>,
+    /// The time in seconds for one cycle of the effect.
+    pub period: Option<f64>,
+    /// The number of times to repeat the effect.
+    pub cycles: Option<f64>,
+    /// If false set the light back to its previous value when effect ends, if true leave the last effect color.
+    pub persist: Option<bool>,
+    /// If true, turn the bulb on if it is not already on.
+    pub power_on: Option<bool>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+    /// Stops the effect with effects_off this long after it starts, see with_auto_off
+    #[serde(skip)]
+    pub auto_off: Option<Duration>,
+}
+impl PulseEffect {
+    /// Returns a new PulseEffect object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut pulse = lifx::PulseEffect::new();
+    ///     pulse.color = Some(format!("red"));
+    ///     pulse.from_color = Some(format!("green"));
+    ///     pulse.period = Some(10);
+    ///     pulse.persist = Some(true);
+    ///     pulse.power_on = Some(true);
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return PulseEffect{
+            color: None,
+            from_color: None,
+            period: None,
+            cycles: None,
+            persist: None,
+            power_on: None,
+            extra_params: Vec::new(),
+            auto_off: None
+        };
+    }
+
+    /// Adds a param that this crate doesn't model yet, see State::raw_param
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the params of this PulseEffect as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.color{
+            Some(color) => params.push(("color".to_string(), color.to_string())),
+            None => {}
+        }
+
+        match &self.from_color{
+            Some(from_color) => params.push(("from_color".to_string(), from_color.to_string())),
+            None => {}
+        }
+
+        match &self.period{
+            Some(period) => params.push(("period".to_string(), period.to_string())),
+            None => {}
+        }
+
+        match &self.cycles{
+            Some(cycles) => params.push(("cycles".to_string(), cycles.to_string())),
+            None => {}
+        }
+
+        match &self.persist{
+            Some(persist) => params.push(("persist".to_string(), persist.to_string())),
+            None => {}
+        }
+
+        match &self.power_on{
+            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
+            None => {}
+        }
+
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+}
+
+/// Used to set the params when posting a EffectsOff event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct EffectsOff {
+    /// If true, the devices will also be turned off
+    pub power_off: Option<bool>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+}
+impl EffectsOff {
+    /// Returns a new EffectsOff object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut ef = lifx::EffectsOff::new();
+    ///     ef.power_off = Some(true);
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return EffectsOff{
+            power_off: None,
+            extra_params: Vec::new()
+        };
+    }
+
+    /// Adds a param that this crate doesn't model yet, see State::raw_param
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the params of this EffectsOff as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.power_off{
+            Some(power_off) => params.push(("power_off".to_string(), power_off.to_string())),
+            None => {}
+        }
+
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+}
+
+
+
+/// Used to set the params when posting a FlameEffect event
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FlameEffect {
+    /// The time in seconds for one cycle of the effect.
+    pub period: Option<i64>,
+    /// How long the animation lasts for in seconds. Not specifying a duration makes the animation never stop. Specifying 0 makes the animation stop. Note that there is a known bug where the tile remains in the animation once it has completed if duration is nonzero.
+    pub duration: Option<f64>,
+    /// If true, turn the bulb on if it is not already on.
+    pub power_on: Option<bool>,
+    /// Execute the query fast, without initial state checks and wait for no results.
+    pub fast: Option<bool>,
+    /// Extra params sent as strings next to the modeled ones, see raw_param
+    #[serde(skip)]
+    pub extra_params: Vec<(String, String)>,
+    /// Stops the effect with effects_off this long after it starts, see with_auto_off
+    #[serde(skip)]
+    pub auto_off: Option<Duration>,
+}
+impl FlameEffect {
+    /// Returns a new FlameEffect object
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut flame_effect = lifx::FlameEffect::new();
+    ///     flame_effect.period = Some(10);
+    ///     flame_effect.duration = Some(0);
+    ///     flame_effect.power_on = Some(true);
+    /// 
+    /// }
+    ///  ```
+    pub fn new() -> Self {
+        return FlameEffect{
+            period: None,
+            duration: None,
+            power_on: None,
+            fast: None,
+            extra_params: Vec::new(),
+            auto_off: None
+        };
+    }
+
+    /// Adds a param that this crate doesn't model yet, see State::raw_param
+    pub fn raw_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the params of this FlameEffect as strings, in field order
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        let mut params: Vec<(String, String)> = vec![];
+        match &self.period{
+            Some(period) => params.push(("period".to_string(), period.to_string())),
+            None => {}
+        }
+
+        match &self.duration{
+            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
+            None => {}
+        }
+
+        match &self.power_on{
+            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
+            None => {}
+        }
+
+        match &self.fast{
+            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
+            None => {}
+        }
+
+        params.extend(self.extra_params.iter().cloned());
+        return params;
+    }
+
+}
+
+/// Used to set the params when activating a scene, see Scene::activate
+///
+/// Unset fields are left out, so the scene is applied as it was saved.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SceneActivation {
+    /// How long in seconds the lights take to reach the scene
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Parts of the scene's states to leave alone, ex: SceneIgnore::Power to restore colors
+    /// without turning any lights on or off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<SceneIgnore>>,
+    /// A state applied over every state in the scene, ex: a lower brightness
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<State>,
+    /// Execute the query fast, without initial state checks and wait for no results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fast: Option<bool>,
+}
+
+impl SceneActivation {
+    /// Returns a new SceneActivation that applies the scene as it was saved
+    pub fn new() -> Self {
+        SceneActivation::default()
+    }
+}
+
+/// A part of a scene's states that SceneActivation::ignore leaves alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SceneIgnore {
+    Power,
+    Infrared,
+    Duration,
+    Intensity,
+    Hue,
+    Saturation,
+    Brightness,
+    Kelvin,
+}
+
+/// Any one of the effects, for APIs that take an effect of either kind, ex: Light::effect_window
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnyEffect {
+    Breathe(BreatheEffect),
+    Move(MoveEffect),
+    Morph(MorphEffect),
+    Pulse(PulseEffect),
+    Flame(FlameEffect),
+}
+
+impl AnyEffect {
+    /// Returns the form params of the wrapped effect
+    pub fn to_params(&self) -> Vec<(String, String)> {
+        match self {
+            AnyEffect::Breathe(breathe) => breathe.to_params(),
+            AnyEffect::Move(move_effect) => move_effect.to_params(),
+            AnyEffect::Morph(morph_effect) => morph_effect.to_params(),
+            AnyEffect::Pulse(pulse_effect) => pulse_effect.to_params(),
+            AnyEffect::Flame(flame_effect) => flame_effect.to_params(),
+        }
+    }
+}
+
+/// Renders a list of strings as a JSON array, ex: the palette param of a MorphEffect
+pub fn string_vec_to_params(input: Vec<String>) -> String {
+    serde_json::to_string(&input).expect("strings always serialize")
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[doc(hidden)]
+pub struct Error {
+    pub field: String,
+    pub message: Vec<String>,
+}
+
+/// The request allowance the LIFX HTTP API reports in the X-RateLimit headers of its responses.
+///
+/// The API allows each access token a fixed number of requests per window (120 per 60 seconds at
+/// the time of writing) and answers 429 Too Many Requests once they're used up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of requests allowed per window, from X-RateLimit-Limit if the API sent it
+    pub limit: Option<u32>,
+    /// The number of requests left in the current window, from X-RateLimit-Remaining
+    pub remaining: u32,
+    /// When the current window ends and the allowance resets, from X-RateLimit-Reset
+    pub reset: SystemTime,
+}
+
+impl RateLimit {
+    /// Returns true if no requests are left in the current window
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// How long to wait from `now` until requests are allowed again. None if some are left, or the
+    /// window has already reset.
+    pub fn wait_time(&self, now: SystemTime) -> Option<Duration> {
+        if !self.is_exhausted() {
+            return None;
+        }
+        self.reset.duration_since(now).ok().filter(|wait| !wait.is_zero())
+    }
+}
+
+/// Generates a fluent builder for a payload: `X::builder()` starts from an empty payload, each
+/// setter fills in one Option field, and `build()` (or `.into()`) returns the payload.
+/// Payloads with extra_params also get a raw_param setter, see State::raw_param.
+macro_rules! payload_builder {
+    ($payload:ident, $builder:ident { $( $field:ident: $t:ty ),* $(,)? } $(, $raw:ident)?) => {
+        #[doc = concat!("A fluent builder for ", stringify!($payload), ", see ", stringify!($payload), "::builder")]
+        #[derive(Default, Debug, Clone, PartialEq)]
+        pub struct $builder($payload);
+
+        impl $payload {
+            #[doc = concat!("Returns a builder for a ", stringify!($payload), " with nothing set")]
+            pub fn builder() -> $builder {
+                $builder($payload::default())
+            }
+        }
+
+        impl $builder {
+            $(
+                #[doc = concat!("Sets `", stringify!($field), "`")]
+                pub fn $field(mut self, $field: impl Into<$t>) -> Self {
+                    self.0.$field = Some($field.into());
+                    self
+                }
+            )*
+
+            $(
+                /// Adds a param that this crate doesn't model yet, see State::raw_param
+                pub fn $raw(mut self, key: &str, value: &str) -> Self {
+                    self.0.extra_params.push((key.to_string(), value.to_string()));
+                    self
+                }
+            )?
+
+            #[doc = concat!("Returns the built ", stringify!($payload))]
+            pub fn build(self) -> $payload {
+                self.0
+            }
+        }
+
+        impl From<$builder> for $payload {
+            fn from(builder: $builder) -> Self {
+                builder.0
+            }
+        }
+    };
+}
+
+/// Adds setters that take a std::time::Duration to a payload builder: `setter => field: f64` for
+/// fields in fractional seconds, `setter => field: i64` for whole seconds, rounded up
+macro_rules! duration_setters {
+    ($builder:ident { $( $setter:ident => $field:ident: $kind:ident ),* $(,)? }) => {
+        impl $builder {
+            $(
+                #[doc = concat!("Sets `", stringify!($field), "` from a Duration")]
+                pub fn $setter(mut self, $field: Duration) -> Self {
+                    self.0.$field = Some(duration_setters!(@seconds $kind, $field));
+                    self
+                }
+            )*
+        }
+    };
+    (@seconds f64, $duration:expr) => { $duration.as_secs_f64() };
+    (@seconds i64, $duration:expr) => { whole_seconds($duration) };
+}
+
+/// Adds with_auto_off to effect payloads and their builders, see BreatheEffect::with_auto_off
+macro_rules! auto_off {
+    ($( $effect:ident => $builder:ident ),* $(,)?) => {
+        $(
+            impl $effect {
+                /// Stops the effect with an effects_off on the same selector once `after` has passed
+                /// since it started, instead of leaving it running until something else stops it.
+                /// The effects_off is sent from a spawned tokio task (or thread, for the blocking
+                /// API) and its result is discarded.
+                pub fn with_auto_off(mut self, after: Duration) -> Self {
+                    self.auto_off = Some(after);
+                    self
+                }
+            }
+
+            impl $builder {
+                #[doc = concat!("Sets `auto_off`, see ", stringify!($effect), "::with_auto_off")]
+                pub fn with_auto_off(mut self, after: Duration) -> Self {
+                    self.0.auto_off = Some(after);
+                    self
+                }
+            }
+        )*
+    };
+}
+
+/// Rounds a duration up to whole seconds, so a fraction of a second isn't lost to 0, which the
+/// API reads as "use the default"
+fn whole_seconds(duration: Duration) -> i64 {
+    let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+    i64::try_from(seconds).unwrap_or(i64::MAX)
+}
+
+payload_builder!(State, StateBuilder {
+    power: String, color: String, brightness: f64, duration: f64, infrared: f64, selector: String, fast: bool,
+}, raw_param);
+payload_builder!(States, StatesBuilder { states: Vec<State>, defaults: State });
+payload_builder!(StateDelta, StateDeltaBuilder {
+    power: String, duration: f64, infrared: f64, hue: f64, saturation: f64, brightness: f64, kelvin: i64, fast: bool,
+});
+payload_builder!(Toggle, ToggleBuilder { duration: f64 });
+payload_builder!(Clean, CleanBuilder { stop: bool });
+payload_builder!(SceneActivation, SceneActivationBuilder {
+    duration: f64, ignore: Vec<SceneIgnore>, overrides: State, fast: bool,
+});
+payload_builder!(BreatheEffect, BreatheEffectBuilder {
+    color: String, from_color: String, period: f64, cycles: f64, persist: bool, power_on: bool, peak: f64,
+}, raw_param);
+payload_builder!(MoveEffect, MoveEffectBuilder {
+    direction: String, period: f64, cycles: f64, power_on: bool, fast: bool,
+}, raw_param);
+payload_builder!(MorphEffect, MorphEffectBuilder {
+    period: f64, duration: f64, palette: Vec<String>, power_on: bool, fast: bool,
+}, raw_param);
+payload_builder!(PulseEffect, PulseEffectBuilder {
+    color: String, from_color: String, period: f64, cycles: f64, persist: bool, power_on: bool,
+}, raw_param);
+payload_builder!(EffectsOff, EffectsOffBuilder { power_off: bool }, raw_param);
+payload_builder!(FlameEffect, FlameEffectBuilder {
+    period: i64, duration: f64, power_on: bool, fast: bool,
+}, raw_param);
+
+duration_setters!(StateBuilder { duration_of => duration: f64 });
+duration_setters!(StateDeltaBuilder { duration_of => duration: f64 });
+duration_setters!(ToggleBuilder { duration_of => duration: f64 });
+duration_setters!(CleanBuilder { duration_of => duration: i64 });
+duration_setters!(SceneActivationBuilder { duration_of => duration: f64 });
+duration_setters!(BreatheEffectBuilder { period_of => period: f64 });
+duration_setters!(MoveEffectBuilder { period_of => period: f64 });
+duration_setters!(MorphEffectBuilder { period_of => period: f64, duration_of => duration: f64 });
+duration_setters!(PulseEffectBuilder { period_of => period: f64 });
+duration_setters!(FlameEffectBuilder { period_of => period: i64, duration_of => duration: f64 });
+
+auto_off!(
+    BreatheEffect => BreatheEffectBuilder,
+    MoveEffect => MoveEffectBuilder,
+    MorphEffect => MorphEffectBuilder,
+    PulseEffect => PulseEffectBuilder,
+    FlameEffect => FlameEffectBuilder,
+);
+
+impl StateBuilder {
+    /// Sets power to "on"
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    ///     let state = lifx::State::builder().power_on().brightness(0.8).build();
+    ///     assert_eq!(state.power.as_deref(), Some("on"));
+    /// }
+    ///  ```
+    pub fn power_on(self) -> Self {
+        self.power("on")
+    }
+
+    /// Sets power to "off"
+    pub fn power_off(self) -> Self {
+        self.power("off")
+    }
+}
+
+impl StateDeltaBuilder {
+    /// Sets power to "on"
+    pub fn power_on(self) -> Self {
+        self.power("on")
+    }
+
+    /// Sets power to "off"
+    pub fn power_off(self) -> Self {
+        self.power("off")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_color_from_str() {
+        let color: Color = "red saturation:0.5 kelvin:3500".parse().unwrap();
+        assert_eq!((color.hue, color.saturation, color.kelvin, color.brightness), (Some(0.0), Some(0.5), Some(3500), None));
+        assert_eq!("rgb:0,0,255 brightness:0.2".parse::<Color>().unwrap().brightness, Some(0.2));
+        assert_eq!("#00ff00".parse::<Color>().unwrap().hue, Some(120.0));
+        assert_eq!("hue:red".parse::<Color>(), Err(ColorParseError::InvalidComponent("hue:red".to_string())));
+        assert_eq!("rgb:1,2".parse::<Color>(), Err(ColorParseError::InvalidComponent("rgb:1,2".to_string())));
+        assert_eq!("mauve".parse::<Color>(), Err(ColorParseError::UnknownName("mauve".to_string())));
+        assert_eq!(" ".parse::<Color>(), Err(ColorParseError::Empty));
+    }
+
+    #[test]
+    fn test_validate_state() {
+        assert_eq!(State::builder().power_on().color("kelvin:2700").brightness(0.5).duration(2.0).build().validate(), Ok(()));
+
+        let state = State::builder().power("dim").color("red saturation:2 kelvin:12000").infrared(-0.1).build();
+        let error = state.validate().unwrap_err();
+        let fields: Vec<&str> = error.fields.iter().map(|invalid| invalid.field).collect();
+        assert_eq!(fields, vec!["power", "color", "color", "infrared"]);
+        assert_eq!(error.to_string(), "invalid power: `dim` is not on or off, invalid color: saturation 2 is not between 0 and 1, \
+            invalid color: kelvin 12000 is not between 1500 and 9000, invalid infrared: -0.1 is not between 0 and 1");
+        assert_eq!(State::builder().color("mauve").build().validate().unwrap_err().fields[0].reason, "unknown color name: `mauve`");
+        assert!(State::builder().duration(f64::NAN).build().validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_state_delta() {
+        let delta = StateDelta { hue: Some(-90.0), brightness: Some(-0.5), kelvin: Some(500), ..Default::default() };
+        assert_eq!(delta.validate(), Ok(()));
+
+        let delta = StateDelta { hue: Some(400.0), duration: Some(4e9), ..Default::default() };
+        let fields: Vec<&str> = delta.validate().unwrap_err().fields.iter().map(|invalid| invalid.field).collect();
+        assert_eq!(fields, vec!["duration", "hue"]);
+    }
+
+    #[test]
+    fn test_validate_palette() {
+        let morph = |palette: Vec<&str>| MorphEffect { palette: Some(palette.into_iter().map(String::from).collect()), ..Default::default() };
+        assert_eq!(MorphEffect::new().validate(), Ok(()));
+        assert_eq!(morph(vec!["red", "#0000ff", "kelvin:2700"]).validate(), Ok(()));
+        assert_eq!(morph(vec![]).validate(), Err(PaletteError::Empty));
+        assert_eq!(morph(vec!["red"; 17]).validate(), Err(PaletteError::TooManyColors(17)));
+        assert!(matches!(morph(vec!["red", "hue:"]).validate(), Err(PaletteError::InvalidColor { index: 1, .. })));
+    }
+
+    #[test]
+    fn test_palette_param() {
+        let morph = MorphEffect { palette: Some(vec!["red".to_string(), "hue:100 saturation:1".to_string()]), ..Default::default() };
+        assert_eq!(morph.to_params(), vec![("palette".to_string(), r#"["red","hue:100 saturation:1"]"#.to_string())]);
+    }
+
+    #[test]
+    fn test_toggle_fractional_duration() {
+        let mut toggle = Toggle::new();
+        toggle.duration = Some(0.5);
+
+        let params = toggle.to_params();
+        assert_eq!(params, vec![("duration".to_string(), "0.5".to_string())]);
+    }
+
+    #[test]
+    fn test_selector_to_string() {
+        assert_eq!(Selector::All.to_string(), "all");
+        assert_eq!(Selector::GroupId(format!("1c8de82b81f445e7cfaafae49b259c71")).to_string(), "group_id:1c8de82b81f445e7cfaafae49b259c71");
+        assert_eq!(Selector::Group(format!("Kitchen")).to_string(), "group:Kitchen");
+        assert_eq!(Selector::Label(format!("Desk Lamp")).to_string(), "label:Desk Lamp");
+
+        let selector: String = Selector::Label(format!("Porch")).into();
+        assert_eq!(selector, "label:Porch");
+    }
+
+    #[test]
+    fn test_default_duration() {
+        let default = Some(0.4);
+
+        assert_eq!(State::new().with_default_duration(default).duration, Some(0.4));
+        assert_eq!(Toggle::new().with_default_duration(default).duration, Some(0.4));
+        assert_eq!(StateDelta::new().with_default_duration(default).duration, Some(0.4));
+
+        let mut state = State::new();
+        state.duration = Some(2.0);
+        assert_eq!(state.with_default_duration(default).duration, Some(2.0));
+
+        let states = States::new().with_default_duration(default);
+        assert_eq!(states.defaults.and_then(|d| d.duration), Some(0.4));
+
+        assert_eq!(State::new().with_default_duration(None).duration, None);
+        assert_eq!(States::new().with_default_duration(None).defaults, None);
+    }
+
+    #[test]
+    fn test_states_from_map() {
+        let mut on = State::new();
+        on.power = Some("on".to_string());
+        let mut off = State::new();
+        off.power = Some("off".to_string());
+
+        let mut map = HashMap::new();
+        map.insert(Selector::Group("Kitchen".to_string()), on);
+        map.insert(Selector::Id("d073d5".to_string()), off);
+
+        let states = States::from_map(map, None).states.unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].selector, Some("group:Kitchen".to_string()));
+        assert_eq!(states[0].power, Some("on".to_string()));
+        assert_eq!(states[1].selector, Some("id:d073d5".to_string()));
+        assert_eq!(states[1].power, Some("off".to_string()));
+    }
+
+    #[test]
+    fn test_breathe_oscillate_params() {
+        let params = BreatheEffect::oscillate("blue", "purple", 4.0).to_params();
+        assert!(params.contains(&("color".to_string(), "purple".to_string())));
+        assert!(params.contains(&("from_color".to_string(), "blue".to_string())));
+        assert!(params.contains(&("period".to_string(), "4".to_string())));
+        assert!(params.contains(&("cycles".to_string(), "1000000000".to_string())));
+        assert!(params.contains(&("persist".to_string(), "false".to_string())));
+        assert!(!params.iter().any(|(key, _)| key == "power_on" || key == "peak"));
+    }
+
+    #[test]
+    fn test_state_mask() {
+        let state = State {
+            power: Some("on".to_string()),
+            color: Some("blue".to_string()),
+            brightness: Some(0.8),
+            duration: Some(1.0),
+            selector: Some("group:Kitchen".to_string()),
+            ..Default::default()
+        };
+
+        let masked = state.mask(&[StateField::Color]);
+        assert_eq!(masked.color, Some("blue".to_string()));
+        assert_eq!(masked.brightness, None);
+        assert_eq!(masked.power, None);
+        assert_eq!(masked.duration, None);
+        assert_eq!(masked.selector, Some("group:Kitchen".to_string()));
+
+        assert_eq!(state.mask(&[StateField::Power, StateField::Color, StateField::Brightness, StateField::Duration]), state);
+    }
+
+    #[test]
+    fn test_raw_params() {
+        let mut state = State::new().raw_param("new_param", "1");
+        state.power = Some("on".to_string());
+        assert_eq!(state.to_params().last(), Some(&("new_param".to_string(), "1".to_string())));
+
+        let pulse = PulseEffect::new().raw_param("a", "x").raw_param("b", "y");
+        assert_eq!(pulse.to_params(), vec![("a".to_string(), "x".to_string()), ("b".to_string(), "y".to_string())]);
+    }
+
+    #[test]
+    fn test_state_ensure_on() {
+        let mut color = State::new();
+        color.color = Some("blue".to_string());
+        assert_eq!(color.ensure_on().power, Some("on".to_string()));
+
+        let mut brightness = State::new();
+        brightness.brightness = Some(0.5);
+        assert_eq!(brightness.ensure_on().power, Some("on".to_string()));
+
+        let mut explicit_off = State::new();
+        explicit_off.color = Some("blue".to_string());
+        explicit_off.power = Some("off".to_string());
+        assert_eq!(explicit_off.ensure_on().power, Some("off".to_string()));
+
+        let mut infrared = State::new();
+        infrared.infrared = Some(1.0);
+        assert_eq!(infrared.ensure_on().power, None);
+    }
+
+    #[test]
+    fn test_selector_from_str() {
+        assert_eq!("all".parse::<Selector>(), Ok(Selector::All));
+        assert_eq!("group:Kitchen".parse::<Selector>(), Ok(Selector::Group(format!("Kitchen"))));
+        assert_eq!("label:Desk Lamp".parse::<Selector>(), Ok(Selector::Label(format!("Desk Lamp"))));
+        assert_eq!("group:".parse::<Selector>(), Err(SelectorParseError::MissingValue(format!("group:"))));
+        assert_eq!("room:Kitchen".parse::<Selector>(), Err(SelectorParseError::UnknownType(format!("room"))));
+    }
+
+    #[test]
+    fn test_state_from_slider() {
+        let off = State::from_slider(0);
+        assert_eq!(off.power, Some("off".to_string()));
+        assert_eq!(off.brightness, None);
+
+        let dim = State::from_slider(1);
+        assert_eq!(dim.power, Some("on".to_string()));
+        assert_eq!(dim.brightness, Some(0.01));
+
+        assert_eq!(State::from_slider(100).brightness, Some(1.0));
+        assert_eq!(State::from_slider(255).brightness, Some(1.0));
+    }
+
+    #[test]
+    fn test_move_single_pass_params() {
+        let params = MoveEffect::single_pass(Direction::Backward, 2.5).to_params();
+        assert!(params.contains(&("direction".to_string(), "backward".to_string())));
+        assert!(params.contains(&("period".to_string(), "2.5".to_string())));
+        assert!(params.contains(&("cycles".to_string(), "1".to_string())));
+        assert!(params.contains(&("power_on".to_string(), "true".to_string())));
+        assert!(!params.iter().any(|(key, _)| key == "fast"));
+    }
+
+    #[test]
+    fn test_selector_validate() {
+        assert_eq!(Selector::GroupId("1c8de82b81f445e7cfaafae49b259c71".to_string()).validate(), Ok(()));
+        assert_eq!(Selector::LocationId("1C8DE82B81F445E7CFAAFAE49B259C71".to_string()).validate(), Ok(()));
+        assert_eq!(
+            Selector::GroupId("d073d5000000".to_string()).validate(),
+            Err(SelectorParseError::InvalidId("d073d5000000".to_string()))
+        );
+        assert!(Selector::LocationId("1c8de82b81f445e7cfaafae49b259c7z".to_string()).validate().is_err());
+        assert!(Selector::GroupId(String::new()).validate().is_err());
+        assert_eq!(Selector::Group("Kitchen".to_string()).validate(), Ok(()));
+        assert_eq!(Selector::Id("d073d5000000".to_string()).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_states_fast_override() {
+        let mut confirmed = State::new();
+        confirmed.selector = Some("id:d073d5".to_string());
+        confirmed.fast = Some(false);
+        let mut inherits = State::new();
+        inherits.selector = Some("id:d073d6".to_string());
+
+        let mut states = States::new().set_default_fast(true);
+        states.states = Some(vec![confirmed, inherits]);
+        let json = serde_json::to_value(&states).unwrap();
+
+        assert_eq!(json["defaults"]["fast"], serde_json::json!(true));
+        assert_eq!(json["states"][0]["fast"], serde_json::json!(false));
+        // An unset field is omitted rather than sent as null, so the default applies
+        assert!(json["states"][1].get("fast").is_none());
+        assert_eq!(json["states"][1], serde_json::json!({"selector": "id:d073d6"}));
+    }
+
+    #[test]
+    fn test_morph_from_weighted() {
+        let hue = |hue: f64| Color { hue: Some(hue), ..Default::default() };
+        let counts = |morph_effect: &MorphEffect| {
+            let palette = morph_effect.palette.clone().unwrap();
+            (palette.iter().filter(|c| *c == "hue:240").count(), palette.iter().filter(|c| *c == "hue:0").count(), palette.len())
+        };
+
+        let weighted = MorphEffect::from_weighted(&[(hue(240.0), 3), (hue(0.0), 1), (hue(120.0), 0)], 5.0);
+        assert_eq!(counts(&weighted), (3, 1, 4));
+        assert_eq!(weighted.period, Some(5.0));
+
+        // Over the 16 color cap the weights are scaled down, keeping their ratio
+        let capped = MorphEffect::from_weighted(&[(hue(240.0), 30), (hue(0.0), 10)], 5.0);
+        assert_eq!(counts(&capped), (12, 4, 16));
+
+        let rare = MorphEffect::from_weighted(&[(hue(240.0), 200), (hue(0.0), 1)], 5.0);
+        assert_eq!(counts(&rare), (15, 1, 16));
+    }
+
+    #[test]
+    fn test_payload_builders() {
+        let mut state = State::new();
+        state.power = Some("on".to_string());
+        state.brightness = Some(0.8);
+        state.duration = Some(1.0);
+        assert_eq!(State::builder().power_on().brightness(0.8).duration(1).build(), state);
+
+        let mut breathe = BreatheEffect::new();
+        breathe.color = Some("red".to_string());
+        breathe.period = Some(2.0);
+        breathe.extra_params.push(("new_param".to_string(), "1".to_string()));
+        assert_eq!(BreatheEffect::builder().color("red").period(2.0).raw_param("new_param", "1").build(), breathe);
+
+        let states = States::builder()
+            .states(vec![State::builder().selector("id:d073d5").fast(false).build()])
+            .defaults(State::builder().fast(true))
+            .build();
+        assert_eq!(states.defaults.and_then(|defaults| defaults.fast), Some(true));
+        assert_eq!(states.states.unwrap()[0].fast, Some(false));
+    }
+
+    #[test]
+    fn test_rate_limit_wait_time() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_456_181_000);
+        let exhausted = RateLimit { limit: Some(120), remaining: 0, reset: now + Duration::from_secs(52) };
+        assert_eq!(exhausted.wait_time(now), Some(Duration::from_secs(52)));
+        assert_eq!(exhausted.wait_time(now + Duration::from_secs(60)), None);
+        assert_eq!(RateLimit { remaining: 1, ..exhausted }.wait_time(now), None);
+    }
+
+    #[test]
+    fn test_color_conversions() {
+        assert_eq!(Color::from_rgb(255, 0, 0).to_param_string(), "hue:0 saturation:1 brightness:1");
+        assert_eq!(Color::from_hex("#ff8000").unwrap(), Color::from_rgb(255, 128, 0));
+        assert_eq!(Color::from_hex("0f0").unwrap().hue, Some(120.0));
+        assert_eq!(Color::from_hex("#ff80"), Err(ColorParseError::InvalidHex("#ff80".to_string())));
+        assert!(Color::from_hex("#gg0000").is_err());
+
+        for hex in ["#ff8000", "#336699", "#000000", "#ffffff", "#8000ff"] {
+            assert_eq!(Color::from_hex(hex).unwrap().to_hex(), hex);
+        }
+        assert_eq!(Color::kelvin(2700).to_rgb(), (255, 255, 255));
+        assert_eq!(Color::from_hsbk(240.0, 1.0, 0.5, 3500).to_rgb(), (0, 0, 128));
+
+        let state = State::builder().color(Color::kelvin(2700)).build();
+        assert_eq!(state.color.as_deref(), Some("saturation:0 kelvin:2700"));
+    }
+
+    #[test]
+    fn test_named_colors() {
+        for color in NamedColor::ALL {
+            assert_eq!(color.to_string().parse::<NamedColor>(), Ok(color));
+        }
+        assert_eq!(" Pink".parse::<NamedColor>(), Ok(NamedColor::Pink));
+        assert_eq!("teal".parse::<NamedColor>(), Err(ColorParseError::UnknownName("teal".to_string())));
+        assert_eq!(Color::from(NamedColor::Cyan).to_hex(), "#00ffff");
+        assert_eq!(BreatheEffect::builder().color(NamedColor::Blue).build().color.as_deref(), Some("blue"));
+    }
+
+    #[test]
+    fn test_kelvin_presets() {
+        assert!(Kelvin::ALL.windows(2).all(|pair| pair[0].kelvin() < pair[1].kelvin()));
+        assert_eq!(Kelvin::Candlelight.to_string(), "Candlelight (1500K)");
+        assert_eq!(String::from(Kelvin::Daylight), "kelvin:5600");
+        assert_eq!(Color::from(Kelvin::Neutral).kelvin, Some(3500));
+
+        let state = State::builder().color(Kelvin::Warm).brightness(0.4).build();
+        assert_eq!(state.to_params(), vec![("color".to_string(), "kelvin:3000".to_string()), ("brightness".to_string(), "0.4".to_string())]);
+    }
+
+    #[test]
+    fn test_duration_setters() {
+        let state = State::builder().duration_of(Duration::from_millis(1500)).build();
+        assert_eq!(state.duration, Some(1.5));
+        assert_eq!(Toggle::builder().duration_of(Duration::from_millis(250)).build().duration, Some(0.25));
+        assert_eq!(Clean::builder().duration_of(Duration::from_millis(7_200_001)).build().duration, Some(7201));
+        assert_eq!(Clean::builder().duration_of(Duration::from_millis(300)).build().duration, Some(1));
+
+        let flame = FlameEffect::builder().period_of(Duration::from_secs(5)).duration_of(Duration::from_secs(90)).build();
+        assert_eq!((flame.period, flame.duration), (Some(5), Some(90.0)));
+        let morph = MorphEffect::builder().period_of(Duration::from_millis(2500)).build();
+        assert_eq!(morph.period, Some(2.5));
+    }
+}
@@ -3,12 +3,15 @@
 //! This lets you control lights on your local area network.  More info can be found here:
 //! https://lan.developer.lifx.com/
 //!
-//! Since this is a low-level library, it does not deal with issues like talking to the network,
-//! caching light state, or waiting for replies.  This should be done at a higher-level library.
+//! Apart from [discover] and [Device], this is a low-level library: it does not deal with issues like
+//! caching light state or retrying lost packets.  This should be done at a higher-level library.
 //!
 //! # Discovery
 //!
-//! To discover lights on your LAN, send a [Message::GetService] message as a UDP broadcast to port 56700
+//! [discover] finds the devices on your LAN and returns their serial, IP and port.  Call
+//! [DiscoveredDevice::connect] to get a [Device] that can get and set the light's color.
+//!
+//! To do it by hand, send a [Message::GetService] message as a UDP broadcast to port 56700
 //! When a device is discovered, the [Service] types and IP port are provided.  To get additional
 //! info about each device, send additional Get messages directly to each device (by setting the
 //! [FrameAddress::target] field to the bulbs target ID, and then send a UDP packet to the IP address
@@ -28,10 +31,17 @@ use thiserror::Error;
 use std::convert::{TryFrom, TryInto};
 use std::io::Cursor;
 use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
 
 
 use serde::{Serialize, Deserialize};
 
+pub mod codec;
+mod device;
+
+pub use device::{Device, LightState, DEFAULT_TIMEOUT, RECONNECT_ATTEMPTS, RECONNECT_BACKOFF};
+
 /// Various message encoding/decoding errors
 #[derive(Error, Debug)]
 pub enum Error {
@@ -235,6 +245,45 @@ where
     }
 }
 
+impl<T, V, const N: usize> LittleEndianWriter<Box<[V; N]>> for T
+where
+    T: WriteBytesExt + LittleEndianWriter<V>,
+    V: Copy,
+{
+    fn write_val(&mut self, v: Box<[V; N]>) -> Result<(), io::Error> {
+        for item in v.iter() {
+            self.write_val(*item)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> LittleEndianWriter<TileInfo> for T
+where
+    T: WriteBytesExt,
+{
+    fn write_val(&mut self, v: TileInfo) -> Result<(), io::Error> {
+        self.write_val(v.accel_meas_x)?;
+        self.write_val(v.accel_meas_y)?;
+        self.write_val(v.accel_meas_z)?;
+        self.write_val(0i16)?;
+        self.write_val(v.user_x)?;
+        self.write_val(v.user_y)?;
+        self.write_val(v.width)?;
+        self.write_val(v.height)?;
+        self.write_val(0u8)?;
+        self.write_val(v.device_version_vendor)?;
+        self.write_val(v.device_version_product)?;
+        self.write_val(0u32)?;
+        self.write_val(v.firmware_build)?;
+        self.write_val(0u64)?;
+        self.write_val(v.firmware_version_minor)?;
+        self.write_val(v.firmware_version_major)?;
+        self.write_val(0u32)?;
+        Ok(())
+    }
+}
+
 impl<T> LittleEndianWriter<PowerLevel> for T
 where
     T: WriteBytesExt,
@@ -287,6 +336,12 @@ impl<R: ReadBytesExt> LittleEndianReader<u8> for R {
     }
 }
 
+impl<R: ReadBytesExt> LittleEndianReader<bool> for R {
+    fn read_val(&mut self) -> Result<bool, io::Error> {
+        Ok(self.read_u8()? != 0)
+    }
+}
+
 impl<R: ReadBytesExt> LittleEndianReader<HSBK> for R {
     fn read_val(&mut self) -> Result<HSBK, io::Error> {
         let hue = self.read_val()?;
@@ -302,6 +357,56 @@ impl<R: ReadBytesExt> LittleEndianReader<HSBK> for R {
     }
 }
 
+impl<R, V, const N: usize> LittleEndianReader<Box<[V; N]>> for R
+where
+    R: ReadBytesExt + LittleEndianReader<V>,
+    V: Copy + Default,
+{
+    fn read_val(&mut self) -> Result<Box<[V; N]>, io::Error> {
+        let mut items = Box::new([V::default(); N]);
+        for item in items.iter_mut() {
+            *item = self.read_val()?;
+        }
+        Ok(items)
+    }
+}
+
+impl<R: ReadBytesExt> LittleEndianReader<TileInfo> for R {
+    fn read_val(&mut self) -> Result<TileInfo, io::Error> {
+        let accel_meas_x = self.read_val()?;
+        let accel_meas_y = self.read_val()?;
+        let accel_meas_z = self.read_val()?;
+        let _reserved: i16 = self.read_val()?;
+        let user_x = self.read_val()?;
+        let user_y = self.read_val()?;
+        let width = self.read_val()?;
+        let height = self.read_val()?;
+        let _reserved: u8 = self.read_val()?;
+        let device_version_vendor = self.read_val()?;
+        let device_version_product = self.read_val()?;
+        let _reserved: u32 = self.read_val()?;
+        let firmware_build = self.read_val()?;
+        let _reserved: u64 = self.read_val()?;
+        let firmware_version_minor = self.read_val()?;
+        let firmware_version_major = self.read_val()?;
+        let _reserved: u32 = self.read_val()?;
+        Ok(TileInfo {
+            accel_meas_x,
+            accel_meas_y,
+            accel_meas_z,
+            user_x,
+            user_y,
+            width,
+            height,
+            device_version_vendor,
+            device_version_product,
+            firmware_build,
+            firmware_version_minor,
+            firmware_version_major,
+        })
+    }
+}
+
 impl<R: ReadBytesExt> LittleEndianReader<LifxIdent> for R {
     fn read_val(&mut self) -> Result<LifxIdent, io::Error> {
         let mut val = [0; 16];
@@ -831,8 +936,140 @@ pub enum Message {
         color6: HSBK,
         color7: HSBK,
     },
+
+    /// SetExtendedColorZones - 510
+    ///
+    /// Sets up to [EXTENDED_ZONES] consecutive zones in one message, starting at `zone_index`. Only
+    /// the first `colors_count` entries of `colors` are used; the rest must still be sent.
+    ///
+    /// Requires firmware with extended multizone support.
+    SetExtendedColorZones {
+        /// Color transition time in milliseconds
+        duration: u32,
+        apply: ApplicationRequest,
+        zone_index: u16,
+        colors_count: u8,
+        colors: Box<[HSBK; EXTENDED_ZONES]>,
+    },
+
+    /// GetExtendedColorZones - 511
+    ///
+    /// Asks for every zone on the device. The device answers with as many
+    /// [Message::StateExtendedColorZones] messages as it takes to cover all of its zones.
+    GetExtendedColorZones,
+
+    /// StateExtendedColorZones - 512
+    ///
+    /// Up to [EXTENDED_ZONES] consecutive zones starting at `zone_index`, out of `zones_count`
+    /// zones on the device. Only the first `colors_count` entries of `colors` are meaningful.
+    StateExtendedColorZones {
+        zones_count: u16,
+        zone_index: u16,
+        colors_count: u8,
+        colors: Box<[HSBK; EXTENDED_ZONES]>,
+    },
+
+    /// GetDeviceChain - 701
+    ///
+    /// Asks a matrix device (Tile, Candle, Ceiling) for the tiles it is made of. The device answers
+    /// with [Message::StateDeviceChain].
+    GetDeviceChain,
+
+    /// StateDeviceChain - 702
+    ///
+    /// Describes up to [MAX_TILES] tiles, starting at `start_index`. Only the first
+    /// `tile_devices_count` entries of `tile_devices` are meaningful.
+    StateDeviceChain {
+        start_index: u8,
+        tile_devices: Box<[TileInfo; MAX_TILES]>,
+        tile_devices_count: u8,
+    },
+
+    /// SetUserPosition - 703
+    ///
+    /// Records where the tile at `tile_index` sits relative to the others, in tile widths. The
+    /// device only stores the position for apps to read back with [Message::GetDeviceChain].
+    SetUserPosition {
+        tile_index: u8,
+        reserved: u16,
+        user_x: f32,
+        user_y: f32,
+    },
+
+    /// Get64 - 707
+    ///
+    /// Asks for up to [TILE_PIXELS] pixels of `length` tiles starting at `tile_index`. The
+    /// rectangle starts at column `x` and row `y` and is `width` pixels wide. Each tile answers
+    /// with a [Message::State64].
+    Get64 {
+        tile_index: u8,
+        length: u8,
+        reserved: u8,
+        x: u8,
+        y: u8,
+        width: u8,
+    },
+
+    /// State64 - 711
+    ///
+    /// The pixels of one tile, row by row, for the rectangle asked for in [Message::Get64].
+    State64 {
+        tile_index: u8,
+        reserved: u8,
+        x: u8,
+        y: u8,
+        width: u8,
+        colors: Box<[HSBK; TILE_PIXELS]>,
+    },
+
+    /// Set64 - 715
+    ///
+    /// Sets up to [TILE_PIXELS] pixels, row by row, on `length` tiles starting at `tile_index`.
+    /// The rectangle starts at column `x` and row `y` and is `width` pixels wide.
+    Set64 {
+        tile_index: u8,
+        length: u8,
+        reserved: u8,
+        x: u8,
+        y: u8,
+        width: u8,
+        /// Color transition time in milliseconds
+        duration: u32,
+        colors: Box<[HSBK; TILE_PIXELS]>,
+    },
+}
+
+/// One tile of a matrix device, from its [Message::StateDeviceChain] reply
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TileInfo {
+    pub accel_meas_x: i16,
+    pub accel_meas_y: i16,
+    pub accel_meas_z: i16,
+    /// Horizontal position, in tile widths, as set with [Message::SetUserPosition]
+    pub user_x: f32,
+    /// Vertical position, in tile heights, as set with [Message::SetUserPosition]
+    pub user_y: f32,
+    /// Width in pixels
+    pub width: u8,
+    /// Height in pixels
+    pub height: u8,
+    pub device_version_vendor: u32,
+    pub device_version_product: u32,
+    pub firmware_build: u64,
+    pub firmware_version_minor: u16,
+    pub firmware_version_major: u16,
 }
 
+/// The number of colors carried by [Message::Set64] and [Message::State64]: one 8x8 tile
+pub const TILE_PIXELS: usize = 64;
+
+/// The number of tiles described by one [Message::StateDeviceChain]
+pub const MAX_TILES: usize = 16;
+
+/// The number of colors carried by [Message::SetExtendedColorZones] and
+/// [Message::StateExtendedColorZones]
+pub const EXTENDED_ZONES: usize = 82;
+
 impl Message {
     pub fn get_num(&self) -> u16 {
         match *self {
@@ -880,6 +1117,15 @@ impl Message {
             Message::GetColorZones { .. } => 502,
             Message::StateZone { .. } => 503,
             Message::StateMultiZone { .. } => 506,
+            Message::SetExtendedColorZones { .. } => 510,
+            Message::GetExtendedColorZones => 511,
+            Message::StateExtendedColorZones { .. } => 512,
+            Message::GetDeviceChain => 701,
+            Message::StateDeviceChain { .. } => 702,
+            Message::SetUserPosition { .. } => 703,
+            Message::Get64 { .. } => 707,
+            Message::State64 { .. } => 711,
+            Message::Set64 { .. } => 715,
         }
     }
 
@@ -923,8 +1169,10 @@ impl Message {
                 version: u32
             )),
             20 => Ok(Message::GetPower),
+            21 => Ok(unpack!(msg, SetPower, level: u16)),
             22 => Ok(unpack!(msg, StatePower, level: u16)),
             23 => Ok(Message::GetLabel),
+            24 => Ok(unpack!(msg, SetLabel, label: LifxString)),
             25 => Ok(unpack!(msg, StateLabel, label: LifxString)),
             32 => Ok(Message::GetVersion),
             33 => Ok(unpack!(
@@ -934,6 +1182,7 @@ impl Message {
                 product: u32,
                 version: u32
             )),
+            34 => Ok(Message::GetInfo),
             35 => Ok(unpack!(
                 msg,
                 StateInfo,
@@ -945,6 +1194,13 @@ impl Message {
                 seq: msg.frame_addr.sequence,
             }),
             48 => Ok(Message::GetLocation),
+            49 => Ok(unpack!(
+                msg,
+                SetLocation,
+                location: LifxIdent,
+                label: LifxString,
+                updated_at: u64
+            )),
             50 => Ok(unpack!(
                 msg,
                 StateLocation,
@@ -953,6 +1209,13 @@ impl Message {
                 updated_at: u64
             )),
             51 => Ok(Message::GetGroup),
+            52 => Ok(unpack!(
+                msg,
+                SetGroup,
+                group: LifxIdent,
+                label: LifxString,
+                updated_at: u64
+            )),
             53 => Ok(unpack!(
                 msg,
                 StateGroup,
@@ -970,6 +1233,17 @@ impl Message {
                 color: HSBK,
                 duration: u32
             )),
+            103 => Ok(unpack!(
+                msg,
+                SetWaveform,
+                reserved: u8,
+                transient: bool,
+                color: HSBK,
+                period: u32,
+                cycles: f32,
+                skew_ratio: i16,
+                waveform: u8
+            )),
             107 => Ok(unpack!(
                 msg,
                 LightState,
@@ -987,7 +1261,24 @@ impl Message {
                     level: c.read_val()?,
                 })
             }
+            119 => Ok(unpack!(
+                msg,
+                SetWaveformOptional,
+                reserved: u8,
+                transient: bool,
+                color: HSBK,
+                period: u32,
+                cycles: f32,
+                skew_ratio: i16,
+                waveform: u8,
+                set_hue: bool,
+                set_saturation: bool,
+                set_brightness: bool,
+                set_kelvin: bool
+            )),
+            120 => Ok(Message::LightGetInfrared),
             121 => Ok(unpack!(msg, LightStateInfrared, brightness: u16)),
+            122 => Ok(unpack!(msg, LightSetInfrared, brightness: u16)),
             501 => Ok(unpack!(
                 msg,
                 SetColorZones,
@@ -1013,6 +1304,81 @@ impl Message {
                 color6: HSBK,
                 color7: HSBK
             )),
+            510 => {
+                let mut c = Cursor::new(&msg.payload);
+                let duration = c.read_val()?;
+                let apply: u8 = c.read_val()?;
+                let zone_index = c.read_val()?;
+                let colors_count = c.read_val()?;
+                Ok(Message::SetExtendedColorZones {
+                    duration,
+                    apply: apply.try_into()?,
+                    zone_index,
+                    colors_count,
+                    colors: c.read_val()?,
+                })
+            }
+            511 => Ok(Message::GetExtendedColorZones),
+            512 => {
+                let mut c = Cursor::new(&msg.payload);
+                Ok(Message::StateExtendedColorZones {
+                    zones_count: c.read_val()?,
+                    zone_index: c.read_val()?,
+                    colors_count: c.read_val()?,
+                    colors: c.read_val()?,
+                })
+            }
+            701 => Ok(Message::GetDeviceChain),
+            702 => {
+                let mut c = Cursor::new(&msg.payload);
+                Ok(Message::StateDeviceChain {
+                    start_index: c.read_val()?,
+                    tile_devices: c.read_val()?,
+                    tile_devices_count: c.read_val()?,
+                })
+            }
+            703 => Ok(unpack!(
+                msg,
+                SetUserPosition,
+                tile_index: u8,
+                reserved: u16,
+                user_x: f32,
+                user_y: f32
+            )),
+            707 => Ok(unpack!(
+                msg,
+                Get64,
+                tile_index: u8,
+                length: u8,
+                reserved: u8,
+                x: u8,
+                y: u8,
+                width: u8
+            )),
+            711 => {
+                let mut c = Cursor::new(&msg.payload);
+                Ok(Message::State64 {
+                    tile_index: c.read_val()?,
+                    reserved: c.read_val()?,
+                    x: c.read_val()?,
+                    y: c.read_val()?,
+                    width: c.read_val()?,
+                    colors: c.read_val()?,
+                })
+            }
+            715 => {
+                let mut c = Cursor::new(&msg.payload);
+                Ok(Message::Set64 {
+                    tile_index: c.read_val()?,
+                    length: c.read_val()?,
+                    reserved: c.read_val()?,
+                    x: c.read_val()?,
+                    y: c.read_val()?,
+                    width: c.read_val()?,
+                    duration: c.read_val()?,
+                    colors: c.read_val()?,
+                })
+            }
             _ => Err(Error::UnknownMessageType(msg.protocol_header.typ)),
         }
     }
@@ -1032,7 +1398,7 @@ impl Message {
 /// When a light is displaying colors, kelvin is ignored.
 ///
 /// To display "pure" colors, set saturation to full (65535).
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HSBK {
     pub hue: u16,
     pub saturation: u16,
@@ -1041,6 +1407,26 @@ pub struct HSBK {
 }
 
 impl HSBK {
+    /// Returns `count` colors fading evenly from this color to `to`, both included.
+    ///
+    /// The hue takes the shorter way around the color wheel, so a gradient from red (hue 65000) to
+    /// orange (hue 3000) passes through red rather than every other hue.
+    pub fn gradient(&self, to: HSBK, count: usize) -> Vec<HSBK> {
+        let lerp = |from: u16, to: u16, t: f64| (from as f64 + (to as f64 - from as f64) * t).round() as u16;
+        let hue_delta = to.hue.wrapping_sub(self.hue) as i16 as f64;
+        (0..count)
+            .map(|step| {
+                let t = if count > 1 { step as f64 / (count - 1) as f64 } else { 0.0 };
+                HSBK {
+                    hue: self.hue.wrapping_add((hue_delta * t).round() as i16 as u16),
+                    saturation: lerp(self.saturation, to.saturation, t),
+                    brightness: lerp(self.brightness, to.brightness, t),
+                    kelvin: lerp(self.kelvin, to.kelvin, t),
+                }
+            })
+            .collect()
+    }
+
     pub fn describe(&self, short: bool) -> String {
         match short {
             true if self.saturation == 0 => format!("{}K", self.kelvin),
@@ -1103,7 +1489,27 @@ pub fn describe_kelvin(k: u16) -> &'static str {
     }
 }
 
-impl HSBK {}
+/// Converts an HTTP API color. Unset components count as hue 0, saturation 0, full brightness
+/// and 3500K, since a LAN color always carries all four.
+impl From<&crate::Color> for HSBK {
+    fn from(color: &crate::Color) -> HSBK {
+        let scale = |value: f64| (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+        HSBK {
+            hue: scale(color.hue.unwrap_or(0.0).rem_euclid(360.0) / 360.0),
+            saturation: scale(color.saturation.unwrap_or(0.0)),
+            brightness: scale(color.brightness.unwrap_or(1.0)),
+            kelvin: color.kelvin.unwrap_or(3500).clamp(0, u16::MAX as i64) as u16,
+        }
+    }
+}
+
+/// Converts to an HTTP API color, with the hue in degrees and the rest scaled to 0.0-1.0
+impl From<HSBK> for crate::Color {
+    fn from(hsbk: HSBK) -> crate::Color {
+        let unscale = |value: u16, max: f64, places: i32| (value as f64 / 65535.0 * max * 10f64.powi(places)).round() / 10f64.powi(places);
+        crate::Color::from_hsbk(unscale(hsbk.hue, 360.0, 2), unscale(hsbk.saturation, 1.0, 4), unscale(hsbk.brightness, 1.0, 4), hsbk.kelvin as i64)
+    }
+}
 
 /// The raw message structure
 ///
@@ -1433,7 +1839,9 @@ impl RawMessage {
             | Message::GetGroup
             | Message::LightGet
             | Message::LightGetPower
-            | Message::LightGetInfrared => {
+            | Message::LightGetInfrared
+            | Message::GetExtendedColorZones
+            | Message::GetDeviceChain => {
                 // these types have no payload
             }
             Message::SetColorZones {
@@ -1530,6 +1938,99 @@ impl RawMessage {
                 v.write_val(color6)?;
                 v.write_val(color7)?;
             }
+            Message::SetExtendedColorZones {
+                duration,
+                apply,
+                zone_index,
+                colors_count,
+                colors,
+            } => {
+                v.write_val(duration)?;
+                v.write_val(apply)?;
+                v.write_val(zone_index)?;
+                v.write_val(colors_count)?;
+                v.write_val(colors)?;
+            }
+            Message::StateExtendedColorZones {
+                zones_count,
+                zone_index,
+                colors_count,
+                colors,
+            } => {
+                v.write_val(zones_count)?;
+                v.write_val(zone_index)?;
+                v.write_val(colors_count)?;
+                v.write_val(colors)?;
+            }
+            Message::StateDeviceChain {
+                start_index,
+                tile_devices,
+                tile_devices_count,
+            } => {
+                v.write_val(start_index)?;
+                v.write_val(tile_devices)?;
+                v.write_val(tile_devices_count)?;
+            }
+            Message::SetUserPosition {
+                tile_index,
+                reserved,
+                user_x,
+                user_y,
+            } => {
+                v.write_val(tile_index)?;
+                v.write_val(reserved)?;
+                v.write_val(user_x)?;
+                v.write_val(user_y)?;
+            }
+            Message::Get64 {
+                tile_index,
+                length,
+                reserved,
+                x,
+                y,
+                width,
+            } => {
+                v.write_val(tile_index)?;
+                v.write_val(length)?;
+                v.write_val(reserved)?;
+                v.write_val(x)?;
+                v.write_val(y)?;
+                v.write_val(width)?;
+            }
+            Message::State64 {
+                tile_index,
+                reserved,
+                x,
+                y,
+                width,
+                colors,
+            } => {
+                v.write_val(tile_index)?;
+                v.write_val(reserved)?;
+                v.write_val(x)?;
+                v.write_val(y)?;
+                v.write_val(width)?;
+                v.write_val(colors)?;
+            }
+            Message::Set64 {
+                tile_index,
+                length,
+                reserved,
+                x,
+                y,
+                width,
+                duration,
+                colors,
+            } => {
+                v.write_val(tile_index)?;
+                v.write_val(length)?;
+                v.write_val(reserved)?;
+                v.write_val(x)?;
+                v.write_val(y)?;
+                v.write_val(width)?;
+                v.write_val(duration)?;
+                v.write_val(colors)?;
+            }
             Message::LightStateInfrared { brightness } => v.write_val(brightness)?,
             Message::LightSetInfrared { brightness } => v.write_val(brightness)?,
             Message::SetLocation {
@@ -1551,8 +2052,9 @@ impl RawMessage {
                 v.write_val(updated_at)?;
             }
             Message::StateService { port, service } => {
-                v.write_val(port)?;
+                // The payload is the service byte followed by the port, as from_raw reads it
                 v.write_val(service as u8)?;
+                v.write_val(port)?;
             }
             Message::StateHostInfo {
                 signal,
@@ -1719,10 +2221,22 @@ impl RawMessage {
     }
     /// Given some bytes (generally read from a network socket), unpack the data into a
     /// `RawMessage` structure.
+    ///
+    /// Returns a [Error::ProtocolError] if `v` is shorter than the headers or than the size the
+    /// frame claims, so truncated packets are rejected rather than read past.
     pub fn unpack(v: &[u8]) -> Result<RawMessage, Error> {
+        let header_size = Frame::packed_size() + FrameAddress::packed_size() + ProtocolHeader::packed_size();
+        if v.len() < header_size {
+            return Err(Error::ProtocolError(format!("Packet of {} bytes is shorter than the {} byte header", v.len(), header_size)));
+        }
         let mut start = 0;
         let frame = Frame::unpack(v)?;
-        frame.validate();
+        if (frame.size as usize) < header_size || (frame.size as usize) > v.len() {
+            return Err(Error::ProtocolError(format!("Frame size {} doesn't fit a {} byte packet", frame.size, v.len())));
+        }
+        if !frame.addressable {
+            return Err(Error::ProtocolError("Unpacked frame isn't addressable".to_string()));
+        }
         start += Frame::packed_size();
         let addr = FrameAddress::unpack(&v[start..])?;
         addr.validate();
@@ -1742,6 +2256,89 @@ impl RawMessage {
     }
 }
 
+/// The UDP port LIFX devices listen on, and the one [discover] broadcasts to
+pub const LIFX_PORT: u16 = 56700;
+
+/// A device that answered [discover]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveredDevice {
+    /// The device's serial number (MAC address), to use as [BuildOptions::target]
+    pub serial: u64,
+    /// The address the device answered from
+    pub ip: IpAddr,
+    /// The UDP port of the device's service, from its [Message::StateService] reply
+    pub port: u16,
+}
+
+/// Broadcasts a [Message::GetService] to port 56700 and collects the [Message::StateService]
+/// replies that arrive within `timeout`.
+///
+/// Each device is listed once, in the order its first reply arrived. Devices whose UDP service is
+/// temporarily unavailable (port 0) are left out. An empty list means nothing answered in time;
+/// the broadcast isn't retried, so a lossy network may need a second call.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// for device in lifx_rs::lan::discover(Duration::from_secs(1)).unwrap() {
+///     println!("{:012x} at {}:{}", device.serial, device.ip, device.port);
+/// }
+/// ```
+pub fn discover(timeout: Duration) -> Result<Vec<DiscoveredDevice>, Error> {
+    discover_on(SocketAddr::from(([255, 255, 255, 255], LIFX_PORT)), timeout)
+}
+
+/// Sends the discovery request to `destination` instead of the broadcast address, see [discover]
+fn discover_on(destination: SocketAddr, timeout: Duration) -> Result<Vec<DiscoveredDevice>, Error> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_broadcast(true)?;
+
+    // A nonzero source makes devices answer us directly instead of broadcasting the reply
+    let source = (crate::random_u64() as u32).max(1);
+    let options = BuildOptions { source, res_required: true, ..Default::default() };
+    socket.send_to(&RawMessage::build(&options, Message::GetService)?.pack()?, destination)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut devices: Vec<DiscoveredDevice> = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => break,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(device) = parse_service_reply(&buf[..len], from, source) {
+            if !devices.iter().any(|known| known.serial == device.serial) {
+                devices.push(device);
+            }
+        }
+    }
+    Ok(devices)
+}
+
+/// Returns the device announced by a StateService reply to our `source`, or None for any other packet
+fn parse_service_reply(packet: &[u8], from: SocketAddr, source: u32) -> Option<DiscoveredDevice> {
+    let raw = RawMessage::unpack(packet).ok()?;
+    if raw.frame.source != source {
+        return None;
+    }
+    match Message::from_raw(&raw).ok()? {
+        Message::StateService { port, service: Service::UDP } if port > 0 => Some(DiscoveredDevice {
+            serial: raw.frame_addr.target,
+            ip: from.ip(),
+            port: u16::try_from(port).ok()?,
+        }),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ProductInfo {
     pub name: &'static str,
@@ -2044,4 +2641,102 @@ mod tests {
             ]
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_color_to_hsbk() {
+        let orange = crate::Color::from_hsbk(30.0, 1.0, 0.5, 3500);
+        let hsbk = HSBK::from(&orange);
+        assert_eq!(hsbk, HSBK { hue: 5461, saturation: 65535, brightness: 32768, kelvin: 3500 });
+
+        let back = crate::Color::from(hsbk);
+        assert_eq!((back.hue, back.saturation, back.kelvin), (Some(30.0), Some(1.0), Some(3500)));
+        assert_eq!(HSBK::from(&crate::Color::kelvin(2700)), HSBK { hue: 0, saturation: 0, brightness: 65535, kelvin: 2700 });
+    }
+
+    #[test]
+    fn test_gradient() {
+        let red = HSBK { hue: 65000, saturation: 65535, brightness: 0, kelvin: 2500 };
+        let orange = HSBK { hue: 3000, saturation: 32767, brightness: 65535, kelvin: 9000 };
+        let gradient = red.gradient(orange, 3);
+        assert_eq!(gradient.len(), 3);
+        assert_eq!((gradient[0], gradient[2]), (red, orange));
+        // Halfway the long way round would be hue 34000
+        assert_eq!(gradient[1], HSBK { hue: 1232, saturation: 49151, brightness: 32768, kelvin: 5750 });
+        assert_eq!(red.gradient(orange, 1), vec![red]);
+        assert!(red.gradient(orange, 0).is_empty());
+    }
+
+    #[test]
+    fn test_extended_color_zones() {
+        let mut colors = Box::new([HSBK::default(); EXTENDED_ZONES]);
+        colors[1] = HSBK { hue: 1, saturation: 2, brightness: 3, kelvin: 4 };
+        let msg = Message::SetExtendedColorZones { duration: 250, apply: ApplicationRequest::Apply, zone_index: 80, colors_count: 2, colors };
+        let raw = RawMessage::build(&BuildOptions::default(), msg).unwrap();
+        assert_eq!(raw.payload.len(), 8 + EXTENDED_ZONES * 8);
+
+        match Message::from_raw(&RawMessage::unpack(&raw.pack().unwrap()).unwrap()).unwrap() {
+            Message::SetExtendedColorZones { duration: 250, apply: ApplicationRequest::Apply, zone_index: 80, colors_count: 2, colors } => {
+                assert_eq!(colors[1], HSBK { hue: 1, saturation: 2, brightness: 3, kelvin: 4 });
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tile_messages() {
+        let mut tile_devices = Box::new([TileInfo::default(); MAX_TILES]);
+        tile_devices[2] = TileInfo { width: 8, height: 8, user_x: 2.0, firmware_version_major: 3, firmware_version_minor: 70, ..Default::default() };
+        let msg = Message::StateDeviceChain { start_index: 0, tile_devices, tile_devices_count: 3 };
+        let raw = RawMessage::build(&BuildOptions::default(), msg).unwrap();
+        // Each tile takes 55 bytes on the wire
+        assert_eq!(raw.payload.len(), 1 + MAX_TILES * 55 + 1);
+        match Message::from_raw(&raw).unwrap() {
+            Message::StateDeviceChain { tile_devices_count: 3, tile_devices, .. } => assert_eq!(tile_devices[2], TileInfo { width: 8, height: 8, user_x: 2.0, firmware_version_major: 3, firmware_version_minor: 70, ..Default::default() }),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        let msg = Message::Set64 { tile_index: 1, length: 1, reserved: 0, x: 0, y: 0, width: 8, duration: 0, colors: Box::new([HSBK::default(); TILE_PIXELS]) };
+        let raw = RawMessage::build(&BuildOptions::default(), msg).unwrap();
+        assert_eq!(raw.payload.len(), 10 + TILE_PIXELS * 8);
+        assert!(matches!(Message::from_raw(&raw).unwrap(), Message::Set64 { tile_index: 1, width: 8, .. }));
+    }
+
+    #[test]
+    fn test_parse_service_reply() {
+        let from: SocketAddr = "192.168.1.20:56700".parse().unwrap();
+        let reply = |source: u32, message: Message| {
+            let options = BuildOptions { target: Some(0x0000_d5d3_73d0), source, ..Default::default() };
+            RawMessage::build(&options, message).unwrap().pack().unwrap()
+        };
+
+        let device = parse_service_reply(&reply(42, Message::StateService { port: 56700, service: Service::UDP }), from, 42);
+        assert_eq!(device, Some(DiscoveredDevice { serial: 0x0000_d5d3_73d0, ip: from.ip(), port: 56700 }));
+
+        // Replies to other clients, unavailable services, other messages and junk are ignored
+        assert_eq!(parse_service_reply(&reply(7, Message::StateService { port: 56700, service: Service::UDP }), from, 42), None);
+        assert_eq!(parse_service_reply(&reply(42, Message::StateService { port: 0, service: Service::UDP }), from, 42), None);
+        assert_eq!(parse_service_reply(&reply(42, Message::GetService), from, 42), None);
+        assert_eq!(parse_service_reply(&[0xff; 12], from, 42), None);
+    }
+
+    #[test]
+    fn test_discover_on() {
+        // A fake device on localhost answers the GetService twice; it should be listed once
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let destination = device.local_addr().unwrap();
+        let responder = std::thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            let (len, from) = device.recv_from(&mut buf).unwrap();
+            let request = RawMessage::unpack(&buf[..len]).unwrap();
+            assert_eq!(request.protocol_header.typ, Message::GetService.get_num());
+            let options = BuildOptions { target: Some(0x0000_d5d3_73d0), source: request.frame.source, ..Default::default() };
+            let reply = RawMessage::build(&options, Message::StateService { port: 56700, service: Service::UDP }).unwrap().pack().unwrap();
+            device.send_to(&reply, from).unwrap();
+            device.send_to(&reply, from).unwrap();
+        });
+
+        let devices = discover_on(destination, Duration::from_millis(300)).unwrap();
+        responder.join().unwrap();
+        assert_eq!(devices, vec![DiscoveredDevice { serial: 0x0000_d5d3_73d0, ip: destination.ip(), port: 56700 }]);
+    }
+}
@@ -0,0 +1,142 @@
+//! Encoding and decoding of whole LAN packets: the frame, frame address and protocol header plus
+//! the message payload.
+//!
+//! [RawMessage] does the byte-level packing. [Codec] sits on top of it and keeps the per-client
+//! state a conversation with devices needs: a source id, so devices answer this client directly,
+//! and a wrapping sequence number, so each reply can be matched to its request.
+//!
+//! # Examples
+//!
+//! ```
+//! use lifx_rs::lan::codec::Codec;
+//! use lifx_rs::lan::Message;
+//!
+//! let mut codec = Codec::new(0x1234);
+//! let (sequence, packet) = codec.encode(Message::GetLabel, Some(0x0000_d5d3_73d0), false, true).unwrap();
+//!
+//! // A device's reply carries the same source and sequence
+//! let (header, _message) = Codec::decode(&packet).unwrap();
+//! assert!(codec.is_reply(&header, sequence));
+//! ```
+
+pub use super::{BuildOptions, Frame, FrameAddress, ProtocolHeader, RawMessage};
+
+use super::{Error, Message};
+
+/// The size in bytes of the frame, frame address and protocol header at the start of every packet
+pub const HEADER_SIZE: usize = 36;
+
+/// Builds packets for one client, filling in its source id and the next sequence number.
+///
+/// The sequence number is 8 bits and wraps around after 255, so only the last 256 requests can be
+/// told apart by it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Codec {
+    source: u32,
+    sequence: u8,
+}
+
+impl Codec {
+    /// Returns a Codec that sends with `source`. A source of zero lets devices broadcast their
+    /// replies to the whole subnet instead of answering this client directly.
+    pub fn new(source: u32) -> Self {
+        Codec { source, sequence: 0 }
+    }
+
+    /// Returns a Codec with a random nonzero source
+    pub fn random() -> Self {
+        Self::new((crate::random_u64() as u32).max(1))
+    }
+
+    /// The source id this codec sends with
+    pub fn source(&self) -> u32 {
+        self.source
+    }
+
+    /// Returns the sequence number for the next packet and advances it, wrapping after 255
+    pub fn next_sequence(&mut self) -> u8 {
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        sequence
+    }
+
+    /// Packs `message` for `target` (or every device if None, which sets the tagged flag), and
+    /// returns the sequence number it was given along with the packet bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to send
+    /// * `target` - The serial of the device to address, or None for all devices
+    /// * `ack_required` - Ask the device for a [Message::Acknowledgement]
+    /// * `res_required` - Ask the device for its state response
+    pub fn encode(&mut self, message: Message, target: Option<u64>, ack_required: bool, res_required: bool) -> Result<(u8, Vec<u8>), Error> {
+        let options = BuildOptions {
+            target,
+            ack_required,
+            res_required,
+            sequence: self.next_sequence(),
+            source: self.source,
+        };
+        let packet = RawMessage::build(&options, message)?.pack()?;
+        Ok((options.sequence, packet))
+    }
+
+    /// Unpacks a packet into its headers and message. Truncated packets and unknown message types
+    /// are errors.
+    pub fn decode(packet: &[u8]) -> Result<(RawMessage, Message), Error> {
+        let raw = RawMessage::unpack(packet)?;
+        let message = Message::from_raw(&raw)?;
+        Ok((raw, message))
+    }
+
+    /// Returns true if `header` answers the request this codec sent with `sequence`
+    pub fn is_reply(&self, header: &RawMessage, sequence: u8) -> bool {
+        header.frame.source == self.source && header.frame_addr.sequence == sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_wraps() {
+        let mut codec = Codec::new(1);
+        for expected in 0..=255u8 {
+            assert_eq!(codec.next_sequence(), expected);
+        }
+        assert_eq!(codec.next_sequence(), 0);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut codec = Codec::new(0xdead_beef);
+        codec.next_sequence();
+
+        let (sequence, packet) = codec.encode(Message::LightSetPower { level: 65535, duration: 500 }, Some(0x0000_d5d3_73d0), true, false).unwrap();
+        assert_eq!(sequence, 1);
+        assert_eq!(packet.len(), HEADER_SIZE + 6);
+
+        let (header, message) = Codec::decode(&packet).unwrap();
+        assert!(codec.is_reply(&header, 1));
+        assert!(!codec.is_reply(&header, 2));
+        assert!(!header.frame.tagged);
+        assert!(header.frame_addr.ack_required && !header.frame_addr.res_required);
+        assert_eq!(header.frame_addr.target, 0x0000_d5d3_73d0);
+        assert!(matches!(message, Message::LightSetPower { level: 65535, duration: 500 }));
+
+        // No target addresses every device
+        let (_, broadcast) = codec.encode(Message::GetService, None, false, true).unwrap();
+        let (header, _) = Codec::decode(&broadcast).unwrap();
+        assert!(header.frame.tagged);
+        assert_eq!(header.frame_addr.target, 0);
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        let (_, packet) = Codec::new(1).encode(Message::SetLabel { label: crate::lan::LifxString::new("Lamp") }, None, false, false).unwrap();
+        assert!(Codec::decode(&packet[..HEADER_SIZE - 1]).is_err());
+        assert!(Codec::decode(&packet[..packet.len() - 1]).is_err());
+        assert!(Codec::decode(&packet).is_ok());
+    }
+}
@@ -0,0 +1,639 @@
+//! Talking to a single device over the LAN.
+//!
+//! A [Device] owns a UDP socket and a [Codec], sends requests straight to the device's address and
+//! waits for the matching reply, so a color change takes a round trip on the local network instead
+//! of a trip through the cloud.
+//!
+//! A socket that stops working, ex: after a laptop sleeps and wakes on another network, is
+//! replaced: a request that fails on it opens a new one with [Device::reconnect] and is sent
+//! again once.
+
+use std::convert::TryFrom;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use super::codec::Codec;
+use super::{ApplicationRequest, DiscoveredDevice, Error, Message, PowerLevel, TileInfo, EXTENDED_ZONES, HSBK, TILE_PIXELS};
+
+/// How long a [Device] waits for a reply unless told otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many times opening a socket is tried before giving up, see [Device::reconnect]
+pub const RECONNECT_ATTEMPTS: u32 = 4;
+
+/// How long to wait after the first failed try at opening a socket, doubling after each one
+/// after that
+pub const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The color, power and label of a light, from its [Message::LightState] reply
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightState {
+    /// Hue, saturation, brightness and kelvin
+    pub color: HSBK,
+    /// Whether the light is on
+    pub power: PowerLevel,
+    /// The light's label
+    pub label: String,
+}
+
+/// A LIFX device reached directly over the LAN
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use lifx_rs::lan::{self, HSBK};
+///
+/// for found in lan::discover(Duration::from_secs(1)).unwrap() {
+///     let mut device = found.connect().unwrap();
+///     let state = device.get_color().unwrap();
+///     println!("{} is at brightness {}", state.label, state.color.brightness);
+///
+///     let red = HSBK { hue: 0, saturation: 65535, brightness: 65535, kelvin: 3500 };
+///     device.set_color(red, Duration::from_millis(500)).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Device {
+    serial: u64,
+    addr: SocketAddr,
+    /// None once closed by a failed reconnect; the next request opens a new one
+    socket: Option<UdpSocket>,
+    codec: Codec,
+    timeout: Duration,
+}
+
+impl Device {
+    /// Opens a socket for talking to the device with `serial` at `addr`
+    pub fn new(serial: u64, addr: SocketAddr) -> Result<Device, Error> {
+        Ok(Device {
+            serial,
+            addr,
+            socket: Some(bind_with_backoff()?),
+            codec: Codec::random(),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// The device's serial number
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    /// The address requests are sent to
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Sets how long to wait for each reply before giving up with a [io::ErrorKind::TimedOut] error
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Closes the device's socket and opens a new one, ex: after a network change. Requests do
+    /// this on their own when the socket fails; this is for doing it ahead of time.
+    ///
+    /// Opening is tried RECONNECT_ATTEMPTS times, waiting RECONNECT_BACKOFF after the first
+    /// failure and twice as long after each one after that. If every try fails, the error is
+    /// returned and the next request tries again.
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        self.socket = None;
+        self.socket = Some(bind_with_backoff()?);
+        Ok(())
+    }
+
+    /// Changes the light's color, fading over `duration`, and waits for the device to acknowledge it.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The color to change to
+    /// * `duration` - The length of the fade, rounded down to whole milliseconds
+    pub fn set_color(&mut self, color: HSBK, duration: Duration) -> Result<(), Error> {
+        match self.request(Message::LightSetColor { reserved: 0, color, duration: millis(duration) }, true, false)? {
+            Message::Acknowledgement { .. } => Ok(()),
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Returns the light's current color, power and label
+    pub fn get_color(&mut self) -> Result<LightState, Error> {
+        match self.request(Message::LightGet, false, true)? {
+            Message::LightState { color, power, label, .. } => Ok(LightState { color, power, label: label.0 }),
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Sets the zones from `start` to `end` (both included) of a multizone light to `color`.
+    ///
+    /// With [ApplicationRequest::NoApply] the change is buffered on the device until a later
+    /// message applies it, so several ranges can change at the same moment.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The first zone to set
+    /// * `end` - The last zone to set
+    /// * `color` - The color to change to
+    /// * `duration` - The length of the fade, rounded down to whole milliseconds
+    /// * `apply` - Whether to apply this change and any buffered ones now
+    pub fn set_color_zones(&mut self, start: u8, end: u8, color: HSBK, duration: Duration, apply: ApplicationRequest) -> Result<(), Error> {
+        let message = Message::SetColorZones { start_index: start, end_index: end, color, duration: millis(duration), apply };
+        match self.request(message, true, false)? {
+            Message::Acknowledgement { .. } => Ok(()),
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Fades the zones from `start` to `end` (both included) evenly from `from` to `to`.
+    ///
+    /// Every zone is sent with [Message::SetColorZones], buffered until the last one, so the whole
+    /// gradient appears at once. This works on every multizone light; for a long strip on recent
+    /// firmware, [Device::set_extended_color_zones] with [HSBK::gradient] needs a single message.
+    pub fn set_gradient(&mut self, start: u8, end: u8, from: HSBK, to: HSBK, duration: Duration) -> Result<(), Error> {
+        if start > end {
+            return Err(Error::ProtocolError(format!("Zone range {}..={} is empty", start, end)));
+        }
+        let colors = from.gradient(to, (end - start) as usize + 1);
+        for (zone, color) in (start..=end).zip(colors) {
+            let apply = if zone == end { ApplicationRequest::Apply } else { ApplicationRequest::NoApply };
+            self.set_color_zones(zone, zone, color, duration, apply)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the colors of the zones from `start` to `end` (both included), stopping early at the
+    /// last zone of the light.
+    pub fn get_color_zones(&mut self, start: u8, end: u8) -> Result<Vec<HSBK>, Error> {
+        let mut zones: Vec<Option<HSBK>> = Vec::new();
+        self.exchange(Message::GetColorZones { start_index: start, end_index: end }, false, true, |reply| {
+            let (count, index, colors) = match reply {
+                Message::StateZone { count, index, color } => (count, index, vec![color]),
+                Message::StateMultiZone { count, index, color0, color1, color2, color3, color4, color5, color6, color7 } => {
+                    (count, index, vec![color0, color1, color2, color3, color4, color5, color6, color7])
+                }
+                other => return Err(unexpected_reply(&other)),
+            };
+            if count <= start {
+                return Ok(Some(()));
+            }
+            let last = end.min(count - 1);
+            zones.resize((last - start) as usize + 1, None);
+            for (zone, color) in (index..).zip(colors) {
+                if zone >= start && zone <= last {
+                    zones[(zone - start) as usize] = Some(color);
+                }
+            }
+            Ok(if zones.iter().all(Option::is_some) { Some(()) } else { None })
+        })?;
+        Ok(zones.into_iter().flatten().collect())
+    }
+
+    /// Sets up to [EXTENDED_ZONES] consecutive zones, starting at `zone_index`, in one message.
+    ///
+    /// Needs firmware with extended multizone support; older strips only answer
+    /// [Device::set_color_zones].
+    ///
+    /// # Arguments
+    ///
+    /// * `zone_index` - The zone that gets the first color
+    /// * `colors` - The colors for each zone
+    /// * `duration` - The length of the fade, rounded down to whole milliseconds
+    /// * `apply` - Whether to apply this change and any buffered ones now
+    pub fn set_extended_color_zones(&mut self, zone_index: u16, colors: &[HSBK], duration: Duration, apply: ApplicationRequest) -> Result<(), Error> {
+        if colors.len() > EXTENDED_ZONES {
+            return Err(Error::ProtocolError(format!("{} colors don't fit in one message of {}", colors.len(), EXTENDED_ZONES)));
+        }
+        let mut padded = Box::new([HSBK::default(); EXTENDED_ZONES]);
+        padded[..colors.len()].copy_from_slice(colors);
+        let message = Message::SetExtendedColorZones {
+            duration: millis(duration),
+            apply,
+            zone_index,
+            colors_count: colors.len() as u8,
+            colors: padded,
+        };
+        match self.request(message, true, false)? {
+            Message::Acknowledgement { .. } => Ok(()),
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Returns the color of every zone, using the extended multizone messages
+    pub fn get_extended_color_zones(&mut self) -> Result<Vec<HSBK>, Error> {
+        let mut zones: Vec<Option<HSBK>> = Vec::new();
+        self.exchange(Message::GetExtendedColorZones, false, true, |reply| {
+            let (zones_count, zone_index, colors_count, colors) = match reply {
+                Message::StateExtendedColorZones { zones_count, zone_index, colors_count, colors } => (zones_count, zone_index, colors_count, colors),
+                other => return Err(unexpected_reply(&other)),
+            };
+            zones.resize(zones_count as usize, None);
+            for (zone, color) in (zone_index as usize..).zip(colors.iter().take(colors_count as usize)) {
+                if let Some(slot) = zones.get_mut(zone) {
+                    *slot = Some(*color);
+                }
+            }
+            Ok(if zones.iter().all(Option::is_some) { Some(()) } else { None })
+        })?;
+        Ok(zones.into_iter().flatten().collect())
+    }
+
+    /// Returns the tiles of a matrix device (Tile, Candle, Ceiling), in chain order
+    pub fn get_device_chain(&mut self) -> Result<Vec<TileInfo>, Error> {
+        match self.request(Message::GetDeviceChain, false, true)? {
+            Message::StateDeviceChain { tile_devices, tile_devices_count, .. } => {
+                Ok(tile_devices.iter().take(tile_devices_count as usize).copied().collect())
+            }
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Records where the tile at `tile_index` sits, in tile widths, for apps that lay out the chain
+    pub fn set_user_position(&mut self, tile_index: u8, user_x: f32, user_y: f32) -> Result<(), Error> {
+        match self.request(Message::SetUserPosition { tile_index, reserved: 0, user_x, user_y }, true, false)? {
+            Message::Acknowledgement { .. } => Ok(()),
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Sets up to [TILE_PIXELS] pixels of the tile at `tile_index`, row by row, in a rectangle
+    /// starting at column `x` and row `y` that is `width` pixels wide.
+    ///
+    /// An 8x8 tile takes all 64 pixels with `x`, `y` of 0 and a `width` of 8. Larger matrices, like
+    /// the 16x8 Ceiling, take one call per 64 pixel block.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_index` - The tile to set, from [Device::get_device_chain]
+    /// * `x` - The column of the first pixel
+    /// * `y` - The row of the first pixel
+    /// * `width` - The width of the rectangle; usually the tile's width
+    /// * `colors` - The pixel colors, row by row
+    /// * `duration` - The length of the fade, rounded down to whole milliseconds
+    pub fn set64(&mut self, tile_index: u8, x: u8, y: u8, width: u8, colors: &[HSBK], duration: Duration) -> Result<(), Error> {
+        if colors.len() > TILE_PIXELS {
+            return Err(Error::ProtocolError(format!("{} colors don't fit in one message of {}", colors.len(), TILE_PIXELS)));
+        }
+        let mut padded = Box::new([HSBK::default(); TILE_PIXELS]);
+        padded[..colors.len()].copy_from_slice(colors);
+        let message = Message::Set64 { tile_index, length: 1, reserved: 0, x, y, width, duration: millis(duration), colors: padded };
+        match self.request(message, true, false)? {
+            Message::Acknowledgement { .. } => Ok(()),
+            other => Err(unexpected_reply(&other)),
+        }
+    }
+
+    /// Returns the [TILE_PIXELS] pixels of the tile at `tile_index`, row by row, for the rectangle
+    /// starting at column `x` and row `y` that is `width` pixels wide.
+    pub fn get64(&mut self, tile_index: u8, x: u8, y: u8, width: u8) -> Result<Vec<HSBK>, Error> {
+        let message = Message::Get64 { tile_index, length: 1, reserved: 0, x, y, width };
+        self.exchange(message, false, true, |reply| match reply {
+            Message::State64 { tile_index: index, colors, .. } if index == tile_index => Ok(Some(colors.to_vec())),
+            Message::State64 { .. } => Ok(None),
+            other => Err(unexpected_reply(&other)),
+        })
+    }
+
+    /// Sends `message` and returns the first reply to it, skipping packets meant for other requests
+    fn request(&mut self, message: Message, ack_required: bool, res_required: bool) -> Result<Message, Error> {
+        self.exchange(message, ack_required, res_required, |reply| Ok(Some(reply)))
+    }
+
+    /// Sends `message` and feeds each reply to it to `on_reply` until that returns a result. If
+    /// the socket fails, it's replaced with a new one and the message is sent again, once.
+    fn exchange<T>(
+        &mut self,
+        message: Message,
+        ack_required: bool,
+        res_required: bool,
+        mut on_reply: impl FnMut(Message) -> Result<Option<T>, Error>,
+    ) -> Result<T, Error> {
+        let (sequence, packet) = self.codec.encode(message, Some(self.serial), ack_required, res_required)?;
+        match self.send_and_receive(&packet, sequence, &mut on_reply) {
+            // Anything but the device not answering means the socket itself is broken
+            Err(Error::Io(err)) if err.kind() != io::ErrorKind::TimedOut => {
+                self.reconnect()?;
+                self.send_and_receive(&packet, sequence, &mut on_reply)
+            }
+            result => result,
+        }
+    }
+
+    /// Sends `packet`, then feeds each reply to request `sequence` to `on_reply` until that
+    /// returns a result
+    fn send_and_receive<T>(
+        &mut self,
+        packet: &[u8],
+        sequence: u8,
+        on_reply: &mut impl FnMut(Message) -> Result<Option<T>, Error>,
+    ) -> Result<T, Error> {
+        let socket = open(&mut self.socket)?;
+        socket.send_to(packet, self.addr)?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = [0u8; 1024];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "no reply from device").into());
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            let len = match socket.recv_from(&mut buf) {
+                Ok((len, from)) if from == self.addr => len,
+                Ok(_) => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if let Ok((header, reply)) = Codec::decode(&buf[..len]) {
+                if self.codec.is_reply(&header, sequence) {
+                    if let Some(done) = on_reply(reply)? {
+                        return Ok(done);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl DiscoveredDevice {
+    /// Opens a [Device] for talking to this device
+    pub fn connect(&self) -> Result<Device, Error> {
+        Device::new(self.serial, SocketAddr::new(self.ip, self.port))
+    }
+}
+
+/// Opens a socket for talking to devices, trying again with backoff, see [Device::reconnect]
+fn bind_with_backoff() -> io::Result<UdpSocket> {
+    let mut delay = RECONNECT_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match UdpSocket::bind("0.0.0.0:0") {
+            Err(_) if attempt < RECONNECT_ATTEMPTS => {
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The socket in `slot`, opening a new one first if it was closed
+fn open(slot: &mut Option<UdpSocket>) -> Result<&UdpSocket, Error> {
+    let socket = match slot.take() {
+        Some(socket) => socket,
+        None => bind_with_backoff()?,
+    };
+    Ok(slot.insert(socket))
+}
+
+/// Converts a fade length to the protocol's milliseconds
+fn millis(duration: Duration) -> u32 {
+    u32::try_from(duration.as_millis()).unwrap_or(u32::MAX)
+}
+
+fn unexpected_reply(reply: &Message) -> Error {
+    Error::ProtocolError(format!("Unexpected reply of type {}", reply.get_num()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lan::{BuildOptions, LifxString, RawMessage};
+
+    #[test]
+    fn test_set_and_get_color() {
+        let serial = 0x0000_d5d3_73d0;
+        let light = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = light.local_addr().unwrap();
+        let color = HSBK { hue: 21845, saturation: 65535, brightness: 32768, kelvin: 3500 };
+
+        // A fake light that acknowledges the SetColor, ignores a stale packet and answers the Get
+        let responder = std::thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            for _ in 0..2 {
+                let (len, from) = light.recv_from(&mut buf).unwrap();
+                let (request, message) = Codec::decode(&buf[..len]).unwrap();
+                assert_eq!(request.frame_addr.target, serial);
+                let options = BuildOptions {
+                    target: Some(serial),
+                    source: request.frame.source,
+                    sequence: request.frame_addr.sequence,
+                    ..Default::default()
+                };
+                let reply = match message {
+                    Message::LightSetColor { color: set, duration, .. } => {
+                        assert_eq!((set, duration), (color, 500));
+                        assert!(request.frame_addr.ack_required);
+                        Message::Acknowledgement { seq: request.frame_addr.sequence }
+                    }
+                    Message::LightGet => {
+                        let stale = BuildOptions { sequence: request.frame_addr.sequence.wrapping_sub(1), ..options.clone() };
+                        light.send_to(&RawMessage::build(&stale, Message::Acknowledgement { seq: 0 }).unwrap().pack().unwrap(), from).unwrap();
+                        Message::LightState {
+                            color,
+                            reserved: 0,
+                            power: PowerLevel::Enabled,
+                            label: LifxString::new("Kitchen"),
+                            reserved2: 0,
+                        }
+                    }
+                    other => panic!("unexpected request {:?}", other),
+                };
+                light.send_to(&RawMessage::build(&options, reply).unwrap().pack().unwrap(), from).unwrap();
+            }
+        });
+
+        let mut device = Device::new(serial, addr).unwrap();
+        device.set_color(color, Duration::from_millis(500)).unwrap();
+        let state = device.get_color().unwrap();
+        responder.join().unwrap();
+        assert_eq!(state, LightState { color, power: PowerLevel::Enabled, label: "Kitchen".to_string() });
+    }
+
+    /// Starts a fake device that answers each of `requests` requests with the messages `handle`
+    /// returns, and records what it was sent
+    fn fake_device<F>(requests: usize, mut handle: F) -> (SocketAddr, std::thread::JoinHandle<Vec<Message>>)
+    where
+        F: FnMut(Message) -> Vec<Message> + Send + 'static,
+    {
+        let light = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = light.local_addr().unwrap();
+        let responder = std::thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            let mut received = Vec::new();
+            for _ in 0..requests {
+                let (len, from) = light.recv_from(&mut buf).unwrap();
+                let (request, message) = Codec::decode(&buf[..len]).unwrap();
+                let options = BuildOptions {
+                    target: Some(request.frame_addr.target),
+                    source: request.frame.source,
+                    sequence: request.frame_addr.sequence,
+                    ..Default::default()
+                };
+                for reply in handle(message.clone()) {
+                    light.send_to(&RawMessage::build(&options, reply).unwrap().pack().unwrap(), from).unwrap();
+                }
+                received.push(message);
+            }
+            received
+        });
+        (addr, responder)
+    }
+
+    fn zone(n: u16) -> HSBK {
+        HSBK { hue: n, saturation: 65535, brightness: 65535, kelvin: 3500 }
+    }
+
+    #[test]
+    fn test_get_color_zones() {
+        // A ten zone strip answers with two StateMultiZone messages, the second running past the end
+        let (addr, responder) = fake_device(2, |_| {
+            vec![
+                Message::StateMultiZone { count: 10, index: 8, color0: zone(8), color1: zone(9), color2: zone(0), color3: zone(0), color4: zone(0), color5: zone(0), color6: zone(0), color7: zone(0) },
+                Message::StateMultiZone { count: 10, index: 0, color0: zone(0), color1: zone(1), color2: zone(2), color3: zone(3), color4: zone(4), color5: zone(5), color6: zone(6), color7: zone(7) },
+            ]
+        });
+        let mut device = Device::new(1, addr).unwrap();
+        let zones = device.get_color_zones(0, 255).unwrap();
+        assert_eq!(zones, (0..10).map(zone).collect::<Vec<_>>());
+
+        let zones = device.get_color_zones(3, 4).unwrap();
+        assert_eq!(zones, vec![zone(3), zone(4)]);
+        responder.join().unwrap();
+    }
+
+    #[test]
+    fn test_set_gradient() {
+        let (addr, responder) = fake_device(3, |_| vec![Message::Acknowledgement { seq: 0 }]);
+        let mut device = Device::new(1, addr).unwrap();
+        device.set_gradient(4, 6, zone(0), zone(1000), Duration::from_secs(1)).unwrap();
+
+        let sent = responder.join().unwrap();
+        let zones: Vec<_> = sent
+            .iter()
+            .map(|message| match message {
+                Message::SetColorZones { start_index, end_index, color, duration: 1000, apply } => (*start_index, *end_index, color.hue, *apply as u8),
+                other => panic!("unexpected request {:?}", other),
+            })
+            .collect();
+        assert_eq!(zones, vec![(4, 4, 0, 0), (5, 5, 500, 0), (6, 6, 1000, 1)]);
+        assert!(device.set_gradient(6, 4, zone(0), zone(1000), Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_extended_color_zones() {
+        // 100 zones take two StateExtendedColorZones messages
+        let (addr, responder) = fake_device(2, |request| match request {
+            Message::SetExtendedColorZones { .. } => vec![Message::Acknowledgement { seq: 0 }],
+            Message::GetExtendedColorZones => {
+                let mut first = Box::new([zone(0); EXTENDED_ZONES]);
+                let mut second = Box::new([zone(0); EXTENDED_ZONES]);
+                for (n, color) in first.iter_mut().enumerate() {
+                    *color = zone(n as u16);
+                }
+                for (n, color) in second.iter_mut().take(18).enumerate() {
+                    *color = zone(82 + n as u16);
+                }
+                vec![
+                    Message::StateExtendedColorZones { zones_count: 100, zone_index: 0, colors_count: 82, colors: first },
+                    Message::StateExtendedColorZones { zones_count: 100, zone_index: 82, colors_count: 18, colors: second },
+                ]
+            }
+            other => panic!("unexpected request {:?}", other),
+        });
+        let mut device = Device::new(1, addr).unwrap();
+        let gradient = zone(0).gradient(zone(20), 3);
+        device.set_extended_color_zones(5, &gradient, Duration::from_millis(0), ApplicationRequest::Apply).unwrap();
+        assert_eq!(device.get_extended_color_zones().unwrap(), (0..100).map(zone).collect::<Vec<_>>());
+
+        let sent = responder.join().unwrap();
+        match &sent[0] {
+            Message::SetExtendedColorZones { zone_index: 5, colors_count: 3, colors, .. } => assert_eq!(&colors[..4], &[zone(0), zone(10), zone(20), HSBK::default()][..]),
+            other => panic!("unexpected request {:?}", other),
+        }
+        assert!(device.set_extended_color_zones(0, &[zone(0); EXTENDED_ZONES + 1], Duration::from_millis(0), ApplicationRequest::Apply).is_err());
+    }
+
+    #[test]
+    fn test_tiles() {
+        let (addr, responder) = fake_device(4, |request| match request {
+            Message::GetDeviceChain => {
+                let mut tile_devices = Box::new([TileInfo::default(); crate::lan::MAX_TILES]);
+                tile_devices[0] = TileInfo { width: 8, height: 8, ..Default::default() };
+                tile_devices[1] = TileInfo { width: 8, height: 8, user_x: 1.0, ..Default::default() };
+                vec![Message::StateDeviceChain { start_index: 0, tile_devices, tile_devices_count: 2 }]
+            }
+            Message::SetUserPosition { .. } | Message::Set64 { .. } => vec![Message::Acknowledgement { seq: 0 }],
+            Message::Get64 { tile_index: 1, x: 0, y: 0, width: 8, .. } => vec![Message::State64 {
+                tile_index: 1,
+                reserved: 0,
+                x: 0,
+                y: 0,
+                width: 8,
+                colors: Box::new([zone(7); TILE_PIXELS]),
+            }],
+            other => panic!("unexpected request {:?}", other),
+        });
+        let mut device = Device::new(1, addr).unwrap();
+        let chain = device.get_device_chain().unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!((chain[1].width, chain[1].height, chain[1].user_x), (8, 8, 1.0));
+
+        device.set_user_position(1, 1.0, 0.5).unwrap();
+        device.set64(1, 0, 4, 8, &[zone(3); 32], Duration::from_millis(100)).unwrap();
+        assert_eq!(device.get64(1, 0, 0, 8).unwrap(), vec![zone(7); TILE_PIXELS]);
+        assert!(device.set64(1, 0, 0, 8, &[zone(3); TILE_PIXELS + 1], Duration::from_millis(0)).is_err());
+
+        let sent = responder.join().unwrap();
+        assert!(matches!(sent[1], Message::SetUserPosition { tile_index: 1, user_x, user_y, .. } if user_x == 1.0 && user_y == 0.5));
+        match &sent[2] {
+            Message::Set64 { tile_index: 1, length: 1, x: 0, y: 4, width: 8, duration: 100, colors, .. } => {
+                assert_eq!(colors[31], zone(3));
+                assert_eq!(colors[32], HSBK::default());
+            }
+            other => panic!("unexpected request {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let (addr, responder) = fake_device(4, |_| vec![Message::Acknowledgement { seq: 0 }]);
+        let mut device = Device::new(1, addr).unwrap();
+        device.set_color(zone(1), Duration::ZERO).unwrap();
+
+        // The socket was closed, ex: by a failed reconnect while the network was down
+        drop(device.socket.take());
+        device.set_color(zone(2), Duration::ZERO).unwrap();
+
+        let local = device.socket.as_ref().unwrap().local_addr().unwrap();
+        device.reconnect().unwrap();
+        assert_ne!(device.socket.as_ref().unwrap().local_addr().unwrap(), local);
+        device.set_color(zone(3), Duration::ZERO).unwrap();
+
+        // A socket that can't reach the device any more, where the host has IPv6 loopback to
+        // make one: the request fails on it, rebinds and goes out on the new socket
+        match UdpSocket::bind("[::1]:0") {
+            Ok(stale) => device.socket = Some(stale),
+            Err(_) => drop(device.socket.take()),
+        }
+        device.set_color(zone(4), Duration::ZERO).unwrap();
+
+        let received = responder.join().unwrap();
+        let colors: Vec<_> = received.iter().map(|message| match message {
+            Message::LightSetColor { color, .. } => color.hue,
+            other => panic!("unexpected request {:?}", other),
+        }).collect();
+        assert_eq!(colors, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut device = Device::new(1, silent.local_addr().unwrap()).unwrap();
+        device.set_timeout(Duration::from_millis(50));
+        match device.get_color() {
+            Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+}
@@ -42,6 +42,16 @@
 //! ## To use offline use the Un-Official API Server:
 //! [lifx-api-server](https://crates.io/crates/lifx-api-server)
 //!
+//! ## Cargo features
+//! * `client` (default) - The HTTP API methods on Light, Scene and Color, using reqwest and tokio.
+//! * `tls` (default) - HTTPS for the HTTP API methods, and the TLS settings of HttpOptions.
+//! * `products-db` - Offline product capability lookups, see the products module.
+//! * `scheduler` - Interval and cron jobs run from a background tokio task, see the scheduler module.
+//! * `config` - LifxConfig::from_file, for reading the config from a JSON file.
+//!
+//! With `default-features = false` only the [core] module is built: the data types and their
+//! param building, without reqwest.
+//!
 //! ## How to use library
 //!
 //! Add the following line to your cargo.toml:
@@ -67,7 +77,8 @@
 //!
 //!     let config = lifx::LifxConfig{
 //!         access_token: key.clone(),
-//!         api_endpoints: api_endpoints
+//!         api_endpoints: api_endpoints,
+//!         ..Default::default()
 //!     };
 //!
 //!     // Build an "OffState" to set
@@ -118,7 +129,8 @@
 //!
 //!     let config = lifx::LifxConfig{
 //!         access_token: key.clone(),
-//!         api_endpoints: api_endpoints
+//!         api_endpoints: api_endpoints,
+//!         ..Default::default()
 //!     };
 //!
 //!     // Build "OffState" to set
@@ -159,449 +171,362 @@
 //!  * XLM: GCJAUMCO2L7PTYMXELQ6GHBTF25MCQKEBNSND2C4QMUPTSVCPEN3LCOG
 //!  * XTZ: tz1SgJppPn56whprsDDGcqR4fxqCr2PXvg1R
 
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub mod client;
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub mod apply;
+#[cfg(all(feature = "config", any(feature = "blocking", feature = "async")))]
+mod config_file;
+pub mod core;
 pub mod lan;
+#[cfg(feature = "products-db")]
+pub mod products;
+#[cfg(feature = "async")]
+pub mod routines;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+#[cfg(feature = "async")]
+pub mod sequence;
+#[cfg(feature = "async")]
+pub mod transition;
+#[cfg(feature = "async")]
+pub mod watch;
+#[cfg(all(any(test, feature = "test-util"), any(feature = "blocking", feature = "async")))]
+pub mod test_util;
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub mod transport;
 
 
 
 use serde::{Serialize, Deserialize};
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::sync::{Mutex, OnceLock};
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub use crate::client::{EndpointHealth, LifxClient, LifxError, RequestTiming, ENDPOINT_RECHECK_AFTER};
+pub use crate::core::*;
 
 
 
 
 
 /// Represents a LIFX Config Object
-/// Supports two api_endpoints.....if the first one fails...falls back on second api
-/// TODO - Support unlimited api_endpoints
-/// TODO - Use multithreaded timeout to detect primary api failures faster
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Supports any number of api_endpoints: if one can't be reached, requests fall back on the next
+///
+/// The access token is redacted when the config is printed with Debug or serialized, so configs
+/// can be logged safely. Use exposing_token to serialize a config with its token, ex: to save it.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LifxConfig {
+    #[serde(serialize_with = "serialize_token")]
     pub access_token: String,
     pub api_endpoints: Vec<String>,
+    /// Transition time in seconds used by set_state, set_states, state_delta and toggle calls
+    /// that don't set a duration of their own. Effect and clean durations are never defaulted,
+    /// since there they control how long the effect runs rather than how long the fade takes.
+    #[serde(default)]
+    pub default_duration: Option<f64>,
+    /// The order api_endpoints are tried in. Defaults to EndpointStrategy::Sequential.
+    #[serde(default)]
+    pub endpoint_strategy: EndpointStrategy,
+    /// Which of the api_endpoints are local servers, ex: "http://localhost:8089".
+    /// Endpoints not listed here are treated as cloud endpoints.
+    #[serde(default)]
+    pub local_endpoints: Vec<String>,
+    /// Priorities, weights and read-only flags for api_endpoints, see EndpointOptions. Endpoints
+    /// without options have priority 0 and weight 1 and take writes.
+    #[serde(default)]
+    pub endpoint_options: Vec<EndpointOptions>,
+    /// When the HTTP API reports that the access token has no requests left, wait until the rate
+    /// limit resets before sending the next request, instead of having it refused with 429 Too
+    /// Many Requests. Off by default. See LifxClient::rate_limit.
+    #[serde(default)]
+    pub wait_on_rate_limit: bool,
+    /// How often a request is retried on an endpoint that can't be reached before falling back to
+    /// the next one. Defaults to no retries.
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// How many retries every request sharing a LifxClient may make between them, so a burst of
+    /// concurrent requests failing together doesn't turn into a retry storm. Defaults to 10
+    /// retries, refilling at 1 a second. See RetryBudget.
+    #[serde(default)]
+    pub retry_budget: RetryBudget,
+    /// Seconds each attempt at a request may take, from connecting to reading the whole response,
+    /// before it fails with a timeout. It applies to every retry and to the fallback endpoint too,
+    /// so one call can take up to this times the number of attempts. None uses reqwest's default,
+    /// which is no timeout for async calls and 30 seconds for blocking ones.
+    #[serde(default)]
+    pub timeout: Option<f64>,
+    /// Proxy, user agent, header and TLS settings for the HTTP clients, see LifxConfig::builder
+    #[serde(default)]
+    pub http: HttpOptions,
+    /// Send effects and clean to lights whose product lacks the capability they need, instead of
+    /// returning LifxError::Unsupported, ex: to try a new product this crate's Capabilities don't
+    /// know about yet. Off by default. Only the instance methods like Light::morph_effect check;
+    /// by-selector calls can't know which lights they'll reach.
+    #[serde(default)]
+    pub skip_capability_checks: bool,
+    /// Fail HTTP responses that have fields this crate doesn't model with
+    /// LifxError::UnexpectedFields, listing them, instead of keeping them in the `extra` maps.
+    /// For spotting a server whose responses have drifted from the API's. Off by default.
+    ///
+    /// Checking costs a second parse of every response body and a serialization of what was
+    /// decoded from it, which is small next to the request itself.
+    #[serde(default)]
+    pub deny_unknown_fields: bool,
+    /// Seconds Scene::list_cached and find_by_name reuse an account's scene list before listing
+    /// the scenes again; 0 turns the cache off. None uses SCENE_CACHE_TTL.
+    #[serde(default)]
+    pub scene_cache_ttl: Option<f64>,
+    /// Where requests go instead of the api_endpoints, see with_transport. None sends them to the
+    /// LIFX HTTP API.
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[serde(skip)]
+    pub transport: Option<transport::SharedTransport>,
 }
 
-
-pub type Lights = Vec<Light>;
-
-/// Represents a LIFX Light Object
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Light {
-    pub id: String,
-    pub uuid: String,
-    pub label: String,
-    pub connected: bool,
-    pub power: String,
-    pub color: Color,
-    pub brightness: f64,
-    pub group: Group,
-    pub location: Location,
-    pub product: Product,
-    #[serde(rename = "last_seen")]
-    pub last_seen: String,
-    #[serde(rename = "seconds_since_seen")]
-    pub seconds_since_seen: i64,
-    pub error: Option<String>,
-    pub errors: Option<Vec<Error>>,
-}
-impl Light {
-
-    /// Asynchronously set the breathe animation for the current light
+impl LifxConfig {
+    /// Starts a fluent builder for a LifxConfig, including the HTTP settings that have no field
+    /// of their own in the struct literal form
     /// 
-    /// # Arguments
-    ///
-    /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `breathe` - A BreatheEffect object containing the values to set
-    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
-    /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
-    ///
-    ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
-    ///     };
+    /// fn main() {
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut breathe = lifx::BreatheEffect::new();
-    ///             breathe.color = Some(format!("red"));
-    ///             breathe.from_color = Some(format!("green"));
-    ///             breathe.period = Some(10);
-    ///             breathe.persist = Some(true);
-    ///             breathe.power_on = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.async_breathe_effect(key.clone(), breathe.clone()).await;
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
-    ///     }
+    ///     let config = lifx::LifxConfig::builder()
+    ///         .access_token("xxx")
+    ///         .endpoint("https://api.lifx.com")
+    ///         .endpoint("http://localhost:8089")
+    ///         .timeout(5.0)
+    ///         .proxy("http://proxy.local:3128")
+    ///         .user_agent("porch-lights/1.0")
+    ///         .header("X-Request-Source", "porch")
+    ///         .build()
+    ///         .unwrap();
+    /// 
+    ///     let lights = lifx::Light::list_all(config);
     /// }
     ///  ```
-    pub async fn async_breathe_effect(&self, config: LifxConfig, breathe: BreatheEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_breathe_effect_by_selector(config, format!("id:{}", self.id), breathe).await;
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub fn builder() -> LifxConfigBuilder {
+        LifxConfigBuilder::default()
     }
 
-    /// Asynchronously activate the breathe animation for the selected light(s)
-    /// 
-    /// # Arguments
-    ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `breathe` - A BreatheEffect object containing the values to set
+    /// Returns a config read from the environment: the access token from LIFX_ACCESS_TOKEN, and
+    /// the api_endpoints from LIFX_API_ENDPOINTS as a comma separated list, in the order to try
+    /// them. Without LIFX_API_ENDPOINTS, requests go to https://api.lifx.com.
     ///
+    /// Returns LifxError::Config if LIFX_ACCESS_TOKEN isn't set, LIFX_API_ENDPOINTS is set but
+    /// lists no endpoints, or either variable isn't unicode.
+    /// 
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
-    /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
-    ///
-    ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
-    ///     };
+    /// fn main() {
     /// 
-    ///     let mut breathe = lifx::BreatheEffect::new();
-    ///     breathe.color = Some(format!("red"));
-    ///     breathe.from_color = Some(format!("green"));
-    ///     breathe.period = Some(10);
-    ///     breathe.persist = Some(true);
-    ///     breathe.power_on = Some(true);
-    ///     
-    ///     // Apply breathe effect to all light(s)
-    ///     lifx::Light::async_breathe_effect_by_selector(key.clone(), format!("all"), breathe).await;
+    ///     // LIFX_ACCESS_TOKEN=xxx LIFX_API_ENDPOINTS=http://localhost:8089,https://api.lifx.com
+    ///     let config = lifx::LifxConfig::from_env().unwrap();
+    ///     let lights = lifx::Light::list_all(config);
     /// }
     ///  ```
-    pub async fn async_breathe_effect_by_selector(config: LifxConfig, selector: String, breathe: BreatheEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/breathe", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&breathe.to_params())
-            .send().await;
-            
-        match request{
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/breathe", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&breathe.to_params())
-                        .send().await;
-                        
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub fn from_env() -> Result<LifxConfig, LifxError> {
+        LifxConfig::from_vars(|name| match std::env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(err) => Err(LifxError::Config(format!("{}: {}", name, err))),
+        })
+    }
+
+    /// from_env, reading the variables through `var`
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn from_vars<F>(var: F) -> Result<LifxConfig, LifxError>
+    where
+        F: Fn(&str) -> Result<Option<String>, LifxError>,
+    {
+        let access_token = var("LIFX_ACCESS_TOKEN")?
+            .ok_or_else(|| LifxError::Config("LIFX_ACCESS_TOKEN is not set".to_string()))?;
+        let api_endpoints = match var("LIFX_API_ENDPOINTS")? {
+            Some(endpoints) => endpoints.split(',').map(str::trim).filter(|endpoint| !endpoint.is_empty()).map(String::from).collect(),
+            None => vec!["https://api.lifx.com".to_string()],
+        };
+        let config = LifxConfig { access_token, api_endpoints, ..Default::default() };
+        match config.validate() {
+            // Only the endpoints come from the environment unchecked, so that's where the blame goes
+            Err(LifxError::Config(message)) if message == client::NO_ENDPOINTS => {
+                Err(LifxError::Config(format!("LIFX_API_ENDPOINTS: {}", message)))
             }
+            Err(err) => Err(err),
+            Ok(()) => Ok(config),
         }
-    
-
     }
 
-
-    /// Asynchronously switch a light to clean mode, with a set duration. 
+    /// Returns a wrapper that serializes this config with its access token, which plain
+    /// serialization redacts. Only use it where the token belongs, ex: a config file only the
+    /// owner can read.
     /// 
-    /// # Arguments
-    ///
-    /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `clean` - A Clean object containing the values to set
-    ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// fn main() {
     /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
-    ///
     ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        access_token: "xxx".to_string(),
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut clean = lifx::Clean::new();
-    ///             clean.duration = Some(0);
-    ///             clean.stop = Some(false);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.async_clean(key.clone(), clean.clone()).await;
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
-    ///     }
+    ///     let logged = serde_json::to_value(&config).unwrap();
+    ///     assert_eq!(logged["accessToken"], "[redacted]");
+    /// 
+    ///     let saved = serde_json::to_value(config.exposing_token()).unwrap();
+    ///     assert_eq!(saved["accessToken"], "xxx");
     /// }
     ///  ```
-    pub async fn async_clean(&self, config: LifxConfig, clean: Clean) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_clean_by_selector(config, format!("id:{}", self.id), clean).await;
+    pub fn exposing_token(&self) -> ExposedToken<'_> {
+        ExposedToken(self)
     }
 
-    /// Asynchronously switch a selected LIFX object to clean mode, with a set duration. 
+    /// Returns a copy of this config that talks to `endpoints` instead of api_endpoints, keeping the
+    /// access token and every other setting. Handy for a one-off call against a test server; the
+    /// original config is left unchanged.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `clean` - A Clean object containing the values to set
+    /// * `endpoints` - The endpoints to use, in the order to try them
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
     ///
     ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut clean = lifx::Clean::new();
-    ///     clean.duration = Some(0);
-    ///     clean.stop = Some(false);
-    ///     
-    ///     // Set all light to clean mode
-    ///     lifx::Light::async_clean_by_selector(key.clone(), format!("all"), clean).await;
+    ///     // List the lights known to a local test server only
+    ///     let lights = lifx::Light::list_all(config.with_endpoints(&["http://localhost:8089"]));
     /// }
     ///  ```
-    pub async fn async_clean_by_selector(config: LifxConfig, selector: String, clean: Clean) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/clean", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&clean.to_params())
-            .send().await;
-
-        match request{
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/clean", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&clean.to_params())
-                        .send().await;
-            
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
+    pub fn with_endpoints(&self, endpoints: &[&str]) -> LifxConfig {
+        LifxConfig {
+            api_endpoints: endpoints.iter().map(|endpoint| endpoint.to_string()).collect(),
+            ..self.clone()
         }
-    
-
     }
 
-
-    /// Stops animation(s) for the current light
+    /// Returns a copy of this config whose requests time out after `timeout` seconds, keeping every
+    /// other setting. Handy for bounding a single call; the original config is left unchanged.
     /// 
     /// # Arguments
     ///
-    /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `flame_effect` - A FlameEffect object containing the values to set
+    /// * `timeout` - Seconds each attempt at a request may take, see LifxConfig::timeout
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
     ///
     ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut effects_off = lifx::EffectsOff::new();
-    ///             effects_off.power_off = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.async_effects_off(key.clone(), effects_off.clone()).await;
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
-    ///     }
+    ///     // Give up on the list after two seconds
+    ///     let lights = lifx::Light::list_all(config.with_timeout(2.0));
     /// }
     ///  ```
-    pub async fn async_effects_off(&self, config: LifxConfig, effects_off: EffectsOff) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_effects_off_by_selector(config, format!("id:{}", self.id), effects_off).await;
+    pub fn with_timeout(&self, timeout: f64) -> LifxConfig {
+        LifxConfig {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
     }
 
-    /// Stops animation(s) for the selected light(s)
+    /// Returns a copy of this config that sends every Light, Scene and Color request through
+    /// `transport` instead of the LIFX HTTP API. Clones of the returned config share the transport.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `effects_off` - A EffectsOff object containing the values to set
+    /// * `transport` - The transport to use, ex: a LAN transport or a fake for tests
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// use std::sync::Arc;
+    /// 
+    /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
     ///
     ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut effects_off = lifx::EffectsOff::new();
-    ///     effects_off.power_off = Some(true);
-    ///     
-    ///     // Send morph effect to all lights
-    ///     lifx::Light::async_effects_off_by_selector(key.clone(), format!("all"), effects_off).await;
+    ///     // The cloud as an explicit transport, ready to be wrapped in one of your own
+    ///     let cloud = Arc::new(lifx::transport::CloudTransport::new(config.clone()));
+    ///     let lights = lifx::Light::list_all(config.with_transport(cloud));
     /// }
     ///  ```
-    pub async fn async_effects_off_by_selector(config: LifxConfig, selector: String, effects_off: EffectsOff) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/off", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&effects_off.to_params())
-            .send().await;
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/off", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&effects_off.to_params())
-                        .send().await;
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                
-                } else {
-                    return Err(err);
-                }
-            }
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub fn with_transport<T: transport::Transport + 'static>(&self, transport: std::sync::Arc<T>) -> LifxConfig {
+        LifxConfig {
+            transport: Some(transport::SharedTransport(transport)),
+            ..self.clone()
         }
-    
-
     }
 
-
-
-    /// Activate the flame animation for the current light
+    /// Starts a LightOps chain over every light on the account
     /// 
-    /// # Arguments
-    ///
-    /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `flame_effect` - A FlameEffect object containing the values to set
-    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -611,123 +536,536 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut flame_effect = lifx::FlameEffect::new();
-    ///             flame_effect.period = Some(10);
-    ///             flame_effect.duration = Some(0);
-    ///             flame_effect.power_on = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.async_flame_effect(key.clone(), flame_effect.clone()).await;
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
-    ///     }
+    ///     // Dim whatever is on in the lounge
+    ///     let results = config.lights().filter_on().in_group("Lounge").set_brightness(0.5).run();
+    ///     println!("{:?}", results);
     /// }
     ///  ```
-    pub async fn async_flame_effect(&self, config: LifxConfig, flame_effect: FlameEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_flame_effect_by_selector(config, format!("id:{}", self.id), flame_effect).await;
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub fn lights(&self) -> LightOps {
+        LightOps { config: self.clone(), filters: Vec::new() }
     }
 
-    /// Activate the flame animation for the selected light(s)
+    /// Checks the settings requests can't be sent with, returning LifxError::Config for the first
+    /// problem found: no api_endpoints (unless the config has a transport), a timeout that isn't
+    /// a positive number of seconds, or a negative or infinite default_duration or
+    /// scene_cache_ttl. HTTP settings are checked when a
+    /// LifxClient is built, see LifxConfigBuilder::build.
     /// 
-    /// # Arguments
-    ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `flame_effect` - A FlameEffect object containing the values to set
-    ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
-    /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
-    ///
+    /// fn main() {
     ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        access_token: "xxx".to_string(),
+    ///        ..Default::default()
     ///     };
-    /// 
-    ///     let mut flame_effect = lifx::FlameEffect::new();
-    ///     flame_effect.period = Some(10);
-    ///     flame_effect.duration = Some(0);
-    ///     flame_effect.power_on = Some(true);
-    ///     
-    ///     // Send morph effect to all lights
-    ///     lifx::Light::async_flame_effect_by_selector(key.clone(), format!("all"), flame_effect).await;
+    ///     assert!(config.validate().is_err());
+    ///     assert!(config.with_endpoints(&["https://api.lifx.com"]).validate().is_ok());
     /// }
     ///  ```
-    pub async fn async_flame_effect_by_selector(config: LifxConfig, selector: String, flame_effect: FlameEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/flame", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&flame_effect.to_params())
-            .send().await;
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/flame", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&flame_effect.to_params())
-                        .send().await;
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub fn validate(&self) -> Result<(), LifxError> {
+        if self.api_endpoints.is_empty() && self.transport.is_none() {
+            return Err(LifxError::Config(client::NO_ENDPOINTS.to_string()));
+        }
+        match self.timeout {
+            Some(timeout) if !(timeout.is_finite() && timeout > 0.0) => {
+                return Err(LifxError::Config(format!("timeout must be a positive number of seconds, got {}", timeout)));
+            }
+            _ => {}
+        }
+        // 0 is allowed for both: an instant transition, and no scene cache
+        let seconds = [("default_duration", self.default_duration), ("scene_cache_ttl", self.scene_cache_ttl)];
+        for (name, value) in seconds {
+            match value {
+                Some(value) if !(value.is_finite() && value >= 0.0) => {
+                    return Err(LifxError::Config(format!("{} must be a non-negative number of seconds, got {}", name, value)));
                 }
+                _ => {}
             }
         }
-    
+        Ok(())
+    }
+
+    /// Returns the api_endpoints in the order requests should try them, per the endpoint_strategy
+    /// and then the endpoint_options. Read-only endpoints are left out for writes.
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn ordered_endpoints(&self, write: bool) -> Vec<&str> {
+        let mut endpoints: Vec<&str> = self.api_endpoints.iter().map(String::as_str).collect();
+        match self.endpoint_strategy {
+            EndpointStrategy::Sequential => {}
+            EndpointStrategy::PreferLocal => {
+                endpoints.sort_by_key(|endpoint| !self.local_endpoints.iter().any(|local| local == endpoint));
+            }
+            EndpointStrategy::Healthiest => client::healthiest_first(&mut endpoints, Instant::now()),
+        }
+        if self.endpoint_options.is_empty() {
+            return endpoints;
+        }
+        if write {
+            endpoints.retain(|endpoint| !self.options_for(endpoint).read_only);
+        }
+        endpoints.sort_by_key(|endpoint| self.options_for(endpoint).priority);
+        // Shuffle each priority by weight, unless every endpoint in it weighs the same
+        let mut ordered = Vec::with_capacity(endpoints.len());
+        for group in endpoints.chunk_by(|a, b| self.options_for(a).priority == self.options_for(b).priority) {
+            let weights: Vec<u32> = group.iter().map(|endpoint| self.options_for(endpoint).weight).collect();
+            if weights.iter().all(|weight| *weight == weights[0]) {
+                ordered.extend_from_slice(group);
+            } else {
+                ordered.extend(weighted_order(group, weights));
+            }
+        }
+        ordered
+    }
+
+    /// The options for `endpoint`, or the defaults if it has none
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn options_for(&self, endpoint: &str) -> EndpointOptions {
+        self.endpoint_options
+            .iter()
+            .find(|options| options.endpoint == endpoint)
+            .cloned()
+            .unwrap_or_else(|| EndpointOptions::new(endpoint))
+    }
+}
 
+impl fmt::Debug for LifxConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("LifxConfig");
+        debug
+            .field("access_token", &redacted(&self.access_token))
+            .field("api_endpoints", &self.api_endpoints)
+            .field("default_duration", &self.default_duration)
+            .field("endpoint_strategy", &self.endpoint_strategy)
+            .field("local_endpoints", &self.local_endpoints)
+            .field("endpoint_options", &self.endpoint_options)
+            .field("wait_on_rate_limit", &self.wait_on_rate_limit)
+            .field("retry_policy", &self.retry_policy)
+            .field("retry_budget", &self.retry_budget)
+            .field("timeout", &self.timeout)
+            .field("http", &self.http)
+            .field("skip_capability_checks", &self.skip_capability_checks)
+            .field("deny_unknown_fields", &self.deny_unknown_fields)
+            .field("scene_cache_ttl", &self.scene_cache_ttl);
+        #[cfg(any(feature = "blocking", feature = "async"))]
+        debug.field("transport", &self.transport);
+        debug.finish()
     }
+}
 
+/// What a LifxConfig's access token is shown as in Debug output and serialized configs
+const REDACTED_TOKEN: &str = "[redacted]";
 
+/// The token as it may be shown; an empty token has nothing to hide
+fn redacted(token: &str) -> &str {
+    if token.is_empty() { token } else { REDACTED_TOKEN }
+}
 
-    /// Asynchronously gets ALL lights belonging to the authenticated account
+thread_local! {
+    /// Set while an ExposedToken is serializing its config
+    static EXPOSE_TOKEN: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Decodes null as the type's default, for fields some servers send as null
+fn null_as_default<'de, D: serde::Deserializer<'de>, T: Default + Deserialize<'de>>(deserializer: D) -> Result<T, D::Error> {
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn serialize_token<S: serde::Serializer>(token: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    if EXPOSE_TOKEN.with(Cell::get) {
+        serializer.serialize_str(token)
+    } else {
+        serializer.serialize_str(redacted(token))
+    }
+}
+
+/// A LifxConfig that serializes with its access token, see LifxConfig::exposing_token
+pub struct ExposedToken<'a>(&'a LifxConfig);
+
+impl Serialize for ExposedToken<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        /// Clears the flag even if serialization panics
+        struct Reset(bool);
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                EXPOSE_TOKEN.with(|expose| expose.set(self.0));
+            }
+        }
+        let _reset = Reset(EXPOSE_TOKEN.with(|expose| expose.replace(true)));
+        self.0.serialize(serializer)
+    }
+}
+
+/// The order in which a LifxConfig's api_endpoints are tried
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EndpointStrategy {
+    /// Try the api_endpoints in list order
+    #[default]
+    Sequential,
+    /// Try the endpoints listed in local_endpoints first, then fall back to the cloud endpoints.
+    /// Both groups keep their list order.
+    PreferLocal,
+    /// Try the endpoint that has been answering fastest first, skipping over endpoints that
+    /// couldn't be reached recently, see EndpointHealth. Ties keep their list order.
+    ///
+    /// Health comes from the requests themselves: an endpoint that failed is only tried again as
+    /// a last resort until ENDPOINT_RECHECK_AFTER has passed, and then the next request probes it.
+    Healthiest,
+}
+
+/// Settings for the HTTP clients a LifxClient talks to the api_endpoints with
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpOptions {
+    /// A proxy every request goes through, ex: "http://proxy.local:3128"
+    pub proxy: Option<String>,
+    /// The User-Agent header sent with every request. None sends no User-Agent.
+    pub user_agent: Option<String>,
+    /// Headers sent with every request, as (name, value) pairs
+    pub headers: Vec<(String, String)>,
+    /// PEM encoded certificates to trust on top of the system's roots, ex: for a local server
+    /// with a self-signed certificate. Needs the `tls` feature.
+    pub root_certificates: Vec<String>,
+    /// Accept any TLS certificate, even expired or self-signed ones. Never use this against the
+    /// LIFX cloud, since anyone on the network could then read the access token. Needs the `tls`
+    /// feature.
+    pub danger_accept_invalid_certs: bool,
+}
+
+/// A fluent builder for LifxConfig, see LifxConfig::builder
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone, Default)]
+pub struct LifxConfigBuilder {
+    config: LifxConfig,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl LifxConfigBuilder {
+    /// Sets the access token
+    pub fn access_token(mut self, access_token: impl Into<String>) -> Self {
+        self.config.access_token = access_token.into();
+        self
+    }
+
+    /// Adds an endpoint after the ones added so far
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.config.api_endpoints.push(endpoint.into());
+        self
+    }
+
+    /// Sets default_duration
+    pub fn default_duration(mut self, default_duration: f64) -> Self {
+        self.config.default_duration = Some(default_duration);
+        self
+    }
+
+    /// Sets endpoint_strategy
+    pub fn endpoint_strategy(mut self, endpoint_strategy: EndpointStrategy) -> Self {
+        self.config.endpoint_strategy = endpoint_strategy;
+        self
+    }
+
+    /// Sets retry_policy
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets deny_unknown_fields, see LifxConfig::deny_unknown_fields
+    pub fn deny_unknown_fields(mut self, deny_unknown_fields: bool) -> Self {
+        self.config.deny_unknown_fields = deny_unknown_fields;
+        self
+    }
+
+    /// Sets retry_budget
+    pub fn retry_budget(mut self, retry_budget: RetryBudget) -> Self {
+        self.config.retry_budget = retry_budget;
+        self
+    }
+
+    /// Sets the seconds each attempt at a request may take, see LifxConfig::timeout
+    pub fn timeout(mut self, timeout: f64) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends every request through `proxy`, ex: "http://proxy.local:3128"
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.config.http.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Sets the User-Agent header
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config.http.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Adds a header to every request
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.http.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Trusts a PEM encoded certificate on top of the system's roots
+    pub fn root_certificate(mut self, pem: impl Into<String>) -> Self {
+        self.config.http.root_certificates.push(pem.into());
+        self
+    }
+
+    /// Accepts any TLS certificate, see HttpOptions::danger_accept_invalid_certs
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.config.http.danger_accept_invalid_certs = accept;
+        self
+    }
+
+    /// Returns the config, or LifxError::Config if an HTTP setting is invalid, ex: a proxy that
+    /// isn't a URL or a header value with a line break in it, or the config fails
+    /// LifxConfig::validate
+    pub fn build(self) -> Result<LifxConfig, LifxError> {
+        client::check_http_options(&self.config.http)?;
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Routing metadata for one of a LifxConfig's api_endpoints
+///
+/// Endpoints are tried in order of priority, lowest first, so a local server can be preferred
+/// over the cloud regardless of where each sits in api_endpoints. Endpoints that share a priority
+/// are tried in a random order in proportion to their weight, or in endpoint_strategy order if
+/// their weights are equal. An endpoint with weight 0 comes last in its priority.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate lifx_rs as lifx;
+/// 
+/// fn main() {
+/// 
+///     let key = "xxx".to_string();
+///     let mut api_endpoints: Vec<String> = Vec::new();
+///
+///     api_endpoints.push(format!("https://api.lifx.com"));
+///     api_endpoints.push(format!("http://localhost:8089"));
+///
+///     // The local server first, the cloud only when it's down
+///     let config = lifx::LifxConfig{
+///        access_token: key.clone(),
+///        api_endpoints: api_endpoints,
+///        endpoint_options: vec![
+///            lifx::EndpointOptions::new("http://localhost:8089"),
+///            lifx::EndpointOptions::new("https://api.lifx.com").priority(10),
+///        ],
+///        ..Default::default()
+///     };
+/// }
+///  ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointOptions {
+    /// The api_endpoint these options apply to, ex: "http://localhost:8089"
+    pub endpoint: String,
+    /// Lower priorities are tried first. Defaults to 0.
+    #[serde(default)]
+    pub priority: u32,
+    /// The share of requests this endpoint is tried first for among endpoints of the same
+    /// priority. Defaults to 1.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Only send reads (GET requests) to this endpoint, ex: for a mirror that can't change the
+    /// lights. Defaults to false.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl EndpointOptions {
+    /// Returns the default options for `endpoint`: priority 0, weight 1, taking writes
+    pub fn new(endpoint: &str) -> Self {
+        EndpointOptions { endpoint: endpoint.to_string(), priority: 0, weight: default_weight(), read_only: false }
+    }
+
+    /// Sets the priority, lower is tried first
+    pub fn priority(self, priority: u32) -> Self {
+        EndpointOptions { priority, ..self }
+    }
+
+    /// Sets the weight among endpoints of the same priority
+    pub fn weight(self, weight: u32) -> Self {
+        EndpointOptions { weight, ..self }
+    }
+
+    /// Marks the endpoint as only taking reads
+    pub fn read_only(self) -> Self {
+        EndpointOptions { read_only: true, ..self }
+    }
+}
+
+/// Returns `endpoints` in a random order where each is picked next in proportion to its weight
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn weighted_order<'a>(endpoints: &[&'a str], weights: Vec<u32>) -> Vec<&'a str> {
+    let mut remaining: Vec<(&str, u64)> = endpoints.iter().copied().zip(weights.into_iter().map(u64::from)).collect();
+    let mut ordered = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let total: u64 = remaining.iter().map(|(_, weight)| weight).sum();
+        let index = if total == 0 {
+            0
+        } else {
+            let mut pick = random_u64() % total;
+            remaining.iter().position(|(_, weight)| {
+                if pick < *weight {
+                    return true;
+                }
+                pick -= weight;
+                false
+            }).unwrap_or(0)
+        };
+        ordered.push(remaining.remove(index).0);
+    }
+    ordered
+}
+
+/// How a request is retried on an endpoint when it can't be sent or its response can't be read,
+/// ex: after a dropped connection or a timeout. The delay before each retry doubles, starting
+/// from base_delay, and is shortened by up to `jitter` of itself at random so that clients
+/// don't all retry in step.
+///
+/// A request that reached the API before the connection dropped may be carried out twice when
+/// retried, which matters for toggle and state_delta.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// Attempts per endpoint, counting the first. 1, the default, never retries.
+    pub max_attempts: u32,
+    /// Seconds to wait before the first retry. Defaults to 0.25.
+    pub base_delay: f64,
+    /// The largest fraction of each delay taken off at random, from 0.0 to 1.0. Defaults to 0.5.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy { max_attempts: 1, base_delay: 0.25, jitter: 0.5 }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the default policy with `max_attempts` attempts per endpoint
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy { max_attempts, ..Default::default() }
+    }
+
+    /// Returns the delay before retry number `retry`, counting from 1
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let backoff = self.base_delay.max(0.0) * 2f64.powi(retry.saturating_sub(1).min(30) as i32);
+        let jitter = self.jitter.clamp(0.0, 1.0) * (random_u64() as f64 / u64::MAX as f64);
+        Duration::try_from_secs_f64(backoff * (1.0 - jitter)).unwrap_or(Duration::MAX)
+    }
+}
+
+/// A token bucket capping the retries made by every request that shares a LifxClient, including
+/// its clones. Each retry takes a token; once they're gone, a request that can't reach an
+/// endpoint falls back to the next one (or fails) straight away instead of retrying, until the
+/// bucket refills. First attempts and fallbacks never take a token.
+///
+/// The Light, Group and Scene functions that take a LifxConfig build a client per call, so each
+/// of those calls gets a full budget. Share one LifxClient to cap retries across a batch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryBudget {
+    /// The most retries the bucket holds, and what a new client starts with. Defaults to 10.
+    pub capacity: u32,
+    /// Retries added back a second, up to capacity. Defaults to 1.0.
+    pub refill_per_second: f64,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        RetryBudget { capacity: 10, refill_per_second: 1.0 }
+    }
+}
+
+
+
+pub type Lights = Vec<Light>;
+
+/// Represents a LIFX Light Object
+///
+/// Only the id is required when decoding. The other fields fall back to their defaults when a
+/// server leaves them out or sends null, as lifx-api-server does for some of them.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Light {
+    pub id: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub uuid: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub label: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub connected: bool,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub power: String,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub color: Color,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub brightness: f64,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub group: Group,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub location: Location,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub product: Product,
+    #[serde(rename = "last_seen", default, deserialize_with = "null_as_default")]
+    pub last_seen: String,
+    #[serde(rename = "seconds_since_seen", default, deserialize_with = "null_as_default")]
+    pub seconds_since_seen: i64,
+    /// The zones of a multizone light, ex: a Z strip or a Beam, with the color each is showing.
+    /// None for lights without zones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zones: Option<Zones>,
+    /// The firmware effect the light is running, if the API reported one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effect: Option<RunningEffect>,
+    pub error: Option<String>,
+    pub errors: Option<Vec<Error>>,
+    /// Fields this crate doesn't model yet, kept as the API sent them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+impl Light {
+
+    /// Returns a Selector for this light by its id, ex: id:d073d5000000
+    pub fn id_selector(&self) -> Selector {
+        Selector::Id(self.id.clone())
+    }
+
+    /// Returns a Selector for every light in this light's group, by group id
+    pub fn group_selector(&self) -> Selector {
+        Selector::GroupId(self.group.id.clone())
+    }
+
+    /// Returns a Selector for every light in this light's location, by location id
     /// 
-    /// # Arguments
-    ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -737,30 +1075,50 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::async_list_all(config).await?;
+    ///     let mut off_state = lifx::State::new();
+    ///     off_state.power = Some(format!("off"));
+    /// 
+    ///     // Turn off everything in the same location as the first light
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         if let Some(light) = lights.first() {
+    ///             let results = lifx::Light::set_state_by_selector(config.clone(), light.location_selector().into(), off_state);
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub async fn async_list_all(config: LifxConfig) -> Result<Lights, reqwest::Error> {
-        return Self::async_list_by_selector(config, format!("all")).await;
+    pub fn location_selector(&self) -> Selector {
+        Selector::LocationId(self.location.id.clone())
     }
 
-    /// Asynchronously gets lights belonging to the authenticated account. Filtering the lights using selectors. Properties such as id, label, group and location can be used in selectors.
+    /// Returns the color the light is actually showing: its color when it is on and connected,
+    /// otherwise None. The API reports the stored color even for lights that are off or unreachable.
+    pub fn visible_color(&self) -> Option<Color> {
+        if self.power == "on" && self.connected {
+            Some(self.color.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Splits lights into those whose product supports a capability and those that don't, keeping
+    /// their order, so an effect can be sent only to the lights that can run it.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `lights` - The lights to split
+    /// * `cap` - The Capability to check for
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// #[tokio::main]
-    /// async fn main() {
+    /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -770,53 +1128,149 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::async_list_by_selector(key, format!("all")).await?;
+    ///     let lights = lifx::Light::list_by_selector(config.clone(), format!("group:Lounge")).unwrap_or_default();
+    ///     let (tiles, others) = lifx::Light::partition_by_capability(&lights, lifx::Capability::Matrix);
+    ///     for light in tiles {
+    ///         let results = light.morph_effect(config.clone(), lifx::MorphEffect::new());
+    ///         println!("{:?}", results);
+    ///     }
+    ///     for light in others {
+    ///         println!("{} can't run the morph effect", light.label);
+    ///     }
     /// }
     ///  ```
-    pub async fn async_list_by_selector(config: LifxConfig, selector: String) -> Result<Lights, reqwest::Error> {
-        let url = format!("{}/v1/lights/{}", config.api_endpoints[0], selector);
-        let request = reqwest::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send().await;
-        match request {
-            Ok(req) => {
-                let json = req.json::<Lights>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}", config.api_endpoints[1], selector);
-                    let request = reqwest::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send().await;
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<Lights>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
+    pub fn partition_by_capability(lights: &[Light], cap: Capability) -> (Vec<Light>, Vec<Light>) {
+        lights.iter().cloned().partition(|light| light.product.capabilities.has(cap))
+    }
+
+    /// Fails with LifxError::Unsupported when this light's product lacks `capability`, which
+    /// `what` needs, unless the config skips capability checks
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn require(&self, config: &LifxConfig, capability: Capability, what: &str) -> Result<(), LifxError> {
+        if config.skip_capability_checks || self.product.capabilities.has(capability) {
+            return Ok(());
+        }
+        Err(self.unsupported(capability, what))
+    }
+
+    /// The LifxError::Unsupported for `what` on this light, which lacks `capability`
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn unsupported(&self, capability: Capability, what: &str) -> LifxError {
+        LifxError::Unsupported(format!("{} on {} ({}), which has no {:?} support", what, self.label, self.product.name, capability))
+    }
+
+    /// Returns true if the other light is in the same group as this one, compared by group id
+    pub fn same_group(&self, other: &Light) -> bool {
+        self.group.id == other.group.id
+    }
+
+    /// Returns true if the other light is in the same location as this one, compared by location id
+    pub fn same_location(&self, other: &Light) -> bool {
+        self.location.id == other.location.id
+    }
+
+    /// Returns last_seen as a time, or None if it isn't an RFC 3339 timestamp like the API's
+    /// "2015-12-03T05:53:18Z"
+    pub fn last_seen_time(&self) -> Option<SystemTime> {
+        parse_rfc3339(&self.last_seen)
+    }
+
+    /// Returns true if the cloud heard from the light less than `within` ago. Uses last_seen, so
+    /// the answer stays right for a light that was listed a while ago; falls back to
+    /// seconds_since_seen if last_seen can't be parsed.
+    pub fn seen_within(&self, within: Duration) -> bool {
+        match self.last_seen_time() {
+            Some(seen) => SystemTime::now().duration_since(seen).map_or(true, |ago| ago < within),
+            None => u64::try_from(self.seconds_since_seen).map_or(true, |ago| Duration::from_secs(ago) < within),
+        }
+    }
+
+    /// Builds a State that puts a light back to its reported power, color and brightness
+    fn snapshot_state(&self) -> State {
+        let mut state = State::new();
+        state.power = Some(self.power.clone());
+        state.color = Some(self.color.to_param_string());
+        state.brightness = Some(self.brightness);
+        state
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl Light {
+    /// Keeps the lights that are on and connected, see list_on
+    fn powered_on(lights: Lights) -> Lights {
+        lights.into_iter().filter(|light| light.power == "on" && light.connected).collect()
+    }
+
+    /// Keeps the lights that report power "off", see list_off
+    fn powered_off(lights: Lights) -> Lights {
+        lights.into_iter().filter(|light| light.power == "off").collect()
+    }
+
+    /// Builds one State per light, by id, with a hue drawn from a SplitMix64 sequence seeded with `seed`
+    fn shuffled_states(lights: &[Light], saturation: f64, brightness: f64, duration: f64, seed: Option<u64>) -> Vec<State> {
+        let mut rng = SplitMix64(seed.unwrap_or_else(random_u64));
+        lights.iter()
+            .map(|light| {
+                let mut state = State::new();
+                state.selector = Some(format!("id:{}", light.id));
+                state.color = Some(format!("hue:{} saturation:{}", rng.next_hue(), saturation));
+                state.brightness = Some(brightness);
+                state.duration = Some(duration);
+                state
+            })
+            .collect()
+    }
+
+    /// Builds a brightness-only StateDelta from percentage points, negated when dimming.
+    /// The delta is clamped to [-1, 1], the full range a relative brightness change can cover.
+    fn brightness_step(percent_points: i8, dim: bool) -> StateDelta {
+        let points = if dim { -(percent_points as f64) } else { percent_points as f64 };
+        let mut delta = StateDelta::new();
+        delta.brightness = Some((points / 100.0).clamp(-1.0, 1.0));
+        delta
+    }
+
+    /// Returns `steps` breathe peaks spread evenly from `peak_range.0` to `peak_range.1`, or
+    /// LifxError::Config if either end is outside [0, 1]
+    #[cfg(feature = "async")]
+    fn drift_peaks(peak_range: (f64, f64), steps: u32) -> Result<Vec<f64>, LifxError> {
+        let (start, end) = peak_range;
+        for peak in [start, end] {
+            if !(0.0..=1.0).contains(&peak) {
+                return Err(LifxError::Config(format!("breathe peaks must be between 0 and 1, got {}", peak)));
             }
         }
+        Ok((0..steps)
+            .map(|step| {
+                let fraction = if steps > 1 { step as f64 / (steps - 1) as f64 } else { 0.0 };
+                start + (end - start) * fraction
+            })
+            .collect())
     }
 
-    /// Asynchronously activate the morph animation for the current light
+    /// Asynchronously sets the state for the current light, then polls it until it reports that
+    /// state, see apply_verified
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `morph_effect` - A MorphEffect object containing the values to set
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `state` - A State object containing the values to set
+    /// * `tolerance` - How far off each reported value may be, as a fraction of its range
+    /// * `timeout` - How long to keep polling once the state's transition has finished
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
+    /// use std::time::Duration;
+    /// 
     /// #[tokio::main]
     /// async fn main() {
     /// 
@@ -828,49 +1282,59 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut morph_effect = lifx::MorphEffect::new();
-    ///             morph_effect.period = Some(10);
-    ///             morph_effect.duration = Some(0);
-    /// 
-    ///             let mut palette: Vec<String> = Vec::new();
-    ///             palette.push(format!("red"));
-    ///             palette.push(format!("green"));
-    /// 
-    ///             morph_effect.palette = Some(palette);
-    ///             morph_effect.power_on = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.async_morph_effect(key.clone(), morph_effect.clone()).await;
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
+    ///     let locked = lifx::State::builder().power_on().color("red").brightness(1.0).duration(2.0).build();
+    ///     if let Ok(lights) = lifx::Light::async_list_by_selector(config.clone(), format!("label:Gate")).await {
+    ///         for light in lights {
+    ///             let verified = light.async_apply_verified(config.clone(), locked.clone(), 0.01, Duration::from_secs(10)).await;
+    ///             println!("{:?}", verified);
+    ///         }
     ///     }
     /// }
     ///  ```
-    pub async fn async_morph_effect(&self, config: LifxConfig, morph_effect: MorphEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_morph_effect_by_selector(config, format!("id:{}", self.id), morph_effect).await;
+    #[cfg(feature = "async")]
+    pub async fn async_apply_verified(&self, config: LifxConfig, state: State, tolerance: f64, timeout: Duration) -> Result<LiFxResults, LifxError> {
+        let (target, fade) = Self::verify_target(&config, &state);
+        let results = self.async_set_state(config.clone(), state).await?;
+        tokio::time::sleep(fade).await;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let lights = Self::async_list_by_selector(config.clone(), format!("id:{}", self.id)).await?;
+            if lights.iter().any(|light| light.id == self.id && apply::reached(&target, light, tolerance)) {
+                return Ok(results);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(self.not_verified(timeout));
+            }
+            tokio::time::sleep(VERIFY_POLL_INTERVAL.min(remaining)).await;
+        }
     }
 
-    /// Asynchronously activate the morph animation for the selected light(s)
+    /// Asynchronously runs the breathe effect `steps` times on the current light, moving the peak across
+    /// `peak_range` from one run to the next for a slowly changing breath.
+    ///
+    /// The API only takes a fixed peak, so this restarts the effect on a client-side timer: each run is
+    /// given `period * cycles` seconds (the API defaults of 1 are used for unset values) before the
+    /// next one starts. Stops at the first failed request.
+    ///
+    /// Returns LifxError::Config before sending anything if either end of `peak_range` is outside
+    /// [0, 1], or if `period * cycles` isn't a usable number of seconds, ex: negative or infinite.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `clean` - A Clean object containing the values to set
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `base` - A BreatheEffect object with the values shared by every run; its peak is replaced
+    /// * `peak_range` - The peak of the first and last run
+    /// * `steps` - How many runs to spread the range over
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// #[tokio::main]
@@ -884,67 +1348,50 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut morph_effect = lifx::MorphEffect::new();
-    ///     morph_effect.period = Some(10);
-    ///     morph_effect.duration = Some(0);
-    /// 
-    ///     let mut palette: Vec<String> = Vec::new();
-    ///     palette.push(format!("red"));
-    ///     palette.push(format!("green"));
-    /// 
-    ///     morph_effect.palette = Some(palette);
-    ///     morph_effect.power_on = Some(true);
-    ///     
-    ///     // Send morph effect to all lights
-    ///     lifx::Light::async_morph_effect_by_selector(key.clone(), format!("all"), morph_effect).await;
+    ///     let mut breathe = lifx::BreatheEffect::new();
+    ///     breathe.color = Some(format!("orange"));
+    ///     breathe.period = Some(4.0);
+    ///     breathe.cycles = Some(2.0);
+    /// 
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_breathe_drift(config.clone(), breathe.clone(), (0.2, 0.8), 5).await;
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub async fn async_morph_effect_by_selector(config: LifxConfig, selector: String, morph_effect: MorphEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/morph", config.api_endpoints[0], selector);
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&morph_effect.to_params())
-            .send().await;
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/morph", config.api_endpoints[1], selector);
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&morph_effect.to_params())
-                        .send().await;
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
+    #[cfg(feature = "async")]
+    pub async fn async_breathe_drift(&self, config: LifxConfig, base: BreatheEffect, peak_range: (f64, f64), steps: u32) -> Result<Vec<LiFxResults>, LifxError> {
+        let run_secs = base.period.unwrap_or(1.0) * base.cycles.unwrap_or(1.0);
+        let run_time = Duration::try_from_secs_f64(run_secs)
+            .map_err(|_| LifxError::Config(format!("breathe period * cycles must be a non-negative number of seconds, got {}", run_secs)))?;
+        let peaks = Self::drift_peaks(peak_range, steps)?;
+        let last = peaks.len().saturating_sub(1);
+
+        let mut results = Vec::new();
+        for (step, peak) in peaks.into_iter().enumerate() {
+            let mut breathe = base.clone();
+            breathe.peak = Some(peak);
+            results.push(self.async_breathe_effect(config.clone(), breathe).await?);
+            if step < last {
+                tokio::time::sleep(run_time).await;
             }
         }
-    
-
+        Ok(results)
     }
 
-    /// Asynchronously activate the move animation for the current light
+    /// Asynchronously set the breathe animation for the current light
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
     /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `move_effect` - A MoveEffect object containing the values to set
+    /// * `breathe` - A BreatheEffect object containing the values to set
     ///
     /// # Examples
     ///
@@ -962,7 +1409,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -970,14 +1418,15 @@ impl Light {
     ///         Ok(lights) => {
     ///             println!("{:?}",lights.clone());
     ///     
-    ///             let mut move_effect = lifx::MoveEffect::new();
-    ///             move_effect.direction = Some(format!("forward")); // or backward
-    ///             move_effect.period = Some(10);
-    ///             move_effect.cycles = Some(0.9);
-    ///             move_effect.power_on = Some(true);
+    ///             let mut breathe = lifx::BreatheEffect::new();
+    ///             breathe.color = Some(format!("red"));
+    ///             breathe.from_color = Some(format!("green"));
+    ///             breathe.period = Some(10);
+    ///             breathe.persist = Some(true);
+    ///             breathe.power_on = Some(true);
     ///         
     ///             for light in lights {
-    ///                 let results = light.async_move_effect(key.clone(), move_effect.clone()).await;
+    ///                 let results = light.async_breathe_effect(key.clone(), breathe.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -985,17 +1434,18 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub async fn async_move_effect(&self, config: LifxConfig, move_effect: MoveEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_move_effect_by_selector(config, format!("id:{}", self.id), move_effect).await;
+    #[cfg(feature = "async")]
+    pub async fn async_breathe_effect(&self, config: LifxConfig, breathe: BreatheEffect) ->  Result<LiFxResults, LifxError>{
+        return Self::async_breathe_effect_by_selector(config, format!("id:{}", self.id), breathe).await;
     }
 
-    /// Asynchronously activate the move animation for the selected light(s)
+    /// Asynchronously activate the breathe animation for the selected light(s)
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `move_effect` - A MoveEffect object containing the values to set
+    /// * `breathe` - A BreatheEffect object containing the values to set
     ///
     /// # Examples
     ///
@@ -1013,70 +1463,40 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut move_effect = lifx::MoveEffect::new();
-    ///     move_effect.direction = Some(format!("forward")); // or backward
-    ///     move_effect.period = Some(10);
-    ///     move_effect.cycles = Some(0.9);
-    ///     move_effect.power_on = Some(true);
+    ///     let mut breathe = lifx::BreatheEffect::new();
+    ///     breathe.color = Some(format!("red"));
+    ///     breathe.from_color = Some(format!("green"));
+    ///     breathe.period = Some(10);
+    ///     breathe.persist = Some(true);
+    ///     breathe.power_on = Some(true);
     ///     
-    ///     // Toggle all lights
-    ///     lifx::Light::async_move_effect_by_selector(key.clone(), format!("all"), move_effect).await;
+    ///     // Apply breathe effect to all light(s)
+    ///     lifx::Light::async_breathe_effect_by_selector(key.clone(), format!("all"), breathe).await;
     /// }
     ///  ```
-    pub async fn async_move_effect_by_selector(config: LifxConfig, selector: String, move_effect: MoveEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/move", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&move_effect.to_params())
-            .send().await;
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/move", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&move_effect.to_params())
-                        .send().await;
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_breathe_effect_by_selector(config: LifxConfig, selector: String, breathe: BreatheEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_breathe_effect_by_selector(selector, breathe).await
     }
 
-    /// Asynchronously activate the pulse animation for the current light
+
+    /// Asynchronously brighten the current light by a number of percentage points, ex: 10 raises the brightness by 0.1.
+    ///
+    /// Sent as a StateDelta, so the resulting brightness is clipped to [0, 1] by the API.
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `pulse_effect` - A PulseEffect object containing the values to set
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `percent_points` - How many percentage points to brighten by
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// #[tokio::main]
@@ -1090,45 +1510,37 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut pulse = lifx::PulseEffect::new();
-    ///             pulse.color = Some(format!("red"));
-    ///             pulse.from_color = Some(format!("green"));
-    ///             pulse.period = Some(10);
-    ///             pulse.persist = Some(true);
-    ///             pulse.power_on = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.async_pulse_effect(key.clone(), pulse.clone()).await;
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_brighten_by(config.clone(), 10).await;
+    ///             println!("{:?}", results);
+    ///         }
     ///     }
     /// }
     ///  ```
-    pub async fn async_pulse_effect(&self, config: LifxConfig, pulse_effect: PulseEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_pulse_effect_by_selector(config, format!("id:{}", self.id), pulse_effect).await;
+    #[cfg(feature = "async")]
+    pub async fn async_brighten_by(&self, config: LifxConfig, percent_points: i8) -> Result<LiFxResults, LifxError> {
+        let delta = Self::brightness_step(percent_points, false);
+        Self::async_state_delta_by_selector(config, format!("id:{}", self.id), delta).await
     }
 
-    /// Asynchronously activate the pulse animation for the selected light(s)
+    /// Asynchronously captures the current state of every light as a Scene, with one State per light selected by id.
+    ///
+    /// The scene only exists locally: it gets a generated uuid and the current time as its created and
+    /// updated timestamps, and is not saved to the account. See Scene::from_lights.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `pulse_effect` - A PulseEffect object containing the values to set
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `name` - The name to give the scene
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// #[tokio::main]
@@ -1142,71 +1554,27 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut pulse = lifx::PulseEffect::new();
-    ///     pulse.color = Some(format!("red"));
-    ///     pulse.from_color = Some(format!("green"));
-    ///     pulse.period = Some(10);
-    ///     pulse.persist = Some(true);
-    ///     pulse.power_on = Some(true);
-    ///     
-    ///     // Toggle all lights
-    ///     lifx::Light::async_pulse_effect_by_selector(key.clone(), format!("all"), pulse).await;
+    ///     let scene = lifx::Light::async_capture_as_scene(config, "Before the party").await;
+    ///     println!("{:?}", scene);
     /// }
     ///  ```
-    pub async fn async_pulse_effect_by_selector(config: LifxConfig, selector: String, pulse_effect: PulseEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/pulse", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&pulse_effect.to_params())
-            .send().await;
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/pulse", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&pulse_effect.to_params())
-                        .send().await;
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                
-            
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_capture_as_scene(config: LifxConfig, name: &str) -> Result<Scene, LifxError> {
+        let lights = Self::async_list_all(config).await?;
+        Ok(Scene::from_lights(name, &lights))
     }
 
-
-
-    /// Asynchronously sets the state for the current light
+    /// Asynchronously switch a light to clean mode, with a set duration. 
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
     /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `state` - A State object containing the values of the State to set
+    /// * `clean` - A Clean object containing the values to set
     ///
     /// # Examples
     ///
@@ -1224,7 +1592,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -1232,12 +1601,12 @@ impl Light {
     ///         Ok(lights) => {
     ///             println!("{:?}",lights.clone());
     ///     
-    ///             let mut state = lifx::State::new();
-    ///             state.power = Some(format!("on"));
-    ///             state.brightness = Some(1.0);
+    ///             let mut clean = lifx::Clean::new();
+    ///             clean.duration = Some(0);
+    ///             clean.stop = Some(false);
     ///         
     ///             for light in lights {
-    ///                 let results = light.async_set_state(key.clone(), state.clone()).await;
+    ///                 let results = light.async_clean(key.clone(), clean.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -1245,17 +1614,19 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub async fn async_set_state(&self, config: LifxConfig, state: State) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_set_state_by_selector(config, format!("id:{}", self.id), state).await;
+    #[cfg(feature = "async")]
+    pub async fn async_clean(&self, config: LifxConfig, clean: Clean) ->  Result<LiFxResults, LifxError>{
+        self.require(&config, Capability::Hev, "clean")?;
+        return Self::async_clean_by_selector(config, format!("id:{}", self.id), clean).await;
     }
 
-    /// Asynchronously sets the state for the selected LIFX object
+    /// Asynchronously switch a selected LIFX object to clean mode, with a set duration. 
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `state` - A State object containing the values of the State to set
+    /// * `clean` - A Clean object containing the values to set
     ///
     /// # Examples
     ///
@@ -1273,156 +1644,36 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut off_state = lifx::State::new();
-    ///     off_state.power = Some(format!("off"));
+    ///     let mut clean = lifx::Clean::new();
+    ///     clean.duration = Some(0);
+    ///     clean.stop = Some(false);
     ///     
-    ///     // Turn off all lights
-    ///     lifx::Light::async_set_state_by_selector(key.clone(), format!("all"), off_state).await;
+    ///     // Set all light to clean mode
+    ///     lifx::Light::async_clean_by_selector(key.clone(), format!("all"), clean).await;
     /// }
     ///  ```
-    pub async fn async_set_state_by_selector(config: LifxConfig, selector: String, state: State) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/state", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().put(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&state.to_params())
-            .send().await;
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/state", config.api_endpoints[0], selector);
-
-                    let request = reqwest::Client::new().put(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&state.to_params())
-                        .send().await;
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                          return Err(err2);  
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_clean_by_selector(config: LifxConfig, selector: String, clean: Clean) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_clean_by_selector(selector, clean).await
     }
 
-    /// Asynchronously sets the state for the selected LIFX object(s)
-    /// 
-    /// # Arguments
-    ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `states` - A vector of States with defaults
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// extern crate lifx_rs as lifx;
-    /// 
-    /// #[tokio::main]
-    /// async fn main() {
-    /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
+    /// Asynchronously dim the current light by a number of percentage points, ex: 10 lowers the brightness by 0.1.
     ///
-    ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
-    ///     };
-    /// 
-    ///     let mut set_states = lifx::States::new();
-    ///     let mut states: Vec<lifx::State> = Vec::new();
-    ///     let mut defaults = lifx::State::new();
-    ///     
-    ///     defaults.brightness = Some(1.0);
-    ///     
-    ///     let mut state_1 = lifx::State::new();
-    ///     state_1.selector = Some(format!("id:xxx"));
-    ///     state_1.power = Some(format!("on"));
-    ///     
-    ///     let mut state_2 = lifx::State::new();
-    ///     state_2.selector = Some(format!("id:xyz"));
-    ///     state_2.power = Some(format!("on"));
-    ///     
-    ///     set_states.states = Some(states);
-    ///     set_states.defaults = Some(defaults);
-    ///     
-    ///     lifx::Light::async_set_states(key.clone(), set_states).await;
-    /// }
-    ///  ```
-    pub async fn async_set_states(config: LifxConfig, states: States) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/state", config.api_endpoints[0]);
-
-        let request = reqwest::blocking::Client::new().put(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .json(&states)
-            .send();
-
-        match request{
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(e) => {
-                if config.api_endpoints.len() > 1 {
-
-                    let url = format!("{}/v1/lights/state", config.api_endpoints[1]);
-
-                    let request = reqwest::blocking::Client::new().put(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .json(&states)
-                        .send();
-            
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(e2) => {
-                            return Err(e2);
-                        }
-                    }
-
-
-                } else {
-                    return Err(e);
-                }
-            }
-        }
-    
-
-    }
-
-    /// Asynchronously set parameters other than power and duration change the state of the lights by the amount specified.
+    /// Sent as a StateDelta, so the resulting brightness is clipped to [0, 1] by the API.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `delta` - A StateDelta object containing the values to set
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `percent_points` - How many percentage points to dim by
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// #[tokio::main]
@@ -1436,66 +1687,32 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut delta = lifx::StateDelta::new();
-    ///     delta.duration = Some(0);
-    ///     delta.power = Some(format!("on"));
-    ///     
-    ///     // Send StateDelta
-    ///     lifx::Light::async_state_delta_by_selector(key.clone(), format!("all"), toggle).await;
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_dim_by(config.clone(), 10).await;
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub async fn async_state_delta_by_selector(config: LifxConfig, selector: String, delta: StateDelta) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/state/delta", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&delta.to_params())
-            .send().await;
-
-        match request{
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/state/delta", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&delta.to_params())
-                        .send().await;
-            
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2)
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_dim_by(&self, config: LifxConfig, percent_points: i8) -> Result<LiFxResults, LifxError> {
+        let delta = Self::brightness_step(percent_points, true);
+        Self::async_state_delta_by_selector(config, format!("id:{}", self.id), delta).await
     }
 
 
-
-    /// Turn off light if on, or turn them on if it is off. 
+    /// Stops animation(s) for the current light
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
     /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `clean` - A Clean object containing the values to set
+    /// * `flame_effect` - A FlameEffect object containing the values to set
     ///
     /// # Examples
     ///
@@ -1513,7 +1730,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -1521,11 +1739,11 @@ impl Light {
     ///         Ok(lights) => {
     ///             println!("{:?}",lights.clone());
     ///     
-    ///             let mut toggle = lifx::Toggle::new();
-    ///             toggle.duration = Some(0);
+    ///             let mut effects_off = lifx::EffectsOff::new();
+    ///             effects_off.power_off = Some(true);
     ///         
     ///             for light in lights {
-    ///                 let results = light.async_toggle(key.clone(), clean.clone()).await;
+    ///                 let results = light.async_effects_off(key.clone(), effects_off.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -1533,22 +1751,23 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub async fn async_toggle(&self, config: LifxConfig, toggle: Toggle) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::async_toggle_by_selector(config, format!("id:{}", self.id), toggle).await;
+    #[cfg(feature = "async")]
+    pub async fn async_effects_off(&self, config: LifxConfig, effects_off: EffectsOff) ->  Result<LiFxResults, LifxError>{
+        return Self::async_effects_off_by_selector(config, format!("id:{}", self.id), effects_off).await;
     }
 
-    /// Turn off lights if any of them are on, or turn them on if they are all off. 
+    /// Stops animation(s) for the selected light(s)
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `clean` - A Clean object containing the values to set
+    /// * `effects_off` - A EffectsOff object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
-    /// extern crate lifx_rs;
+    /// extern crate lifx_rs as lifx;
     /// 
     /// #[tokio::main]
     /// async fn main() {
@@ -1561,78 +1780,39 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut toggle = lifx_rs::Toggle::new();
-    ///     toggle.duration = Some(0);
+    ///     let mut effects_off = lifx::EffectsOff::new();
+    ///     effects_off.power_off = Some(true);
     ///     
-    ///     // Toggle all lights
-    ///     lifx_rs::Light::async_toggle_by_selector(key.clone(), format!("all"), toggle).await?;
+    ///     // Send morph effect to all lights
+    ///     lifx::Light::async_effects_off_by_selector(key.clone(), format!("all"), effects_off).await;
     /// }
     ///  ```
-    pub async fn async_toggle_by_selector(config: LifxConfig, selector: String, toggle: Toggle) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/toggle", config.api_endpoints[0], selector);
-
-        let request = reqwest::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&toggle.to_params())
-            .send().await;
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/toggle", config.api_endpoints[1], selector);
-
-                    let request = reqwest::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&toggle.to_params())
-                        .send().await;
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_effects_off_by_selector(config: LifxConfig, selector: String, effects_off: EffectsOff) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_effects_off_by_selector(selector, effects_off).await
     }
 
-    // =======================================
-    // END OF ASYNC FUNCTIONS
-    // =======================================
 
-    // =======================================
-    // BEGINING OF SYNC FUNCTIONS
-    // =======================================
 
-    /// Set the breathe animation for the current light
+    /// Activate the flame animation for the current light
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
     /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `breathe` - A BreatheEffect object containing the values to set
+    /// * `flame_effect` - A FlameEffect object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -1642,7 +1822,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -1650,15 +1831,13 @@ impl Light {
     ///         Ok(lights) => {
     ///             println!("{:?}",lights.clone());
     ///     
-    ///             let mut breathe = lifx::BreatheEffect::new();
-    ///             breathe.color = Some(format!("red"));
-    ///             breathe.from_color = Some(format!("green"));
-    ///             breathe.period = Some(10);
-    ///             breathe.persist = Some(true);
-    ///             breathe.power_on = Some(true);
+    ///             let mut flame_effect = lifx::FlameEffect::new();
+    ///             flame_effect.period = Some(10);
+    ///             flame_effect.duration = Some(0);
+    ///             flame_effect.power_on = Some(true);
     ///         
     ///             for light in lights {
-    ///                 let results = light.breathe_effect(key.clone(), breathe.clone());
+    ///                 let results = light.async_flame_effect(key.clone(), flame_effect.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -1666,24 +1845,27 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub fn breathe_effect(&self, config: LifxConfig, breathe: BreatheEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::breathe_by_selector_effect(config, format!("id:{}", self.id), breathe);
+    #[cfg(feature = "async")]
+    pub async fn async_flame_effect(&self, config: LifxConfig, flame_effect: FlameEffect) ->  Result<LiFxResults, LifxError>{
+        self.require(&config, Capability::Matrix, "the flame effect")?;
+        return Self::async_flame_effect_by_selector(config, format!("id:{}", self.id), flame_effect).await;
     }
 
-    /// Activate the breathe animation for the selected light(s)
+    /// Activate the flame animation for the selected light(s)
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `breathe` - A BreatheEffect object containing the values to set
+    /// * `flame_effect` - A FlameEffect object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -1693,122 +1875,49 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut breathe = lifx::BreatheEffect::new();
-    ///     breathe.color = Some(format!("red"));
-    ///     breathe.from_color = Some(format!("green"));
-    ///     breathe.period = Some(10);
-    ///     breathe.persist = Some(true);
-    ///     breathe.power_on = Some(true);
+    ///     let mut flame_effect = lifx::FlameEffect::new();
+    ///     flame_effect.period = Some(10);
+    ///     flame_effect.duration = Some(0);
+    ///     flame_effect.power_on = Some(true);
     ///     
-    ///     // Apply breathe effect to all light(s)
-    ///     lifx::Light::breathe_by_selector_effect(key.clone(), format!("all"), breathe);
+    ///     // Send morph effect to all lights
+    ///     lifx::Light::async_flame_effect_by_selector(key.clone(), format!("all"), flame_effect).await;
     /// }
     ///  ```
-    pub fn breathe_by_selector_effect(config: LifxConfig, selector: String, breathe: BreatheEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/breathe", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&breathe.to_params())
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(e) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/breathe", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&breathe.to_params())
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(e2) => {
-                            return Err(e2);
-                        }
-                    }
-                } else {
-                    return Err(e);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_flame_effect_by_selector(config: LifxConfig, selector: String, flame_effect: FlameEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_flame_effect_by_selector(selector, flame_effect).await
     }
 
-    /// This endpoint lets you switch a light to clean mode, with a set duration. 
+
+
+    /// Asynchronously flashes the current light to a temporary state, holds it, then restores the power,
+    /// color and brightness the light had before the flash.
+    ///
+    /// The light is re-read before flashing so the restored snapshot is current. The restore is sent even if
+    /// the flash request fails (the flash error is then returned). If the returned future is dropped during
+    /// the hold, the light is left in the flash state.
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `clean` - A Clean object containing the values to set
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `flash` - A State object containing the temporary values to set
+    /// * `hold` - How long to hold the flash state before restoring
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
-    /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
-    ///
-    ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
-    ///     };
-    /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut clean = lifx::Clean::new();
-    ///             clean.duration = Some(0);
-    ///             clean.stop = Some(false);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.clean(key.clone(), clean.clone());
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
-    ///     }
-    /// }
-    ///  ```
-    pub fn clean(&self, config: LifxConfig, clean: Clean) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::clean_by_selector(config, format!("id:{}", self.id), clean);
-    }
-
-    /// This endpoint lets you switch a selected LIFX object to clean mode, with a set duration. 
-    /// 
-    /// # Arguments
-    ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `clean` - A Clean object containing the values to set
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// extern crate lifx_rs as lifx;
+    /// use std::time::Duration;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -1818,71 +1927,52 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut clean = lifx::Clean::new();
-    ///     clean.duration = Some(0);
-    ///     clean.stop = Some(false);
-    ///     
-    ///     // Set all light to clean mode
-    ///     lifx::Light::clean_by_selector(key.clone(), format!("all"), clean);
+    ///     let mut flash = lifx::State::new();
+    ///     flash.power = Some(format!("on"));
+    ///     flash.color = Some(format!("red"));
+    ///     flash.brightness = Some(1.0);
+    /// 
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_flash_and_restore(config.clone(), flash.clone(), Duration::from_secs(2)).await;
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn clean_by_selector(config: LifxConfig, selector: String, clean: Clean) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/clean", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&clean.to_params())
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/clean", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&clean.to_params())
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
+    #[cfg(feature = "async")]
+    pub async fn async_flash_and_restore(&self, config: LifxConfig, flash: State, hold: Duration) -> Result<LiFxResults, LifxError> {
+        let selector = format!("id:{}", self.id);
+        let current = Self::async_list_by_selector(config.clone(), selector.clone()).await?;
+        let snapshot = current.first().unwrap_or(self).snapshot_state();
+
+        let flashed = Self::async_set_state_by_selector(config.clone(), selector.clone(), flash).await;
+        if flashed.is_ok() {
+            tokio::time::sleep(hold).await;
         }
-    
+        let restored = Self::async_set_state_by_selector(config, selector, snapshot).await;
 
+        flashed?;
+        restored
     }
 
-    /// Stops animation(s) for the current light
+    /// Asynchronously gets ALL lights belonging to the authenticated account
     /// 
     /// # Arguments
     ///
-    /// * `self` - A Light object.
     /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `flame_effect` - A FlameEffect object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -1892,44 +1982,32 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut effects_off = lifx::EffectsOff::new();
-    ///             effects_off.power_off = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.effects_off(key.clone(), effects_off.clone());
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
-    ///     }
+    ///     let all_lights = lifx::Light::async_list_all(config).await?;
     /// }
     ///  ```
-    pub fn effects_off(&self, config: LifxConfig, effects_off: EffectsOff) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::effects_off_by_selector(config, format!("id:{}", self.id), effects_off);
+    #[cfg(feature = "async")]
+    pub async fn async_list_all(config: LifxConfig) -> Result<Lights, LifxError> {
+        return Self::async_list_by_selector(config, format!("all")).await;
     }
 
-    /// Stops animation(s) for the selected light(s)
+    /// Asynchronously gets lights belonging to the authenticated account. Filtering the lights using selectors. Properties such as id, label, group and location can be used in selectors.
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `effects_off` - A EffectsOff object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -1939,70 +2017,31 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut effects_off = lifx::EffectsOff::new();
-    ///     effects_off.power_off = Some(true);
-    ///     
-    ///     // Send morph effect to all lights
-    ///     lifx::Light::effects_off_by_selector(key.clone(), format!("all"), effects_off);
+    ///     let all_lights = lifx::Light::async_list_by_selector(key, format!("all")).await?;
     /// }
     ///  ```
-    pub fn effects_off_by_selector(config: LifxConfig, selector: String, effects_off: EffectsOff) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/off", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&effects_off.to_params())
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/off", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&effects_off.to_params())
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_list_by_selector(config: LifxConfig, selector: String) -> Result<Lights, LifxError> {
+        LifxClient::new(config).async_list_by_selector(selector).await
     }
 
-    /// Activate the flame animation for the current light
+    /// Asynchronously gets all lights belonging to the authenticated account that report power "off", including disconnected ones.
     /// 
     /// # Arguments
     ///
-    /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `flame_effect` - A FlameEffect object containing the values to set
+    /// * `config` - A LifxConfig with the access token and endpoints to use
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2012,119 +2051,36 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut flame_effect = lifx::FlameEffect::new();
-    ///             flame_effect.period = Some(10);
-    ///             flame_effect.duration = Some(0);
-    ///             flame_effect.power_on = Some(true);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.flame_effect(key.clone(), flame_effect.clone());
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
+    ///     if let Ok(lights) = lifx::Light::async_list_off(config.clone()).await {
+    ///         for light in lights {
+    ///             println!("{}", light.label);
+    ///         }
     ///     }
     /// }
     ///  ```
-    pub fn flame_effect(&self, config: LifxConfig, flame_effect: FlameEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::flame_effect_by_selector(config, format!("id:{}", self.id), flame_effect);
-    }
-
-    /// Activate the flame animation for the selected light(s)
-    /// 
-    /// # Arguments
-    ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `flame_effect` - A FlameEffect object containing the values to set
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// extern crate lifx_rs as lifx;
-    /// 
-    /// fn main() {
-    /// 
-    ///     let key = "xxx".to_string();
-    ///     let mut api_endpoints: Vec<String> = Vec::new();
-    ///
-    ///     api_endpoints.push(format!("https://api.lifx.com"));
-    ///     api_endpoints.push(format!("http://localhost:8089"));
-    ///
-    ///     let config = lifx::LifxConfig{
-    ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
-    ///     };
-    /// 
-    ///     let mut flame_effect = lifx::FlameEffect::new();
-    ///     flame_effect.period = Some(10);
-    ///     flame_effect.duration = Some(0);
-    ///     flame_effect.power_on = Some(true);
-    ///     
-    ///     // Send morph effect to all lights
-    ///     lifx::Light::flame_effect_by_selector(key.clone(), format!("all"), flame_effect);
-    /// }
-    ///  ```
-    pub fn flame_effect_by_selector(config: LifxConfig, selector: String, flame_effect: FlameEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/flame", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&flame_effect.to_params())
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/flame", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&flame_effect.to_params())
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_list_off(config: LifxConfig) -> Result<Lights, LifxError> {
+        Self::async_list_all(config).await.map(Self::powered_off)
     }
 
-    /// Gets ALL lights belonging to the authenticated account
+    /// Asynchronously gets all lights belonging to the authenticated account that are on and connected. Disconnected lights are left out even if they report "on",
+    /// since their last reported power may be stale.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `config` - A LifxConfig with the access token and endpoints to use
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2134,29 +2090,40 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config)?;
+    ///     if let Ok(lights) = lifx::Light::async_list_on(config.clone()).await {
+    ///         for light in lights {
+    ///             println!("{}", light.label);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn list_all(config: LifxConfig) -> Result<Lights, reqwest::Error> {
-        return Self::list_by_selector(config, format!("all"));
+    #[cfg(feature = "async")]
+    pub async fn async_list_on(config: LifxConfig) -> Result<Lights, LifxError> {
+        Self::async_list_all(config).await.map(Self::powered_on)
     }
 
-    /// Gets lights belonging to the authenticated account. Filtering the lights using selectors. Properties such as id, label, group and location can be used in selectors.
+    /// Asynchronously lists the lights for each selector, running at most `concurrency` listings at
+    /// once. Results are returned in the same order as `selectors`, each paired with its selector, and
+    /// one failed listing doesn't affect the others. A concurrency of 0 is treated as 1.
+    /// Must be called from within a tokio runtime, since each listing is spawned as its own task.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `selectors` - The Selectors to list
+    /// * `concurrency` - The most listings to have in flight at a time
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2166,42 +2133,25 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_by_selector(key, format!("all"))?;
+    ///     let groups = vec![lifx::Selector::Group(format!("Lounge")), lifx::Selector::Group(format!("Kitchen"))];
+    ///     for (selector, lights) in lifx::Light::async_list_selectors(config, &groups, 4).await {
+    ///         println!("{}: {:?}", selector, lights.map(|lights| lights.len()));
+    ///     }
     /// }
     ///  ```
-    pub fn list_by_selector(config: LifxConfig, selector: String) -> Result<Lights, reqwest::Error> {
-        let url = format!("{}/v1/lights/{}", config.api_endpoints[0], selector);
-        let request = reqwest::blocking::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send();
-        match request {
-            Ok(req) => {
-                let json = req.json::<Lights>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}", config.api_endpoints[1], selector);
-                    let request = reqwest::blocking::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send();
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<Lights>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
+    #[cfg(feature = "async")]
+    pub async fn async_list_selectors(config: LifxConfig, selectors: &[Selector], concurrency: usize) -> Vec<(Selector, Result<Lights, LifxError>)> {
+        let results = bounded_join(selectors.to_vec(), concurrency, move |selector: Selector| {
+            Self::async_list_by_selector(config.clone(), selector.to_string())
+        }).await;
+        selectors.iter().cloned().zip(results).collect()
     }
 
-    /// Activate the morph animation for the current light
+    /// Asynchronously activate the morph animation for the current light
     /// 
     /// # Arguments
     ///
@@ -2214,7 +2164,8 @@ impl Light {
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2224,7 +2175,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -2233,7 +2185,7 @@ impl Light {
     ///             println!("{:?}",lights.clone());
     ///     
     ///             let mut morph_effect = lifx::MorphEffect::new();
-    ///             morph_effect.period = Some(10);
+    ///             morph_effect.period = Some(10.0);
     ///             morph_effect.duration = Some(0);
     /// 
     ///             let mut palette: Vec<String> = Vec::new();
@@ -2244,7 +2196,7 @@ impl Light {
     ///             morph_effect.power_on = Some(true);
     ///         
     ///             for light in lights {
-    ///                 let results = light.morph_effect(key.clone(), morph_effect.clone());
+    ///                 let results = light.async_morph_effect(key.clone(), morph_effect.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -2252,11 +2204,15 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub fn morph_effect(&self, config: LifxConfig, morph_effect: MorphEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::morph_effect_by_selector(config, format!("id:{}", self.id), morph_effect);
+    #[cfg(feature = "async")]
+    pub async fn async_morph_effect(&self, config: LifxConfig, morph_effect: MorphEffect) ->  Result<LiFxResults, LifxError>{
+        if !config.skip_capability_checks {
+            morph_effect.validate_for(self)?;
+        }
+        return Self::async_morph_effect_by_selector(config, format!("id:{}", self.id), morph_effect).await;
     }
 
-    /// Activate the morph animation for the selected light(s)
+    /// Asynchronously activate the morph animation for the selected light(s)
     /// 
     /// # Arguments
     ///
@@ -2269,7 +2225,8 @@ impl Light {
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2279,11 +2236,12 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let mut morph_effect = lifx::MorphEffect::new();
-    ///     morph_effect.period = Some(10);
+    ///     morph_effect.period = Some(10.0);
     ///     morph_effect.duration = Some(0);
     /// 
     ///     let mut palette: Vec<String> = Vec::new();
@@ -2294,41 +2252,80 @@ impl Light {
     ///     morph_effect.power_on = Some(true);
     ///     
     ///     // Send morph effect to all lights
-    ///     lifx::Light::morph_effect_by_selector(key.clone(), format!("all"), morph_effect);
+    ///     lifx::Light::async_morph_effect_by_selector(key.clone(), format!("all"), morph_effect).await;
     /// }
     ///  ```
-    pub fn morph_effect_by_selector(config: LifxConfig, selector: String, morph_effect: MorphEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/morph", config.api_endpoints[0], selector);
-        let request = reqwest::blocking::Client::new().post(url).header("Authorization", format!("Bearer {}", config.access_token)).form(&morph_effect.to_params()).send();
-        match request{
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/morph", config.api_endpoints[1], selector);
-                    let request = reqwest::blocking::Client::new().post(url).header("Authorization", format!("Bearer {}", config.access_token)).form(&morph_effect.to_params()).send();
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
-
-
+    #[cfg(feature = "async")]
+    pub async fn async_morph_effect_by_selector(config: LifxConfig, selector: String, morph_effect: MorphEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_morph_effect_by_selector(selector, morph_effect).await
     }
 
-    /// Activate the move animation for the current light
+    /// Asynchronously starts the same morph animation on each light, staggering the starts by `phase_offset`
+    /// so separate bulbs show a traveling wave. The n-th light starts `n * phase_offset` after the first.
+    ///
+    /// The phase offset is approximate: the delays are timed on the client, so network latency adds jitter.
+    /// Must be called from within a tokio runtime, since each delayed start is spawned as its own task.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `lights` - The lights to run the wave across, in start order
+    /// * `morph_effect` - A MorphEffect object containing the values to set
+    /// * `phase_offset` - The delay between the start of one light and the next
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::time::Duration;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut morph_effect = lifx::MorphEffect::new();
+    ///     morph_effect.period = Some(10.0);
+    ///     morph_effect.palette = Some(vec![format!("red"), format!("blue")]);
+    /// 
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         let results = lifx::Light::async_morph_wave(config, &lights, morph_effect, Duration::from_millis(500)).await;
+    ///         println!("{:?}", results);
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_morph_wave(config: LifxConfig, lights: &[Light], morph_effect: MorphEffect, phase_offset: Duration) -> Vec<Result<LiFxResults, LifxError>> {
+        let mut handles = Vec::new();
+        for (light, delay) in lights.iter().zip(stagger_delays(lights.len(), phase_offset)) {
+            let config = config.clone();
+            let light = light.clone();
+            let morph_effect = morph_effect.clone();
+            handles.push(tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                light.async_morph_effect(config, morph_effect).await
+            }));
+        }
+
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await.expect("morph wave task panicked"));
+        }
+        results
+    }
+
+    /// Asynchronously activate the move animation for the current light
     /// 
     /// # Arguments
     ///
@@ -2341,7 +2338,8 @@ impl Light {
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2351,7 +2349,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -2361,12 +2360,12 @@ impl Light {
     ///     
     ///             let mut move_effect = lifx::MoveEffect::new();
     ///             move_effect.direction = Some(format!("forward")); // or backward
-    ///             move_effect.period = Some(10);
+    ///             move_effect.period = Some(10.0);
     ///             move_effect.cycles = Some(0.9);
     ///             move_effect.power_on = Some(true);
     ///         
     ///             for light in lights {
-    ///                 let results = light.move_effect(key.clone(), move_effect.clone());
+    ///                 let results = light.async_move_effect(key.clone(), move_effect.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -2374,24 +2373,27 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub fn move_effect(&self, config: LifxConfig, move_effect: MoveEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::move_effect_by_selector(config, format!("id:{}", self.id), move_effect);
+    #[cfg(feature = "async")]
+    pub async fn async_move_effect(&self, config: LifxConfig, move_effect: MoveEffect) ->  Result<LiFxResults, LifxError>{
+        self.require(&config, Capability::Multizone, "the move effect")?;
+        return Self::async_move_effect_by_selector(config, format!("id:{}", self.id), move_effect).await;
     }
 
-    /// Activate the move animation for the selected light(s)
+    /// Asynchronously activate the move animation for the selected light(s)
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `clean` - A Clean object containing the values to set
+    /// * `move_effect` - A MoveEffect object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2401,49 +2403,26 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let mut move_effect = lifx::MoveEffect::new();
     ///     move_effect.direction = Some(format!("forward")); // or backward
-    ///     move_effect.period = Some(10);
+    ///     move_effect.period = Some(10.0);
     ///     move_effect.cycles = Some(0.9);
     ///     move_effect.power_on = Some(true);
     ///     
     ///     // Toggle all lights
-    ///     lifx::Light::move_effect_by_selector(key.clone(), format!("all"), move_effect);
+    ///     lifx::Light::async_move_effect_by_selector(key.clone(), format!("all"), move_effect).await;
     /// }
     ///  ```
-    pub fn move_effect_by_selector(config: LifxConfig, selector: String, move_effect: MoveEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/move", config.api_endpoints[0], selector);
-        let request = reqwest::blocking::Client::new().post(url).header("Authorization", format!("Bearer {}", config.access_token)).form(&move_effect.to_params()).send();
-        match request{
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/move", config.api_endpoints[1], selector);
-                    let request = reqwest::blocking::Client::new().post(url).header("Authorization", format!("Bearer {}", config.access_token)).form(&move_effect.to_params()).send();
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
+    #[cfg(feature = "async")]
+    pub async fn async_move_effect_by_selector(config: LifxConfig, selector: String, move_effect: MoveEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_move_effect_by_selector(selector, move_effect).await
     }
 
-    /// Activate the pulse animation for the current light
+    /// Asynchronously activate the pulse animation for the current light
     /// 
     /// # Arguments
     ///
@@ -2456,7 +2435,8 @@ impl Light {
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2466,7 +2446,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -2482,7 +2463,7 @@ impl Light {
     ///             pulse.power_on = Some(true);
     ///         
     ///             for light in lights {
-    ///                 let results = light.pulse_effect(key.clone(), pulse.clone());
+    ///                 let results = light.async_pulse_effect(key.clone(), pulse.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -2490,11 +2471,12 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub fn pulse_effect(&self, config: LifxConfig, pulse_effect: PulseEffect) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::pulse_effect_by_selector(config, format!("id:{}", self.id), pulse_effect);
+    #[cfg(feature = "async")]
+    pub async fn async_pulse_effect(&self, config: LifxConfig, pulse_effect: PulseEffect) ->  Result<LiFxResults, LifxError>{
+        return Self::async_pulse_effect_by_selector(config, format!("id:{}", self.id), pulse_effect).await;
     }
 
-    /// Activate the pulse animation for the selected light(s)
+    /// Asynchronously activate the pulse animation for the selected light(s)
     /// 
     /// # Arguments
     ///
@@ -2507,7 +2489,8 @@ impl Light {
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2517,7 +2500,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let mut pulse = lifx::PulseEffect::new();
@@ -2528,45 +2512,55 @@ impl Light {
     ///     pulse.power_on = Some(true);
     ///     
     ///     // Toggle all lights
-    ///     lifx::Light::pulse_effect_by_selector(key.clone(), format!("all"), pulse);
+    ///     lifx::Light::async_pulse_effect_by_selector(key.clone(), format!("all"), pulse).await;
     /// }
     ///  ```
-    pub fn pulse_effect_by_selector(config: LifxConfig, selector: String, pulse_effect: PulseEffect) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/effects/pulse", config.api_endpoints[0], selector);
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&pulse_effect.to_params())
-            .send();
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/effects/pulse", config.api_endpoints[1], selector);
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&pulse_effect.to_params())
-                        .send();
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
+    #[cfg(feature = "async")]
+    pub async fn async_pulse_effect_by_selector(config: LifxConfig, selector: String, pulse_effect: PulseEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_pulse_effect_by_selector(selector, pulse_effect).await
+    }
+
+
 
+    /// Asynchronously resolves a selector to the lights it currently matches, without changing them.
+    /// Useful for previewing which lights a destructive operation (e.g. "all off") will affect.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `selector` - A typed LIFX Selector ex: Selector::All, Selector::GroupId(xxx)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let selector = lifx::Selector::Group(format!("Kitchen"));
+    ///     let affected = lifx::Light::async_resolve_selector(config, &selector).await;
+    ///     println!("{:?}", affected);
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_resolve_selector(config: LifxConfig, selector: &Selector) -> Result<Lights, LifxError> {
+        Self::async_list_by_selector(config, selector.to_string()).await
     }
 
-    /// Sets the state for the current light
+    /// Asynchronously sets the state for the current light
     /// 
     /// # Arguments
     ///
@@ -2579,7 +2573,8 @@ impl Light {
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2589,7 +2584,8 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let all_lights = lifx::Light::list_all(config.clone());
@@ -2602,7 +2598,7 @@ impl Light {
     ///             state.brightness = Some(1.0);
     ///         
     ///             for light in lights {
-    ///                 let results = light.set_state(key.clone(), state.clone());
+    ///                 let results = light.async_set_state(key.clone(), state.clone()).await;
     ///                 println!("{:?}",results);
     ///             }
     ///         },
@@ -2610,24 +2606,25 @@ impl Light {
     ///     }
     /// }
     ///  ```
-    pub fn set_state(&self, config: LifxConfig, state: State) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::set_state_by_selector(config, format!("id:{}", self.id), state);
+    #[cfg(feature = "async")]
+    pub async fn async_set_state(&self, config: LifxConfig, state: State) ->  Result<LiFxResults, LifxError>{
+        return Self::async_set_state_by_selector(config, format!("id:{}", self.id), state).await;
     }
 
-    /// Sets the state for the selected LIFX object
+    /// Asynchronously turns the light on, keeping its color and brightness
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `state` - A State object containing the values of the State to set
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2637,67 +2634,37 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut off_state = lifx::State::new();
-    ///     off_state.power = Some(format!("off"));
-    ///     
-    ///     // Turn off all lights
-    ///     lifx::Light::set_state_by_selector(key.clone(), format!("all"), off_state);
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_turn_on(config.clone()).await;
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn set_state_by_selector(config: LifxConfig, selector: String, state: State) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/state", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().put(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&state.to_params())
-            .send();
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/state", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().put(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&state.to_params())
-                        .send();
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_turn_on(&self, config: LifxConfig) ->  Result<LiFxResults, LifxError>{
+        self.async_set_state(config, State::builder().power_on().build()).await
     }
 
-    /// Sets the state for the selected LIFX object
+    /// Asynchronously turns the light off
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `states` - A vector of States with defaults
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2707,83 +2674,38 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut set_states = lifx::States::new();
-    ///     let mut states: Vec<lifx::State> = Vec::new();
-    ///     let mut defaults = lifx::State::new();
-    ///     
-    ///     defaults.brightness = Some(1.0);
-    ///     
-    ///     let mut state_1 = lifx::State::new();
-    ///     state_1.selector = Some(format!("id:xxx"));
-    ///     state_1.power = Some(format!("on"));
-    ///     
-    ///     let mut state_2 = lifx::State::new();
-    ///     state_2.selector = Some(format!("id:xyz"));
-    ///     state_2.power = Some(format!("on"));
-    ///     
-    ///     set_states.states = Some(states);
-    ///     set_states.defaults = Some(defaults);
-    ///     
-    ///     lifx::Light::set_states(key.clone(), set_states);
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_turn_off(config.clone()).await;
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn set_states(config: LifxConfig, states: States) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/state", config.api_endpoints[0]);
-
-        let request = reqwest::blocking::Client::new().put(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .json(&states)
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/state", config.api_endpoints[1]);
-
-                    let request = reqwest::blocking::Client::new().put(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .json(&states)
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_turn_off(&self, config: LifxConfig) ->  Result<LiFxResults, LifxError>{
+        self.async_set_state(config, State::builder().power_off().build()).await
     }
 
-    /// Set parameters other than power and duration change the state of the lights by the amount specified.
+    /// Asynchronously sets the brightness of the light, keeping its power and color
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `delta` - A StateDelta object containing the values to set
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
+    /// * `brightness` - The brightness level from 0.0 to 1.0.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2793,71 +2715,38 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut delta = lifx::StateDelta::new();
-    ///     delta.duration = Some(0);
-    ///     delta.power = Some(format!("on"));
-    ///     
-    ///     // Send StateDelta
-    ///     lifx::Light::state_delta_by_selector(key.clone(), format!("all"), toggle);
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_set_brightness(config.clone(), 0.5).await;
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn state_delta_by_selector(config: LifxConfig, selector: String, delta: StateDelta) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/state/delta", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&delta.to_params())
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/state/delta", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&delta.to_params())
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
+    #[cfg(feature = "async")]
+    pub async fn async_set_brightness(&self, config: LifxConfig, brightness: f64) ->  Result<LiFxResults, LifxError>{
+        self.async_set_state(config, State::builder().brightness(brightness).build()).await
     }
 
-
-    /// Turn off light if on, or turn them on if it is off. 
+    /// Asynchronously sets the color of the light, keeping its power and brightness
     /// 
     /// # Arguments
     ///
     /// * `self` - A Light object.
-    /// * `access_token` - A personal acces token for authentication with LIFX.
-    /// * `clean` - A Clean object containing the values to set
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
+    /// * `color` - The color to set, ex: "red", a Color, a NamedColor or a Kelvin preset.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2867,44 +2756,38 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let all_lights = lifx::Light::list_all(config.clone());
-    ///     match all_lights {
-    ///         Ok(lights) => {
-    ///             println!("{:?}",lights.clone());
-    ///     
-    ///             let mut toggle = lifx::Toggle::new();
-    ///             toggle.duration = Some(0);
-    ///         
-    ///             for light in lights {
-    ///                 let results = light.toggle(key.clone(), clean.clone());
-    ///                 println!("{:?}",results);
-    ///             }
-    ///         },
-    ///         Err(e) => println!("{}",e)
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             let results = light.async_set_color(config.clone(), lifx::NamedColor::Blue).await;
+    ///             println!("{:?}",results);
+    ///         }
     ///     }
     /// }
     ///  ```
-    pub fn toggle(&self, config: LifxConfig, toggle: Toggle) ->  Result<LiFxResults, reqwest::Error>{
-        return Self::toggle_by_selector(config, format!("id:{}", self.id), toggle);
+    #[cfg(feature = "async")]
+    pub async fn async_set_color(&self, config: LifxConfig, color: impl Into<String>) ->  Result<LiFxResults, LifxError>{
+        self.async_set_state(config, State::builder().color(color).build()).await
     }
 
-    /// Turn off lights if any of them are on, or turn them on if they are all off. 
+    /// Asynchronously sets the state for the selected LIFX object
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
     /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
-    /// * `clean` - A Clean object containing the values to set
+    /// * `state` - A State object containing the values of the State to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -2914,81 +2797,32 @@ impl Light {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut toggle = lifx::Toggle::new();
-    ///     toggle.duration = Some(0);
+    ///     let mut off_state = lifx::State::new();
+    ///     off_state.power = Some(format!("off"));
     ///     
-    ///     // Toggle all lights
-    ///     lifx::Light::toggle_by_selector(key.clone(), format!("all"), toggle);
+    ///     // Turn off all lights
+    ///     lifx::Light::async_set_state_by_selector(key.clone(), format!("all"), off_state).await;
     /// }
     ///  ```
-    pub fn toggle_by_selector(config: LifxConfig, selector: String, toggle: Toggle) ->  Result<LiFxResults, reqwest::Error>{
-        let url = format!("{}/v1/lights/{}/toggle", config.api_endpoints[0], selector);
-
-        let request = reqwest::blocking::Client::new().post(url)
-            .header("Authorization", format!("Bearer {}", config.access_token))
-            .form(&toggle.to_params())
-            .send();
-
-        match request {
-            Ok(req) => {
-                let json = req.json::<LiFxResults>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/lights/{}/toggle", config.api_endpoints[1], selector);
-
-                    let request = reqwest::blocking::Client::new().post(url)
-                        .header("Authorization", format!("Bearer {}", config.access_token))
-                        .form(&toggle.to_params())
-                        .send();
-            
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<LiFxResults>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-    
-
+    #[cfg(feature = "async")]
+    pub async fn async_set_state_by_selector(config: LifxConfig, selector: String, state: State) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_set_state_by_selector(selector, state).await
     }
-}
-
-pub type Scenes = Vec<Scene>;
 
-/// Represents an LIFX Scene
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Scene {
-    pub uuid: String,
-    pub name: String,
-    pub account: Account,
-    pub states: Vec<State>,
-    #[serde(rename = "created_at")]
-    pub created_at: i64,
-    #[serde(rename = "updated_at")]
-    pub updated_at: i64,
-    pub error: Option<String>,
-    pub errors: Option<Vec<Error>>,
-}
-impl Scene {
-    /// Asynchronously gets ALL scenes belonging to the authenticated account
+    /// Asynchronously sets the state for the selected LIFX object(s)
+    ///
+    /// The cloud API takes at most 50 states a request, so more are sent as one request per 50,
+    /// one after the other, and their results returned together. The first error stops the rest,
+    /// leaving the chunks before it applied.
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `states` - A vector of States with defaults
     ///
     /// # Examples
     ///
@@ -3006,54 +2840,57 @@ impl Scene {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let scenes = lifx::Scene::async_list(config).await?;
-    /// }
-    ///  ```
-    pub async fn async_list(config: LifxConfig) -> Result<Scenes, reqwest::Error> {
-        let url = format!("{}/v1/scenes", config.api_endpoints[0]);
-        let request = reqwest::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send().await;
-        match request {
-            Ok(req) => {
-                let json = req.json::<Scenes>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/scenes", config.api_endpoints[1]);
-                    let request = reqwest::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send().await;
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<Scenes>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-            
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
+    ///     let mut set_states = lifx::States::new();
+    ///     let mut states: Vec<lifx::State> = Vec::new();
+    ///     let mut defaults = lifx::State::new();
+    ///     
+    ///     defaults.brightness = Some(1.0);
+    ///     
+    ///     let mut state_1 = lifx::State::new();
+    ///     state_1.selector = Some(format!("id:xxx"));
+    ///     state_1.power = Some(format!("on"));
+    ///     
+    ///     let mut state_2 = lifx::State::new();
+    ///     state_2.selector = Some(format!("id:xyz"));
+    ///     state_2.power = Some(format!("on"));
+    ///     
+    ///     set_states.states = Some(states);
+    ///     set_states.defaults = Some(defaults);
+    ///     
+    ///     lifx::Light::async_set_states(key.clone(), set_states).await;
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_set_states(config: LifxConfig, states: States) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_set_states(states).await
     }
 
-    /// Gets ALL scenes belonging to the authenticated account
+    /// Asynchronously gives each light a random hue at the given saturation and brightness, sent as one set_states call.
+    ///
+    /// Hues come from a small built-in PRNG, so no rand dependency is needed. Pass a `seed` to get the
+    /// same hues every time (ex: in tests); with None a fresh seed is drawn for each call. The API
+    /// accepts at most 50 states per set_states call, so shuffle larger sets in chunks.
     /// 
     /// # Arguments
     ///
-    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `lights` - The lights to recolor
+    /// * `saturation` - The saturation for every light, 0.0 to 1.0
+    /// * `brightness` - The brightness for every light, 0.0 to 1.0
+    /// * `duration` - How long in seconds the color change takes
+    /// * `seed` - Seed for the hue sequence, or None for a random one
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -3063,62 +2900,30 @@ impl Scene {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let scenes = lifx::Scene::list_all(config)?;
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         let results = lifx::Light::async_shuffle_colors(config.clone(), &lights, 1.0, 0.8, 0.5, None).await;
+    ///         println!("{:?}", results);
+    ///     }
     /// }
     ///  ```
-    pub fn list(config: LifxConfig) -> Result<Scenes, reqwest::Error> {
-        let url = format!("{}/v1/scenes", config.api_endpoints[0]);
-        let request = reqwest::blocking::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send();
-
-        match request{
-            Ok(req) => {
-                let json = req.json::<Scenes>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/scenes", config.api_endpoints[1]);
-                    let request = reqwest::blocking::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send();
-            
-                    match request{
-                        Ok(req) => {
-                            let json = req.json::<Scenes>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
-
+    #[cfg(feature = "async")]
+    pub async fn async_shuffle_colors(config: LifxConfig, lights: &[Light], saturation: f64, brightness: f64, duration: f64, seed: Option<u64>) -> Result<LiFxResults, LifxError> {
+        let mut states = States::new();
+        states.states = Some(Self::shuffled_states(lights, saturation, brightness, duration, seed));
+        Self::async_set_states(config, states).await
     }
-}
 
-/// Represents an LIFX Color
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Color {
-    pub hue: Option<f64>,
-    pub saturation: Option<f64>,
-    pub kelvin: Option<i64>,
-    pub brightness: Option<f64>,
-    pub error: Option<String>,
-    pub errors: Option<Vec<Error>>,
-}
-impl Color {
-    /// Asynchronously validates a color
+    /// Asynchronously set parameters other than power and duration change the state of the lights by the amount specified.
     /// 
     /// # Arguments
     ///
     /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `delta` - A StateDelta object containing the values to set
     ///
     /// # Examples
     ///
@@ -3136,53 +2941,40 @@ impl Color {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let scenes = lifx::Color::async_validate(key, format!("red")).await?;
+    ///     let mut delta = lifx::StateDelta::new();
+    ///     delta.duration = Some(0);
+    ///     delta.power = Some(format!("on"));
+    ///     
+    ///     // Send StateDelta
+    ///     lifx::Light::async_state_delta_by_selector(key.clone(), format!("all"), toggle).await;
     /// }
     ///  ```
-    pub async fn async_validate(config: LifxConfig, color: String) -> Result<Color, reqwest::Error> {
-        let url = format!("{}/v1/color?string={}", config.api_endpoints[0], color);
-        let request = reqwest::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send().await;
-        match request {
-            Ok(req) => {
-                let json = req.json::<Color>().await?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/color?string={}", config.api_endpoints[1], color);
-                    let request = reqwest::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send().await;
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<Color>().await?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
+    #[cfg(feature = "async")]
+    pub async fn async_state_delta_by_selector(config: LifxConfig, selector: String, delta: StateDelta) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_state_delta_by_selector(selector, delta).await
     }
 
-    /// Validates a color
+
+
+    /// Turn off light if on, or turn them on if it is off. 
     /// 
     /// # Arguments
     ///
+    /// * `self` - A Light object.
     /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `clean` - A Clean object containing the values to set
     ///
     /// # Examples
     ///
     /// ```
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -3192,106 +2984,47 @@ impl Color {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let scenes = lifx::Color::validate(config)?;
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut toggle = lifx::Toggle::new();
+    ///             toggle.duration = Some(0.5);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.async_toggle(key.clone(), clean.clone()).await;
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
     /// }
     ///  ```
-    pub fn validate(config: LifxConfig, color: String) -> Result<Color, reqwest::Error> {
-        let url = format!("{}/v1/color?string={}", config.api_endpoints[0], color);
-        let request = reqwest::blocking::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send();
-        match request {
-            Ok(req) => {
-                let json = req.json::<Color>()?;
-                return Ok(json);
-            },
-            Err(err) => {
-                if config.api_endpoints.len() > 1 {
-                    let url = format!("{}/v1/color?string={}", config.api_endpoints[1], color);
-                    let request = reqwest::blocking::Client::new().get(url).header("Authorization", format!("Bearer {}", config.access_token)).send();
-                    match request {
-                        Ok(req) => {
-                            let json = req.json::<Color>()?;
-                            return Ok(json);
-                        },
-                        Err(err2) => {
-                            return Err(err2);
-                        }
-                    }
-                } else {
-                    return Err(err);
-                }
-            }
-        }
-
-
-    }
-}
-
-/// Used to set the duration/state of the HEV Clean array
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Clean {
-    /// Turn the device on / off
-    pub stop: Option<bool>,
-    /// Duration in seconds (leaving blank or 0 sets the default duration for the device)
-    pub duration: Option<i64>
-}
-impl Clean {
-    pub fn new() -> Self {
-        return Clean{
-            stop: None,
-            duration: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.stop{
-            Some(stop) => params.push(("stop".to_string(), stop.to_string())),
-            None => {}
-        }
-        match &self.duration{
-            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
-            None => {}
-        }
-       
-        return params;
+    #[cfg(feature = "async")]
+    pub async fn async_toggle(&self, config: LifxConfig, toggle: Toggle) ->  Result<LiFxResults, LifxError>{
+        return Self::async_toggle_by_selector(config, format!("id:{}", self.id), toggle).await;
     }
 
-
-}
-
-/// Used to descripe the state of an LIFX Light Source
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct State {
-    /// The power state you want to set on the selector. on or off
-    pub power: Option<String>,
-    /// The color to set the light to.
-    pub color: Option<String>,
-    /// The brightness level from 0.0 to 1.0. Overrides any brightness set in color (if any).
-    pub brightness: Option<f64>,
-    /// How long in seconds you want the power action to take. Range: 0.0 – 3155760000.0 (100 years)
-    pub duration: Option<f64>,
-    /// The maximum brightness of the infrared channel from 0.0 to 1.0.
-    pub infrared: Option<f64>,
-    /// The selector to limit which light to use for set_states()
-    pub selector:  Option<String>,
-    /// Execute the query fast, without initial state checks and wait for no results.
-    pub fast: Option<bool>
-}
-impl State {
-
-    /// Returns a new State object
+    /// Turn off lights if any of them are on, or turn them on if they are all off. 
     /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `clean` - A Clean object containing the values to set
+    ///
     /// # Examples
     ///
     /// ```
-    /// extern crate lifx_rs as lifx;
+    /// extern crate lifx_rs;
     /// 
-    /// fn main() {
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -3301,76 +3034,45 @@ impl State {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut state = lifx::State::new();
-    ///     state.power = Some(format!("off"));
+    ///     let mut toggle = lifx_rs::Toggle::new();
+    ///     toggle.duration = Some(0.5);
+    ///     
+    ///     // Toggle all lights
+    ///     lifx_rs::Light::async_toggle_by_selector(key.clone(), format!("all"), toggle).await?;
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return State{
-            power: None,
-            color: None,
-            brightness: None,
-            duration: None,
-            infrared: None,
-            selector: None,
-            fast: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.power{
-            Some(power) => params.push(("power".to_string(), power.to_string())),
-            None => {}
-        }
-        match &self.color{
-            Some(color) => params.push(("color".to_string(), color.to_string())),
-            None => {}
-        }
-        match &self.brightness{
-            Some(brightness) => params.push(("brightness".to_string(), brightness.to_string())),
-            None => {}
-        }
-        match &self.duration{
-            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
-            None => {}
-        }
-        match &self.infrared{
-            Some(infrared) => params.push(("infrared".to_string(), infrared.to_string())),
-            None => {}
-        }
-        match &self.selector{
-            Some(selector) => params.push(("selector".to_string(), selector.to_string())),
-            None => {}
-        }
-        match &self.fast{
-            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
-            None => {}
-        }
-        return params;
+    #[cfg(feature = "async")]
+    pub async fn async_toggle_by_selector(config: LifxConfig, selector: String, toggle: Toggle) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).async_toggle_by_selector(selector, toggle).await
     }
 
-
-}
-
-/// Used to set the params when posting a Toggle event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Toggle {
-    pub duration: Option<i64>
-}
-impl Toggle {
-    /// Returns a new Toggle object
+    /// Asynchronously waits for a light to show up in list_all as connected, ex: while onboarding a new bulb.
+    ///
+    /// Polls list_all every `poll_interval` until the light with the given id is listed with
+    /// connected == true, and returns it. Returns LifxError::NotFound once `timeout` has passed
+    /// without that happening, or the first error listing the lights.
+    /// Every poll is one API request, so keep the interval in line with the rate limit.
     /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `id` - The id (serial number) of the light to wait for
+    /// * `timeout` - How long to wait before giving up
+    /// * `poll_interval` - How long to wait between polls
+    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
-    /// fn main() {
+    /// use std::time::Duration;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
     /// 
     ///     let key = "xxx".to_string();
     ///     let mut api_endpoints: Vec<String> = Vec::new();
@@ -3380,47 +3082,65 @@ impl Toggle {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut toggle = lifx::Toggle::new();
-    ///     toggle.duration = Some(0);
+    ///     let light = lifx::Light::async_wait_until_present(config, "d073d5000000", Duration::from_secs(120), Duration::from_secs(5)).await;
+    ///     println!("{:?}", light);
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return Toggle{
-            duration: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.duration{
-            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
-            None => {}
+    #[cfg(feature = "async")]
+    pub async fn async_wait_until_present(config: LifxConfig, id: &str, timeout: Duration, poll_interval: Duration) -> Result<Light, LifxError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let lights = Self::async_list_all(config.clone()).await?;
+            if let Some(light) = Self::find_present(&lights, id) {
+                return Ok(light);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Self::not_present(id, timeout));
+            }
+            tokio::time::sleep(poll_interval.min(remaining)).await;
         }
-        return params;
     }
 
+    // =======================================
+    // END OF ASYNC FUNCTIONS
+    // =======================================
 
-}
-
+    // =======================================
+    // BEGINING OF SYNC FUNCTIONS
+    // =======================================
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[doc(hidden)]
-pub struct States {
-    pub states: Option<Vec<State>>,
-    pub defaults: Option<State>,
-}
-impl States {
-    /// Returns a new States object
+    /// Sets the state for the current light, then polls it until it reports that state, for
+    /// automation where a missed command matters.
+    ///
+    /// Waits out the state's duration (or the config's default_duration) first, so the light has
+    /// finished fading, then lists the light every VERIFY_POLL_INTERVAL until its power, color
+    /// and brightness match the ones the state sets, each within `tolerance` as a fraction of its
+    /// range: 0.01 allows 0.01 of brightness and saturation, 3.6 degrees of hue and 75 kelvin.
+    /// Infrared isn't reported by the API, so it isn't checked.
+    ///
+    /// Returns the set_state results once the light matches, LifxError::VerificationFailed if it
+    /// doesn't within `timeout` of the fade ending, or the first error setting or listing it.
     /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `state` - A State object containing the values to set
+    /// * `tolerance` - How far off each reported value may be, as a fraction of its range
+    /// * `timeout` - How long to keep polling once the state's transition has finished
+    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
+    /// use std::time::Duration;
+    /// 
     /// fn main() {
     /// 
     ///     let key = "xxx".to_string();
@@ -3431,44 +3151,46 @@ impl States {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut states = lifx::States::new();
+    ///     let locked = lifx::State::builder().power_on().color("red").brightness(1.0).duration(2.0).build();
+    ///     if let Ok(lights) = lifx::Light::list_by_selector(config.clone(), format!("label:Gate")) {
+    ///         for light in lights {
+    ///             let verified = light.apply_verified(config.clone(), locked.clone(), 0.01, Duration::from_secs(10));
+    ///             println!("{:?}", verified);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return States{
-            states: None,
-            defaults: None
-        };
+    #[cfg(feature = "blocking")]
+    pub fn apply_verified(&self, config: LifxConfig, state: State, tolerance: f64, timeout: Duration) -> Result<LiFxResults, LifxError> {
+        let (target, fade) = Self::verify_target(&config, &state);
+        let results = self.set_state(config.clone(), state)?;
+        std::thread::sleep(fade);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let lights = Self::list_by_selector(config.clone(), format!("id:{}", self.id))?;
+            if lights.iter().any(|light| light.id == self.id && apply::reached(&target, light, tolerance)) {
+                return Ok(results);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(self.not_verified(timeout));
+            }
+            std::thread::sleep(VERIFY_POLL_INTERVAL.min(remaining));
+        }
     }
-}
 
-/// Used to set the params when posting a StateDelta event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct StateDelta {
-    /// The power state you want to set on the selector. on or off
-    pub power: Option<String>,
-    /// How long in seconds you want the power action to take. Range: 0.0 – 3155760000.0 (100 years)
-    pub duration: Option<f64>,
-    /// The maximum brightness of the infrared channel.
-    pub infrared: Option<f64>,
-    /// Rotate the hue by this angle in degrees. Range: -360.0 – 360.0 degrees
-    pub hue: Option<f64>,
-    /// Change the saturation by this additive amount; the resulting saturation is clipped to [0, 1].
-    pub saturation: Option<f64>,
-    /// Change the brightness by this additive amount; the resulting brightness is clipped to [0, 1].
-    pub brightness: Option<f64>,
-    /// Change the kelvin by this additive amount; the resulting kelvin is clipped to [2500, 9000].
-    pub kelvin: Option<i64>,
-    /// Execute the query fast, without initial state checks and wait for no results.
-    pub fast: Option<bool>,
-}
-impl StateDelta {
-    /// Returns a new StateDelta object
+    /// Set the breathe animation for the current light
     /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `breathe` - A BreatheEffect object containing the values to set
+    ///
     /// # Examples
     ///
     /// ```
@@ -3484,95 +3206,44 @@ impl StateDelta {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut delta = lifx::StateDelta::new();
-    ///     delta.duration = Some(0);
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut breathe = lifx::BreatheEffect::new();
+    ///             breathe.color = Some(format!("red"));
+    ///             breathe.from_color = Some(format!("green"));
+    ///             breathe.period = Some(10);
+    ///             breathe.persist = Some(true);
+    ///             breathe.power_on = Some(true);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.breathe_effect(key.clone(), breathe.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return StateDelta{
-            power: None,
-            duration: None,
-            infrared: None,
-            hue: None,
-            saturation: None,
-            brightness: None,
-            kelvin: None,
-            fast: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.power{
-            Some(power) => params.push(("power".to_string(), power.to_string())),
-            None => {}
-        }
-
-        match &self.duration{
-            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
-            None => {}
-        }
-
-        match &self.infrared{
-            Some(infrared) => params.push(("infrared".to_string(), infrared.to_string())),
-            None => {}
-        }
-
-        match &self.hue{
-            Some(hue) => params.push(("hue".to_string(), hue.to_string())),
-            None => {}
-        }
-
-        match &self.saturation{
-            Some(saturation) => params.push(("saturation".to_string(), saturation.to_string())),
-            None => {}
-        }
-
-        match &self.brightness{
-            Some(brightness) => params.push(("brightness".to_string(), brightness.to_string())),
-            None => {}
-        }
-
-        match &self.kelvin{
-            Some(kelvin) => params.push(("kelvin".to_string(), kelvin.to_string())),
-            None => {}
-        }
-
-        match &self.fast{
-            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
-            None => {}
-        }
-
-        return params;
+    #[cfg(feature = "blocking")]
+    pub fn breathe_effect(&self, config: LifxConfig, breathe: BreatheEffect) ->  Result<LiFxResults, LifxError>{
+        return Self::breathe_by_selector_effect(config, format!("id:{}", self.id), breathe);
     }
 
-}
-
-/// Used to set the params when posting a BreatheEffect event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BreatheEffect {
-    /// The color to use for the breathe effect.
-    pub color: Option<String>,
-    /// The color to start the effect from. If this parameter is omitted then the color the bulb is currently set to is used instead.
-    pub from_color: Option<String>,
-    /// The time in seconds for one cycle of the effect.
-    pub period: Option<f64>,
-    /// The number of times to repeat the effect.
-    pub cycles: Option<f64>,
-    /// If false set the light back to its previous value when effect ends, if true leave the last effect color.
-    pub persist: Option<bool>,
-    /// If true, turn the bulb on if it is not already on.
-    pub power_on: Option<bool>,
-    /// Defines where in a period the target color is at its maximum. Minimum 0.0, maximum 1.0.
-    pub peak: Option<f64>,
-}
-impl BreatheEffect {
-    /// Returns a new BreatheEffect object
+    /// Activate the breathe animation for the selected light(s)
     /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `breathe` - A BreatheEffect object containing the values to set
+    ///
     /// # Examples
     ///
     /// ```
@@ -3588,7 +3259,8 @@ impl BreatheEffect {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
     ///     let mut breathe = lifx::BreatheEffect::new();
@@ -3597,83 +3269,29 @@ impl BreatheEffect {
     ///     breathe.period = Some(10);
     ///     breathe.persist = Some(true);
     ///     breathe.power_on = Some(true);
-    /// }
-    ///  ```
-    pub fn new() -> Self {
-        return BreatheEffect{
-            color: None,
-            from_color: None,
-            period: None,
-            cycles: None,
-            persist: None,
-            power_on: None,
-            peak: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.color{
-            Some(color) => params.push(("color".to_string(), color.to_string())),
-            None => {}
-        }
-
-        match &self.from_color{
-            Some(from_color) => params.push(("from_color".to_string(), from_color.to_string())),
-            None => {}
-        }
-
-        match &self.period{
-            Some(period) => params.push(("period".to_string(), period.to_string())),
-            None => {}
-        }
-
-        match &self.cycles{
-            Some(cycles) => params.push(("cycles".to_string(), cycles.to_string())),
-            None => {}
-        }
-
-        match &self.persist{
-            Some(persist) => params.push(("persist".to_string(), persist.to_string())),
-            None => {}
-        }
-
-        match &self.power_on{
-            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
-            None => {}
-        }
-
-        match &self.peak{
-            Some(peak) => params.push(("peak".to_string(), peak.to_string())),
-            None => {}
-        }
-
-        return params;
+    ///     
+    ///     // Apply breathe effect to all light(s)
+    ///     lifx::Light::breathe_by_selector_effect(key.clone(), format!("all"), breathe);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn breathe_by_selector_effect(config: LifxConfig, selector: String, breathe: BreatheEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).breathe_effect_by_selector(selector, breathe)
     }
 
-}
-
-/// Used to set the params when posting a MoveEffect event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MoveEffect {
-    /// The color to use for the breathe effect.
-    pub direction: Option<String>,
-    /// The time in seconds for one cycle of the effect.
-    pub period: Option<i64>,
-    /// The number of times to repeat the effect.
-    pub cycles: Option<f64>,
-    /// If true, turn the bulb on if it is not already on.
-    pub power_on: Option<bool>,
-    /// Execute the query fast, without initial state checks and wait for no results.
-    pub fast: Option<bool>,
-}
-impl MoveEffect {
-    /// Returns a new MoveEffect object
+    /// Brighten the current light by a number of percentage points, ex: 10 raises the brightness by 0.1.
+    ///
+    /// Sent as a StateDelta, so the resulting brightness is clipped to [0, 1] by the API.
     /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `percent_points` - How many percentage points to brighten by
+    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// fn main() {
@@ -3686,79 +3304,38 @@ impl MoveEffect {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut move_effect = lifx::MoveEffect::new();
-    ///     move_effect.direction = Some(format!("forward")); // or backward
-    ///     move_effect.period = Some(10);
-    ///     move_effect.cycles = Some(0.9);
-    ///     move_effect.power_on = Some(true);
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.brighten_by(config.clone(), 10);
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return MoveEffect{
-            direction: None,
-            period: None,
-            cycles: None,
-            power_on: None,
-            fast: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.direction{
-            Some(direction) => params.push(("direction".to_string(), direction.to_string())),
-            None => {}
-        }
-
-        match &self.period{
-            Some(period) => params.push(("period".to_string(), period.to_string())),
-            None => {}
-        }
-
-        match &self.cycles{
-            Some(cycles) => params.push(("cycles".to_string(), cycles.to_string())),
-            None => {}
-        }
-
-        match &self.power_on{
-            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
-            None => {}
-        }
-
-        match &self.fast{
-            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
-            None => {}
-        }
-
-        return params;
+    #[cfg(feature = "blocking")]
+    pub fn brighten_by(&self, config: LifxConfig, percent_points: i8) -> Result<LiFxResults, LifxError> {
+        let delta = Self::brightness_step(percent_points, false);
+        Self::state_delta_by_selector(config, format!("id:{}", self.id), delta)
     }
 
-}
 
-/// Used to set the params when posting a MorphEffect event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct MorphEffect {
-    /// The time in seconds for one cycle of the effect.
-    pub period: Option<i64>,
-    /// How long the animation lasts for in seconds. Not specifying a duration makes the animation never stop. Specifying 0 makes the animation stop. Note that there is a known bug where the tile remains in the animation once it has completed if duration is nonzero.
-    pub duration: Option<f64>,
-    /// You can control the colors in the animation by specifying a list of color specifiers. For example ["red", "hue:100 saturation:1"]. See https://api.developer.lifx.com/docs/colors
-    pub palette: Option<Vec<String>>,
-    /// If true, turn the bulb on if it is not already on.
-    pub power_on: Option<bool>,
-    /// Execute the query fast, without initial state checks and wait for no results.
-    pub fast: Option<bool>,
-}
-impl MorphEffect {
-    /// Returns a new MorphEffect object
+    /// Captures the current state of every light as a Scene, with one State per light selected by id.
+    ///
+    /// The scene only exists locally: it gets a generated uuid and the current time as its created and
+    /// updated timestamps, and is not saved to the account. See Scene::from_lights.
     /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `name` - The name to give the scene
+    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// fn main() {
@@ -3771,86 +3348,79 @@ impl MorphEffect {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut morph_effect = lifx::MorphEffect::new();
-    ///     morph_effect.period = Some(10);
-    ///     morph_effect.duration = Some(0);
+    ///     let scene = lifx::Light::capture_as_scene(config, "Before the party");
+    ///     println!("{:?}", scene);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn capture_as_scene(config: LifxConfig, name: &str) -> Result<Scene, LifxError> {
+        let lights = Self::list_all(config)?;
+        Ok(Scene::from_lights(name, &lights))
+    }
+
+    /// This endpoint lets you switch a light to clean mode, with a set duration. 
     /// 
-    ///     let mut palette: Vec<String> = Vec::new();
-    ///     palette.push("red");
-    ///     palette.push("green");
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `clean` - A Clean object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
     /// 
-    ///     morph_effect.palette = Some(palette);
-    ///     morph_effect.power_on = Some(true);
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
     /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut clean = lifx::Clean::new();
+    ///             clean.duration = Some(0);
+    ///             clean.stop = Some(false);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.clean(key.clone(), clean.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return MorphEffect{
-            period: None,
-            duration: None,
-            palette: None,
-            power_on: None,
-            fast: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.period{
-            Some(period) => params.push(("period".to_string(), period.to_string())),
-            None => {}
-        }
-
-        match &self.duration{
-            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
-            None => {}
-        }
-
-        match &self.palette{
-            Some(palette) => params.push(("palette".to_string(), string_vec_to_params(palette.to_vec()))),
-            None => {}
-        }
-
-        match &self.power_on{
-            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
-            None => {}
-        }
-
-        match &self.fast{
-            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
-            None => {}
-        }
-
-        return params;
+    #[cfg(feature = "blocking")]
+    pub fn clean(&self, config: LifxConfig, clean: Clean) ->  Result<LiFxResults, LifxError>{
+        self.require(&config, Capability::Hev, "clean")?;
+        return Self::clean_by_selector(config, format!("id:{}", self.id), clean);
     }
 
-}
-
-
-
-/// Used to set the params when posting a PulseEffect event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PulseEffect {
-    /// The color to use for the breathe effect.
-    pub color: Option<String>,
-    /// The color to start the effect from. If this parameter is omitted then the color the bulb is currently set to is used instead.
-    pub from_color: Option<String>,
-    /// The time in seconds for one cycle of the effect.
-    pub period: Option<f64>,
-    /// The number of times to repeat the effect.
-    pub cycles: Option<f64>,
-    /// If false set the light back to its previous value when effect ends, if true leave the last effect color.
-    pub persist: Option<bool>,
-    /// If true, turn the bulb on if it is not already on.
-    pub power_on: Option<bool>,
-}
-impl PulseEffect {
-    /// Returns a new PulseEffect object
+    /// This endpoint lets you switch a selected LIFX object to clean mode, with a set duration. 
     /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `clean` - A Clean object containing the values to set
+    ///
     /// # Examples
     ///
     /// ```
@@ -3866,78 +3436,35 @@ impl PulseEffect {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut pulse = lifx::PulseEffect::new();
-    ///     pulse.color = Some(format!("red"));
-    ///     pulse.from_color = Some(format!("green"));
-    ///     pulse.period = Some(10);
-    ///     pulse.persist = Some(true);
-    ///     pulse.power_on = Some(true);
+    ///     let mut clean = lifx::Clean::new();
+    ///     clean.duration = Some(0);
+    ///     clean.stop = Some(false);
+    ///     
+    ///     // Set all light to clean mode
+    ///     lifx::Light::clean_by_selector(key.clone(), format!("all"), clean);
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return PulseEffect{
-            color: None,
-            from_color: None,
-            period: None,
-            cycles: None,
-            persist: None,
-            power_on: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.color{
-            Some(color) => params.push(("color".to_string(), color.to_string())),
-            None => {}
-        }
-
-        match &self.from_color{
-            Some(from_color) => params.push(("from_color".to_string(), from_color.to_string())),
-            None => {}
-        }
-
-        match &self.period{
-            Some(period) => params.push(("period".to_string(), period.to_string())),
-            None => {}
-        }
-
-        match &self.cycles{
-            Some(cycles) => params.push(("cycles".to_string(), cycles.to_string())),
-            None => {}
-        }
-
-        match &self.persist{
-            Some(persist) => params.push(("persist".to_string(), persist.to_string())),
-            None => {}
-        }
-
-        match &self.power_on{
-            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
-            None => {}
-        }
-
-        return params;
+    #[cfg(feature = "blocking")]
+    pub fn clean_by_selector(config: LifxConfig, selector: String, clean: Clean) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).clean_by_selector(selector, clean)
     }
-
-}
-
-/// Used to set the params when posting a EffectsOff event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct EffectsOff {
-    /// If true, the devices will also be turned off
-    pub power_off: Option<bool>,
-}
-impl EffectsOff {
-    /// Returns a new EffectsOff object
+    /// Dim the current light by a number of percentage points, ex: 10 lowers the brightness by 0.1.
+    ///
+    /// Sent as a StateDelta, so the resulting brightness is clipped to [0, 1] by the API.
     /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `percent_points` - How many percentage points to dim by
+    ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// extern crate lifx_rs as lifx;
     /// 
     /// fn main() {
@@ -3950,49 +3477,95 @@ impl EffectsOff {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut ef = lifx::EffectsOff::new();
-    ///     ef.power_off = Some(true);
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.dim_by(config.clone(), 10);
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return EffectsOff{
-            power_off: None,
-        };
+    #[cfg(feature = "blocking")]
+    pub fn dim_by(&self, config: LifxConfig, percent_points: i8) -> Result<LiFxResults, LifxError> {
+        let delta = Self::brightness_step(percent_points, true);
+        Self::state_delta_by_selector(config, format!("id:{}", self.id), delta)
     }
 
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.power_off{
-            Some(power_off) => params.push(("power_off".to_string(), power_off.to_string())),
-            None => {}
-        }
 
-        return params;
+    /// Runs an effect on the current light from `start` until `end`, then stops it with effects_off.
+    ///
+    /// Both boundaries are wall-clock times read against the system clock once, when scheduling; a
+    /// `start` in the past starts the effect right away and an `end` before `start` stops it as soon
+    /// as it has started. Like schedule_state this runs on a spawned tokio task and only lives as long
+    /// as the process. The handle's result is the effects_off result, or the error from starting the
+    /// effect. Cancelling before `start` means nothing is sent; cancelling during the window leaves the
+    /// effect running.
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `effect` - The AnyEffect to run
+    /// * `start` - When to start the effect
+    /// * `end` - When to stop the effect
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::time::{Duration, SystemTime};
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             // Run the flame effect for the next three hours
+    ///             let start = SystemTime::now();
+    ///             let end = start + Duration::from_secs(3 * 60 * 60);
+    ///             let flame = lifx::AnyEffect::Flame(lifx::FlameEffect::new());
+    ///             let handle = light.effect_window(config.clone(), flame, start, end);
+    ///             println!("{:?}", handle.result().await);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub fn effect_window(&self, config: LifxConfig, effect: AnyEffect, start: SystemTime, end: SystemTime) -> ScheduledHandle {
+        let selector = format!("id:{}", self.id);
+        let run_time = end.duration_since(start).unwrap_or(Duration::ZERO);
+        ScheduledHandle::spawn_at(start, async move {
+            effect.async_apply(config.clone(), selector.clone()).await?;
+            tokio::time::sleep(run_time).await;
+            Self::async_effects_off_by_selector(config, selector, EffectsOff::new()).await
+        })
     }
 
-}
-
-
-
-/// Used to set the params when posting a FlameEffect event
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct FlameEffect {
-    /// The time in seconds for one cycle of the effect.
-    pub period: Option<i64>,
-    /// How long the animation lasts for in seconds. Not specifying a duration makes the animation never stop. Specifying 0 makes the animation stop. Note that there is a known bug where the tile remains in the animation once it has completed if duration is nonzero.
-    pub duration: Option<f64>,
-    /// If true, turn the bulb on if it is not already on.
-    pub power_on: Option<bool>,
-    /// Execute the query fast, without initial state checks and wait for no results.
-    pub fast: Option<bool>,
-}
-impl FlameEffect {
-    /// Returns a new FlameEffect object
+    /// Stops animation(s) for the current light
     /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `flame_effect` - A FlameEffect object containing the values to set
+    ///
     /// # Examples
     ///
     /// ```
@@ -4008,87 +3581,2926 @@ impl FlameEffect {
     ///
     ///     let config = lifx::LifxConfig{
     ///        access_token: key.clone(),
-    ///        api_endpoints: api_endpoints
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
     ///     };
     /// 
-    ///     let mut flame_effect = lifx::FlameEffect::new();
-    ///     flame_effect.period = Some(10);
-    ///     flame_effect.duration = Some(0);
-    ///     flame_effect.power_on = Some(true);
-    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut effects_off = lifx::EffectsOff::new();
+    ///             effects_off.power_off = Some(true);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.effects_off(key.clone(), effects_off.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
     /// }
     ///  ```
-    pub fn new() -> Self {
-        return FlameEffect{
-            period: None,
-            duration: None,
-            power_on: None,
-            fast: None
-        };
-    }
-
-    fn to_params(&self) -> Vec<(String, String)> {
-        let mut params: Vec<(String, String)> = vec![];
-        match &self.period{
-            Some(period) => params.push(("period".to_string(), period.to_string())),
-            None => {}
-        }
-
-        match &self.duration{
-            Some(duration) => params.push(("duration".to_string(), duration.to_string())),
-            None => {}
-        }
-
-        match &self.power_on{
-            Some(power_on) => params.push(("power_on".to_string(), power_on.to_string())),
-            None => {}
-        }
-
-        match &self.fast{
-            Some(fast) => params.push(("fast".to_string(), fast.to_string())),
-            None => {}
-        }
-
-        return params;
+    #[cfg(feature = "blocking")]
+    pub fn effects_off(&self, config: LifxConfig, effects_off: EffectsOff) ->  Result<LiFxResults, LifxError>{
+        return Self::effects_off_by_selector(config, format!("id:{}", self.id), effects_off);
     }
 
-}
-
-pub fn string_vec_to_params(input: Vec<String>) -> String {
-
-    let mut params = String::new();
-    let count = 0;
-    for iput in input {
-        if count == 0 {
-            params = format!("[\"{}\"", iput);
-        } else {
-            params = format!("{}, \"{}\"",params, iput);
-        }
+    /// Stops animation(s) for the selected light(s)
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `effects_off` - A EffectsOff object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut effects_off = lifx::EffectsOff::new();
+    ///     effects_off.power_off = Some(true);
+    ///     
+    ///     // Send morph effect to all lights
+    ///     lifx::Light::effects_off_by_selector(key.clone(), format!("all"), effects_off);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn effects_off_by_selector(config: LifxConfig, selector: String, effects_off: EffectsOff) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).effects_off_by_selector(selector, effects_off)
+    }
+
+    /// Activate the flame animation for the current light
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `flame_effect` - A FlameEffect object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut flame_effect = lifx::FlameEffect::new();
+    ///             flame_effect.period = Some(10);
+    ///             flame_effect.duration = Some(0);
+    ///             flame_effect.power_on = Some(true);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.flame_effect(key.clone(), flame_effect.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn flame_effect(&self, config: LifxConfig, flame_effect: FlameEffect) ->  Result<LiFxResults, LifxError>{
+        self.require(&config, Capability::Matrix, "the flame effect")?;
+        return Self::flame_effect_by_selector(config, format!("id:{}", self.id), flame_effect);
+    }
+
+    /// Activate the flame animation for the selected light(s)
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `flame_effect` - A FlameEffect object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut flame_effect = lifx::FlameEffect::new();
+    ///     flame_effect.period = Some(10);
+    ///     flame_effect.duration = Some(0);
+    ///     flame_effect.power_on = Some(true);
+    ///     
+    ///     // Send morph effect to all lights
+    ///     lifx::Light::flame_effect_by_selector(key.clone(), format!("all"), flame_effect);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn flame_effect_by_selector(config: LifxConfig, selector: String, flame_effect: FlameEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).flame_effect_by_selector(selector, flame_effect)
+    }
+
+    /// Flashes the current light to a temporary state, holds it, then restores the power, color and
+    /// brightness the light had before the flash.
+    ///
+    /// The light is re-read before flashing so the restored snapshot is current. The restore is held by a
+    /// guard, so it is also sent if the flash request fails or the thread panics during the hold.
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `flash` - A State object containing the temporary values to set
+    /// * `hold` - How long to hold the flash state before restoring
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::time::Duration;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut flash = lifx::State::new();
+    ///     flash.power = Some(format!("on"));
+    ///     flash.color = Some(format!("red"));
+    ///     flash.brightness = Some(1.0);
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.flash_and_restore(config.clone(), flash.clone(), Duration::from_secs(2));
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn flash_and_restore(&self, config: LifxConfig, flash: State, hold: Duration) -> Result<LiFxResults, LifxError> {
+        let selector = format!("id:{}", self.id);
+        let current = Self::list_by_selector(config.clone(), selector.clone())?;
+        let snapshot = current.first().unwrap_or(self).snapshot_state();
+
+        let guard = RestoreGuard {
+            config: config.clone(),
+            selector: selector.clone(),
+            state: Some(snapshot),
+        };
+        Self::set_state_by_selector(config, selector, flash)?;
+        std::thread::sleep(hold);
+        guard.restore()
+    }
+
+    /// Gets ALL lights belonging to the authenticated account
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config)?;
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn list_all(config: LifxConfig) -> Result<Lights, LifxError> {
+        return Self::list_by_selector(config, format!("all"));
+    }
+
+    /// Gets lights belonging to the authenticated account. Filtering the lights using selectors. Properties such as id, label, group and location can be used in selectors.
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_by_selector(key, format!("all"))?;
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn list_by_selector(config: LifxConfig, selector: String) -> Result<Lights, LifxError> {
+        LifxClient::new(config).list_by_selector(selector)
+    }
+
+    /// Gets all lights belonging to the authenticated account that report power "off", including disconnected ones.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig with the access token and endpoints to use
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_off(config.clone()) {
+    ///         for light in lights {
+    ///             println!("{}", light.label);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn list_off(config: LifxConfig) -> Result<Lights, LifxError> {
+        Self::list_all(config).map(Self::powered_off)
+    }
+
+    /// Gets all lights belonging to the authenticated account that are on and connected. Disconnected lights are left out even if they report "on",
+    /// since their last reported power may be stale.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig with the access token and endpoints to use
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_on(config.clone()) {
+    ///         for light in lights {
+    ///             println!("{}", light.label);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn list_on(config: LifxConfig) -> Result<Lights, LifxError> {
+        Self::list_all(config).map(Self::powered_on)
+    }
+
+    /// Activate the morph animation for the current light
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `morph_effect` - A MorphEffect object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut morph_effect = lifx::MorphEffect::new();
+    ///             morph_effect.period = Some(10.0);
+    ///             morph_effect.duration = Some(0);
+    /// 
+    ///             let mut palette: Vec<String> = Vec::new();
+    ///             palette.push(format!("red"));
+    ///             palette.push(format!("green"));
+    /// 
+    ///             morph_effect.palette = Some(palette);
+    ///             morph_effect.power_on = Some(true);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.morph_effect(key.clone(), morph_effect.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn morph_effect(&self, config: LifxConfig, morph_effect: MorphEffect) ->  Result<LiFxResults, LifxError>{
+        if !config.skip_capability_checks {
+            morph_effect.validate_for(self)?;
+        }
+        return Self::morph_effect_by_selector(config, format!("id:{}", self.id), morph_effect);
+    }
+
+    /// Activate the morph animation for the selected light(s)
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `clean` - A Clean object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut morph_effect = lifx::MorphEffect::new();
+    ///     morph_effect.period = Some(10.0);
+    ///     morph_effect.duration = Some(0);
+    /// 
+    ///     let mut palette: Vec<String> = Vec::new();
+    ///     palette.push(format!("red"));
+    ///     palette.push(format!("green"));
+    /// 
+    ///     morph_effect.palette = Some(palette);
+    ///     morph_effect.power_on = Some(true);
+    ///     
+    ///     // Send morph effect to all lights
+    ///     lifx::Light::morph_effect_by_selector(key.clone(), format!("all"), morph_effect);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn morph_effect_by_selector(config: LifxConfig, selector: String, morph_effect: MorphEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).morph_effect_by_selector(selector, morph_effect)
+    }
+
+    /// Activate the move animation for the current light
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `move_effect` - A MoveEffect object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut move_effect = lifx::MoveEffect::new();
+    ///             move_effect.direction = Some(format!("forward")); // or backward
+    ///             move_effect.period = Some(10.0);
+    ///             move_effect.cycles = Some(0.9);
+    ///             move_effect.power_on = Some(true);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.move_effect(key.clone(), move_effect.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn move_effect(&self, config: LifxConfig, move_effect: MoveEffect) ->  Result<LiFxResults, LifxError>{
+        self.require(&config, Capability::Multizone, "the move effect")?;
+        return Self::move_effect_by_selector(config, format!("id:{}", self.id), move_effect);
+    }
+
+    /// Activate the move animation for the selected light(s)
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `clean` - A Clean object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut move_effect = lifx::MoveEffect::new();
+    ///     move_effect.direction = Some(format!("forward")); // or backward
+    ///     move_effect.period = Some(10.0);
+    ///     move_effect.cycles = Some(0.9);
+    ///     move_effect.power_on = Some(true);
+    ///     
+    ///     // Toggle all lights
+    ///     lifx::Light::move_effect_by_selector(key.clone(), format!("all"), move_effect);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn move_effect_by_selector(config: LifxConfig, selector: String, move_effect: MoveEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).move_effect_by_selector(selector, move_effect)
+    }
+
+    /// Activate the pulse animation for the current light
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `pulse_effect` - A PulseEffect object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut pulse = lifx::PulseEffect::new();
+    ///             pulse.color = Some(format!("red"));
+    ///             pulse.from_color = Some(format!("green"));
+    ///             pulse.period = Some(10);
+    ///             pulse.persist = Some(true);
+    ///             pulse.power_on = Some(true);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.pulse_effect(key.clone(), pulse.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn pulse_effect(&self, config: LifxConfig, pulse_effect: PulseEffect) ->  Result<LiFxResults, LifxError>{
+        return Self::pulse_effect_by_selector(config, format!("id:{}", self.id), pulse_effect);
+    }
+
+    /// Activate the pulse animation for the selected light(s)
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `pulse_effect` - A PulseEffect object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut pulse = lifx::PulseEffect::new();
+    ///     pulse.color = Some(format!("red"));
+    ///     pulse.from_color = Some(format!("green"));
+    ///     pulse.period = Some(10);
+    ///     pulse.persist = Some(true);
+    ///     pulse.power_on = Some(true);
+    ///     
+    ///     // Toggle all lights
+    ///     lifx::Light::pulse_effect_by_selector(key.clone(), format!("all"), pulse);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn pulse_effect_by_selector(config: LifxConfig, selector: String, pulse_effect: PulseEffect) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).pulse_effect_by_selector(selector, pulse_effect)
+    }
+
+    /// Resolves a selector to the lights it currently matches, without changing them.
+    /// Useful for previewing which lights a destructive operation (e.g. "all off") will affect.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `selector` - A typed LIFX Selector ex: Selector::All, Selector::GroupId(xxx)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let selector = lifx::Selector::Group(format!("Kitchen"));
+    ///     let affected = lifx::Light::resolve_selector(config, &selector);
+    ///     println!("{:?}", affected);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn resolve_selector(config: LifxConfig, selector: &Selector) -> Result<Lights, LifxError> {
+        Self::list_by_selector(config, selector.to_string())
+    }
+
+    /// Schedules a state change for the current light at a wall-clock time, returning a handle that
+    /// can cancel it before it fires.
+    ///
+    /// The change runs on a spawned tokio task, so this must be called from within a tokio runtime.
+    /// The schedule only lives as long as the process: nothing is persisted, and a pending change is
+    /// lost if the program exits. `at` is read against the system clock once, when scheduling; times
+    /// in the past fire right away, and later clock adjustments don't move the schedule.
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `state` - A State object containing the values to set
+    /// * `at` - When to apply the state
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::time::{Duration, SystemTime};
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut on_state = lifx::State::new();
+    ///     on_state.power = Some(format!("on"));
+    /// 
+    ///     if let Ok(lights) = lifx::Light::async_list_all(config.clone()).await {
+    ///         for light in lights {
+    ///             // Turn the light on in an hour
+    ///             let at = SystemTime::now() + Duration::from_secs(60 * 60);
+    ///             let handle = light.schedule_state(config.clone(), on_state.clone(), at);
+    ///             println!("{:?}", handle.result().await);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub fn schedule_state(&self, config: LifxConfig, state: State, at: SystemTime) -> ScheduledHandle {
+        let selector = format!("id:{}", self.id);
+        ScheduledHandle::spawn_at(at, async move {
+            Self::async_set_state_by_selector(config, selector, state).await
+        })
+    }
+
+    /// Sets the state for the current light
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `state` - A State object containing the values of the State to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut state = lifx::State::new();
+    ///             state.power = Some(format!("on"));
+    ///             state.brightness = Some(1.0);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.set_state(key.clone(), state.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn set_state(&self, config: LifxConfig, state: State) ->  Result<LiFxResults, LifxError>{
+        return Self::set_state_by_selector(config, format!("id:{}", self.id), state);
+    }
+
+    /// Turns the light on, keeping its color and brightness
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.turn_on(config.clone());
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn turn_on(&self, config: LifxConfig) ->  Result<LiFxResults, LifxError>{
+        self.set_state(config, State::builder().power_on().build())
+    }
+
+    /// Turns the light off
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.turn_off(config.clone());
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn turn_off(&self, config: LifxConfig) ->  Result<LiFxResults, LifxError>{
+        self.set_state(config, State::builder().power_off().build())
+    }
+
+    /// Sets the brightness of the light, keeping its power and color
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
+    /// * `brightness` - The brightness level from 0.0 to 1.0.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.set_brightness(config.clone(), 0.5);
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn set_brightness(&self, config: LifxConfig, brightness: f64) ->  Result<LiFxResults, LifxError>{
+        self.set_state(config, State::builder().brightness(brightness).build())
+    }
+
+    /// Sets the color of the light, keeping its power and brightness
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `config` - A LifxConfig object containing your access token and api endpoints.
+    /// * `color` - The color to set, ex: "red", a Color, a NamedColor or a Kelvin preset.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         for light in lights {
+    ///             let results = light.set_color(config.clone(), lifx::NamedColor::Blue);
+    ///             println!("{:?}",results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn set_color(&self, config: LifxConfig, color: impl Into<String>) ->  Result<LiFxResults, LifxError>{
+        self.set_state(config, State::builder().color(color).build())
+    }
+
+    /// Sets the state for the selected LIFX object
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `state` - A State object containing the values of the State to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut off_state = lifx::State::new();
+    ///     off_state.power = Some(format!("off"));
+    ///     
+    ///     // Turn off all lights
+    ///     lifx::Light::set_state_by_selector(key.clone(), format!("all"), off_state);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn set_state_by_selector(config: LifxConfig, selector: String, state: State) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).set_state_by_selector(selector, state)
+    }
+
+    /// Sets the state for the selected LIFX object
+    ///
+    /// The cloud API takes at most 50 states a request, so more are sent as one request per 50,
+    /// one after the other, and their results returned together. The first error stops the rest,
+    /// leaving the chunks before it applied.
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `states` - A vector of States with defaults
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut set_states = lifx::States::new();
+    ///     let mut states: Vec<lifx::State> = Vec::new();
+    ///     let mut defaults = lifx::State::new();
+    ///     
+    ///     defaults.brightness = Some(1.0);
+    ///     
+    ///     let mut state_1 = lifx::State::new();
+    ///     state_1.selector = Some(format!("id:xxx"));
+    ///     state_1.power = Some(format!("on"));
+    ///     
+    ///     let mut state_2 = lifx::State::new();
+    ///     state_2.selector = Some(format!("id:xyz"));
+    ///     state_2.power = Some(format!("on"));
+    ///     
+    ///     set_states.states = Some(states);
+    ///     set_states.defaults = Some(defaults);
+    ///     
+    ///     lifx::Light::set_states(key.clone(), set_states);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn set_states(config: LifxConfig, states: States) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).set_states(states)
+    }
+
+    /// Gives each light a random hue at the given saturation and brightness, sent as one set_states call.
+    ///
+    /// Hues come from a small built-in PRNG, so no rand dependency is needed. Pass a `seed` to get the
+    /// same hues every time (ex: in tests); with None a fresh seed is drawn for each call. The API
+    /// accepts at most 50 states per set_states call, so shuffle larger sets in chunks.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `lights` - The lights to recolor
+    /// * `saturation` - The saturation for every light, 0.0 to 1.0
+    /// * `brightness` - The brightness for every light, 0.0 to 1.0
+    /// * `duration` - How long in seconds the color change takes
+    /// * `seed` - Seed for the hue sequence, or None for a random one
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+    ///         let results = lifx::Light::shuffle_colors(config.clone(), &lights, 1.0, 0.8, 0.5, None);
+    ///         println!("{:?}", results);
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn shuffle_colors(config: LifxConfig, lights: &[Light], saturation: f64, brightness: f64, duration: f64, seed: Option<u64>) -> Result<LiFxResults, LifxError> {
+        let mut states = States::new();
+        states.states = Some(Self::shuffled_states(lights, saturation, brightness, duration, seed));
+        Self::set_states(config, states)
+    }
+
+    /// Set parameters other than power and duration change the state of the lights by the amount specified.
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `delta` - A StateDelta object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut delta = lifx::StateDelta::new();
+    ///     delta.duration = Some(0);
+    ///     delta.power = Some(format!("on"));
+    ///     
+    ///     // Send StateDelta
+    ///     lifx::Light::state_delta_by_selector(key.clone(), format!("all"), toggle);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn state_delta_by_selector(config: LifxConfig, selector: String, delta: StateDelta) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).state_delta_by_selector(selector, delta)
+    }
+
+
+    /// Turn off light if on, or turn them on if it is off. 
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Light object.
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `clean` - A Clean object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let all_lights = lifx::Light::list_all(config.clone());
+    ///     match all_lights {
+    ///         Ok(lights) => {
+    ///             println!("{:?}",lights.clone());
+    ///     
+    ///             let mut toggle = lifx::Toggle::new();
+    ///             toggle.duration = Some(0.5);
+    ///         
+    ///             for light in lights {
+    ///                 let results = light.toggle(key.clone(), clean.clone());
+    ///                 println!("{:?}",results);
+    ///             }
+    ///         },
+    ///         Err(e) => println!("{}",e)
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn toggle(&self, config: LifxConfig, toggle: Toggle) ->  Result<LiFxResults, LifxError>{
+        return Self::toggle_by_selector(config, format!("id:{}", self.id), toggle);
+    }
+
+    /// Turn off lights if any of them are on, or turn them on if they are all off. 
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    /// * `selector` - An LIFX selector ex: all, id:xxx, group_id:xxx
+    /// * `clean` - A Clean object containing the values to set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut toggle = lifx::Toggle::new();
+    ///     toggle.duration = Some(0.5);
+    ///     
+    ///     // Toggle all lights
+    ///     lifx::Light::toggle_by_selector(key.clone(), format!("all"), toggle);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn toggle_by_selector(config: LifxConfig, selector: String, toggle: Toggle) ->  Result<LiFxResults, LifxError>{
+        LifxClient::new(config).toggle_by_selector(selector, toggle)
+    }
+
+    /// Waits for a light to show up in list_all as connected, ex: while onboarding a new bulb.
+    ///
+    /// Polls list_all every `poll_interval` until the light with the given id is listed with
+    /// connected == true, and returns it. Returns LifxError::NotFound once `timeout` has passed
+    /// without that happening, or the first error listing the lights.
+    /// Every poll is one API request, so keep the interval in line with the rate limit.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `id` - The id (serial number) of the light to wait for
+    /// * `timeout` - How long to wait before giving up
+    /// * `poll_interval` - How long to wait between polls
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::time::Duration;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let light = lifx::Light::wait_until_present(config, "d073d5000000", Duration::from_secs(120), Duration::from_secs(5));
+    ///     println!("{:?}", light);
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn wait_until_present(config: LifxConfig, id: &str, timeout: Duration, poll_interval: Duration) -> Result<Light, LifxError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let lights = Self::list_all(config.clone())?;
+            if let Some(light) = Self::find_present(&lights, id) {
+                return Ok(light);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Self::not_present(id, timeout));
+            }
+            std::thread::sleep(poll_interval.min(remaining));
+        }
+    }
+
+    /// Returns the light with the given id if it is listed and connected
+    fn find_present(lights: &[Light], id: &str) -> Option<Light> {
+        lights.iter().find(|light| light.id == id && light.connected).cloned()
+    }
+
+    /// The state apply_verified checks for, with the config's default duration filled in, and
+    /// how long its fade lasts
+    fn verify_target(config: &LifxConfig, state: &State) -> (State, Duration) {
+        let target = state.clone().with_default_duration(config.default_duration);
+        let fade = target.duration.and_then(|duration| Duration::try_from_secs_f64(duration).ok()).unwrap_or_default();
+        (target, fade)
+    }
+
+    /// The error apply_verified returns when the light doesn't report the state in time
+    fn not_verified(&self, timeout: Duration) -> LifxError {
+        LifxError::VerificationFailed(format!("light {} didn't report the state within {:?} of it being set", self.id, timeout))
+    }
+
+    /// The error wait_until_present returns when the light doesn't show up in time
+    fn not_present(id: &str, timeout: Duration) -> LifxError {
+        LifxError::NotFound(format!("light {} wasn't listed as connected within {:?}", id, timeout))
+    }
+}
+
+pub type Scenes = Vec<Scene>;
+
+/// Represents an LIFX Scene
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scene {
+    pub uuid: String,
+    pub name: String,
+    pub account: Account,
+    pub states: Vec<State>,
+    #[serde(rename = "created_at")]
+    pub created_at: i64,
+    #[serde(rename = "updated_at")]
+    pub updated_at: i64,
+    pub error: Option<String>,
+    pub errors: Option<Vec<Error>>,
+    /// Fields this crate doesn't model yet, kept as the API sent them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+impl Scene {
+    /// Builds a Scene from the current state of `lights`: one State per light, selected by id, holding
+    /// its power, color and brightness. The scene gets a generated uuid and the current time as its
+    /// created and updated timestamps.
+    /// 
+    /// # Arguments
+    ///
+    /// * `name` - The name to give the scene
+    /// * `lights` - The lights to capture
+    pub fn from_lights(name: &str, lights: &[Light]) -> Scene {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        let states = lights.iter()
+            .map(|light| {
+                let mut state = light.snapshot_state();
+                state.selector = Some(format!("id:{}", light.id));
+                state
+            })
+            .collect();
+
+        Scene {
+            uuid: generate_uuid(),
+            name: name.to_string(),
+            states,
+            created_at: now,
+            updated_at: now,
+            ..Default::default()
+        }
+    }
+
+    /// Returns created_at, in seconds since the unix epoch, as a time
+    pub fn created_time(&self) -> SystemTime {
+        unix_time(self.created_at)
+    }
+
+    /// Returns updated_at, in seconds since the unix epoch, as a time
+    pub fn updated_time(&self) -> SystemTime {
+        unix_time(self.updated_at)
+    }
+}
+
+/// Returns the time `seconds` after the unix epoch, or before it if negative
+fn unix_time(seconds: i64) -> SystemTime {
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+    if seconds < 0 { UNIX_EPOCH - offset } else { UNIX_EPOCH + offset }
+}
+
+/// Like unix_time, but returns None rather than panicking if the time can't be represented
+fn checked_unix_time(seconds: i64) -> Option<SystemTime> {
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+    if seconds < 0 { UNIX_EPOCH.checked_sub(offset) } else { UNIX_EPOCH.checked_add(offset) }
+}
+
+/// Parses an RFC 3339 timestamp, ex: "2015-12-03T05:53:18Z" or "2015-12-03T15:53:18.250+10:00".
+/// Returns None for anything malformed or out of range, rather than panicking.
+fn parse_rfc3339(timestamp: &str) -> Option<SystemTime> {
+    let number = |digits: &str| -> Option<i64> {
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    };
+    let (date, time) = timestamp.split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-');
+    let (year, month, day) = (number(date_parts.next()?)?, number(date_parts.next()?)?, number(date_parts.next()?)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Split off the zone: Z, or an offset like +10:00
+    let (time, offset) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else {
+        let sign_at = time.rfind(['+', '-'])?;
+        let (hours, minutes) = time[sign_at + 1..].split_once(':')?;
+        let (hours, minutes) = (number(hours)?, number(minutes)?);
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        let offset = hours * 3600 + minutes * 60;
+        (&time[..sign_at], if &time[sign_at..sign_at + 1] == "-" { -offset } else { offset })
+    };
+    let (time, nanos) = match time.split_once('.') {
+        Some((time, fraction)) => {
+            // Checked before slicing, so the slice can't split a multi-byte character
+            if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let digits = &fraction[..fraction.len().min(9)];
+            (time, number(digits)? * 10i64.pow(9 - digits.len() as u32))
+        }
+        None => (time, 0),
+    };
+    let mut time_parts = time.splitn(3, ':');
+    let (hour, minute, second) = (number(time_parts.next()?)?, number(time_parts.next()?)?, number(time_parts.next()?)?);
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days since the epoch from the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y.rem_euclid(400);
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)?;
+
+    let seconds = days.checked_mul(86_400)?.checked_add(hour * 3600 + minute * 60 + second - offset)?;
+    checked_unix_time(seconds)?.checked_add(Duration::from_nanos(nanos as u64))
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl Scene {
+    /// Asynchronously activates the scene, see activate
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Scene object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `activation` - A SceneActivation with the duration, ignored properties and overrides to use
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(scenes) = lifx::Scene::async_list(config.clone()).await {
+    ///         for scene in scenes {
+    ///             let results = scene.async_activate(config.clone(), lifx::SceneActivation::builder().duration(2.0).build()).await;
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_activate(&self, config: LifxConfig, activation: SceneActivation) -> Result<LiFxResults, LifxError> {
+        Self::async_activate_by_uuid(config, self.uuid.clone(), activation).await
+    }
+
+    /// Asynchronously activates the scene with the given uuid, see activate
+    #[cfg(feature = "async")]
+    pub async fn async_activate_by_uuid(config: LifxConfig, uuid: String, activation: SceneActivation) -> Result<LiFxResults, LifxError> {
+        LifxClient::new(config).async_activate_scene(uuid, activation).await
+    }
+
+    /// Asynchronously applies the scene only to lights that are currently on, leaving lights that are off untouched.
+    ///
+    /// Each scene state's selector is resolved to its lights, lights that are off are dropped, and the
+    /// remaining lights are set in one set_states call. If no lights are on nothing is sent and an empty
+    /// result is returned.
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Scene object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(scenes) = lifx::Scene::async_list(config.clone()).await {
+    ///         for scene in scenes {
+    ///             let results = scene.async_activate_on_active_only(config.clone()).await;
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_activate_on_active_only(&self, config: LifxConfig) -> Result<LiFxResults, LifxError> {
+        let mut resolved: Vec<Lights> = Vec::new();
+        for state in &self.states {
+            let lights = match &state.selector {
+                Some(selector) => Light::async_list_by_selector(config.clone(), selector.clone()).await?,
+                None => Vec::new(),
+            };
+            resolved.push(lights);
+        }
+
+        let states = Self::active_only_states(&self.states, &resolved);
+        if states.is_empty() {
+            return Ok(LiFxResults { results: Some(Vec::new()), ..Default::default() });
+        }
+
+        let mut request = States::new();
+        request.states = Some(states);
+        Light::async_set_states(config, request).await
+    }
+
+    /// Asynchronously gets ALL scenes belonging to the authenticated account
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let scenes = lifx::Scene::async_list(config).await?;
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_list(config: LifxConfig) -> Result<Scenes, LifxError> {
+        LifxClient::new(config).async_list_scenes().await
+    }
+
+    /// Activates the scene, applying its saved states to its lights. The SceneActivation can set a
+    /// transition duration, leave parts of the states alone, ex: power, or apply a State over every
+    /// state in the scene.
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Scene object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `activation` - A SceneActivation with the duration, ignored properties and overrides to use
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     // Restore the colors of "Movie Night" at half brightness, without turning any lights on
+    ///     let activation = lifx::SceneActivation::builder()
+    ///         .ignore(vec![lifx::SceneIgnore::Power])
+    ///         .overrides(lifx::State::builder().brightness(0.5).build())
+    ///         .build();
+    /// 
+    ///     if let Ok(scenes) = lifx::Scene::list(config.clone()) {
+    ///         for scene in scenes.iter().filter(|scene| scene.name == "Movie Night") {
+    ///             let results = scene.activate(config.clone(), activation.clone());
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn activate(&self, config: LifxConfig, activation: SceneActivation) -> Result<LiFxResults, LifxError> {
+        Self::activate_by_uuid(config, self.uuid.clone(), activation)
+    }
+
+    /// Activates the scene with the given uuid, see activate
+    #[cfg(feature = "blocking")]
+    pub fn activate_by_uuid(config: LifxConfig, uuid: String, activation: SceneActivation) -> Result<LiFxResults, LifxError> {
+        LifxClient::new(config).activate_scene(uuid, activation)
+    }
+
+    /// Applies the scene only to lights that are currently on, leaving lights that are off untouched.
+    ///
+    /// Each scene state's selector is resolved to its lights, lights that are off are dropped, and the
+    /// remaining lights are set in one set_states call. If no lights are on nothing is sent and an empty
+    /// result is returned.
+    /// 
+    /// # Arguments
+    ///
+    /// * `self` - A Scene object.
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(scenes) = lifx::Scene::list(config.clone()) {
+    ///         for scene in scenes {
+    ///             let results = scene.activate_on_active_only(config.clone());
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn activate_on_active_only(&self, config: LifxConfig) -> Result<LiFxResults, LifxError> {
+        let mut resolved: Vec<Lights> = Vec::new();
+        for state in &self.states {
+            let lights = match &state.selector {
+                Some(selector) => Light::list_by_selector(config.clone(), selector.clone())?,
+                None => Vec::new(),
+            };
+            resolved.push(lights);
+        }
+
+        let states = Self::active_only_states(&self.states, &resolved);
+        if states.is_empty() {
+            return Ok(LiFxResults { results: Some(Vec::new()), ..Default::default() });
+        }
+
+        let mut request = States::new();
+        request.states = Some(states);
+        Light::set_states(config, request)
+    }
+
+    /// Gets ALL scenes belonging to the authenticated account
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let scenes = lifx::Scene::list_all(config)?;
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn list(config: LifxConfig) -> Result<Scenes, LifxError> {
+        LifxClient::new(config).list_scenes()
+    }
+
+    /// Asynchronously lists the account's scenes from the scene cache, see list_cached
+    #[cfg(feature = "async")]
+    pub async fn async_list_cached(config: LifxConfig) -> Result<Scenes, LifxError> {
+        if let Some(scenes) = Self::cached_scenes(&config)? {
+            return Ok(scenes);
+        }
+        let scenes = Self::async_list(config.clone()).await?;
+        Self::cache_scenes(&config, &scenes);
+        Ok(scenes)
+    }
+
+    /// Asynchronously finds a scene by its exact name, see find_by_name
+    #[cfg(feature = "async")]
+    pub async fn async_find_by_name(config: LifxConfig, name: &str) -> Result<Option<Scene>, LifxError> {
+        Ok(Self::async_list_cached(config).await?.into_iter().find(|scene| scene.name == name))
+    }
+
+    /// Lists the account's scenes, serving repeat calls from a process-wide cache keyed by access
+    /// token. A cached list is reused for the config's scene_cache_ttl, so scenes added or changed
+    /// in the app in the meantime show up late; clear_scene_cache forces a fresh list.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     // Only the first call goes to the API
+    ///     let first = lifx::Scene::list_cached(config.clone());
+    ///     let second = lifx::Scene::list_cached(config.clone());
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn list_cached(config: LifxConfig) -> Result<Scenes, LifxError> {
+        if let Some(scenes) = Self::cached_scenes(&config)? {
+            return Ok(scenes);
+        }
+        let scenes = Self::list(config.clone())?;
+        Self::cache_scenes(&config, &scenes);
+        Ok(scenes)
+    }
+
+    /// Finds a scene by its exact name, ex: "Movie Night", from the scene cache, see list_cached.
+    /// Returns None if the account has no scene with that name.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `name` - The name of the scene, as shown in the LIFX app
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(Some(scene)) = lifx::Scene::find_by_name(config.clone(), "Movie Night") {
+    ///         let results = scene.activate(config.clone(), lifx::SceneActivation::new());
+    ///         println!("{:?}", results);
+    ///     }
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn find_by_name(config: LifxConfig, name: &str) -> Result<Option<Scene>, LifxError> {
+        Ok(Self::list_cached(config)?.into_iter().find(|scene| scene.name == name))
+    }
+
+    /// Empties the cache used by list_cached and find_by_name, for every account
+    pub fn clear_scene_cache() {
+        scene_cache().lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Returns LifxError::Config if the config's scene_cache_ttl is negative or not finite
+    fn cached_scenes(config: &LifxConfig) -> Result<Option<Scenes>, LifxError> {
+        let ttl = match config.scene_cache_ttl {
+            Some(ttl) => Duration::try_from_secs_f64(ttl)
+                .map_err(|_| LifxError::Config(format!("scene_cache_ttl must be a non-negative number of seconds, got {}", ttl)))?,
+            None => SCENE_CACHE_TTL,
+        };
+        let cache = scene_cache().lock().unwrap_or_else(|e| e.into_inner());
+        Ok(match cache.get(&config.access_token) {
+            Some((listed, scenes)) if listed.elapsed() < ttl => Some(scenes.clone()),
+            _ => None,
+        })
+    }
+
+    fn cache_scenes(config: &LifxConfig, scenes: &Scenes) {
+        scene_cache().lock().unwrap_or_else(|e| e.into_inner()).insert(config.access_token.clone(), (Instant::now(), scenes.clone()));
+    }
+    /// Expands each scene state into one state per resolved light that is on, selected by id.
+    /// `resolved[i]` holds the lights matched by `states[i]`'s selector.
+    fn active_only_states(states: &[State], resolved: &[Lights]) -> Vec<State> {
+        let mut active = Vec::new();
+        for (state, lights) in states.iter().zip(resolved) {
+            for light in lights.iter().filter(|light| light.power == "on") {
+                let mut light_state = state.clone();
+                light_state.selector = Some(format!("id:{}", light.id));
+                active.push(light_state);
+            }
+        }
+        active
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl Color {
+    /// Asynchronously validates a color
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let scenes = lifx::Color::async_validate(key, format!("red")).await?;
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_validate(config: LifxConfig, color: String) -> Result<Color, LifxError> {
+        LifxClient::new(config).async_validate_color(color).await
+    }
+
+    /// Asynchronously validates a color, serving repeat lookups of the same string from a process-wide cache.
+    ///
+    /// The cache is kept apart per access token and api_endpoints, so lookups against one
+    /// account or server never answer for another. To keep a cache with a client instead, see
+    /// LifxClient::validate_cached.
+    ///
+    /// The cache assumes a color string always resolves to the same Color, which holds for the
+    /// named colors and hue/saturation/kelvin strings the API accepts. Only clean results (no
+    /// error or errors) are cached, so a failed lookup is retried on the next call.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `color` - The color string to validate, ex: "warm white"
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     // Only the first call goes to the API
+    ///     let first = lifx::Color::async_validate_cached(config.clone(), format!("warm white")).await;
+    ///     let second = lifx::Color::async_validate_cached(config.clone(), format!("warm white")).await;
+    /// }
+    ///  ```
+    #[cfg(feature = "async")]
+    pub async fn async_validate_cached(config: LifxConfig, color: String) -> Result<Color, LifxError> {
+        if let Some(cached) = Self::cached_validation(&config, &color) {
+            return Ok(cached);
+        }
+        let validated = Self::async_validate(config.clone(), color.clone()).await?;
+        Self::cache_validation(&config, color, &validated);
+        Ok(validated)
+    }
+
+    /// Validates a color
+    /// 
+    /// # Arguments
+    ///
+    /// * `access_token` - A personal acces token for authentication with LIFX.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let scenes = lifx::Color::validate(config)?;
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn validate(config: LifxConfig, color: String) -> Result<Color, LifxError> {
+        LifxClient::new(config).validate_color(color)
+    }
+
+    /// Validates a color, serving repeat lookups of the same string from a process-wide cache.
+    ///
+    /// The cache is kept apart per access token and api_endpoints, so lookups against one
+    /// account or server never answer for another. To keep a cache with a client instead, see
+    /// LifxClient::validate_cached.
+    ///
+    /// The cache assumes a color string always resolves to the same Color, which holds for the
+    /// named colors and hue/saturation/kelvin strings the API accepts. Only clean results (no
+    /// error or errors) are cached, so a failed lookup is retried on the next call.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `color` - The color string to validate, ex: "warm white"
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     // Only the first call goes to the API
+    ///     let first = lifx::Color::validate_cached(config.clone(), format!("warm white"));
+    ///     let second = lifx::Color::validate_cached(config.clone(), format!("warm white"));
+    /// }
+    ///  ```
+    #[cfg(feature = "blocking")]
+    pub fn validate_cached(config: LifxConfig, color: String) -> Result<Color, LifxError> {
+        if let Some(cached) = Self::cached_validation(&config, &color) {
+            return Ok(cached);
+        }
+        let validated = Self::validate(config.clone(), color.clone())?;
+        Self::cache_validation(&config, color, &validated);
+        Ok(validated)
+    }
+
+    /// Empties the cache used by validate_cached and async_validate_cached, for every access
+    /// token
+    pub fn clear_validation_cache() {
+        validation_cache().lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    fn cached_validation(config: &LifxConfig, color: &str) -> Option<Color> {
+        validation_cache().lock().unwrap_or_else(|e| e.into_inner()).get(&validation_key(config, color)).cloned()
+    }
+
+    fn cache_validation(config: &LifxConfig, color: String, validated: &Color) {
+        if validated.error.is_none() && validated.errors.is_none() {
+            validation_cache().lock().unwrap_or_else(|e| e.into_inner()).insert(validation_key(config, &color), validated.clone());
+        }
+    }
+}
+
+/// How often Light::apply_verified lists the light while waiting for it to report the state
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub const VERIFY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long Scene::list_cached reuses a scene list when the config doesn't set scene_cache_ttl
+pub const SCENE_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// The process-wide access token -> (listed at, scenes) cache behind Scene::list_cached
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn scene_cache() -> &'static Mutex<HashMap<String, (Instant, Scenes)>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (Instant, Scenes)>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// What Color::validate_cached keys a lookup by: the access token, the api_endpoints and the
+/// color string
+#[cfg(any(feature = "blocking", feature = "async"))]
+type ValidationKey = (String, Vec<String>, String);
+
+/// The process-wide ValidationKey -> Color cache behind Color::validate_cached
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn validation_cache() -> &'static Mutex<HashMap<ValidationKey, Color>> {
+    static CACHE: OnceLock<Mutex<HashMap<ValidationKey, Color>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn validation_key(config: &LifxConfig, color: &str) -> ValidationKey {
+    (config.access_token.clone(), config.api_endpoints.clone(), color.to_string())
+}
+
+#[cfg(feature = "async")]
+impl AnyEffect {
+    /// Asynchronously starts the wrapped effect on the selected lights
+    async fn async_apply(self, config: LifxConfig, selector: String) -> Result<LiFxResults, LifxError> {
+        match self {
+            AnyEffect::Breathe(breathe) => Light::async_breathe_effect_by_selector(config, selector, breathe).await,
+            AnyEffect::Move(move_effect) => Light::async_move_effect_by_selector(config, selector, move_effect).await,
+            AnyEffect::Morph(morph_effect) => Light::async_morph_effect_by_selector(config, selector, morph_effect).await,
+            AnyEffect::Pulse(pulse_effect) => Light::async_pulse_effect_by_selector(config, selector, pulse_effect).await,
+            AnyEffect::Flame(flame_effect) => Light::async_flame_effect_by_selector(config, selector, flame_effect).await,
+        }
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl MorphEffect {
+    /// The most colors a morph palette can hold on `light`, or None if it has no matrix to run
+    /// the morph effect on. Every matrix product LIFX makes, from the Tile to the Candle, holds
+    /// 16.
+    pub fn max_palette_for(light: &Light) -> Option<usize> {
+        if light.product.capabilities.has_matrix {
+            Some(MAX_MORPH_PALETTE)
+        } else {
+            None
+        }
+    }
+
+    /// Checks that `light` can run this effect before it's sent: the light must have a matrix,
+    /// and the palette must fit the light's max_palette_for and pass validate. The API cuts a
+    /// palette that's too long at an unpredictable point, so it's refused here instead.
+    /// Light::morph_effect runs this check, unless the config skips capability checks.
+    ///
+    /// Returns LifxError::Unsupported if the light has no matrix or the palette is too long for
+    /// it, or LifxError::Palette for any other problem validate finds.
+    pub fn validate_for(&self, light: &Light) -> Result<(), LifxError> {
+        let max = Self::max_palette_for(light).ok_or_else(|| light.unsupported(Capability::Matrix, "the morph effect"))?;
+        if let Some(palette) = self.palette.as_ref().filter(|palette| palette.len() > max) {
+            return Err(LifxError::Unsupported(format!("a morph palette of {} colors on {} ({}), which holds at most {}", palette.len(), light.label, light.product.name, max)));
+        }
+        Ok(self.validate()?)
+    }
+}
+
+/// A pending change started by Light::schedule_state or Light::effect_window, which can be
+/// cancelled until it fires.
+/// Dropping the handle does not cancel the change.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct ScheduledHandle {
+    task: tokio::task::JoinHandle<Result<LiFxResults, LifxError>>,
+}
+
+#[cfg(feature = "async")]
+impl ScheduledHandle {
+    /// Spawns `request` to run once `at` is reached
+    fn spawn_at<F>(at: SystemTime, request: F) -> Self
+    where
+        F: Future<Output = Result<LiFxResults, LifxError>> + Send + 'static,
+    {
+        Self::spawn_after(at.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO), request)
+    }
+
+    /// Spawns `request` to run once `delay` has passed
+    fn spawn_after<F>(delay: Duration, request: F) -> Self
+    where
+        F: Future<Output = Result<LiFxResults, LifxError>> + Send + 'static,
+    {
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            request.await
+        });
+        ScheduledHandle { task }
+    }
+
+    /// Cancels the change if it hasn't been sent yet. A request already in flight may still land.
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+
+    /// Returns true once the change has been applied, has failed, or was cancelled
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Waits for the scheduled time and returns the result of applying the change,
+    /// or None if it was cancelled first.
+    pub async fn result(self) -> Option<Result<LiFxResults, LifxError>> {
+        self.task.await.ok()
+    }
+}
+
+/// Coalesces rapid brightness changes, e.g. from a slider, into one request per selector.
+///
+/// Each call to set_brightness_debounced restarts that selector's debounce window; only the
+/// latest value is sent, once no new value has arrived for the whole window. Selectors are
+/// debounced independently.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let config = lifx_rs::LifxConfig::default();
+///     let setter = lifx_rs::DebouncedSetter::new(config, Duration::from_millis(250));
+///     for value in 1..=10 {
+///         setter.set_brightness_debounced("group:Lounge".to_string(), value as f64 / 10.0);
+///     }
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct DebouncedSetter {
+    config: LifxConfig,
+    window: Duration,
+    pending: std::sync::Arc<Mutex<HashMap<String, tokio::task::AbortHandle>>>,
+}
+
+#[cfg(feature = "async")]
+impl DebouncedSetter {
+    /// Creates a setter that waits `window` after the last value before sending it
+    pub fn new(config: LifxConfig, window: Duration) -> Self {
+        DebouncedSetter { config, window, pending: Default::default() }
+    }
+
+    /// Queues `brightness` (0.0 - 1.0) for the selected lights, replacing any value still waiting
+    /// for the same selector. Must be called from within a tokio runtime.
+    ///
+    /// The returned handle resolves to None if a newer value superseded this one.
+    pub fn set_brightness_debounced(&self, selector: String, brightness: f64) -> ScheduledHandle {
+        let mut state = State::new();
+        state.brightness = Some(brightness);
+        let request = Light::async_set_state_by_selector(self.config.clone(), selector.clone(), state);
+        let handle = ScheduledHandle::spawn_after(self.window, request);
+
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(superseded) = pending.insert(selector, handle.task.abort_handle()) {
+            superseded.abort();
+        }
+        handle
+    }
+}
+
+impl Selector {
+    /// Checks whether the API would include `light` in what this selector selects, ex: to work
+    /// out locally which of the listed lights an operation covers. SceneId selectors depend on the
+    /// scene's contents, which a Light doesn't carry, so they match nothing.
+    pub fn matches(&self, light: &Light) -> bool {
+        match self {
+            Selector::All => true,
+            Selector::Id(id) => &light.id == id,
+            Selector::Label(label) => &light.label == label,
+            Selector::GroupId(id) => &light.group.id == id,
+            Selector::Group(name) => &light.group.name == name,
+            Selector::LocationId(id) => &light.location.id == id,
+            Selector::Location(name) => &light.location.name == name,
+            Selector::SceneId(_) => false,
+        }
+    }
+}
+
+/// A chain of filters over the account's lights, started with LifxConfig::lights and finished
+/// with an operation (set_state, set_brightness, toggle or breathe) that is sent on run.
+///
+/// Each filter narrows the lights matched so far. With no filters the operation goes straight to
+/// the "all" selector. Otherwise run lists all lights once, applies the filters in order, and sends
+/// the operation to the lights left, by id. If no light is left, nothing is sent and run returns
+/// an empty LiFxResults. An error listing the lights or sending the operation is returned as is.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone)]
+pub struct LightOps {
+    config: LifxConfig,
+    filters: Vec<LightFilter>,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone, PartialEq)]
+enum LightFilter {
+    On,
+    Off,
+    Group(String),
+    Location(String),
+    Capability(Capability),
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl LightFilter {
+    fn matches(&self, light: &Light) -> bool {
+        match self {
+            LightFilter::On => light.power == "on" && light.connected,
+            LightFilter::Off => light.power == "off",
+            LightFilter::Group(name) => &light.group.name == name,
+            LightFilter::Location(name) => &light.location.name == name,
+            LightFilter::Capability(cap) => light.product.capabilities.has(*cap),
+        }
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl LightOps {
+    /// Keeps the lights that are on and connected, as Light::list_on does
+    pub fn filter_on(self) -> Self {
+        self.filter(LightFilter::On)
+    }
+
+    /// Keeps the lights that report power "off", as Light::list_off does
+    pub fn filter_off(self) -> Self {
+        self.filter(LightFilter::Off)
+    }
+
+    /// Keeps the lights in the group with this name
+    pub fn in_group(self, name: &str) -> Self {
+        self.filter(LightFilter::Group(name.to_string()))
+    }
+
+    /// Keeps the lights in the location with this name
+    pub fn in_location(self, name: &str) -> Self {
+        self.filter(LightFilter::Location(name.to_string()))
+    }
+
+    /// Keeps the lights whose product supports a capability
+    pub fn with_capability(self, cap: Capability) -> Self {
+        self.filter(LightFilter::Capability(cap))
+    }
+
+    /// Sets a State on the matching lights
+    pub fn set_state(self, state: State) -> LightCommand {
+        LightCommand { ops: self, op: LightOp::SetState(state) }
+    }
+
+    /// Sets the brightness (0.0 - 1.0) of the matching lights
+    pub fn set_brightness(self, brightness: f64) -> LightCommand {
+        let mut state = State::new();
+        state.brightness = Some(brightness);
+        self.set_state(state)
+    }
+
+    /// Toggles the power of the matching lights
+    pub fn toggle(self) -> LightCommand {
+        LightCommand { ops: self, op: LightOp::Toggle(Toggle::new()) }
+    }
+
+    /// Starts a breathe effect on the matching lights
+    pub fn breathe(self, breathe: BreatheEffect) -> LightCommand {
+        LightCommand { ops: self, op: LightOp::Breathe(breathe) }
+    }
+
+    fn filter(mut self, filter: LightFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Returns the id selector for the lights that pass every filter, or None if none do
+    fn resolve(&self, lights: &[Light]) -> Option<String> {
+        let ids: Vec<String> = lights.iter()
+            .filter(|light| self.filters.iter().all(|filter| filter.matches(light)))
+            .map(|light| light.id_selector().to_string())
+            .collect();
+        if ids.is_empty() {
+            None
+        } else {
+            Some(ids.join(","))
+        }
+    }
+}
+
+/// A LightOps chain with its operation chosen, ready to run
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone)]
+pub struct LightCommand {
+    ops: LightOps,
+    op: LightOp,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone, PartialEq)]
+enum LightOp {
+    SetState(State),
+    Toggle(Toggle),
+    Breathe(BreatheEffect),
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl LightCommand {
+    /// Asynchronously resolves the filters and sends the operation, see LightOps
+    #[cfg(feature = "async")]
+    pub async fn async_run(self) -> Result<LiFxResults, LifxError> {
+        let config = self.ops.config.clone();
+        let selector = if self.ops.filters.is_empty() {
+            "all".to_string()
+        } else {
+            let lights = Light::async_list_all(config.clone()).await?;
+            match self.ops.resolve(&lights) {
+                Some(selector) => selector,
+                None => return Ok(LiFxResults { results: Some(Vec::new()), ..Default::default() }),
+            }
+        };
+        match self.op {
+            LightOp::SetState(state) => Light::async_set_state_by_selector(config, selector, state).await,
+            LightOp::Toggle(toggle) => Light::async_toggle_by_selector(config, selector, toggle).await,
+            LightOp::Breathe(breathe) => Light::async_breathe_effect_by_selector(config, selector, breathe).await,
+        }
+    }
+
+    /// Resolves the filters and sends the operation, see LightOps
+    #[cfg(feature = "blocking")]
+    pub fn run(self) -> Result<LiFxResults, LifxError> {
+        let config = self.ops.config.clone();
+        let selector = if self.ops.filters.is_empty() {
+            "all".to_string()
+        } else {
+            let lights = Light::list_all(config.clone())?;
+            match self.ops.resolve(&lights) {
+                Some(selector) => selector,
+                None => return Ok(LiFxResults { results: Some(Vec::new()), ..Default::default() }),
+            }
+        };
+        match self.op {
+            LightOp::SetState(state) => Light::set_state_by_selector(config, selector, state),
+            LightOp::Toggle(toggle) => Light::toggle_by_selector(config, selector, toggle),
+            LightOp::Breathe(breathe) => Light::breathe_by_selector_effect(config, selector, breathe),
+        }
+    }
+}
+
+/// Restores a captured State when dropped, unless it has already been restored explicitly.
+/// Used by Light::flash_and_restore so a failed flash or a panic still puts the light back.
+#[cfg(feature = "blocking")]
+struct RestoreGuard {
+    config: LifxConfig,
+    selector: String,
+    state: Option<State>,
+}
+
+#[cfg(feature = "blocking")]
+impl RestoreGuard {
+    fn restore(mut self) -> Result<LiFxResults, LifxError> {
+        let state = self.state.take().unwrap_or_default();
+        Light::set_state_by_selector(self.config.clone(), self.selector.clone(), state)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Drop for RestoreGuard {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            let _ = Light::set_state_by_selector(self.config.clone(), self.selector.clone(), state);
+        }
+    }
+}
+
+/// Returns 64 random bits, taken from std's per-process hasher keys plus a counter so repeated
+/// calls differ. Good enough for ids and shuffles, not for anything security related.
+fn random_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+/// Returns a random version 4 style uuid for scenes built locally, ex: 9b1d0c2e-5a3f-4e21-8c4d-0f6a2b7e9d13
+fn generate_uuid() -> String {
+    let high = random_u64();
+    let low = random_u64();
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0x0fff,
+        ((low >> 48) & 0x3fff) | 0x8000,
+        low & 0xffff_ffff_ffff,
+    )
+}
+
+/// A tiny seedable PRNG (SplitMix64), so a seeded shuffle always gives the same colors
+#[cfg(any(feature = "blocking", feature = "async"))]
+struct SplitMix64(u64);
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a whole-degree hue in [0, 360)
+    fn next_hue(&mut self) -> u64 {
+        self.next_u64() % 360
+    }
+}
+
+/// The changes between two lists of lights, as returned by diff_lights
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LightsDiff {
+    /// Lights in the new list whose id wasn't in the old one
+    pub added: Vec<Light>,
+    /// Lights in the old list whose id isn't in the new one
+    pub removed: Vec<Light>,
+    /// (old, new) pairs for lights whose power, brightness, color or connected state changed
+    pub changed: Vec<(Light, Light)>,
+}
+
+impl LightsDiff {
+    /// Returns true if nothing was added, removed or changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares two polls of the light list, matching lights by id.
+///
+/// Only power, brightness, color and connected are compared; fields that move on every poll, like
+/// seconds_since_seen and last_seen, are ignored so an unchanged light never shows up as changed.
+/// 
+/// # Arguments
+///
+/// * `old` - The lights from the previous poll
+/// * `new` - The lights from the latest poll
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate lifx_rs as lifx;
+/// 
+/// fn main() {
+/// 
+///     let key = "xxx".to_string();
+///     let mut api_endpoints: Vec<String> = Vec::new();
+///
+///     api_endpoints.push(format!("https://api.lifx.com"));
+///     api_endpoints.push(format!("http://localhost:8089"));
+///
+///     let config = lifx::LifxConfig{
+///        access_token: key.clone(),
+///        api_endpoints: api_endpoints,
+///        ..Default::default()
+///     };
+/// 
+///     let old = lifx::Light::list_all(config.clone()).unwrap_or_default();
+///     let new = lifx::Light::list_all(config.clone()).unwrap_or_default();
+///     for (before, after) in lifx::diff_lights(&old, &new).changed {
+///         println!("{} went from {} to {}", after.label, before.power, after.power);
+///     }
+/// }
+///  ```
+pub fn diff_lights(old: &[Light], new: &[Light]) -> LightsDiff {
+    let find = |lights: &[Light], id: &str| lights.iter().find(|light| light.id == id).cloned();
+    let mut diff = LightsDiff::default();
+
+    for light in new {
+        match find(old, &light.id) {
+            None => diff.added.push(light.clone()),
+            Some(previous) => {
+                let changed = previous.power != light.power
+                    || previous.brightness != light.brightness
+                    || previous.color != light.color
+                    || previous.connected != light.connected;
+                if changed {
+                    diff.changed.push((previous, light.clone()));
+                }
+            }
+        }
+    }
+    for light in old {
+        if find(new, &light.id).is_none() {
+            diff.removed.push(light.clone());
+        }
+    }
+    diff
+}
+
+/// The most states the cloud API accepts in a single set_states call
+const SET_STATES_CHUNK: usize = 50;
+
+/// Splits `states` into the set_states requests it takes, each with the same defaults. States
+/// with 50 or fewer states (or none) stay one request.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn set_states_chunks(states: States) -> Vec<States> {
+    match &states.states {
+        Some(list) if list.len() > SET_STATES_CHUNK => list
+            .chunks(SET_STATES_CHUNK)
+            .map(|chunk| States { states: Some(chunk.to_vec()), defaults: states.defaults.clone() })
+            .collect(),
+        _ => vec![states],
+    }
+}
+
+/// Joins the results of several requests into one, keeping the rate limit of the last one
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn combine_results(all: Vec<LiFxResults>) -> LiFxResults {
+    let empty = LiFxResults { results: Some(Vec::new()), ..Default::default() };
+    all.into_iter().fold(empty, |mut combined, results| {
+        combined.results.get_or_insert_with(Vec::new).extend(results.results.unwrap_or_default());
+        combined.error = combined.error.or(results.error);
+        combined.rate_limit = results.rate_limit.or(combined.rate_limit);
+        combined.fast_accepted |= results.fast_accepted;
+        combined
+    })
+}
+
+/// The shape of a batch operation, used to estimate its API request cost with estimated_requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchOperation {
+    /// One call covering every light through a selector, ex: set_state_by_selector with group:xxx
+    Selector,
+    /// One call per light, ex: a loop of set_state calls or Light::async_morph_wave
+    PerLight,
+    /// A set_states call with one state per light, which sends one request per 50 states
+    SetStates,
+    /// Light::flash_and_restore on every light: a re-read, the flash and the restore per light
+    FlashAndRestore,
+}
+
+/// Estimates how many API requests a batch operation over `light_count` lights will make, so a
+/// caller can check it against the cloud rate limit (about 60 requests per minute) before sending.
+///
+/// The estimate assumes every request succeeds on the first endpoint. A failover to the next
+/// endpoint costs one more request per failed call, and any list calls made beforehand to find
+/// the lights are not included.
+/// 
+/// # Arguments
+///
+/// * `light_count` - How many lights the operation covers
+/// * `operation` - The BatchOperation being sent
+///
+/// # Examples
+///
+/// ```
+/// extern crate lifx_rs as lifx;
+/// 
+/// fn main() {
+///     assert_eq!(lifx::estimated_requests(120, lifx::BatchOperation::SetStates), 3);
+///     assert_eq!(lifx::estimated_requests(120, lifx::BatchOperation::Selector), 1);
+/// }
+///  ```
+pub fn estimated_requests(light_count: usize, operation: BatchOperation) -> u32 {
+    if light_count == 0 {
+        return 0;
+    }
+    let requests = match operation {
+        BatchOperation::Selector => 1,
+        BatchOperation::PerLight => light_count,
+        BatchOperation::SetStates => light_count.div_ceil(SET_STATES_CHUNK),
+        BatchOperation::FlashAndRestore => light_count * 3,
+    };
+    u32::try_from(requests).unwrap_or(u32::MAX)
+}
+
+/// Returns the start delay for each of `count` staggered operations: zero for the first, then
+/// increasing by `offset` for each one after it.
+#[cfg(feature = "async")]
+fn stagger_delays(count: usize, offset: Duration) -> Vec<Duration> {
+    (0..count).map(|i| offset * i as u32).collect()
+}
+
+/// Runs `task` on every item with at most `concurrency` (at least 1) running at once, returning
+/// the outputs in item order
+#[cfg(feature = "async")]
+async fn bounded_join<T, F, Fut>(items: Vec<T>, concurrency: usize, task: F) -> Vec<Fut::Output>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let mut handles = Vec::new();
+    for item in items {
+        let permits = permits.clone();
+        let request = task(item);
+        handles.push(tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("semaphore is never closed");
+            request.await
+        }));
     }
 
-    params = format!("{}]", params);
+    let mut results = Vec::new();
+    for handle in handles {
+        results.push(handle.await.expect("bounded task panicked"));
+    }
+    results
+}
+
+/// A group or location with the ids of its lights, see Group::list_all and Location::list_all
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Members<T> {
+    pub of: T,
+    /// The ids of the lights in it, in the order the API listed them
+    pub light_ids: Vec<String>,
+}
+
+impl<T> Members<T> {
+    /// The number of lights in it
+    pub fn count(&self) -> usize {
+        self.light_ids.len()
+    }
+}
+
+/// Adds the HTTP API operations to a Group or Location, each sent to every light in it through a
+/// `$selector` id selector. `$field` is the Light field holding it.
+macro_rules! selector_operations {
+    ($type:ident, $selector:ident, $field:ident, $noun:literal) => {
+        selector_operations!(@impl $type, $selector, $field, $noun,
+            set_state async_set_state set_state_by_selector async_set_state_by_selector (state: State) "Sets the state of";
+            state_delta async_state_delta state_delta_by_selector async_state_delta_by_selector (delta: StateDelta) "Changes the state, relative to where it is, of";
+            toggle async_toggle toggle_by_selector async_toggle_by_selector (toggle: Toggle) "Toggles the power of";
+            breathe_effect async_breathe_effect breathe_effect_by_selector async_breathe_effect_by_selector (breathe: BreatheEffect) "Runs the breathe effect on";
+            move_effect async_move_effect move_effect_by_selector async_move_effect_by_selector (move_effect: MoveEffect) "Runs the move effect on";
+            morph_effect async_morph_effect morph_effect_by_selector async_morph_effect_by_selector (morph_effect: MorphEffect) "Runs the morph effect on";
+            pulse_effect async_pulse_effect pulse_effect_by_selector async_pulse_effect_by_selector (pulse_effect: PulseEffect) "Runs the pulse effect on";
+            flame_effect async_flame_effect flame_effect_by_selector async_flame_effect_by_selector (flame_effect: FlameEffect) "Runs the flame effect on";
+            effects_off async_effects_off effects_off_by_selector async_effects_off_by_selector (effects_off: EffectsOff) "Stops the effects running on";
+            clean async_clean clean_by_selector async_clean_by_selector (clean: Clean) "Runs an HEV clean cycle on";
+        );
+    };
+    (@impl $type:ident, $selector:ident, $field:ident, $noun:literal,
+        $($name:ident $async_name:ident $by_selector:ident $async_by_selector:ident ($arg:ident: $ty:ty) $doc:literal;)*) => {
+        impl $type {
+            #[doc = concat!("Returns a Selector for every light in this ", $noun, ", by id")]
+            pub fn selector(&self) -> Selector {
+                Selector::$selector(self.id.clone())
+            }
+
+            #[doc = concat!("Returns each ", $noun, " the lights are in, with the ids of its lights, in the order they first appear. ")]
+            #[doc = concat!("Each ", $noun, " is compared by id.")]
+            pub fn from_lights(lights: &[Light]) -> Vec<Members<$type>> {
+                let mut members: Vec<Members<$type>> = Vec::new();
+                for light in lights {
+                    match members.iter_mut().find(|entry| entry.of.id == light.$field.id) {
+                        Some(entry) => entry.light_ids.push(light.id.clone()),
+                        None => members.push(Members { of: light.$field.clone(), light_ids: vec![light.id.clone()] }),
+                    }
+                }
+                members
+            }
+
+            #[doc = concat!("Lists every ", $noun, " on the account, with the ids of its lights. ")]
+            #[doc = concat!("The API has no endpoint for this, so it lists all lights, see from_lights.")]
+            #[cfg(feature = "blocking")]
+            pub fn list_all(config: LifxConfig) -> Result<Vec<Members<$type>>, LifxError> {
+                Ok(Self::from_lights(&Light::list_all(config)?))
+            }
+
+            #[doc = concat!("Asynchronously lists every ", $noun, " on the account, with the ids of its lights, see list_all")]
+            #[cfg(feature = "async")]
+            pub async fn async_list_all(config: LifxConfig) -> Result<Vec<Members<$type>>, LifxError> {
+                Ok(Self::from_lights(&Light::async_list_all(config).await?))
+            }
+
+            #[doc = concat!("Lists the lights in this ", $noun)]
+            #[cfg(feature = "blocking")]
+            pub fn lights(&self, config: LifxConfig) -> Result<Lights, LifxError> {
+                LifxClient::new(config).list_by_selector(self.selector().to_string())
+            }
+
+            #[doc = concat!("Asynchronously lists the lights in this ", $noun)]
+            #[cfg(feature = "async")]
+            pub async fn async_lights(&self, config: LifxConfig) -> Result<Lights, LifxError> {
+                LifxClient::new(config).async_list_by_selector(self.selector().to_string()).await
+            }
+
+            $(
+                #[doc = concat!($doc, " every light in this ", $noun)]
+                #[cfg(feature = "blocking")]
+                pub fn $name(&self, config: LifxConfig, $arg: $ty) -> Result<LiFxResults, LifxError> {
+                    LifxClient::new(config).$by_selector(self.selector().to_string(), $arg)
+                }
 
-    return params;
+                #[doc = concat!("The async version of ", stringify!($name))]
+                #[cfg(feature = "async")]
+                pub async fn $async_name(&self, config: LifxConfig, $arg: $ty) -> Result<LiFxResults, LifxError> {
+                    LifxClient::new(config).$async_by_selector(self.selector().to_string(), $arg).await
+                }
+            )*
+        }
+    };
 }
 
+/// A group of lights, usually a room, as the API lists it on each Light.
+///
+/// The state and effect methods go to every light in the group, ex: `group.toggle(config, ..)`
+/// sends one request with a `group_id:` selector, and lights lists them.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate lifx_rs as lifx;
+/// 
+/// fn main() {
+/// 
+///     let key = "xxx".to_string();
+///     let mut api_endpoints: Vec<String> = Vec::new();
+///
+///     api_endpoints.push(format!("https://api.lifx.com"));
+///     api_endpoints.push(format!("http://localhost:8089"));
+///
+///     let config = lifx::LifxConfig{
+///        access_token: key.clone(),
+///        api_endpoints: api_endpoints,
+///        ..Default::default()
+///     };
+/// 
+///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+///         if let Some(light) = lights.first() {
+///             let results = light.group.set_state(config.clone(), lifx::State::builder().power_off().build());
+///             println!("{:?}", results);
+///         }
+///     }
+/// }
+///  ```
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[doc(hidden)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Group {
     pub id: String,
     pub name: String,
 }
 
+selector_operations!(Group, GroupId, group, "group");
+
+#[cfg(feature = "async")]
+impl Group {
+    /// Asynchronously turns the group's lights off one by one, in the order the API lists them,
+    /// waiting `stagger` between each start. Each light fades out over `duration` seconds, so with a
+    /// stagger shorter than the duration the fades overlap.
+    ///
+    /// Returns an error if the group's lights can't be listed, otherwise one result per light.
+    /// A failed light doesn't stop the cascade.
+    /// 
+    /// # Arguments
+    ///
+    /// * `config` - A LifxConfig object with the access token and api endpoints.
+    /// * `stagger` - The delay between turning off one light and the next
+    /// * `duration` - How long in seconds each light takes to fade out
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// use std::time::Duration;
+    /// 
+    /// #[tokio::main]
+    /// async fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     if let Ok(lights) = lifx::Light::async_list_by_selector(config.clone(), format!("group:Lounge")).await {
+    ///         if let Some(light) = lights.first() {
+    ///             let results = light.group.async_cascade_off(config, Duration::from_millis(400), 2.0).await;
+    ///             println!("{:?}", results);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    pub async fn async_cascade_off(&self, config: LifxConfig, stagger: Duration, duration: f64) -> Result<Vec<Result<LiFxResults, LifxError>>, LifxError> {
+        let lights = Light::async_list_by_selector(config.clone(), Selector::GroupId(self.id.clone()).to_string()).await?;
+
+        let start = Instant::now();
+        let mut results = Vec::new();
+        for (delay, selector, state) in Self::cascade_off_plan(&lights, stagger, duration) {
+            tokio::time::sleep(delay.saturating_sub(start.elapsed())).await;
+            results.push(Light::async_set_state_by_selector(config.clone(), selector, state).await);
+        }
+        Ok(results)
+    }
+
+    /// Returns the start delay, id selector and off State for each light in a cascade
+    fn cascade_off_plan(lights: &[Light], stagger: Duration, duration: f64) -> Vec<(Duration, String, State)> {
+        lights.iter()
+            .zip(stagger_delays(lights.len(), stagger))
+            .map(|(light, delay)| {
+                let mut state = State::new();
+                state.power = Some("off".to_string());
+                state.duration = Some(duration);
+                (delay, light.id_selector().to_string(), state)
+            })
+            .collect()
+    }
+}
+
+/// A location of lights, usually a home, as the API lists it on each Light.
+///
+/// Like Group, its state and effect methods go to every light in it, here through a
+/// `location_id:` selector, and lights lists them.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate lifx_rs as lifx;
+/// 
+/// fn main() {
+/// 
+///     let key = "xxx".to_string();
+///     let mut api_endpoints: Vec<String> = Vec::new();
+///
+///     api_endpoints.push(format!("https://api.lifx.com"));
+///     api_endpoints.push(format!("http://localhost:8089"));
+///
+///     let config = lifx::LifxConfig{
+///        access_token: key.clone(),
+///        api_endpoints: api_endpoints,
+///        ..Default::default()
+///     };
+/// 
+///     if let Ok(lights) = lifx::Light::list_all(config.clone()) {
+///         if let Some(light) = lights.first() {
+///             let home = &light.location;
+///             println!("{} has {:?} lights", home.name, home.lights(config.clone()).map(|lights| lights.len()));
+///             let results = home.toggle(config.clone(), lifx::Toggle::new());
+///             println!("{:?}", results);
+///         }
+///     }
+/// }
+///  ```
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[doc(hidden)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Location {
     pub id: String,
     pub name: String,
 }
 
+selector_operations!(Location, LocationId, location, "location");
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 #[doc(hidden)]
 pub struct Product {
     pub name: String,
@@ -4101,28 +6513,297 @@ pub struct Product {
     pub capabilities: Capabilities,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[doc(hidden)]
-pub struct Capabilities {
-    #[serde(rename = "has_color")]
-    pub has_color: bool,
-    #[serde(rename = "has_variable_color_temp")]
-    pub has_variable_color_temp: bool,
-    #[serde(rename = "has_ir")]
-    pub has_ir: bool,
-    #[serde(rename = "has_hev")]
-    pub has_hev: bool,
-    #[serde(rename = "has_chain")]
-    pub has_chain: bool,
-    #[serde(rename = "has_matrix")]
-    pub has_matrix: bool,
-    #[serde(rename = "has_multizone")]
-    pub has_multizone: bool,
-    #[serde(rename = "min_kelvin")]
-    pub min_kelvin: i64,
-    #[serde(rename = "max_kelvin")]
-    pub max_kelvin: i64,
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+#[doc(hidden)]
+pub struct Capabilities {
+    #[serde(rename = "has_color")]
+    pub has_color: bool,
+    #[serde(rename = "has_variable_color_temp")]
+    pub has_variable_color_temp: bool,
+    #[serde(rename = "has_ir")]
+    pub has_ir: bool,
+    #[serde(rename = "has_hev")]
+    pub has_hev: bool,
+    #[serde(rename = "has_chain")]
+    pub has_chain: bool,
+    #[serde(rename = "has_matrix")]
+    pub has_matrix: bool,
+    #[serde(rename = "has_multizone")]
+    pub has_multizone: bool,
+    #[serde(rename = "min_kelvin")]
+    pub min_kelvin: i64,
+    #[serde(rename = "max_kelvin")]
+    pub max_kelvin: i64,
+}
+
+impl Capabilities {
+    /// Returns true if the product supports the given Capability
+    pub fn has(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Color => self.has_color,
+            Capability::Matrix => self.has_matrix,
+            Capability::Multizone => self.has_multizone,
+            Capability::Hev => self.has_hev,
+            Capability::Ir => self.has_ir,
+        }
+    }
+}
+
+/// The zones of a multizone light, see Light::zones
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Zones {
+    /// How many zones the light has
+    pub count: usize,
+    /// The color of each zone, in zone order. May be empty if the API only sent the count.
+    pub zones: Vec<Zone>,
+}
+
+/// One zone of a multizone light and the color it's showing
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    /// The zone's index, from 0
+    #[serde(rename = "zone", default)]
+    pub index: usize,
+    #[serde(flatten)]
+    pub color: Color,
+}
+
+/// A firmware effect a light reports running, see Light::effect.
+///
+/// The API reports either the effect's name, ex: "MORPH", or an object with the name as `type`
+/// next to the effect's params, ex: {"type": "MORPH", "period": 5, "palette": ["red", "blue"]}.
+/// The params are kept in the effect's builder struct, so a running effect can be sent again,
+/// reversed or stopped with the same settings, see as_move, as_morph and as_flame. An effect
+/// reported by name alone carries a builder with no params set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ReportedEffect", into = "ReportedEffect")]
+pub enum RunningEffect {
+    /// No effect is running
+    Off,
+    Move(MoveEffect),
+    Morph(MorphEffect),
+    Flame(FlameEffect),
+    /// An effect this crate doesn't know, as the API named it
+    Other(String),
+}
+
+impl RunningEffect {
+    /// Returns true unless this is RunningEffect::Off
+    pub fn is_running(&self) -> bool {
+        *self != RunningEffect::Off
+    }
+
+    /// The params of a running move effect, ready to send again, or None for any other effect
+    pub fn as_move(&self) -> Option<MoveEffect> {
+        match self {
+            RunningEffect::Move(move_effect) => Some(move_effect.clone()),
+            _ => None,
+        }
+    }
+
+    /// The params of a running morph effect, ready to send again, or None for any other effect
+    pub fn as_morph(&self) -> Option<MorphEffect> {
+        match self {
+            RunningEffect::Morph(morph_effect) => Some(morph_effect.clone()),
+            _ => None,
+        }
+    }
+
+    /// The params of a running flame effect, ready to send again, or None for any other effect
+    pub fn as_flame(&self) -> Option<FlameEffect> {
+        match self {
+            RunningEffect::Flame(flame_effect) => Some(flame_effect.clone()),
+            _ => None,
+        }
+    }
+
+    /// The effect named `name`, with `params` as the API reported them. Params that don't fit
+    /// the effect's builder struct are passed over, so the light still lists.
+    fn with_params(name: String, params: serde_json::Map<String, serde_json::Value>) -> Self {
+        fn builder<T: serde::de::DeserializeOwned + Default>(params: serde_json::Map<String, serde_json::Value>) -> T {
+            serde_json::from_value(serde_json::Value::Object(params)).unwrap_or_default()
+        }
+        match name.to_ascii_uppercase().as_str() {
+            "OFF" => RunningEffect::Off,
+            "MOVE" => RunningEffect::Move(builder(params)),
+            "MORPH" => RunningEffect::Morph(builder(params)),
+            "FLAME" => RunningEffect::Flame(builder(params)),
+            _ => RunningEffect::Other(name),
+        }
+    }
+
+    /// The effect's name as the API reports it, ex: "MORPH"
+    pub fn name(&self) -> &str {
+        match self {
+            RunningEffect::Off => "OFF",
+            RunningEffect::Move(_) => "MOVE",
+            RunningEffect::Morph(_) => "MORPH",
+            RunningEffect::Flame(_) => "FLAME",
+            RunningEffect::Other(name) => name,
+        }
+    }
+}
+
+impl From<String> for RunningEffect {
+    fn from(name: String) -> Self {
+        RunningEffect::with_params(name, serde_json::Map::new())
+    }
+}
+
+impl From<RunningEffect> for String {
+    fn from(effect: RunningEffect) -> String {
+        effect.name().to_string()
+    }
+}
+
+/// How the API reports a RunningEffect: by name, or as an object with its params
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ReportedEffect {
+    Name(String),
+    Params {
+        #[serde(rename = "type")]
+        name: String,
+        #[serde(flatten)]
+        params: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+impl From<ReportedEffect> for RunningEffect {
+    fn from(reported: ReportedEffect) -> Self {
+        match reported {
+            ReportedEffect::Name(name) => RunningEffect::from(name),
+            ReportedEffect::Params { name, params } => RunningEffect::with_params(name, params),
+        }
+    }
+}
+
+impl From<RunningEffect> for ReportedEffect {
+    fn from(effect: RunningEffect) -> Self {
+        let params = match &effect {
+            RunningEffect::Move(move_effect) => serde_json::to_value(move_effect),
+            RunningEffect::Morph(morph_effect) => serde_json::to_value(morph_effect),
+            RunningEffect::Flame(flame_effect) => serde_json::to_value(flame_effect),
+            RunningEffect::Off | RunningEffect::Other(_) => Ok(serde_json::Value::Null),
+        };
+        let mut params = match params {
+            Ok(serde_json::Value::Object(params)) => params,
+            _ => serde_json::Map::new(),
+        };
+        params.retain(|_, value| !value.is_null());
+        if params.is_empty() {
+            ReportedEffect::Name(String::from(effect))
+        } else {
+            ReportedEffect::Params { name: String::from(effect), params }
+        }
+    }
+}
+
+/// A feature a LIFX product may support, see Capabilities::has and Light::partition_by_capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Full color, rather than white only
+    Color,
+    /// A 2D grid of zones, ex: Tile and Candle; needed for the morph and flame effects
+    Matrix,
+    /// A strip of zones, ex: Z and Beam; needed for the move effect
+    Multizone,
+    /// HEV germicidal light, needed for clean
+    Hev,
+    /// Infrared night vision
+    Ir,
+}
+
+/// A way a State may render differently on a light than intended, see State::check_reproducible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GamutWarning {
+    /// The state asks for a saturated color, but the light is white only
+    NoColorSupport { color: String },
+    /// The state asks for a kelvin outside the light's range, so the light clamps it
+    KelvinOutOfRange { kelvin: i64, min_kelvin: i64, max_kelvin: i64 },
+}
+
+impl State {
+    /// Checks this state's color against what the light's product can show, warning about
+    /// saturated colors on a white-only light and kelvin values outside the light's range.
+    /// Saturation is taken as requested by a nonzero saturation:, a named color other than white,
+    /// or a #hex or rgb: color. A state without a color, or a light reporting no kelvin range, gives
+    /// no warnings for that check.
+    /// 
+    /// # Arguments
+    ///
+    /// * `light` - The Light the state is meant for
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate lifx_rs as lifx;
+    /// 
+    /// fn main() {
+    /// 
+    ///     let key = "xxx".to_string();
+    ///     let mut api_endpoints: Vec<String> = Vec::new();
+    ///
+    ///     api_endpoints.push(format!("https://api.lifx.com"));
+    ///     api_endpoints.push(format!("http://localhost:8089"));
+    ///
+    ///     let config = lifx::LifxConfig{
+    ///        access_token: key.clone(),
+    ///        api_endpoints: api_endpoints,
+    ///        ..Default::default()
+    ///     };
+    /// 
+    ///     let mut state = lifx::State::new();
+    ///     state.color = Some(format!("hue:240 saturation:1 kelvin:2500"));
+    /// 
+    ///     for light in lifx::Light::list_all(config).unwrap_or_default() {
+    ///         if let Err(warnings) = state.check_reproducible(&light) {
+    ///             println!("{}: {:?}", light.label, warnings);
+    ///         }
+    ///     }
+    /// }
+    ///  ```
+    pub fn check_reproducible(&self, light: &Light) -> Result<(), Vec<GamutWarning>> {
+        let color = match &self.color {
+            Some(color) => color,
+            None => return Ok(()),
+        };
+        let capabilities = &light.product.capabilities;
+        let mut warnings = Vec::new();
+        let mut saturated = false;
+
+        for part in color.split_whitespace() {
+            let part = part.to_lowercase();
+            if let Some(saturation) = part.strip_prefix("saturation:") {
+                saturated |= saturation.parse::<f64>().map(|s| s > 0.0).unwrap_or(false);
+            } else if let Some(kelvin) = part.strip_prefix("kelvin:") {
+                if let Ok(kelvin) = kelvin.parse::<i64>() {
+                    let has_range = capabilities.min_kelvin > 0 || capabilities.max_kelvin > 0;
+                    if has_range && (kelvin < capabilities.min_kelvin || kelvin > capabilities.max_kelvin) {
+                        warnings.push(GamutWarning::KelvinOutOfRange {
+                            kelvin,
+                            min_kelvin: capabilities.min_kelvin,
+                            max_kelvin: capabilities.max_kelvin,
+                        });
+                    }
+                }
+            } else if part.starts_with('#') || part.starts_with("rgb:")
+                || ["red", "orange", "yellow", "green", "cyan", "blue", "purple", "pink"].contains(&part.as_str()) {
+                saturated = true;
+            }
+        }
+
+        if saturated && !capabilities.has_color {
+            warnings.insert(0, GamutWarning::NoColorSupport { color: color.clone() });
+        }
+        if warnings.is_empty() {
+            Ok(())
+        } else {
+            Err(warnings)
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -4135,21 +6816,24 @@ pub struct Account {
 
 
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[doc(hidden)]
-pub struct Error {
-    pub field: String,
-    pub message: Vec<String>,
-}
-
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[doc(hidden)]
 pub struct LiFxResults {
     pub results: Option<Vec<LiFxResult>>,
-    pub error: Option<String>
+    pub error: Option<String>,
+    /// The rate limit the HTTP API reported with these results. None if they came from a
+    /// transport or a server that doesn't send X-RateLimit headers.
+    #[serde(skip)]
+    pub rate_limit: Option<RateLimit>,
+    /// True when the API accepted a `fast` request without reporting results. The lights were
+    /// not waited on, so there's nothing to tell whether they carried it out.
+    #[serde(skip)]
+    pub fast_accepted: bool,
+    /// Fields this crate doesn't model yet, kept as the API sent them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -4159,4 +6843,1226 @@ pub struct LiFxResult {
     pub id: String,
     pub label: String,
     pub status: String,
+    /// Fields this crate doesn't model yet, kept as the API sent them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LiFxResults {
+    /// The results for lights that carried out the request
+    pub fn succeeded(&self) -> Vec<&LiFxResult> {
+        self.with_status(|result| result.is_ok())
+    }
+
+    /// The results for lights the cloud couldn't reach
+    pub fn offline(&self) -> Vec<&LiFxResult> {
+        self.with_status(|result| result.status == "offline")
+    }
+
+    /// The results for lights that didn't answer in time
+    pub fn timed_out(&self) -> Vec<&LiFxResult> {
+        self.with_status(|result| result.status == "timed_out")
+    }
+
+    /// The results for lights that didn't carry out the request, whatever the reason.
+    /// These are the ones worth retrying.
+    pub fn failed(&self) -> Vec<&LiFxResult> {
+        self.with_status(|result| !result.is_ok())
+    }
+
+    /// Returns true if there's no error and every light carried out the request
+    pub fn is_fully_ok(&self) -> bool {
+        self.error.is_none() && self.failed().is_empty()
+    }
+
+    fn with_status(&self, matches: impl Fn(&LiFxResult) -> bool) -> Vec<&LiFxResult> {
+        self.results.iter().flatten().filter(|result| matches(result)).collect()
+    }
+}
+
+impl LiFxResult {
+    /// Returns true if the light carried out the request
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_stagger_delays() {
+        let delays = stagger_delays(4, Duration::from_millis(250));
+        assert_eq!(delays, vec![
+            Duration::from_millis(0),
+            Duration::from_millis(250),
+            Duration::from_millis(500),
+            Duration::from_millis(750),
+        ]);
+        assert!(stagger_delays(0, Duration::from_secs(1)).is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_morph_wave() {
+        use crate::test_util::{fixtures, MockTransport};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let mut lights = fixtures::lights();
+        for tile in &mut lights[..2] {
+            tile.product.capabilities.has_matrix = true;
+        }
+        let mut morph_effect = MorphEffect::new();
+        morph_effect.period = Some(5.0);
+
+        let started = Instant::now();
+        let results = Light::async_morph_wave(mock.config(), &lights, morph_effect.clone(), Duration::from_millis(40)).await;
+        assert!(started.elapsed() >= Duration::from_millis(80));
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok() && results[1].is_ok());
+        // Bedside isn't a matrix, so its start fails on its own without holding up the others
+        assert!(matches!(&results[2], Err(LifxError::Unsupported(_))));
+        let sent = mock.operations();
+        assert_eq!(sent, vec![
+            ("id:d073d5000001".to_string(), Operation::Morph(morph_effect.clone())),
+            ("id:d073d5000002".to_string(), Operation::Morph(morph_effect)),
+        ]);
+        assert!(Light::async_morph_wave(mock.config(), &[], MorphEffect::new(), Duration::from_secs(1)).await.is_empty());
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_snapshot_state() {
+        let light = Light {
+            power: "off".to_string(),
+            brightness: 0.4,
+            color: Color {
+                hue: Some(120.0),
+                saturation: Some(1.0),
+                kelvin: Some(3500),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let state = light.snapshot_state();
+        assert_eq!(state.power, Some("off".to_string()));
+        assert_eq!(state.color, Some("hue:120 saturation:1 kelvin:3500".to_string()));
+        assert_eq!(state.brightness, Some(0.4));
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_brightness_step() {
+        assert_eq!(Light::brightness_step(10, true).brightness, Some(-0.1));
+        assert_eq!(Light::brightness_step(10, false).brightness, Some(0.1));
+        assert_eq!(Light::brightness_step(-25, false).brightness, Some(-0.25));
+        assert_eq!(Light::brightness_step(-25, true).brightness, Some(0.25));
+        assert_eq!(Light::brightness_step(127, false).brightness, Some(1.0));
+        assert_eq!(Light::brightness_step(-128, true).brightness, Some(1.0));
+        assert_eq!(Light::brightness_step(0, true).power, None);
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_active_only_states() {
+        let light = |id: &str, power: &str| Light {
+            id: id.to_string(),
+            power: power.to_string(),
+            ..Default::default()
+        };
+        let mut kitchen = State::new();
+        kitchen.selector = Some("group:Kitchen".to_string());
+        kitchen.brightness = Some(0.3);
+        let mut desk = State::new();
+        desk.selector = Some("id:d1".to_string());
+
+        let resolved = vec![
+            vec![light("k1", "on"), light("k2", "off")],
+            vec![light("d1", "off")],
+        ];
+        let states = Scene::active_only_states(&[kitchen, desk.clone()], &resolved);
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].selector, Some("id:k1".to_string()));
+        assert_eq!(states[0].brightness, Some(0.3));
+
+        let none_on = Scene::active_only_states(&[desk], &[vec![light("d1", "off")]]);
+        assert!(none_on.is_empty());
+    }
+
+    #[test]
+    fn test_estimated_requests() {
+        assert_eq!(estimated_requests(0, BatchOperation::Selector), 0);
+        assert_eq!(estimated_requests(30, BatchOperation::Selector), 1);
+        assert_eq!(estimated_requests(30, BatchOperation::PerLight), 30);
+        assert_eq!(estimated_requests(50, BatchOperation::SetStates), 1);
+        assert_eq!(estimated_requests(51, BatchOperation::SetStates), 2);
+        assert_eq!(estimated_requests(4, BatchOperation::FlashAndRestore), 12);
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_ordered_endpoints() {
+        let mut config = LifxConfig {
+            api_endpoints: vec![
+                "https://api.lifx.com".to_string(),
+                "http://localhost:8089".to_string(),
+                "http://10.0.0.2:8089".to_string(),
+            ],
+            local_endpoints: vec!["http://10.0.0.2:8089".to_string(), "http://localhost:8089".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(config.ordered_endpoints(true), vec!["https://api.lifx.com", "http://localhost:8089", "http://10.0.0.2:8089"]);
+
+        config.endpoint_strategy = EndpointStrategy::PreferLocal;
+        assert_eq!(config.ordered_endpoints(false), vec!["http://localhost:8089", "http://10.0.0.2:8089", "https://api.lifx.com"]);
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_endpoint_options() {
+        let config = LifxConfig {
+            api_endpoints: vec![
+                "https://api.lifx.com".to_string(),
+                "http://localhost:8089".to_string(),
+                "http://mirror:8089".to_string(),
+            ],
+            endpoint_options: vec![
+                EndpointOptions::new("https://api.lifx.com").priority(10),
+                EndpointOptions::new("http://mirror:8089").read_only(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(config.ordered_endpoints(false), vec!["http://localhost:8089", "http://mirror:8089", "https://api.lifx.com"]);
+        assert_eq!(config.ordered_endpoints(true), vec!["http://localhost:8089", "https://api.lifx.com"]);
+
+        let weighted = LifxConfig {
+            endpoint_options: vec![
+                EndpointOptions::new("http://localhost:8089").weight(0),
+                EndpointOptions::new("http://mirror:8089").weight(3),
+            ],
+            ..config.clone()
+        };
+        for _ in 0..20 {
+            let order = weighted.ordered_endpoints(false);
+            assert_eq!(order[2], "http://localhost:8089");
+        }
+
+        let options: EndpointOptions = serde_json::from_str(r#"{"endpoint":"http://localhost:8089","priority":2}"#).unwrap();
+        assert_eq!(options, EndpointOptions::new("http://localhost:8089").priority(2));
+    }
+
+    #[test]
+    fn test_light_selectors() {
+        let light = Light {
+            id: "d073d5".to_string(),
+            group: Group { id: "g1".to_string(), name: "Kitchen".to_string() },
+            location: Location { id: "l1".to_string(), name: "Home".to_string() },
+            ..Default::default()
+        };
+        assert_eq!(light.id_selector().to_string(), "id:d073d5");
+        assert_eq!(light.group_selector().to_string(), "group_id:g1");
+        assert_eq!(light.location_selector().to_string(), "location_id:l1");
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_drift_peaks() {
+        let close = |actual: Vec<f64>, expected: &[f64]| {
+            actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-9)
+        };
+        assert!(close(Light::drift_peaks((0.2, 0.8), 4).unwrap(), &[0.2, 0.4, 0.6, 0.8]));
+        assert!(close(Light::drift_peaks((0.9, 0.1), 2).unwrap(), &[0.9, 0.1]));
+        assert!(close(Light::drift_peaks((0.5, 1.0), 1).unwrap(), &[0.5]));
+        assert!(close(Light::drift_peaks((0.0, 1.0), 3).unwrap(), &[0.0, 0.5, 1.0]));
+        assert!(Light::drift_peaks((0.2, 0.8), 0).unwrap().is_empty());
+        assert!(matches!(Light::drift_peaks((-0.5, 0.8), 3), Err(LifxError::Config(message)) if message.contains("-0.5")));
+        assert!(matches!(Light::drift_peaks((0.2, 1.5), 3), Err(LifxError::Config(message)) if message.contains("1.5")));
+        assert!(matches!(Light::drift_peaks((f64::NAN, 0.8), 3), Err(LifxError::Config(_))));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_breathe_drift_invalid() {
+        use crate::test_util::{fixtures, MockTransport};
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let lamp = fixtures::lights().remove(0);
+        let mut endless = BreatheEffect::new();
+        endless.period = Some(f64::INFINITY);
+        let result = lamp.async_breathe_drift(mock.config(), endless, (0.2, 0.8), 3).await;
+        assert!(matches!(result, Err(LifxError::Config(message)) if message.contains("period * cycles")));
+
+        let mut huge = BreatheEffect::new();
+        huge.period = Some(1e300);
+        huge.cycles = Some(1e300);
+        assert!(matches!(lamp.async_breathe_drift(mock.config(), huge, (0.2, 0.8), 3).await, Err(LifxError::Config(_))));
+
+        let result = lamp.async_breathe_drift(mock.config(), BreatheEffect::new(), (0.2, 1.2), 3).await;
+        assert!(matches!(result, Err(LifxError::Config(message)) if message.contains("between 0 and 1")));
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_validate_cached_skips_network() {
+        // Nothing listens on the discard port, so any network call would fail
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        let warm = Color { kelvin: Some(2700), ..Default::default() };
+        Color::cache_validation(&unreachable, "test cached warm".to_string(), &warm);
+
+        assert_eq!(Color::validate_cached(unreachable.clone(), "test cached warm".to_string()).unwrap(), warm);
+        assert_eq!(Color::validate_cached(unreachable.clone(), "test cached warm".to_string()).unwrap(), warm);
+        assert!(Color::validate_cached(unreachable.clone(), "test uncached".to_string()).is_err());
+
+        // Another account's lookups aren't served from this one's
+        let other_account = LifxConfig { access_token: "other".to_string(), ..unreachable.clone() };
+        assert!(Color::validate_cached(other_account, "test cached warm".to_string()).is_err());
+        let other_server = LifxConfig { api_endpoints: vec!["http://127.0.0.1:7".to_string()], ..unreachable };
+        assert!(Color::validate_cached(other_server, "test cached warm".to_string()).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_scene_cache() {
+        use crate::test_util::MockTransport;
+        use crate::test_util::Request;
+
+        let mock = MockTransport::new();
+        let scene = |name: &str| Scene { uuid: format!("uuid-{}", name), name: name.to_string(), ..Default::default() };
+        mock.set_scenes(vec![scene("Movie Night"), scene("Morning")]);
+        // Cached per access token, so this test's token keeps it apart from other tests
+        let config = LifxConfig { access_token: "scene-cache".to_string(), ..mock.config() };
+
+        assert_eq!(Scene::find_by_name(config.clone(), "Morning").unwrap().unwrap().uuid, "uuid-Morning");
+        assert_eq!(Scene::find_by_name(config.clone(), "Evening").unwrap(), None);
+        assert_eq!(Scene::list_cached(config.clone()).unwrap().len(), 2);
+        assert_eq!(mock.requests(), vec![Request::ListScenes]);
+
+        let uncached = LifxConfig { scene_cache_ttl: Some(0.0), ..config.clone() };
+        Scene::list_cached(uncached).unwrap();
+        Scene::clear_scene_cache();
+        Scene::list_cached(config).unwrap();
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_scene_cache_invalid_ttl() {
+        use crate::test_util::MockTransport;
+
+        let mock = MockTransport::new();
+        for ttl in [-1.0, f64::NAN, f64::INFINITY] {
+            let config = LifxConfig { access_token: "scene-cache-ttl".to_string(), scene_cache_ttl: Some(ttl), ..mock.config() };
+            assert!(matches!(Scene::list_cached(config.clone()), Err(LifxError::Config(message)) if message.contains("scene_cache_ttl")));
+            assert!(matches!(config.validate(), Err(LifxError::Config(_))));
+        }
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_validation_cache_skips_errors() {
+        let invalid = Color { error: Some("Unable to parse color".to_string()), ..Default::default() };
+        let config = LifxConfig::default();
+        Color::cache_validation(&config, "test invalid color".to_string(), &invalid);
+        assert_eq!(Color::cached_validation(&config, "test invalid color"), None);
+    }
+
+    #[test]
+    fn test_diff_lights() {
+        let light = |id: &str, power: &str| Light {
+            id: id.to_string(),
+            power: power.to_string(),
+            connected: true,
+            ..Default::default()
+        };
+        let mut seen_later = light("a", "on");
+        seen_later.seconds_since_seen = 30;
+        let old = vec![light("a", "on"), light("b", "on"), light("c", "off")];
+        let new = vec![seen_later, light("b", "off"), light("d", "on")];
+
+        let diff = diff_lights(&old, &new);
+        assert_eq!(diff.added, vec![light("d", "on")]);
+        assert_eq!(diff.removed, vec![light("c", "off")]);
+        assert_eq!(diff.changed, vec![(light("b", "on"), light("b", "off"))]);
+
+        let mut disconnected = light("a", "on");
+        disconnected.connected = false;
+        assert_eq!(diff_lights(&[light("a", "on")], &[disconnected]).changed.len(), 1);
+        assert!(diff_lights(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_same_group_and_location() {
+        let light = |group: &str, location: &str| Light {
+            group: Group { id: group.to_string(), name: String::new() },
+            location: Location { id: location.to_string(), name: String::new() },
+            ..Default::default()
+        };
+        let kitchen = light("g1", "home");
+        assert!(kitchen.same_group(&light("g1", "home")));
+        assert!(kitchen.same_location(&light("g2", "home")));
+        assert!(!kitchen.same_group(&light("g2", "home")));
+        assert!(!kitchen.same_location(&light("g1", "office")));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_schedule_state() {
+        // Nothing listens on the discard port, so a fired change comes back as a connection error
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        let light = Light { id: "d073d5".to_string(), ..Default::default() };
+        let soon = SystemTime::now() + Duration::from_millis(20);
+
+        let fired = light.schedule_state(unreachable.clone(), State::new(), soon);
+        assert!(matches!(fired.result().await, Some(Err(_))));
+
+        let cancelled = light.schedule_state(unreachable, State::new(), soon + Duration::from_secs(60));
+        cancelled.cancel();
+        assert!(cancelled.result().await.is_none());
+    }
+
+    #[test]
+    fn test_visible_color() {
+        let light = |power: &str, connected: bool| Light {
+            power: power.to_string(),
+            connected,
+            color: Color { hue: Some(240.0), ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(light("on", true).visible_color().and_then(|c| c.hue), Some(240.0));
+        assert_eq!(light("off", true).visible_color(), None);
+        assert_eq!(light("on", false).visible_color(), None);
+        assert_eq!(light("off", false).visible_color(), None);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_effect_window() {
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        let light = Light { id: "d073d5".to_string(), ..Default::default() };
+        let start = SystemTime::now() + Duration::from_millis(20);
+        let end = start + Duration::from_secs(60);
+
+        // The start request fails, so the window ends early with that error
+        let window = light.effect_window(unreachable.clone(), AnyEffect::Pulse(PulseEffect::new()), start, end);
+        assert!(!window.is_finished());
+        assert!(matches!(window.result().await, Some(Err(_))));
+
+        let cancelled = light.effect_window(unreachable, AnyEffect::Flame(FlameEffect::new()), start + Duration::from_secs(60), end);
+        cancelled.cancel();
+        assert!(cancelled.result().await.is_none());
+    }
+
+    #[test]
+    fn test_partition_by_capability() {
+        let light = |id: &str, has_matrix: bool, has_color: bool| {
+            let mut light = Light { id: id.to_string(), ..Default::default() };
+            light.product.capabilities.has_matrix = has_matrix;
+            light.product.capabilities.has_color = has_color;
+            light
+        };
+        let lights = vec![light("tile", true, true), light("white", false, false), light("color", false, true)];
+
+        let (supported, unsupported) = Light::partition_by_capability(&lights, Capability::Matrix);
+        assert_eq!(supported.iter().map(|l| l.id.as_str()).collect::<Vec<_>>(), vec!["tile"]);
+        assert_eq!(unsupported.iter().map(|l| l.id.as_str()).collect::<Vec<_>>(), vec!["white", "color"]);
+
+        let (supported, unsupported) = Light::partition_by_capability(&lights, Capability::Color);
+        assert_eq!(supported.len(), 2);
+        assert_eq!(unsupported[0].id, "white");
+        assert!(Light::partition_by_capability(&lights, Capability::Hev).0.is_empty());
+    }
+
+    #[test]
+    fn test_with_endpoints() {
+        let config = LifxConfig {
+            access_token: "token".to_string(),
+            api_endpoints: vec!["https://api.lifx.com".to_string()],
+            default_duration: Some(0.4),
+            ..Default::default()
+        };
+        let test_config = config.with_endpoints(&["http://localhost:8089", "http://localhost:8090"]);
+
+        assert_eq!(test_config.api_endpoints, vec!["http://localhost:8089", "http://localhost:8090"]);
+        assert_eq!(test_config.access_token, "token");
+        assert_eq!(test_config.default_duration, Some(0.4));
+        assert_eq!(config.api_endpoints, vec!["https://api.lifx.com"]);
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_config_builder() {
+        let config = LifxConfig::builder()
+            .access_token("token")
+            .endpoint("https://api.lifx.com")
+            .endpoint("http://localhost:8089")
+            .timeout(5.0)
+            .user_agent("porch-lights/1.0")
+            .header("X-Request-Source", "porch")
+            .build()
+            .unwrap();
+        assert_eq!(config.api_endpoints, vec!["https://api.lifx.com", "http://localhost:8089"]);
+        assert_eq!(config.timeout, Some(5.0));
+        assert_eq!(config.http.headers, vec![("X-Request-Source".to_string(), "porch".to_string())]);
+
+        assert!(matches!(LifxConfig::builder().proxy("not a url").build(), Err(LifxError::Config(_))));
+        assert!(matches!(LifxConfig::builder().header("X-Bad", "line\nbreak").build(), Err(LifxError::Config(_))));
+        assert!(matches!(LifxConfig::builder().root_certificate("not a certificate").build(), Err(LifxError::Config(_))));
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_config_builder_validation() {
+        let builder = || LifxConfig::builder().access_token("token").endpoint("https://api.lifx.com");
+        let invalid = |result: Result<LifxConfig, LifxError>, field: &str| {
+            matches!(result, Err(LifxError::Config(message)) if message.contains(field))
+        };
+        assert!(builder().timeout(0.5).default_duration(2.0).build().is_ok());
+        assert!(invalid(LifxConfig::builder().access_token("token").build(), "no API endpoints"));
+        assert!(invalid(builder().timeout(0.0).build(), "timeout"));
+        assert!(invalid(builder().timeout(-1.0).build(), "timeout"));
+        assert!(invalid(builder().timeout(f64::NAN).build(), "timeout"));
+        assert!(builder().default_duration(0.0).build().is_ok());
+        assert!(invalid(builder().default_duration(-2.0).build(), "default_duration"));
+        assert!(invalid(builder().default_duration(f64::NAN).build(), "default_duration"));
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_config_from_vars() {
+        let vars = |token: Option<&'static str>, endpoints: Option<&'static str>| {
+            move |name: &str| Ok(match name {
+                "LIFX_ACCESS_TOKEN" => token.map(String::from),
+                "LIFX_API_ENDPOINTS" => endpoints.map(String::from),
+                _ => None,
+            })
+        };
+        let config = LifxConfig::from_vars(vars(Some("xxx"), Some("http://localhost:8089, https://api.lifx.com,"))).unwrap();
+        assert_eq!(config.access_token, "xxx");
+        assert_eq!(config.api_endpoints, vec!["http://localhost:8089", "https://api.lifx.com"]);
+
+        let config = LifxConfig::from_vars(vars(Some("xxx"), None)).unwrap();
+        assert_eq!(config.api_endpoints, vec!["https://api.lifx.com"]);
+        assert!(matches!(LifxConfig::from_vars(vars(None, None)), Err(LifxError::Config(_))));
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_config_from_vars_without_endpoints() {
+        for endpoints in ["", ",", " , "] {
+            let vars = |name: &str| Ok(match name {
+                "LIFX_ACCESS_TOKEN" => Some("xxx".to_string()),
+                "LIFX_API_ENDPOINTS" => Some(endpoints.to_string()),
+                _ => None,
+            });
+            let result = LifxConfig::from_vars(vars);
+            assert!(matches!(&result, Err(LifxError::Config(message)) if message.contains("LIFX_API_ENDPOINTS")), "{:?}: {:?}", endpoints, result);
+            assert_eq!(result.unwrap_err().to_string(), "invalid config: LIFX_API_ENDPOINTS: no API endpoints configured");
+        }
+    }
+
+    #[test]
+    fn test_config_redacts_token() {
+        let config = LifxConfig { access_token: "c87c73a896b554367fac61f71dd3656af8d93a525a4e87df5952c6078a89d192".to_string(), ..Default::default() };
+        let debug = format!("{:?}", config);
+        assert!(debug.contains(r#"access_token: "[redacted]""#), "{}", debug);
+        assert!(!debug.contains("c87c73a8"));
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("c87c73a8"));
+        let saved: LifxConfig = serde_json::from_str(&serde_json::to_string(&config.exposing_token()).unwrap()).unwrap();
+        assert_eq!(saved, config);
+        // Exposing is scoped to the wrapper
+        assert_eq!(serde_json::to_string(&config).unwrap(), json);
+        assert!(format!("{:?}", LifxConfig::default()).contains(r#"access_token: """#));
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let at = |seconds: u64| Some(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(parse_rfc3339("2015-12-03T05:53:18Z"), at(1_449_121_998));
+        assert_eq!(parse_rfc3339("2015-12-03T15:53:18+10:00"), at(1_449_121_998));
+        assert_eq!(parse_rfc3339("2000-02-29T23:59:59-00:30"), at(951_870_599));
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00.25Z"), Some(UNIX_EPOCH + Duration::from_millis(250)));
+        assert_eq!(parse_rfc3339("2015-13-03T05:53:18Z"), None);
+        assert_eq!(parse_rfc3339("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse_rfc3339_malformed() {
+        // A multi-byte character straddling the ninth byte of the fraction
+        assert_eq!(parse_rfc3339("2015-12-03T05:53:18.12345678\u{e9}Z"), None);
+        assert_eq!(parse_rfc3339("2015-12-03T05:53:18.Z"), None);
+        assert_eq!(parse_rfc3339("2015-12-03T05:53:18+99999999999999999:00"), None);
+        assert_eq!(parse_rfc3339("999999999999999999-12-03T05:53:18Z"), None);
+        assert_eq!(parse_rfc3339("2015-12-03T05:53:18.1234567891Z"), parse_rfc3339("2015-12-03T05:53:18.123456789Z"));
+    }
+
+    #[test]
+    fn test_seen_within() {
+        let mut light = Light { seconds_since_seen: 45, ..Default::default() };
+        assert!(light.seen_within(Duration::from_secs(60)));
+        assert!(!light.seen_within(Duration::from_secs(30)));
+
+        light.last_seen = "2015-12-03T05:53:18Z".to_string();
+        assert!(!light.seen_within(Duration::from_secs(60)));
+        assert_eq!(Scene { created_at: 1_449_121_998, ..Default::default() }.created_time(), light.last_seen_time().unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_light_convenience_methods() {
+        use crate::test_util::{fixtures, MockTransport};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::new();
+        let light = fixtures::light("d073d5000001", "Lamp");
+        light.turn_on(mock.config()).unwrap();
+        light.set_brightness(mock.config(), 0.5).unwrap();
+        light.set_color(mock.config(), Kelvin::Warm).unwrap();
+
+        let states: Vec<State> = mock.operations().into_iter().map(|(selector, operation)| {
+            assert_eq!(selector, "id:d073d5000001");
+            match operation {
+                Operation::SetState(state) => state,
+                other => panic!("expected a state, got {:?}", other),
+            }
+        }).collect();
+        assert_eq!(states, vec![
+            State::builder().power_on().build(),
+            State::builder().brightness(0.5).build(),
+            State::builder().color(Kelvin::Warm).build(),
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_capability_checks() {
+        use crate::test_util::{fixtures, MockTransport};
+
+        let mock = MockTransport::new();
+        let bulb = fixtures::light("d073d5000001", "Lamp");
+        let result = bulb.morph_effect(mock.config(), MorphEffect::new());
+        assert!(matches!(result, Err(LifxError::Unsupported(message)) if message == "the morph effect on Lamp (LIFX Color), which has no Matrix support"));
+        assert!(matches!(bulb.clean(mock.config(), Clean::new()), Err(LifxError::Unsupported(_))));
+        assert!(mock.requests().is_empty());
+
+        let mut tile = bulb.clone();
+        tile.product.capabilities.has_matrix = true;
+        tile.flame_effect(mock.config(), FlameEffect::new()).unwrap();
+        let skipping = LifxConfig { skip_capability_checks: true, ..mock.config() };
+        bulb.move_effect(skipping, MoveEffect::new()).unwrap();
+        assert_eq!(mock.operations().len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_morph_validate_for() {
+        use crate::test_util::{fixtures, MockTransport};
+
+        let bulb = fixtures::light("d073d5000001", "Lamp");
+        let mut tile = fixtures::light("d073d5000002", "Tile");
+        tile.product.name = "LIFX Tile".to_string();
+        tile.product.capabilities.has_matrix = true;
+        assert_eq!((MorphEffect::max_palette_for(&bulb), MorphEffect::max_palette_for(&tile)), (None, Some(16)));
+
+        let mut morph_effect = MorphEffect::new();
+        morph_effect.palette = Some(vec!["red".to_string(); 17]);
+        let result = morph_effect.validate_for(&tile);
+        assert!(matches!(result, Err(LifxError::Unsupported(message)) if message == "a morph palette of 17 colors on Tile (LIFX Tile), which holds at most 16"));
+        assert!(matches!(morph_effect.validate_for(&bulb), Err(LifxError::Unsupported(message)) if message.contains("no Matrix support")));
+
+        let mock = MockTransport::new();
+        assert!(matches!(tile.morph_effect(mock.config(), morph_effect.clone()), Err(LifxError::Unsupported(_))));
+        assert!(mock.requests().is_empty());
+
+        morph_effect.palette = Some(vec!["red".to_string(), "reddish".to_string()]);
+        assert!(matches!(morph_effect.validate_for(&tile), Err(LifxError::Palette(_))));
+        morph_effect.palette = Some(vec!["red".to_string(); 16]);
+        assert!(morph_effect.validate_for(&tile).is_ok());
+        tile.morph_effect(mock.config(), morph_effect).unwrap();
+        assert_eq!(mock.operations().len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_invalid_palette_is_not_sent() {
+        use crate::test_util::MockTransport;
+
+        let mock = MockTransport::new();
+        let morph_effect = MorphEffect { palette: Some(Vec::new()), ..Default::default() };
+        let result = Light::morph_effect_by_selector(mock.config(), "all".to_string(), morph_effect);
+        assert!(matches!(result, Err(LifxError::Palette(PaletteError::Empty))));
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_auto_off() {
+        use crate::test_util::MockTransport;
+        use crate::transport::Operation;
+
+        let mock = MockTransport::new();
+        let pulse = PulseEffect::builder().color("red").cycles(100.0).with_auto_off(Duration::from_millis(30)).build();
+        Light::pulse_effect_by_selector(mock.config(), "label:Lamp".to_string(), pulse.clone()).unwrap();
+        assert_eq!(mock.operations(), vec![("label:Lamp".to_string(), Operation::Pulse(pulse))]);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(mock.operations()[1], ("label:Lamp".to_string(), Operation::EffectsOff(EffectsOff::new())));
+
+        // Nothing to stop if the effect didn't start
+        mock.clear_requests();
+        mock.push_response(Err(LifxError::Api("Invalid token".to_string())));
+        let breathe = BreatheEffect::new().with_auto_off(Duration::from_millis(10));
+        assert!(Light::breathe_by_selector_effect(mock.config(), "all".to_string(), breathe).is_err());
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(mock.operations().len(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_auto_off() {
+        use crate::test_util::MockTransport;
+        use crate::transport::Operation;
+
+        let mock = MockTransport::new();
+        let flame_effect = FlameEffect::new().with_auto_off(Duration::from_millis(30));
+        Light::async_flame_effect_by_selector(mock.config(), "group:Lounge".to_string(), flame_effect).await.unwrap();
+        assert_eq!(mock.operations().len(), 1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock.operations()[1], ("group:Lounge".to_string(), Operation::EffectsOff(EffectsOff::new())));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_light_convenience_methods() {
+        use crate::test_util::{fixtures, MockTransport};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::new();
+        fixtures::light("d073d5000002", "Ceiling").async_turn_off(mock.config()).await.unwrap();
+        assert_eq!(mock.operations(), vec![("id:d073d5000002".to_string(), Operation::SetState(State::builder().power_off().build()))]);
+    }
+
+    #[test]
+    fn test_partition_results() {
+        let result = |id: &str, status: &str| LiFxResult { id: id.to_string(), label: String::new(), status: status.to_string(), ..Default::default() };
+        let mut results = LiFxResults {
+            results: Some(vec![result("a", "ok"), result("b", "offline"), result("c", "timed_out"), result("d", "ok")]),
+            ..Default::default()
+        };
+        let ids = |results: Vec<&LiFxResult>| results.into_iter().map(|result| result.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(results.succeeded()), vec!["a", "d"]);
+        assert_eq!(ids(results.offline()), vec!["b"]);
+        assert_eq!(ids(results.timed_out()), vec!["c"]);
+        assert_eq!(ids(results.failed()), vec!["b", "c"]);
+        assert!(!results.is_fully_ok());
+
+        results.results.as_mut().unwrap().retain(|result| result.is_ok());
+        assert!(results.is_fully_ok());
+        results.error = Some("unauthorized".to_string());
+        assert!(!results.is_fully_ok());
+        assert!(LiFxResults::default().is_fully_ok());
+    }
+
+    #[test]
+    fn test_tolerant_light() {
+        let light: Light = serde_json::from_str(r#"{
+            "id": "d073d5000001",
+            "uuid": null,
+            "label": "Lamp",
+            "connected": true,
+            "power": "on",
+            "color": {"hue": 0, "saturation": 0, "kelvin": 3500},
+            "brightness": 1,
+            "group": {"id": "1c8de82b81f445e7cfaafae49b259c71", "name": "Living Room"},
+            "product": {"name": "LIFX Color", "capabilities": {"has_color": true}},
+            "seconds_since_seen": null
+        }"#).unwrap();
+        assert_eq!((light.uuid.as_str(), light.seconds_since_seen), ("", 0));
+        assert_eq!(light.location, Location::default());
+        assert!(light.product.capabilities.has_color);
+        assert_eq!(light.product.identifier, "");
+
+        let minimal: Light = serde_json::from_str(r#"{"id": "d073d5000002"}"#).unwrap();
+        assert_eq!(minimal, Light { id: "d073d5000002".to_string(), ..Default::default() });
+        assert!(serde_json::from_str::<Light>(r#"{"label": "No id"}"#).is_err());
+    }
+
+    #[test]
+    fn test_zones_and_effect() {
+        let light: Light = serde_json::from_str(r#"{
+            "id": "d073d5000001",
+            "effect": "MOVE",
+            "zones": {"count": 2, "zones": [
+                {"zone": 0, "hue": 120, "saturation": 1, "brightness": 0.5, "kelvin": 3500},
+                {"zone": 1, "hue": 240, "saturation": 1, "brightness": 0.5, "kelvin": 3500}
+            ]}
+        }"#).unwrap();
+        assert_eq!(light.effect, Some(RunningEffect::Move(MoveEffect::new())));
+        let zones = light.zones.clone().unwrap();
+        assert_eq!(zones.count, 2);
+        assert_eq!((zones.zones[1].index, zones.zones[1].color.hue), (1, Some(240.0)));
+        assert!(light.extra.is_empty());
+        assert_eq!(serde_json::to_value(&light).unwrap()["effect"], "MOVE");
+
+        let tile: Light = serde_json::from_str(r#"{"id": "d073d5000002", "effect": "SKY"}"#).unwrap();
+        assert_eq!(tile.effect, Some(RunningEffect::Other("SKY".to_string())));
+        assert!(!RunningEffect::Off.is_running());
+        assert_eq!(Light::default().zones, None);
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        let light: Light = serde_json::from_str(r#"{"id": "d073d5000001", "firmware": "3.70", "chain": {"count": 5}}"#).unwrap();
+        assert_eq!(light.extra["firmware"], "3.70");
+        assert_eq!(light.extra["chain"]["count"], 5);
+        assert_eq!(serde_json::to_value(&light).unwrap()["firmware"], "3.70");
+
+        let results: LiFxResults = serde_json::from_str(r#"{"results": [{"id": "d073d5000001", "label": "Lamp", "status": "ok", "duration": 1.5}], "warnings": []}"#).unwrap();
+        assert_eq!(results.results.unwrap()[0].extra["duration"], 1.5);
+        assert!(results.extra.contains_key("warnings"));
+        let scene: Scene = serde_json::from_str(r#"{"uuid": "abc", "name": "Evening", "account": {"uuid": "def"}, "states": [], "created_at": 0, "updated_at": 0, "favorite": true}"#).unwrap();
+        assert_eq!(scene.extra["favorite"], true);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_group_operations() {
+        use crate::test_util::{fixtures, MockTransport};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let group = fixtures::light("d073d5000001", "Lamp").group;
+        let results = group.toggle(mock.config(), Toggle::new()).unwrap();
+        assert_eq!(results.results.unwrap().len(), 2);
+        assert_eq!(mock.operations(), vec![("group_id:1c8de82b81f445e7cfaafae49b259c71".to_string(), Operation::Toggle(Toggle::new()))]);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_location_operations() {
+        use crate::test_util::{fixtures, MockTransport};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let home = fixtures::light("d073d5000001", "Lamp").location;
+        assert_eq!(home.lights(mock.config()).unwrap().len(), 3);
+        assert_eq!(fixtures::lights()[2].group.lights(mock.config()).unwrap()[0].label, "Bedside");
+
+        let state = State::builder().power_off().build();
+        home.set_state(mock.config(), state.clone()).unwrap();
+        assert_eq!(mock.operations(), vec![("location_id:1d6fe8ef0fde4c6d77b0012dc736662c".to_string(), Operation::SetState(state))]);
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_list_groups_and_locations() {
+        use crate::test_util::{fixtures, MockTransport};
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let groups = Group::list_all(mock.config()).unwrap();
+        let summary: Vec<(&str, usize)> = groups.iter().map(|members| (members.of.name.as_str(), members.count())).collect();
+        assert_eq!(summary, vec![("Living Room", 2), ("Bedroom", 1)]);
+        assert_eq!(groups[0].light_ids, vec!["d073d5000001", "d073d5000002"]);
+
+        let locations = Location::from_lights(&fixtures::lights());
+        assert_eq!(locations.len(), 1);
+        assert_eq!((locations[0].of.name.as_str(), locations[0].count()), ("Home", 3));
+        assert!(Group::from_lights(&[]).is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_group_operations() {
+        use crate::test_util::{fixtures, MockTransport};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::new();
+        let group = fixtures::light("d073d5000001", "Lamp").group;
+        group.async_effects_off(mock.config(), EffectsOff::new()).await.unwrap();
+        assert_eq!(mock.operations(), vec![(group.selector().to_string(), Operation::EffectsOff(EffectsOff::new()))]);
+        let location = fixtures::light("d073d5000001", "Lamp").location;
+        assert!(location.async_lights(mock.config()).await.unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_activate_scene() {
+        use crate::test_util::MockTransport;
+        use crate::transport::Operation;
+
+        let mock = MockTransport::new();
+        let scene = Scene { uuid: "0a1b2c".to_string(), name: "Movie Night".to_string(), ..Default::default() };
+        let activation = SceneActivation::builder().ignore(vec![SceneIgnore::Power]).fast(true).build();
+        scene.activate(mock.config(), activation.clone()).unwrap();
+        assert_eq!(mock.operations(), vec![("scene_id:0a1b2c".to_string(), Operation::ActivateScene(activation))]);
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let config = LifxConfig { access_token: "token".to_string(), ..Default::default() };
+        let bounded = config.with_timeout(2.5);
+
+        assert_eq!(bounded.timeout, Some(2.5));
+        assert_eq!(bounded.access_token, "token");
+        assert_eq!(config.timeout, None);
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_find_present() {
+        let light = |id: &str, connected: bool| Light { id: id.to_string(), connected, ..Default::default() };
+
+        let before = vec![light("other", true)];
+        assert_eq!(Light::find_present(&before, "new"), None);
+
+        let offline = vec![light("other", true), light("new", false)];
+        assert_eq!(Light::find_present(&offline, "new"), None);
+
+        let online = vec![light("other", true), light("new", true)];
+        assert_eq!(Light::find_present(&online, "new"), Some(light("new", true)));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_wait_until_present() {
+        use crate::test_util::{fixtures, MockTransport, Request};
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        // Porch is left out of the first three polls and turns up on the fourth
+        for _ in 0..3 {
+            mock.push_lights(fixtures::lights());
+        }
+        let mut onboarded = fixtures::lights();
+        onboarded.push(fixtures::light("d073d5000004", "Porch"));
+        mock.push_lights(onboarded);
+        let light = Light::async_wait_until_present(mock.config(), "d073d5000004", Duration::from_secs(5), Duration::from_millis(10)).await.unwrap();
+        assert_eq!(light.label, "Porch");
+        assert_eq!(mock.requests(), vec![Request::List("all".to_string()); 4]);
+
+        let missing = Light::async_wait_until_present(mock.config(), "d073d5000005", Duration::from_millis(30), Duration::from_millis(10)).await;
+        assert!(matches!(missing, Err(LifxError::NotFound(message)) if message.contains("d073d5000005")));
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_wait_until_present() {
+        use crate::test_util::{fixtures, MockTransport, Request};
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        // Bedside is listed on the first two polls, but not connected until the third
+        let mut offline = fixtures::lights();
+        offline[2].connected = false;
+        mock.push_lights(offline.clone());
+        mock.push_lights(offline);
+        mock.push_lights(fixtures::lights());
+        let light = Light::wait_until_present(mock.config(), "d073d5000003", Duration::from_secs(5), Duration::from_millis(10)).unwrap();
+        assert!(light.connected);
+        assert_eq!(mock.requests(), vec![Request::List("all".to_string()); 3]);
+
+        let missing = Light::wait_until_present(mock.config(), "d073d5000005", Duration::ZERO, Duration::from_millis(10));
+        assert!(matches!(missing, Err(LifxError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_apply_verified() {
+        use crate::test_util::{fixtures, MockTransport, Request};
+        use crate::transport::Operation;
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let bedside = fixtures::lights().remove(2);
+        let state = State::builder().power_on().brightness(0.4).duration(0.05).build();
+        // Still off on the first poll, then reporting the state
+        let mut reported = fixtures::lights();
+        reported[2].power = "on".to_string();
+        reported[2].brightness = 0.41;
+        mock.push_lights(fixtures::lights());
+        mock.push_lights(reported);
+        let results = bedside.async_apply_verified(mock.config(), state.clone(), 0.02, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(results.results.unwrap()[0].id, "d073d5000003");
+        assert_eq!(mock.requests(), vec![
+            Request::Send("id:d073d5000003".to_string(), Operation::SetState(state)),
+            Request::List("id:d073d5000003".to_string()),
+            Request::List("id:d073d5000003".to_string()),
+        ]);
+
+        // Still 0.41, off by more than the tolerance
+        let dimmer = State::builder().brightness(0.2).duration(0.0).build();
+        let missed = bedside.async_apply_verified(mock.config(), dimmer, 0.02, Duration::from_millis(50)).await;
+        assert!(matches!(missed, Err(LifxError::VerificationFailed(message)) if message.contains("d073d5000003")));
+    }
+
+    #[test]
+    #[cfg(feature = "blocking")]
+    fn test_apply_verified() {
+        use crate::test_util::{fixtures, MockTransport, Request};
+
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let lamp = fixtures::lights().remove(0);
+        let state = State::builder().color("hue:200 saturation:1").duration(0.05).build();
+        let mut reported = fixtures::lights();
+        reported[0].color = Color { hue: Some(201.0), saturation: Some(1.0), kelvin: Some(3500), ..Default::default() };
+        mock.push_lights(fixtures::lights());
+        mock.push_lights(fixtures::lights());
+        mock.push_lights(reported);
+        assert!(lamp.apply_verified(mock.config(), state, 0.01, Duration::from_secs(5)).is_ok());
+        let polls = mock.requests().into_iter().filter(|request| matches!(request, Request::List(_))).count();
+        assert_eq!(polls, 3);
+
+        let off = State::builder().power_off().duration(0.0).build();
+        let started = Instant::now();
+        let missed = lamp.apply_verified(mock.config(), off, 0.01, Duration::from_millis(50));
+        assert!(matches!(missed, Err(LifxError::VerificationFailed(_))));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_running_effect_params() {
+        let reported = serde_json::json!({
+            "id": "d073d5000002",
+            "effect": {"type": "MORPH", "period": 5.0, "duration": 60.0, "palette": ["red", "hue:240 saturation:1"]}
+        });
+        let tile: Light = serde_json::from_value(reported.clone()).unwrap();
+        let mut morph_effect = MorphEffect::new();
+        morph_effect.period = Some(5.0);
+        morph_effect.duration = Some(60.0);
+        morph_effect.palette = Some(vec!["red".to_string(), "hue:240 saturation:1".to_string()]);
+        let effect = tile.effect.clone().unwrap();
+        assert_eq!(effect.as_morph(), Some(morph_effect.clone()));
+        assert_eq!((effect.as_move(), effect.as_flame()), (None, None));
+        assert_eq!(serde_json::to_value(&tile).unwrap()["effect"], reported["effect"]);
+        assert_eq!(RunningEffect::Morph(morph_effect.clone()).as_morph(), Some(morph_effect));
+
+        let strip: Light = serde_json::from_value(serde_json::json!({
+            "id": "d073d5000001",
+            "effect": {"type": "move", "direction": "backward", "period": 2.5, "cycles": 3.0}
+        })).unwrap();
+        let move_effect = strip.effect.as_ref().and_then(RunningEffect::as_move).unwrap();
+        assert_eq!((move_effect.direction.as_deref(), move_effect.period, move_effect.cycles), (Some("backward"), Some(2.5), Some(3.0)));
+        let resent = serde_json::to_value(RunningEffect::Move(move_effect)).unwrap();
+        assert_eq!(serde_json::from_value::<RunningEffect>(resent).unwrap(), strip.effect.unwrap());
+
+        let candle: Light = serde_json::from_value(serde_json::json!({"id": "d073d5000003", "effect": {"type": "FLAME", "period": 4}})).unwrap();
+        assert_eq!(candle.effect.unwrap().as_flame().unwrap().period, Some(4));
+        // A param of the wrong type leaves the builder empty rather than failing the list
+        let odd: RunningEffect = serde_json::from_value(serde_json::json!({"type": "MORPH", "period": "slow"})).unwrap();
+        assert_eq!(odd, RunningEffect::Morph(MorphEffect::new()));
+    }
+
+    #[test]
+    fn test_scene_from_lights() {
+        let light = |id: &str, power: &str, brightness: f64| Light {
+            id: id.to_string(),
+            power: power.to_string(),
+            brightness,
+            color: Color { kelvin: Some(2700), ..Default::default() },
+            ..Default::default()
+        };
+        let scene = Scene::from_lights("Evening", &[light("a", "on", 0.5), light("b", "off", 1.0)]);
+
+        assert_eq!(scene.name, "Evening");
+        assert_eq!(scene.states.len(), 2);
+        assert_eq!(scene.states[0].selector, Some("id:a".to_string()));
+        assert_eq!(scene.states[0].power, Some("on".to_string()));
+        assert_eq!(scene.states[0].brightness, Some(0.5));
+        assert_eq!(scene.states[0].color, Some("kelvin:2700".to_string()));
+        assert_eq!(scene.states[1].power, Some("off".to_string()));
+        assert_eq!(scene.created_at, scene.updated_at);
+        assert!(scene.created_at > 0);
+    }
+
+    #[test]
+    fn test_generate_uuid() {
+        let uuid = generate_uuid();
+        let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+        assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+        assert_eq!(uuid.chars().nth(14), Some('4'));
+        assert_ne!(uuid, generate_uuid());
+    }
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn test_shuffled_states() {
+        let lights: Vec<Light> = ["a", "b", "c"].iter()
+            .map(|id| Light { id: id.to_string(), ..Default::default() })
+            .collect();
+
+        let states = Light::shuffled_states(&lights, 1.0, 0.8, 0.5, Some(42));
+        let colors: Vec<String> = states.iter().map(|state| state.color.clone().unwrap()).collect();
+        assert_eq!(colors, vec!["hue:253 saturation:1", "hue:91 saturation:1", "hue:18 saturation:1"]);
+        assert_eq!(states[1].selector, Some("id:b".to_string()));
+        assert_eq!(states[1].brightness, Some(0.8));
+        assert_eq!(states[1].duration, Some(0.5));
+
+        assert_eq!(Light::shuffled_states(&lights, 1.0, 0.8, 0.5, Some(42)), states);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_debounced_setter() {
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        let setter = DebouncedSetter::new(unreachable, Duration::from_millis(20));
+
+        // Superseded values are dropped; only the last one is actually sent (and fails to connect)
+        let first = setter.set_brightness_debounced("all".to_string(), 0.1);
+        let second = setter.set_brightness_debounced("all".to_string(), 0.2);
+        let other = setter.set_brightness_debounced("group:Lounge".to_string(), 0.5);
+        let last = setter.set_brightness_debounced("all".to_string(), 0.3);
+        assert!(first.result().await.is_none());
+        assert!(second.result().await.is_none());
+        assert!(matches!(last.result().await, Some(Err(_))));
+        assert!(matches!(other.result().await, Some(Err(_))));
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn test_powered_on_off() {
+        let light = |id: &str, power: &str, connected: bool| Light {
+            id: id.to_string(),
+            power: power.to_string(),
+            connected,
+            ..Default::default()
+        };
+        let lights = vec![light("on", "on", true), light("stale", "on", false), light("off", "off", true), light("gone", "off", false)];
+        let ids = |lights: Lights| lights.into_iter().map(|light| light.id).collect::<Vec<_>>();
+
+        // A disconnected light reporting "on" is neither listed as on nor as off
+        assert_eq!(ids(Light::powered_on(lights.clone())), vec!["on"]);
+        assert_eq!(ids(Light::powered_off(lights)), vec!["off", "gone"]);
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    #[test]
+    fn test_light_ops_resolve() {
+        let light = |id: &str, power: &str, group: &str| Light {
+            id: id.to_string(),
+            power: power.to_string(),
+            connected: true,
+            group: Group { id: format!("{}-id", group), name: group.to_string() },
+            ..Default::default()
+        };
+        let lights = vec![light("a", "on", "Lounge"), light("b", "off", "Lounge"), light("c", "on", "Lounge"), light("d", "on", "Kitchen")];
+        let config = LifxConfig::default();
+
+        let dim = config.lights().filter_on().in_group("Lounge").set_brightness(0.5);
+        assert_eq!(dim.ops.resolve(&lights), Some("id:a,id:c".to_string()));
+        let mut expected = State::new();
+        expected.brightness = Some(0.5);
+        assert_eq!(dim.op, LightOp::SetState(expected));
+
+        let toggle = config.lights().filter_off().in_group("Kitchen").toggle();
+        assert_eq!(toggle.ops.resolve(&lights), None);
+        assert_eq!(toggle.op, LightOp::Toggle(Toggle::new()));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_light_ops_run() {
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        // Listing the lights fails, so the filtered chain stops with that error
+        assert!(unreachable.lights().filter_on().breathe(BreatheEffect::new()).async_run().await.is_err());
+        assert!(unreachable.lights().toggle().async_run().await.is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_cascade_off_plan() {
+        let lights: Vec<Light> = ["a", "b", "c"].iter()
+            .map(|id| Light { id: id.to_string(), ..Default::default() })
+            .collect();
+        let plan = Group::cascade_off_plan(&lights, Duration::from_millis(300), 1.5);
+
+        assert_eq!(plan.len(), 3);
+        let delays: Vec<Duration> = plan.iter().map(|(delay, _, _)| *delay).collect();
+        assert_eq!(delays, vec![Duration::ZERO, Duration::from_millis(300), Duration::from_millis(600)]);
+        let selectors: Vec<&str> = plan.iter().map(|(_, selector, _)| selector.as_str()).collect();
+        assert_eq!(selectors, vec!["id:a", "id:b", "id:c"]);
+        for (_, _, state) in &plan {
+            assert_eq!(state.power.as_deref(), Some("off"));
+            assert_eq!(state.duration, Some(1.5));
+        }
+    }
+
+    #[test]
+    fn test_check_reproducible() {
+        let mut white_only = Light::default();
+        white_only.product.capabilities.min_kelvin = 2700;
+        white_only.product.capabilities.max_kelvin = 6500;
+        let mut color_light = white_only.clone();
+        color_light.product.capabilities.has_color = true;
+        let state = |color: &str| State { color: Some(color.to_string()), ..Default::default() };
+
+        assert_eq!(state("hue:240 saturation:1").check_reproducible(&white_only),
+            Err(vec![GamutWarning::NoColorSupport { color: "hue:240 saturation:1".to_string() }]));
+        assert!(state("blue").check_reproducible(&white_only).is_err());
+        assert!(state("#ff0000").check_reproducible(&white_only).is_err());
+        assert_eq!(state("hue:240 saturation:1").check_reproducible(&color_light), Ok(()));
+        assert_eq!(state("white saturation:0 kelvin:3500").check_reproducible(&white_only), Ok(()));
+        assert_eq!(State::new().check_reproducible(&white_only), Ok(()));
+
+        assert_eq!(state("red kelvin:1500").check_reproducible(&white_only), Err(vec![
+            GamutWarning::NoColorSupport { color: "red kelvin:1500".to_string() },
+            GamutWarning::KelvinOutOfRange { kelvin: 1500, min_kelvin: 2700, max_kelvin: 6500 },
+        ]));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_bounded_join() {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Arc;
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (running_task, peak_task) = (running.clone(), peak.clone());
+        let results = bounded_join((0..10u64).collect(), 3, move |i| {
+            let (running, peak) = (running_task.clone(), peak_task.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Later items finish first, so ordering comes from the join and not from timing
+                tokio::time::sleep(Duration::from_millis(20 - i)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                i * 10
+            }
+        }).await;
+
+        assert_eq!(results, (0..10u64).map(|i| i * 10).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_list_selectors() {
+        let unreachable = LifxConfig {
+            api_endpoints: vec!["http://127.0.0.1:9".to_string()],
+            ..Default::default()
+        };
+        let selectors = vec![Selector::Group("Lounge".to_string()), Selector::Id("d073d5".to_string())];
+        let results = Light::async_list_selectors(unreachable, &selectors, 0).await;
+
+        assert_eq!(results.iter().map(|(selector, _)| selector.clone()).collect::<Vec<_>>(), selectors);
+        assert!(results.iter().all(|(_, lights)| lights.is_err()));
+    }
 }
@@ -0,0 +1,76 @@
+//! Offline lookup of LIFX product capabilities by vendor and product id.
+//!
+//! This lets you decide what a product supports (color, infrared, HEV, multizone, ...) before or
+//! without listing its lights from the cloud API.
+//!
+//! # Data source
+//!
+//! The data is the product table in [crate::lan::get_product_info], which is generated from the
+//! official list at https://github.com/LIFX/products/blob/master/products.json. To pick up new
+//! products, regenerate that table from the latest products.json; this module needs no changes.
+//!
+//! Only compiled with the `products-db` feature.
+
+use crate::lan;
+use crate::Capabilities;
+
+/// Looks up the capabilities of a LIFX product, or None if the product isn't in the database.
+///
+/// # Arguments
+///
+/// * `vendor_id` - The vendor id, 1 for LIFX
+/// * `product_id` - The product id, ex: 90 for the LIFX Clean
+///
+/// # Examples
+///
+/// ```
+/// extern crate lifx_rs as lifx;
+///
+/// fn main() {
+///     if let Some(capabilities) = lifx::products::lookup(1, 90) {
+///         println!("HEV: {}", capabilities.has_hev);
+///     }
+/// }
+///  ```
+pub fn lookup(vendor_id: u32, product_id: u32) -> Option<Capabilities> {
+    let info = lan::get_product_info(vendor_id, product_id)?;
+    let capabilities = &info.capabilities;
+    Some(Capabilities {
+        has_color: capabilities.has_color,
+        has_variable_color_temp: capabilities.has_variable_color_temp,
+        has_ir: capabilities.has_ir,
+        has_hev: capabilities.has_hev,
+        has_chain: capabilities.has_chain,
+        has_matrix: capabilities.has_matrix,
+        has_multizone: capabilities.has_multizone,
+        min_kelvin: capabilities.min_kelvin,
+        max_kelvin: capabilities.max_kelvin,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_clean() {
+        let capabilities = lookup(1, 90).unwrap();
+        assert!(capabilities.has_color);
+        assert!(capabilities.has_hev);
+        assert!(!capabilities.has_multizone);
+    }
+
+    #[test]
+    fn test_lookup_white() {
+        let capabilities = lookup(1, 10).unwrap();
+        assert!(!capabilities.has_color);
+        assert_eq!(capabilities.min_kelvin, 2700);
+        assert_eq!(capabilities.max_kelvin, 6500);
+    }
+
+    #[test]
+    fn test_lookup_unknown() {
+        assert!(lookup(1, 65535).is_none());
+        assert!(lookup(2, 1).is_none());
+    }
+}
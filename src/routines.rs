@@ -0,0 +1,204 @@
+//! Wake-up and wind-down fades that run for longer than a single transition should.
+//!
+//! [sunrise] and [sunset] ramp brightness and color temperature across many small steps, each
+//! one a set_state whose duration lasts until the next, so the firmware fades smoothly between
+//! them. A [Ramp] describes the start and end of a fade; [run] plays any ramp.
+//!
+//! The routines run on a spawned tokio task and return a ScheduledHandle: cancel it to stop the
+//! fade where it is, or await its result to get the results of the last step. The first failed
+//! step ends the routine with its error.
+//!
+//! Only compiled with `async`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! extern crate lifx_rs as lifx;
+//!
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let config = lifx::LifxConfig{
+//!        access_token: "xxx".to_string(),
+//!        api_endpoints: vec![format!("https://api.lifx.com")],
+//!        ..Default::default()
+//!     };
+//!
+//!     let wake_up = lifx::routines::sunrise(config.clone(), "group:Bedroom", Duration::from_secs(30 * 60));
+//!     // Snoozed: leave the lights where they got to
+//!     tokio::time::sleep(Duration::from_secs(60)).await;
+//!     wake_up.cancel();
+//!
+//!     let wind_down = lifx::routines::sunset(config, "group:Lounge", Duration::from_secs(45 * 60));
+//!     println!("{:?}", wind_down.result().await);
+//! }
+//! ```
+
+use std::time::Duration;
+
+use crate::{Color, Kelvin, LifxConfig, Light, ScheduledHandle, State, StateBuilder};
+
+/// How many steps Ramp::sunrise and Ramp::sunset fade across. At a minute or more per routine
+/// this stays within the API's rate limit.
+pub const DEFAULT_STEPS: u32 = 60;
+
+/// A fade from one brightness and color temperature to another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ramp {
+    /// The brightness the fade starts at, from 0.0 to 1.0
+    pub from_brightness: f64,
+    /// The brightness the fade ends at, from 0.0 to 1.0
+    pub to_brightness: f64,
+    /// The color temperature the fade starts at
+    pub from_kelvin: i64,
+    /// The color temperature the fade ends at
+    pub to_kelvin: i64,
+    /// How many steps the fade is split into, at least 1
+    pub steps: u32,
+    /// Turns the lights on at the starting brightness before fading, for lights that are off
+    pub power_on: bool,
+    /// Turns the lights off once the fade has finished
+    pub power_off: bool,
+}
+
+impl Ramp {
+    /// A dim, warm glow brightening to full cool white, turning the lights on first
+    pub fn sunrise() -> Self {
+        Ramp {
+            from_brightness: 0.01,
+            to_brightness: 1.0,
+            from_kelvin: Kelvin::Sunset.kelvin(),
+            to_kelvin: Kelvin::Cool.kelvin(),
+            steps: DEFAULT_STEPS,
+            power_on: true,
+            power_off: false,
+        }
+    }
+
+    /// Full warm white dimming to candlelight, turning the lights off at the end
+    pub fn sunset() -> Self {
+        Ramp {
+            from_brightness: 1.0,
+            to_brightness: 0.01,
+            from_kelvin: Kelvin::Warm.kelvin(),
+            to_kelvin: Kelvin::Candlelight.kelvin(),
+            steps: DEFAULT_STEPS,
+            power_on: false,
+            power_off: true,
+        }
+    }
+
+    /// The states a fade lasting `over` sends, in order. Each one is sent once the previous
+    /// one's duration has passed.
+    ///
+    /// The first state jumps to the start of the ramp, then one state per step fades a step
+    /// further, ending at the end of the ramp; with power_off a last state turns the lights off.
+    pub fn states(&self, over: Duration) -> Vec<State> {
+        let steps = self.steps.max(1);
+        let step_duration = over.as_secs_f64() / f64::from(steps);
+        let mut states = Vec::with_capacity(steps as usize + 2);
+        let mut start = self.state_at(0.0).duration(0.0);
+        if self.power_on {
+            start = start.power_on();
+        }
+        states.push(start.build());
+        for step in 1..=steps {
+            states.push(self.state_at(f64::from(step) / f64::from(steps)).duration(step_duration).build());
+        }
+        if self.power_off {
+            states.push(State::builder().power_off().duration(0.0).build());
+        }
+        states
+    }
+
+    /// The ramp's brightness and color temperature `progress` of the way through, from 0.0 to 1.0
+    fn state_at(&self, progress: f64) -> StateBuilder {
+        let brightness = self.from_brightness + (self.to_brightness - self.from_brightness) * progress;
+        let kelvin = self.from_kelvin as f64 + (self.to_kelvin - self.from_kelvin) as f64 * progress;
+        State::builder().color(Color::kelvin(kelvin.round() as i64)).brightness(brightness)
+    }
+}
+
+/// Starts a sunrise on the selected lights lasting `over`, see Ramp::sunrise
+pub fn sunrise(config: LifxConfig, selector: impl Into<String>, over: Duration) -> ScheduledHandle {
+    run(config, selector, Ramp::sunrise(), over)
+}
+
+/// Starts a sunset on the selected lights lasting `over`, see Ramp::sunset
+pub fn sunset(config: LifxConfig, selector: impl Into<String>, over: Duration) -> ScheduledHandle {
+    run(config, selector, Ramp::sunset(), over)
+}
+
+/// Starts `ramp` on the selected lights, lasting `over`. Must be called from within a tokio
+/// runtime.
+pub fn run(config: LifxConfig, selector: impl Into<String>, ramp: Ramp, over: Duration) -> ScheduledHandle {
+    let selector = selector.into();
+    let states = ramp.states(over);
+    ScheduledHandle::spawn_after(Duration::ZERO, async move {
+        let mut results = Default::default();
+        for state in states {
+            let wait = Duration::from_secs_f64(state.duration.unwrap_or(0.0));
+            results = Light::async_set_state_by_selector(config.clone(), selector.clone(), state).await?;
+            tokio::time::sleep(wait).await;
+        }
+        Ok(results)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixtures, MockTransport};
+    use crate::transport::Operation;
+    use crate::LifxError;
+
+    #[test]
+    fn test_ramp_states() {
+        let ramp = Ramp { steps: 4, ..Ramp::sunrise() };
+        let states = ramp.states(Duration::from_secs(60));
+        assert_eq!(states.len(), 5);
+        assert_eq!(states[0], State::builder().power_on().color("saturation:0 kelvin:2000").brightness(0.01).duration(0.0).build());
+        assert_eq!(states[2].color.as_deref(), Some("saturation:0 kelvin:3000"));
+        assert_eq!(states[2].duration, Some(15.0));
+        assert_eq!((states[4].brightness, states[4].power.as_deref()), (Some(1.0), None));
+
+        let states = Ramp { steps: 2, ..Ramp::sunset() }.states(Duration::from_secs(10));
+        let kelvins: Vec<_> = states.iter().map(|state| state.color.clone()).collect();
+        assert_eq!(kelvins[..3], [Some("saturation:0 kelvin:3000".to_string()), Some("saturation:0 kelvin:2250".to_string()), Some("saturation:0 kelvin:1500".to_string())]);
+        assert_eq!(states[3], State::builder().power_off().duration(0.0).build());
+    }
+
+    #[tokio::test]
+    async fn test_run_and_cancel() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let ramp = Ramp { steps: 3, ..Ramp::sunset() };
+        let results = run(mock.config(), "group:Bedroom", ramp.clone(), Duration::from_millis(60)).result().await;
+        assert!(results.unwrap().is_ok());
+        let sent: Vec<_> = mock.operations().into_iter().map(|(_, operation)| operation).collect();
+        assert_eq!(sent, ramp.states(Duration::from_millis(60)).into_iter().map(Operation::SetState).collect::<Vec<_>>());
+
+        mock.clear_requests();
+        let handle = run(mock.config(), "group:Bedroom", ramp, Duration::from_secs(30));
+        // The jump to the start and the first step, which lasts 10 seconds
+        let started = async {
+            while mock.operations().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), started).await.unwrap();
+        handle.cancel();
+        assert!(handle.result().await.is_none());
+        assert_eq!(mock.operations().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stops_at_first_error() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        mock.push_response(Ok(fixtures::results(&["d073d5000003"])));
+        mock.push_response(Err(LifxError::Api("Rate limit exceeded".to_string())));
+        let result = run(mock.config(), "group:Bedroom", Ramp { steps: 3, ..Ramp::sunrise() }, Duration::from_millis(30)).result().await;
+        assert!(matches!(result, Some(Err(LifxError::Api(_)))));
+        assert_eq!(mock.operations().len(), 2);
+    }
+}
@@ -0,0 +1,485 @@
+//! Recurring light changes run in the background: every so often, or on a cron schedule.
+//!
+//! A [Scheduler] holds jobs, each an [Action] on a selector with a [Schedule]. Once started, a
+//! spawned tokio task sleeps until the next job is due, sends its action, and works out when it
+//! runs next. Jobs can be added, removed and listed while the scheduler is running.
+//!
+//! Cron schedules are evaluated in UTC, since this crate has no time zone data; shift the hours
+//! to match your local time. A job that comes due while its previous run is still in flight runs
+//! again anyway, and runs missed while the scheduler was stopped are skipped, not caught up.
+//!
+//! Only compiled with the `scheduler` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! extern crate lifx_rs as lifx;
+//!
+//! use lifx::scheduler::{Action, Schedule, Scheduler};
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let config = lifx::LifxConfig{
+//!        access_token: "xxx".to_string(),
+//!        api_endpoints: vec![format!("https://api.lifx.com")],
+//!        ..Default::default()
+//!     };
+//!
+//!     let scheduler = Scheduler::new(config);
+//!     // Warm white at 07:30 UTC on weekdays
+//!     let wake_up = lifx::State::builder().power_on().color(lifx::Color::kelvin(2700)).duration(600.0).build();
+//!     scheduler.add("group:Bedroom", "30 7 * * 1-5".parse::<Schedule>().unwrap(), Action::SetState(wake_up)).unwrap();
+//!     // Flip the porch light every 10 minutes
+//!     scheduler.add("label:Porch", Schedule::Every(Duration::from_secs(600)), Action::Toggle(lifx::Toggle::new())).unwrap();
+//!     scheduler.start();
+//!
+//!     tokio::time::sleep(Duration::from_secs(3600)).await;
+//!     for job in scheduler.list() {
+//!         println!("{:?} next runs at {:?}, last error: {:?}", job.id, job.next_run, job.last_error);
+//!     }
+//!     scheduler.stop();
+//! }
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::{LifxConfig, LifxError};
+
+/// What a job sends to its selector when it runs
+pub use crate::sequence::Action;
+
+/// The longest the scheduler sleeps before looking at its jobs again, so a change to the system
+/// clock is noticed within this long
+pub const MAX_IDLE: Duration = Duration::from_secs(60);
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// Every interval, starting one interval after the job is added. The interval must be longer
+    /// than zero, see Scheduler::add.
+    Every(Duration),
+    /// On the minutes a cron expression matches, in UTC
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// The first time after `after` the job should run, or None if it never runs again, ex: a
+    /// cron expression for February 30th
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        match self {
+            Schedule::Every(interval) => Some(after + *interval),
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+/// Parses a cron expression into Schedule::Cron, see Cron
+impl FromStr for Schedule {
+    type Err = CronParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Schedule::Cron)
+    }
+}
+
+/// A standard 5 field cron expression: minute (0-59), hour (0-23), day of month (1-31), month
+/// (1-12) and day of week (0-6 from Sunday, 7 is Sunday too), ex: "30 7 * * 1-5".
+///
+/// Each field is `*`, a number, a range `a-b`, any of those with a step (`*/15`, `0-30/10`), or a
+/// comma separated list of them. As in cron, when both the day of month and the day of week are
+/// restricted, a day matching either one matches. Month and day names aren't supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// How many days ahead Cron::next_after looks before deciding an expression never matches
+const CRON_SEARCH_DAYS: i64 = 366 * 5;
+
+impl Cron {
+    /// The first whole minute after `after` that the expression matches, or None if it matches
+    /// none in the next five years
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let seconds = match after.duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()).ok()?,
+            Err(before) => -i64::try_from(before.duration().as_secs()).ok()? - 1,
+        };
+        let start = seconds.div_euclid(60) + 1;
+        let (first_day, first_minute) = (start.div_euclid(1440), start.rem_euclid(1440));
+        for day in first_day..first_day + CRON_SEARCH_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+            let from = if day == first_day { first_minute } else { 0 };
+            if let Some(minute) = (from..1440).find(|minute| has(self.hours, minute / 60) && has(self.minutes, minute % 60)) {
+                let at = (day * 1440 + minute) * 60;
+                return Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(at).ok()?));
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, days_since_epoch: i64) -> bool {
+        let (_, month, day) = civil_from_days(days_since_epoch);
+        // 1970-01-01 was a Thursday
+        let weekday = (days_since_epoch + 4).rem_euclid(7);
+        let day_matches = has(self.days, day);
+        let weekday_matches = has(self.weekdays, weekday);
+        let day_ok = match (self.any_day, self.any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        };
+        has(self.months, month) && day_ok
+    }
+}
+
+/// Renders the expression as it was parsed
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+impl FromStr for Cron {
+    type Err = CronParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(CronParseError::FieldCount(fields.len()));
+        }
+        let mut weekdays = parse_field(fields[4], "day of week", 0, 7)?;
+        // 7 is another name for Sunday
+        if has(weekdays, 7) {
+            weekdays |= 1;
+        }
+        Ok(Cron {
+            expression: fields.join(" "),
+            minutes: parse_field(fields[0], "minute", 0, 59)?,
+            hours: parse_field(fields[1], "hour", 0, 23)?,
+            days: parse_field(fields[2], "day of month", 1, 31)?,
+            months: parse_field(fields[3], "month", 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+}
+
+/// Errors returned when parsing a [Cron] expression
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CronParseError {
+    /// The expression doesn't have the 5 fields
+    #[error("expected 5 cron fields, found {0}")]
+    FieldCount(usize),
+    /// A field that isn't a valid value, range, step or list, or is out of the field's range
+    #[error("invalid {field} field: `{value}`")]
+    InvalidField { field: &'static str, value: String },
+}
+
+/// Parses one cron field into a bit set of the values it matches
+fn parse_field(field: &str, name: &'static str, min: i64, max: i64) -> Result<u64, CronParseError> {
+    let invalid = || CronParseError::InvalidField { field: name, value: field.to_string() };
+    let number = |value: &str| value.parse::<i64>().ok().filter(|value| (min..=max).contains(value)).ok_or_else(invalid);
+    let mut values = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<i64>().ok().filter(|step| *step > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (number(first)?, number(last)?),
+            None => (number(range)?, number(range)?),
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        for value in (first..=last).step_by(step as usize) {
+            values |= 1 << value;
+        }
+    }
+    Ok(values)
+}
+
+fn has(values: u64, value: i64) -> bool {
+    values & (1 << value) != 0
+}
+
+/// The (year, month, day) of a day counted from 1970-01-01, see
+/// http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let m = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * m + 2) / 5 + 1;
+    let month = if m < 10 { m + 3 } else { m - 9 };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Identifies a job within its Scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JobId(pub u64);
+
+/// A job of a Scheduler, as returned by Scheduler::list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub id: JobId,
+    pub selector: String,
+    pub schedule: Schedule,
+    pub action: Action,
+    /// When the job runs next, or None if its schedule never comes round again
+    pub next_run: Option<SystemTime>,
+    /// When the job last ran, or None if it hasn't yet
+    pub last_run: Option<SystemTime>,
+    /// The error of the last run, or None if it succeeded or hasn't run
+    pub last_error: Option<String>,
+}
+
+/// Runs jobs on their schedules from a background tokio task, see the module docs.
+///
+/// Clones share the same jobs and task. Dropping every clone doesn't stop a started scheduler;
+/// call stop.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: LifxConfig,
+    jobs: Mutex<Vec<Job>>,
+    next_id: AtomicU64,
+    wake: Notify,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Scheduler {
+    /// Returns a stopped scheduler with no jobs, sending through `config`
+    pub fn new(config: LifxConfig) -> Self {
+        Scheduler {
+            inner: Arc::new(Inner {
+                config,
+                jobs: Mutex::new(Vec::new()),
+                next_id: AtomicU64::new(1),
+                wake: Notify::new(),
+                task: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Adds a job that sends `action` to `selector` on `schedule`, returning its id. The first run
+    /// is the schedule's first time after now.
+    ///
+    /// Returns LifxError::Config for Schedule::Every(Duration::ZERO), which would be due again
+    /// on every pass and send the action as fast as the scheduler could loop.
+    pub fn add(&self, selector: impl Into<String>, schedule: Schedule, action: Action) -> Result<JobId, LifxError> {
+        if schedule == Schedule::Every(Duration::ZERO) {
+            return Err(LifxError::Config("a Schedule::Every interval must be longer than zero".to_string()));
+        }
+        let id = JobId(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        let next_run = schedule.next_after(SystemTime::now());
+        let job = Job { id, selector: selector.into(), schedule, action, next_run, last_run: None, last_error: None };
+        self.inner.jobs().push(job);
+        self.inner.wake.notify_one();
+        Ok(id)
+    }
+
+    /// Removes a job, returning false if there was no job with that id. A run already in flight
+    /// may still land.
+    pub fn remove(&self, id: JobId) -> bool {
+        let mut jobs = self.inner.jobs();
+        let before = jobs.len();
+        jobs.retain(|job| job.id != id);
+        before != jobs.len()
+    }
+
+    /// The jobs, in the order they were added
+    pub fn list(&self) -> Vec<Job> {
+        self.inner.jobs().clone()
+    }
+
+    /// Starts running jobs on a spawned task. Does nothing if already started. Must be called
+    /// from within a tokio runtime.
+    pub fn start(&self) {
+        let mut task = self.inner.task.lock().unwrap_or_else(|e| e.into_inner());
+        if task.as_ref().is_none_or(|task| task.is_finished()) {
+            *task = Some(tokio::spawn(run(self.inner.clone())));
+        }
+    }
+
+    /// Stops running jobs, keeping them so start picks up where this left off. Runs already in
+    /// flight may still land.
+    pub fn stop(&self) {
+        if let Some(task) = self.inner.task.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            task.abort();
+        }
+    }
+
+    /// Returns true between start and stop
+    pub fn is_running(&self) -> bool {
+        self.inner.task.lock().unwrap_or_else(|e| e.into_inner()).as_ref().is_some_and(|task| !task.is_finished())
+    }
+}
+
+impl Inner {
+    fn jobs(&self) -> std::sync::MutexGuard<'_, Vec<Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the jobs due at `now` and moves each one's next_run on past `now`
+    fn take_due(&self, now: SystemTime) -> Vec<(JobId, String, Action)> {
+        let mut due = Vec::new();
+        for job in self.jobs().iter_mut().filter(|job| job.next_run.is_some_and(|at| at <= now)) {
+            job.next_run = job.schedule.next_after(now);
+            due.push((job.id, job.selector.clone(), job.action.clone()));
+        }
+        due
+    }
+
+    /// How long to sleep from `now` until the next job is due, at most MAX_IDLE
+    fn idle_time(&self, now: SystemTime) -> Duration {
+        self.jobs()
+            .iter()
+            .filter_map(|job| job.next_run)
+            .map(|at| at.duration_since(now).unwrap_or(Duration::ZERO))
+            .min()
+            .map_or(MAX_IDLE, |wait| wait.min(MAX_IDLE))
+    }
+
+    fn record(&self, id: JobId, ran_at: SystemTime, error: Option<LifxError>) {
+        if let Some(job) = self.jobs().iter_mut().find(|job| job.id == id) {
+            job.last_run = Some(ran_at);
+            job.last_error = error.map(|error| error.to_string());
+        }
+    }
+}
+
+/// The scheduler's background task
+async fn run(inner: Arc<Inner>) {
+    loop {
+        let now = SystemTime::now();
+        for (id, selector, action) in inner.take_due(now) {
+            let inner = inner.clone();
+            tokio::spawn(async move {
+                let result = action.run(inner.config.clone(), selector).await;
+                inner.record(id, now, result.err());
+            });
+        }
+        // Woken early when a job is added, since it may be due before the current next one
+        let _ = tokio::time::timeout(inner.idle_time(now), inner.wake.notified()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixtures, MockTransport};
+    use crate::transport::Operation;
+    use crate::{EffectsOff, Toggle};
+
+    fn at(timestamp: &str) -> SystemTime {
+        crate::parse_rfc3339(timestamp).unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let weekday_mornings: Cron = "30 7 * * 1-5".parse().unwrap();
+        // 2026-10-16 is a Friday
+        assert_eq!(weekday_mornings.next_after(at("2026-10-16T07:00:00Z")), Some(at("2026-10-16T07:30:00Z")));
+        assert_eq!(weekday_mornings.next_after(at("2026-10-16T07:30:00Z")), Some(at("2026-10-19T07:30:00Z")));
+
+        let quarter_hours: Cron = "*/15 9-17 * * *".parse().unwrap();
+        assert_eq!(quarter_hours.next_after(at("2026-10-16T12:07:59Z")), Some(at("2026-10-16T12:15:00Z")));
+        assert_eq!(quarter_hours.next_after(at("2026-10-16T17:50:00Z")), Some(at("2026-10-17T09:00:00Z")));
+
+        // Either the 1st of the month or a Sunday
+        let first_or_sunday: Cron = "0 0 1 * 7".parse().unwrap();
+        assert_eq!(first_or_sunday.next_after(at("2026-10-16T00:00:00Z")), Some(at("2026-10-18T00:00:00Z")));
+        assert_eq!(first_or_sunday.next_after(at("2026-10-25T00:00:00Z")), Some(at("2026-11-01T00:00:00Z")));
+
+        let leap_day: Cron = "0 12 29 2 *".parse().unwrap();
+        assert_eq!(leap_day.next_after(at("2026-10-16T00:00:00Z")), Some(at("2028-02-29T12:00:00Z")));
+        assert_eq!("0 0 30 2 *".parse::<Cron>().unwrap().next_after(at("2026-10-16T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert_eq!("* * * *".parse::<Cron>(), Err(CronParseError::FieldCount(4)));
+        let invalid = |field: &'static str, value: &str| Err(CronParseError::InvalidField { field, value: value.to_string() });
+        assert_eq!("60 * * * *".parse::<Cron>(), invalid("minute", "60"));
+        assert_eq!("* 5-2 * * *".parse::<Cron>(), invalid("hour", "5-2"));
+        assert_eq!("* * 0 * *".parse::<Cron>(), invalid("day of month", "0"));
+        assert_eq!("* * * */0 *".parse::<Cron>(), invalid("month", "*/0"));
+        assert_eq!("* * * * mon".parse::<Cron>(), invalid("day of week", "mon"));
+        assert_eq!("0,30  */2 * * *".parse::<Cron>().unwrap().to_string(), "0,30 */2 * * *");
+    }
+
+    #[test]
+    fn test_add_list_remove() {
+        let scheduler = Scheduler::new(LifxConfig::default());
+        let hourly = scheduler.add("all", Schedule::Every(Duration::from_secs(3600)), Action::Toggle(Toggle::new())).unwrap();
+        let nightly = scheduler.add("group:Bedroom", "0 22 * * *".parse().unwrap(), Action::EffectsOff(EffectsOff::new())).unwrap();
+        let jobs = scheduler.list();
+        assert_eq!(jobs.iter().map(|job| job.id).collect::<Vec<_>>(), vec![hourly, nightly]);
+        assert!(jobs.iter().all(|job| job.next_run.is_some() && job.last_run.is_none()));
+
+        assert!(scheduler.remove(hourly));
+        assert!(!scheduler.remove(hourly));
+        assert_eq!(scheduler.list().len(), 1);
+        assert!(!scheduler.is_running());
+    }
+
+    #[test]
+    fn test_add_zero_interval() {
+        let scheduler = Scheduler::new(LifxConfig::default());
+        let result = scheduler.add("all", Schedule::Every(Duration::ZERO), Action::Toggle(Toggle::new()));
+        assert!(matches!(result, Err(LifxError::Config(message)) if message.contains("longer than zero")));
+        assert!(scheduler.list().is_empty());
+        assert!(scheduler.add("all", Schedule::Every(Duration::from_nanos(1)), Action::Toggle(Toggle::new())).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_runs_jobs() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let scheduler = Scheduler::new(mock.config());
+        let job = scheduler.add("label:Lamp", Schedule::Every(Duration::from_millis(40)), Action::Toggle(Toggle::new())).unwrap();
+        scheduler.start();
+        scheduler.start();
+        assert!(scheduler.is_running());
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        scheduler.stop();
+        assert!(!scheduler.is_running());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let sent = mock.operations();
+        assert!(sent.len() >= 2, "ran {} times", sent.len());
+        assert_eq!(sent[0], ("label:Lamp".to_string(), Operation::Toggle(Toggle::new())));
+
+        let listed = &scheduler.list()[0];
+        assert_eq!(listed.id, job);
+        assert!(listed.last_run.is_some() && listed.last_error.is_none());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock.operations().len(), sent.len());
+    }
+}
@@ -0,0 +1,334 @@
+//! Multi-step light shows: effects, state changes and pauses run one after another.
+//!
+//! A [Sequence] is built step by step, ex: `Sequence::new().breathe(...).wait(...).set_state(...)`,
+//! and run against a selector. Each step's request is awaited before the next one starts, and a
+//! wait step sleeps; effects are started, not waited on, so follow one with a wait for as long as
+//! it should show. [Sequence::repeat] runs the whole list of steps several times.
+//!
+//! By default the first failed step ends the run; with [Sequence::keep_going] the remaining steps
+//! still run and every failure is collected into the [SequenceError]. Awaiting
+//! [Sequence::run] ties the show to the caller, while [Sequence::spawn] runs it on its own task
+//! and returns a [SequenceHandle] that can cancel it between requests.
+//!
+//! Only compiled with `async`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! extern crate lifx_rs as lifx;
+//!
+//! use lifx::sequence::Sequence;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let config = lifx::LifxConfig{
+//!        access_token: "xxx".to_string(),
+//!        api_endpoints: vec![format!("https://api.lifx.com")],
+//!        ..Default::default()
+//!     };
+//!
+//!     let doorbell = Sequence::new()
+//!         .breathe(lifx::BreatheEffect::oscillate("blue", "white", 0.5))
+//!         .wait(Duration::from_secs(5))
+//!         .effects_off(lifx::EffectsOff::new())
+//!         .set_state(lifx::State::builder().color("kelvin:2700").brightness(0.6).build())
+//!         .wait(Duration::from_secs(1))
+//!         .repeat(3);
+//!
+//!     match doorbell.run(config, "group:Hallway").await {
+//!         Ok(results) => println!("{} requests sent", results.len()),
+//!         Err(e) => println!("{}", e),
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+use crate::{
+    AnyEffect, BreatheEffect, EffectsOff, FlameEffect, LiFxResults, LifxConfig, LifxError, Light, MorphEffect,
+    MoveEffect, PulseEffect, State, StateDelta, Toggle,
+};
+
+/// A request sent to a selector, as a Sequence step or a scheduler job
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Sets the state of the selected lights, see Light::set_state_by_selector
+    SetState(State),
+    /// Changes the state relative to the current one, see Light::state_delta_by_selector
+    StateDelta(StateDelta),
+    /// Toggles the power, see Light::toggle_by_selector
+    Toggle(Toggle),
+    /// Starts an effect
+    Effect(AnyEffect),
+    /// Stops any running effect, see Light::effects_off_by_selector
+    EffectsOff(EffectsOff),
+}
+
+impl Action {
+    pub(crate) async fn run(self, config: LifxConfig, selector: String) -> Result<LiFxResults, LifxError> {
+        match self {
+            Action::SetState(state) => Light::async_set_state_by_selector(config, selector, state).await,
+            Action::StateDelta(delta) => Light::async_state_delta_by_selector(config, selector, delta).await,
+            Action::Toggle(toggle) => Light::async_toggle_by_selector(config, selector, toggle).await,
+            Action::Effect(effect) => effect.async_apply(config, selector).await,
+            Action::EffectsOff(effects_off) => Light::async_effects_off_by_selector(config, selector, effects_off).await,
+        }
+    }
+}
+
+/// One step of a Sequence
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Sends the action and waits for its response
+    Send(Action),
+    /// Waits before the next step
+    Wait(Duration),
+}
+
+/// A list of steps run in order against a selector, see the module docs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence {
+    steps: Vec<Step>,
+    repetitions: u32,
+    keep_going: bool,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Sequence::new()
+    }
+}
+
+impl Sequence {
+    /// Returns a sequence with no steps that runs once and stops at the first failure
+    pub fn new() -> Self {
+        Sequence { steps: Vec::new(), repetitions: 1, keep_going: false }
+    }
+
+    /// Adds a step that sends `action`
+    pub fn then(mut self, action: Action) -> Self {
+        self.steps.push(Step::Send(action));
+        self
+    }
+
+    /// Adds a step that sets the state, see Light::set_state_by_selector
+    pub fn set_state(self, state: State) -> Self {
+        self.then(Action::SetState(state))
+    }
+
+    /// Adds a step that changes the state relative to the current one, see Light::state_delta_by_selector
+    pub fn state_delta(self, delta: StateDelta) -> Self {
+        self.then(Action::StateDelta(delta))
+    }
+
+    /// Adds a step that toggles the power, see Light::toggle_by_selector
+    pub fn toggle(self, toggle: Toggle) -> Self {
+        self.then(Action::Toggle(toggle))
+    }
+
+    /// Adds a step that starts a breathe effect
+    pub fn breathe(self, breathe: BreatheEffect) -> Self {
+        self.then(Action::Effect(AnyEffect::Breathe(breathe)))
+    }
+
+    /// Adds a step that starts a move effect
+    pub fn move_effect(self, move_effect: MoveEffect) -> Self {
+        self.then(Action::Effect(AnyEffect::Move(move_effect)))
+    }
+
+    /// Adds a step that starts a morph effect
+    pub fn morph(self, morph_effect: MorphEffect) -> Self {
+        self.then(Action::Effect(AnyEffect::Morph(morph_effect)))
+    }
+
+    /// Adds a step that starts a pulse effect
+    pub fn pulse(self, pulse_effect: PulseEffect) -> Self {
+        self.then(Action::Effect(AnyEffect::Pulse(pulse_effect)))
+    }
+
+    /// Adds a step that starts a flame effect
+    pub fn flame(self, flame_effect: FlameEffect) -> Self {
+        self.then(Action::Effect(AnyEffect::Flame(flame_effect)))
+    }
+
+    /// Adds a step that stops any running effect, see Light::effects_off_by_selector
+    pub fn effects_off(self, effects_off: EffectsOff) -> Self {
+        self.then(Action::EffectsOff(effects_off))
+    }
+
+    /// Adds a step that waits for `duration` before the next step
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Wait(duration));
+        self
+    }
+
+    /// Runs all the steps `times` times in all, ex: repeat(3) runs them three times. 0 runs nothing.
+    pub fn repeat(mut self, times: u32) -> Self {
+        self.repetitions = times;
+        self
+    }
+
+    /// Runs the remaining steps after one fails, instead of stopping
+    pub fn keep_going(mut self) -> Self {
+        self.keep_going = true;
+        self
+    }
+
+    /// The steps, in the order they run
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Runs the sequence against the selected lights, returning the results of every request in
+    /// the order they were sent. Dropping the future stops the sequence before its next request.
+    pub async fn run(&self, config: LifxConfig, selector: impl Into<String>) -> Result<Vec<LiFxResults>, SequenceError> {
+        let selector = selector.into();
+        let mut results = Vec::new();
+        let mut failures = Vec::new();
+        for repetition in 0..self.repetitions {
+            for (index, step) in self.steps.iter().enumerate() {
+                match step {
+                    Step::Wait(duration) => tokio::time::sleep(*duration).await,
+                    Step::Send(action) => match action.clone().run(config.clone(), selector.clone()).await {
+                        Ok(step_results) => results.push(step_results),
+                        Err(error) => {
+                            failures.push(StepFailure { repetition, step: index, error });
+                            if !self.keep_going {
+                                return Err(SequenceError { results, failures });
+                            }
+                        }
+                    },
+                }
+            }
+        }
+        if failures.is_empty() {
+            Ok(results)
+        } else {
+            Err(SequenceError { results, failures })
+        }
+    }
+
+    /// Runs the sequence on a spawned task, see run. Must be called from within a tokio runtime.
+    pub fn spawn(self, config: LifxConfig, selector: impl Into<String>) -> SequenceHandle {
+        let selector = selector.into();
+        SequenceHandle { task: tokio::spawn(async move { self.run(config, selector).await }) }
+    }
+}
+
+/// A step that failed while running a Sequence
+#[derive(Debug)]
+pub struct StepFailure {
+    /// Which run through the steps it failed on, counting from 0, see Sequence::repeat
+    pub repetition: u32,
+    /// The index of the step in Sequence::steps
+    pub step: usize,
+    pub error: LifxError,
+}
+
+impl fmt::Display for StepFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {} (repetition {}): {}", self.step, self.repetition, self.error)
+    }
+}
+
+/// The error returned when steps of a Sequence fail
+#[derive(Error, Debug)]
+#[error("{} sequence step(s) failed, first at {}", failures.len(), failures[0])]
+pub struct SequenceError {
+    /// The results of the requests that succeeded, in the order they were sent
+    pub results: Vec<LiFxResults>,
+    /// The steps that failed, in the order they ran. Never empty.
+    pub failures: Vec<StepFailure>,
+}
+
+/// A Sequence running on its own task, started by Sequence::spawn.
+/// Dropping the handle does not cancel the sequence.
+#[derive(Debug)]
+pub struct SequenceHandle {
+    task: JoinHandle<Result<Vec<LiFxResults>, SequenceError>>,
+}
+
+impl SequenceHandle {
+    /// Stops the sequence before its next request. A request already in flight may still land.
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+
+    /// Returns true once the sequence has finished, has failed, or was cancelled
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Waits for the sequence to finish and returns its result, or None if it was cancelled first
+    pub async fn result(self) -> Option<Result<Vec<LiFxResults>, SequenceError>> {
+        self.task.await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixtures, MockTransport};
+    use crate::transport::Operation;
+
+    fn show() -> Sequence {
+        Sequence::new()
+            .breathe(BreatheEffect::oscillate("blue", "white", 0.5))
+            .wait(Duration::from_millis(10))
+            .effects_off(EffectsOff::new())
+            .set_state(State::builder().brightness(0.6).build())
+    }
+
+    #[tokio::test]
+    async fn test_run_repeats_steps() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let results = show().repeat(2).run(mock.config(), "label:Lamp").await.unwrap();
+        assert_eq!(results.len(), 6);
+        let sent: Vec<_> = mock.operations().into_iter().map(|(_, operation)| operation).collect();
+        assert_eq!(sent.len(), 6);
+        assert_eq!(sent[0], Operation::Breathe(BreatheEffect::oscillate("blue", "white", 0.5)));
+        assert_eq!(sent[1], Operation::EffectsOff(EffectsOff::new()));
+        assert_eq!(sent[3], sent[0]);
+
+        mock.clear_requests();
+        assert!(show().repeat(0).run(mock.config(), "label:Lamp").await.unwrap().is_empty());
+        assert!(mock.operations().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failures() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        mock.push_response(Err(LifxError::Api("Rate limit exceeded".to_string())));
+        let error = show().run(mock.config(), "label:Lamp").await.unwrap_err();
+        assert_eq!((error.results.len(), error.failures.len(), error.failures[0].step), (0, 1, 0));
+        assert_eq!(mock.operations().len(), 1);
+        assert_eq!(error.to_string(), "1 sequence step(s) failed, first at step 0 (repetition 0): LIFX API error: Rate limit exceeded");
+
+        mock.clear_requests();
+        mock.push_response(Ok(fixtures::results(&["d073d5000001"])));
+        mock.push_response(Err(LifxError::Api("Rate limit exceeded".to_string())));
+        let error = show().repeat(2).keep_going().run(mock.config(), "label:Lamp").await.unwrap_err();
+        assert_eq!((error.results.len(), error.failures.len(), error.failures[0].step), (5, 1, 2));
+        assert_eq!(mock.operations().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_and_cancel() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let handle = Sequence::new()
+            .toggle(Toggle::new())
+            .wait(Duration::from_secs(30))
+            .toggle(Toggle::new())
+            .spawn(mock.config(), "label:Lamp");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        handle.cancel();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(handle.is_finished());
+        assert!(handle.result().await.is_none());
+        assert_eq!(mock.operations(), vec![("label:Lamp".to_string(), Operation::Toggle(Toggle::new()))]);
+    }
+}
@@ -0,0 +1,263 @@
+//! Test helpers for code built on lifx-rs: a [MockTransport] that records requests instead of
+//! sending them, and [fixtures] to fill it with.
+//!
+//! Only compiled with the `test-util` feature (and `blocking` or `async`). Enable it in your
+//! dev-dependencies:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! lifx-rs = { version = "0.1", features = ["test-util"] }
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate lifx_rs as lifx;
+//!
+//! use lifx::test_util::{fixtures, MockTransport};
+//!
+//! // The code under test: turn off whatever is on
+//! fn lights_out(config: lifx::LifxConfig) {
+//!     for light in lifx::Light::list_on(config.clone()).unwrap() {
+//!         let _ = light.set_state(config.clone(), lifx::State::builder().power_off().build());
+//!     }
+//! }
+//!
+//! fn main() {
+//!     let mock = MockTransport::with_lights(fixtures::lights());
+//!     lights_out(mock.config());
+//!
+//!     let turned_off: Vec<String> = mock.operations().into_iter().map(|(selector, _)| selector).collect();
+//!     assert_eq!(turned_off, vec!["id:d073d5000001", "id:d073d5000002"]);
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::transport::{Operation, Transport};
+use crate::{LiFxResult, LiFxResults, LifxConfig, LifxError, Lights, Scenes, Selector};
+
+/// A request received by a [MockTransport]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    /// A list of the lights matching the selector
+    List(String),
+    /// An operation on the lights matching the selector
+    Send(String, Operation),
+    /// A list of the account's scenes
+    ListScenes,
+}
+
+/// A [Transport] that answers from canned data and records every request.
+///
+/// Lists return the configured lights matching the selector, after switching to the next set
+/// queued with push_lights if there is one. Operations return the next queued
+/// response, or an "ok" result for each matching light when the queue is empty. Selectors are
+/// matched the way the API matches them, including comma separated lists; scene_id selectors
+/// match nothing.
+///
+/// set_states is recorded as one [Request::Send] per state, see Transport::set_states.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    lights: Mutex<Lights>,
+    queued_lights: Mutex<VecDeque<Lights>>,
+    scenes: Mutex<Scenes>,
+    responses: Mutex<VecDeque<Result<LiFxResults, LifxError>>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MockTransport {
+    /// Returns a MockTransport with no lights or scenes
+    pub fn new() -> Arc<Self> {
+        Arc::new(MockTransport::default())
+    }
+
+    /// Returns a MockTransport that knows about `lights`
+    pub fn with_lights(lights: Lights) -> Arc<Self> {
+        let mock = MockTransport::new();
+        mock.set_lights(lights);
+        mock
+    }
+
+    /// Returns a LifxConfig that sends everything to this transport
+    pub fn config(self: &Arc<Self>) -> LifxConfig {
+        LifxConfig::default().with_transport(self.clone())
+    }
+
+    /// Replaces the lights that lists and default results are made from
+    pub fn set_lights(&self, lights: Lights) {
+        *self.lights.lock().unwrap() = lights;
+    }
+
+    /// Queues the lights the next list is made from, ex: a light that turns up on the third
+    /// poll. Each list switches to the next queued set, in the order they were queued; once the
+    /// queue is empty, the last one stays.
+    pub fn push_lights(&self, lights: Lights) {
+        self.queued_lights.lock().unwrap().push_back(lights);
+    }
+
+    /// Replaces the scenes returned by list_scenes
+    pub fn set_scenes(&self, scenes: Scenes) {
+        *self.scenes.lock().unwrap() = scenes;
+    }
+
+    /// Queues a response for the next operation, ex: an error to test failure handling.
+    /// Responses are used in the order they were queued.
+    pub fn push_response(&self, response: Result<LiFxResults, LifxError>) {
+        self.responses.lock().unwrap().push_back(response);
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The selector and operation of every [Request::Send] received so far, oldest first
+    pub fn operations(&self) -> Vec<(String, Operation)> {
+        self.requests()
+            .into_iter()
+            .filter_map(|request| match request {
+                Request::Send(selector, operation) => Some((selector, operation)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Forgets the requests received so far
+    pub fn clear_requests(&self) {
+        self.requests.lock().unwrap().clear();
+    }
+
+    fn record(&self, request: Request) {
+        self.requests.lock().unwrap().push(request);
+    }
+
+    fn matching(&self, selector: &str) -> Lights {
+        let selectors: Vec<Selector> = selector.split(',').filter_map(|part| part.trim().parse().ok()).collect();
+        self.lights
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|light| selectors.iter().any(|selector| selector.matches(light)))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Transport for MockTransport {
+    fn list(&self, selector: &str) -> Result<Lights, LifxError> {
+        self.record(Request::List(selector.to_string()));
+        if let Some(lights) = self.queued_lights.lock().unwrap().pop_front() {
+            self.set_lights(lights);
+        }
+        Ok(self.matching(selector))
+    }
+
+    fn send(&self, selector: &str, operation: Operation) -> Result<LiFxResults, LifxError> {
+        self.record(Request::Send(selector.to_string(), operation));
+        if let Some(response) = self.responses.lock().unwrap().pop_front() {
+            return response;
+        }
+        let results = self
+            .matching(selector)
+            .into_iter()
+            .map(|light| LiFxResult { id: light.id, label: light.label, status: "ok".to_string(), ..Default::default() })
+            .collect();
+        Ok(LiFxResults { results: Some(results), ..Default::default() })
+    }
+
+    fn list_scenes(&self) -> Result<Scenes, LifxError> {
+        self.record(Request::ListScenes);
+        Ok(self.scenes.lock().unwrap().clone())
+    }
+}
+
+/// Ready-made lights and results for tests
+pub mod fixtures {
+    use crate::{Capabilities, Color, Group, LiFxResult, LiFxResults, Light, Lights, Location, Product};
+
+    /// A connected, powered on LIFX Color bulb at full brightness, in group "Living Room" of
+    /// location "Home"
+    pub fn light(id: &str, label: &str) -> Light {
+        Light {
+            id: id.to_string(),
+            uuid: format!("8fa5f072-af97-44ed-ae54-{}", id),
+            label: label.to_string(),
+            connected: true,
+            power: "on".to_string(),
+            color: Color { hue: Some(0.0), saturation: Some(0.0), kelvin: Some(3500), ..Default::default() },
+            brightness: 1.0,
+            group: Group { id: "1c8de82b81f445e7cfaafae49b259c71".to_string(), name: "Living Room".to_string() },
+            location: Location { id: "1d6fe8ef0fde4c6d77b0012dc736662c".to_string(), name: "Home".to_string() },
+            product: Product {
+                name: "LIFX Color".to_string(),
+                identifier: "lifx_color".to_string(),
+                company: "LIFX".to_string(),
+                vendor_id: 1,
+                product_id: 91,
+                capabilities: Capabilities {
+                    has_color: true,
+                    has_variable_color_temp: true,
+                    min_kelvin: 1500,
+                    max_kelvin: 9000,
+                    ..Default::default()
+                },
+            },
+            last_seen: "2026-01-01T00:00:00Z".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Three lights: "Lamp" and "Ceiling" on in the Living Room, and "Bedside" off in the Bedroom
+    pub fn lights() -> Lights {
+        let mut bedside = light("d073d5000003", "Bedside");
+        bedside.power = "off".to_string();
+        bedside.group = Group { id: "4a1c6e0b2d8f4e5a9b3c7d1e0f2a4b6c".to_string(), name: "Bedroom".to_string() };
+        vec![light("d073d5000001", "Lamp"), light("d073d5000002", "Ceiling"), bedside]
+    }
+
+    /// An "ok" result for each of the light ids
+    pub fn results(ids: &[&str]) -> LiFxResults {
+        LiFxResults {
+            results: Some(
+                ids.iter()
+                    .map(|id| LiFxResult { id: id.to_string(), label: String::new(), status: "ok".to_string(), ..Default::default() })
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_matching() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let ids = |selector: &str| mock.list(selector).unwrap().into_iter().map(|light| light.id).collect::<Vec<_>>();
+        assert_eq!(ids("all").len(), 3);
+        assert_eq!(ids("group:Bedroom"), vec!["d073d5000003"]);
+        assert_eq!(ids("id:d073d5000001,label:Bedside"), vec!["d073d5000001", "d073d5000003"]);
+        assert!(ids("scene_id:abc").is_empty());
+        assert_eq!(mock.requests()[0], Request::List("all".to_string()));
+    }
+
+    #[test]
+    fn test_queued_responses() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        mock.push_response(Err(LifxError::Api("Invalid token".to_string())));
+        let toggle = Operation::Toggle(crate::Toggle::new());
+        assert!(matches!(mock.send("all", toggle.clone()), Err(LifxError::Api(_))));
+
+        let results = mock.send("location:Home", toggle.clone()).unwrap().results.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!((results[0].label.as_str(), results[0].status.as_str()), ("Lamp", "ok"));
+        assert_eq!(mock.operations(), vec![("all".to_string(), toggle.clone()), ("location:Home".to_string(), toggle)]);
+
+        mock.clear_requests();
+        assert!(mock.requests().is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//! Smooth color transitions driven from the client, for when a firmware fade isn't good enough.
+//!
+//! Some products step visibly through long or large fades, and a firmware fade only goes in a
+//! straight line through its color space. A [Transition] instead works out the colors between
+//! two HSBK colors itself and sends them one frame at a time, as `fast` set_state calls at a
+//! fixed frame rate. Each frame still fades over one frame interval, so the firmware smooths out
+//! the gaps between frames.
+//!
+//! The cloud API rate limits fast requests too, to about 2 a second; higher frame rates are
+//! meant for lifx-api-server or short bursts.
+//!
+//! Only compiled with `async`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! extern crate lifx_rs as lifx;
+//!
+//! use lifx::transition::Transition;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let config = lifx::LifxConfig{
+//!        access_token: "xxx".to_string(),
+//!        api_endpoints: vec![format!("http://localhost:8089")],
+//!        ..Default::default()
+//!     };
+//!
+//!     let red = lifx::Color::from_hsbk(0.0, 1.0, 1.0, 3500);
+//!     let blue = lifx::Color::from_hsbk(240.0, 1.0, 0.5, 3500);
+//!     // 5 seconds at 10 frames a second
+//!     let transition = Transition { frame_rate: 10.0, ..Transition::new(red, blue, 50) };
+//!     println!("{:?}", transition.run(config, "label:Strip").result().await);
+//! }
+//! ```
+
+use std::time::Duration;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{Color, LifxConfig, LifxError, Light, ScheduledHandle, State};
+
+/// The frame rate Transition::new uses, in frames a second: as fast as the cloud API's rate
+/// limit allows
+pub const DEFAULT_FRAME_RATE: f64 = 2.0;
+
+/// A transition from one color to another over a number of steps, see the module docs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    /// The color the transition starts at
+    pub from: Color,
+    /// The color the transition ends at
+    pub to: Color,
+    /// How many frames it takes to get from `from` to `to`, at least 1
+    pub steps: u32,
+    /// How many frames are sent a second, a positive number
+    pub frame_rate: f64,
+}
+
+impl Transition {
+    /// Returns a transition from `from` to `to` over `steps` frames, at DEFAULT_FRAME_RATE
+    pub fn new(from: Color, to: Color, steps: u32) -> Self {
+        Transition { from, to, steps, frame_rate: DEFAULT_FRAME_RATE }
+    }
+
+    /// How long each frame lasts.
+    ///
+    /// Returns LifxError::Config if frame_rate isn't a positive number, or is so high a frame
+    /// would take no time at all.
+    pub fn frame_interval(&self) -> Result<Duration, LifxError> {
+        let invalid = || LifxError::Config(format!("frame_rate must be a positive number of frames a second, got {}", self.frame_rate));
+        if !(self.frame_rate.is_finite() && self.frame_rate > 0.0) {
+            return Err(invalid());
+        }
+        match Duration::try_from_secs_f64(1.0 / self.frame_rate) {
+            Ok(interval) if !interval.is_zero() => Ok(interval),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// How long the whole transition takes, or LifxError::Config for a bad frame_rate, see
+    /// frame_interval
+    pub fn duration(&self) -> Result<Duration, LifxError> {
+        Ok(self.frame_interval()?.saturating_mul(self.steps.max(1)))
+    }
+
+    /// The color `progress` of the way from `from` to `to`, from 0.0 to 1.0.
+    ///
+    /// Hue goes the short way round the color wheel, ex: from 350 to 10 through 0. A component
+    /// only one of the colors sets is held at that value, and one neither sets stays unset.
+    pub fn color_at(&self, progress: f64) -> Color {
+        let lerp = |from: Option<f64>, to: Option<f64>| match (from, to) {
+            (Some(from), Some(to)) => Some(from + (to - from) * progress),
+            (from, to) => from.or(to),
+        };
+        let hue = match (self.from.hue, self.to.hue) {
+            (Some(from), Some(to)) => {
+                let delta = (to - from + 540.0).rem_euclid(360.0) - 180.0;
+                Some((from + delta * progress).rem_euclid(360.0))
+            }
+            (from, to) => from.or(to),
+        };
+        let kelvin = lerp(self.from.kelvin.map(|kelvin| kelvin as f64), self.to.kelvin.map(|kelvin| kelvin as f64));
+        Color {
+            hue,
+            saturation: lerp(self.from.saturation, self.to.saturation),
+            brightness: lerp(self.from.brightness, self.to.brightness),
+            kelvin: kelvin.map(|kelvin| kelvin.round() as i64),
+            ..Default::default()
+        }
+    }
+
+    /// The states sent, one per frame: `from`, then each step on to `to`. Every state is `fast`
+    /// and fades over one frame interval. Returns LifxError::Config for a bad frame_rate, see
+    /// frame_interval.
+    pub fn frames(&self) -> Result<Vec<State>, LifxError> {
+        let steps = self.steps.max(1);
+        let interval = self.frame_interval()?.as_secs_f64();
+        Ok((0..=steps)
+            .map(|step| {
+                let color = self.color_at(f64::from(step) / f64::from(steps));
+                let duration = if step == 0 { 0.0 } else { interval };
+                State::builder().color(color).duration(duration).fast(true).build()
+            })
+            .collect())
+    }
+
+    /// Starts sending the frames to the selected lights on a spawned task, one each frame
+    /// interval. Must be called from within a tokio runtime.
+    ///
+    /// A frame whose request takes longer than a frame interval delays the ones after it rather
+    /// than being skipped. The first failed frame ends the transition with its error; otherwise
+    /// the handle's result is the results of the last frame. A bad frame_rate ends it with
+    /// LifxError::Config before anything is sent.
+    pub fn run(&self, config: LifxConfig, selector: impl Into<String>) -> ScheduledHandle {
+        let selector = selector.into();
+        let frames = self.frames();
+        let interval = self.frame_interval();
+        ScheduledHandle::spawn_after(Duration::ZERO, async move {
+            let (frames, interval) = (frames?, interval?);
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut results = Default::default();
+            for frame in frames {
+                ticks.tick().await;
+                results = Light::async_set_state_by_selector(config.clone(), selector.clone(), frame).await?;
+            }
+            Ok(results)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixtures, MockTransport};
+    use crate::transport::Operation;
+    use std::time::Instant;
+
+    #[test]
+    fn test_color_at() {
+        let transition = Transition::new(Color::from_hsbk(350.0, 1.0, 0.2, 2500), Color::from_hsbk(30.0, 0.0, 1.0, 4500), 4);
+        assert_eq!(transition.color_at(0.0), Color::from_hsbk(350.0, 1.0, 0.2, 2500));
+        assert_eq!(transition.color_at(0.25), Color::from_hsbk(0.0, 0.75, 0.4, 3000));
+        assert_eq!(transition.color_at(1.0), Color::from_hsbk(30.0, 0.0, 1.0, 4500));
+
+        let to_white = Transition::new(Color::from_hsbk(120.0, 1.0, 1.0, 3500), Color::kelvin(6500), 2);
+        let halfway = to_white.color_at(0.5);
+        assert_eq!((halfway.hue, halfway.saturation, halfway.brightness, halfway.kelvin), (Some(120.0), Some(0.5), Some(1.0), Some(5000)));
+    }
+
+    #[test]
+    fn test_frames() {
+        let transition = Transition { frame_rate: 4.0, ..Transition::new(Color::from_hsbk(0.0, 1.0, 1.0, 3500), Color::from_hsbk(90.0, 1.0, 1.0, 3500), 3) };
+        assert_eq!(transition.duration().unwrap(), Duration::from_millis(750));
+        let frames = transition.frames().unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], State::builder().color("hue:0 saturation:1 brightness:1 kelvin:3500").duration(0.0).fast(true).build());
+        assert_eq!(frames[2].color.as_deref(), Some("hue:60 saturation:1 brightness:1 kelvin:3500"));
+        assert!(frames[1..].iter().all(|frame| frame.duration == Some(0.25) && frame.fast == Some(true)));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let transition = Transition { frame_rate: 50.0, ..Transition::new(Color::kelvin(2500), Color::kelvin(4500), 5) };
+        let started = Instant::now();
+        let result = transition.run(mock.config(), "label:Lamp").result().await;
+        assert!(result.unwrap().is_ok());
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        let sent: Vec<_> = mock.operations().into_iter().map(|(_, operation)| operation).collect();
+        assert_eq!(sent, transition.frames().unwrap().into_iter().map(Operation::SetState).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_frame_rate() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        for frame_rate in [0.0, -2.0, f64::NAN, f64::INFINITY, 1e12] {
+            let transition = Transition { frame_rate, ..Transition::new(Color::kelvin(2500), Color::kelvin(4500), 5) };
+            assert!(matches!(transition.frame_interval(), Err(LifxError::Config(_))), "{}", frame_rate);
+            assert!(transition.frames().is_err());
+            let result = transition.run(mock.config(), "label:Lamp").result().await;
+            assert!(matches!(result, Some(Err(LifxError::Config(message))) if message.contains("frame_rate")));
+        }
+        assert!(mock.operations().is_empty());
+    }
+}
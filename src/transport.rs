@@ -0,0 +1,298 @@
+//! Pluggable transports for the high-level API.
+//!
+//! Every `Light`, `Scene` and `Color` call that takes a LifxConfig comes down to a handful of
+//! requests: list the lights matching a selector, or carry out an [Operation] on them. By default
+//! those go to the LIFX HTTP API. A config made with [crate::LifxConfig::with_transport] sends them
+//! through any other [Transport] instead, ex: a LAN implementation, or a fake that records calls in
+//! tests.
+//!
+//! Transports are blocking. The async methods run them on tokio's blocking thread pool.
+//!
+//! Only compiled with the `blocking` or `async` feature.
+
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::Body;
+#[cfg(feature = "blocking")]
+use crate::{LifxClient, LifxConfig};
+use crate::{
+    BreatheEffect, Clean, Color, EffectsOff, FlameEffect, LiFxResults, LifxError, Lights,
+    MorphEffect, MoveEffect, PulseEffect, SceneActivation, Scenes, State, StateDelta, States, Toggle,
+};
+
+/// A change to the lights matching a selector
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    SetState(State),
+    StateDelta(StateDelta),
+    Toggle(Toggle),
+    Breathe(BreatheEffect),
+    Move(MoveEffect),
+    Morph(MorphEffect),
+    Pulse(PulseEffect),
+    Flame(FlameEffect),
+    EffectsOff(EffectsOff),
+    Clean(Clean),
+    /// Activates the scene selected by a `scene_id:` selector
+    ActivateScene(SceneActivation),
+}
+
+impl Operation {
+    /// How long after this effect starts it should be stopped, see BreatheEffect::with_auto_off
+    pub(crate) fn auto_off(&self) -> Option<Duration> {
+        match self {
+            Operation::Breathe(breathe) => breathe.auto_off,
+            Operation::Move(move_effect) => move_effect.auto_off,
+            Operation::Morph(morph_effect) => morph_effect.auto_off,
+            Operation::Pulse(pulse_effect) => pulse_effect.auto_off,
+            Operation::Flame(flame_effect) => flame_effect.auto_off,
+            _ => None,
+        }
+    }
+
+    /// The HTTP API request that carries out this operation on `selector`
+    pub(crate) fn to_request(&self, selector: &str) -> (Method, String, Body) {
+        let lights = |action: &str| format!("/v1/lights/{}/{}", selector, action);
+        match self {
+            Operation::SetState(state) => (Method::PUT, lights("state"), json_body(state, &state.extra_params)),
+            Operation::StateDelta(delta) => (Method::POST, lights("state/delta"), json_body(delta, &[])),
+            Operation::Toggle(toggle) => (Method::POST, lights("toggle"), json_body(toggle, &[])),
+            Operation::Breathe(breathe) => (Method::POST, lights("effects/breathe"), json_body(breathe, &breathe.extra_params)),
+            Operation::Move(move_effect) => (Method::POST, lights("effects/move"), json_body(move_effect, &move_effect.extra_params)),
+            Operation::Morph(morph_effect) => (Method::POST, lights("effects/morph"), json_body(morph_effect, &morph_effect.extra_params)),
+            Operation::Pulse(pulse_effect) => (Method::POST, lights("effects/pulse"), json_body(pulse_effect, &pulse_effect.extra_params)),
+            Operation::Flame(flame_effect) => (Method::POST, lights("effects/flame"), json_body(flame_effect, &flame_effect.extra_params)),
+            Operation::EffectsOff(effects_off) => (Method::POST, lights("effects/off"), json_body(effects_off, &effects_off.extra_params)),
+            Operation::Clean(clean) => (Method::POST, lights("clean"), json_body(clean, &[])),
+            Operation::ActivateScene(activation) => (Method::PUT, format!("/v1/scenes/{}/activate", selector), json_body(activation, &[])),
+        }
+    }
+}
+
+/// The JSON body the API expects for `payload`. Unset fields are left out, and params added with
+/// raw_param are sent as strings next to the modeled ones.
+fn json_body<T: Serialize>(payload: &T, extra_params: &[(String, String)]) -> Body {
+    let mut body = serde_json::to_value(payload).expect("payloads always serialize");
+    if let Value::Object(fields) = &mut body {
+        fields.retain(|_, value| !value.is_null());
+        for (key, value) in extra_params {
+            fields.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+    Body::Json(body)
+}
+
+/// Carries requests from the high-level API to the lights.
+///
+/// Configured defaults, like LifxConfig::default_duration, are already filled in on the operations
+/// a transport receives.
+pub trait Transport: Send + Sync {
+    /// Lists the lights matching `selector`, ex: "all" or "id:d073d5000000"
+    fn list(&self, selector: &str) -> Result<Lights, LifxError>;
+
+    /// Carries out `operation` on the lights matching `selector`
+    fn send(&self, selector: &str, operation: Operation) -> Result<LiFxResults, LifxError>;
+
+    /// Sets several states at once. By default, each state is sent as its own
+    /// [Operation::SetState] on its selector, on top of the defaults, and the results are combined.
+    fn set_states(&self, states: States) -> Result<LiFxResults, LifxError> {
+        let defaults = states.defaults.unwrap_or_default();
+        let mut results = Vec::new();
+        for state in states.states.unwrap_or_default() {
+            let selector = state.selector.clone().unwrap_or_else(|| "all".to_string());
+            let state = State {
+                power: state.power.or_else(|| defaults.power.clone()),
+                color: state.color.or_else(|| defaults.color.clone()),
+                brightness: state.brightness.or(defaults.brightness),
+                duration: state.duration.or(defaults.duration),
+                infrared: state.infrared.or(defaults.infrared),
+                selector: None,
+                fast: state.fast.or(defaults.fast),
+                extra_params: state.extra_params,
+            };
+            results.extend(self.send(&selector, Operation::SetState(state))?.results.unwrap_or_default());
+        }
+        Ok(LiFxResults { results: Some(results), ..Default::default() })
+    }
+
+    /// Lists the scenes belonging to the account. Unsupported unless the transport overrides it.
+    fn list_scenes(&self) -> Result<Scenes, LifxError> {
+        Err(LifxError::Unsupported("listing scenes".to_string()))
+    }
+
+    /// Validates a color string. Unsupported unless the transport overrides it.
+    fn validate_color(&self, color: &str) -> Result<Color, LifxError> {
+        Err(LifxError::Unsupported(format!("validating color {:?}", color)))
+    }
+}
+
+/// A transport shared between the clones of a LifxConfig
+///
+/// Configs compare equal when they share the same transport.
+#[derive(Clone)]
+pub struct SharedTransport(pub Arc<dyn Transport>);
+
+impl fmt::Debug for SharedTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SharedTransport(..)")
+    }
+}
+
+impl PartialEq for SharedTransport {
+    fn eq(&self, other: &SharedTransport) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The LIFX HTTP API as a Transport, using the endpoints and access token of a LifxConfig.
+///
+/// This is what a config without a transport uses already; it's useful to wrap in transports that
+/// add behavior on top of the cloud, ex: logging, or trying the LAN first.
+///
+/// Only compiled with the `blocking` feature.
+#[cfg(feature = "blocking")]
+#[derive(Debug, Clone)]
+pub struct CloudTransport {
+    client: LifxClient,
+}
+
+#[cfg(feature = "blocking")]
+impl CloudTransport {
+    /// Returns a CloudTransport for `config`. Any transport set on the config is ignored.
+    pub fn new(config: LifxConfig) -> Self {
+        CloudTransport { client: LifxClient::new(LifxConfig { transport: None, ..config }) }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Transport for CloudTransport {
+    fn list(&self, selector: &str) -> Result<Lights, LifxError> {
+        self.client.list_by_selector(selector.to_string())
+    }
+
+    fn send(&self, selector: &str, operation: Operation) -> Result<LiFxResults, LifxError> {
+        let (method, path, body) = operation.to_request(selector);
+        self.client.send_results(method, &path, &body)
+    }
+
+    fn set_states(&self, states: States) -> Result<LiFxResults, LifxError> {
+        self.client.set_states(states)
+    }
+
+    fn list_scenes(&self) -> Result<Scenes, LifxError> {
+        self.client.list_scenes()
+    }
+
+    fn validate_color(&self, color: &str) -> Result<Color, LifxError> {
+        self.client.validate_color(color.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records every operation and answers each with one "ok" result
+    #[derive(Default)]
+    struct Recorder {
+        sent: Mutex<Vec<(String, Operation)>>,
+    }
+
+    impl Transport for Recorder {
+        fn list(&self, _selector: &str) -> Result<Lights, LifxError> {
+            Ok(Vec::new())
+        }
+
+        fn send(&self, selector: &str, operation: Operation) -> Result<LiFxResults, LifxError> {
+            self.sent.lock().unwrap().push((selector.to_string(), operation));
+            let result = crate::LiFxResult { id: selector.to_string(), label: String::new(), status: "ok".to_string(), ..Default::default() };
+            Ok(LiFxResults { results: Some(vec![result]), ..Default::default() })
+        }
+    }
+
+    #[test]
+    fn test_to_request() {
+        let (method, path, body) = Operation::Toggle(Toggle { duration: Some(2.0) }).to_request("group:Lounge");
+        assert_eq!(method, Method::POST);
+        assert_eq!(path, "/v1/lights/group:Lounge/toggle");
+        assert!(matches!(body, Body::Json(json) if json == serde_json::json!({"duration": 2.0})));
+
+        let (method, path, _) = Operation::SetState(State::new()).to_request("all");
+        assert_eq!((method, path.as_str()), (Method::PUT, "/v1/lights/all/state"));
+    }
+
+    #[test]
+    fn test_json_bodies() {
+        let body = |operation: Operation| match operation.to_request("all").2 {
+            Body::Json(json) => json,
+            _ => panic!("expected a JSON body"),
+        };
+        let morph = MorphEffect::builder().palette(vec!["red".to_string(), "hue:100 saturation:1".to_string()]).power_on(true).build();
+        assert_eq!(body(Operation::Morph(morph)), serde_json::json!({"palette": ["red", "hue:100 saturation:1"], "power_on": true}));
+
+        let breathe = BreatheEffect::builder().from_color("blue").cycles(2.0).build().raw_param("new_param", "1");
+        assert_eq!(body(Operation::Breathe(breathe)), serde_json::json!({"from_color": "blue", "cycles": 2.0, "new_param": "1"}));
+        assert_eq!(body(Operation::Clean(Clean { stop: Some(false), duration: Some(7200) })), serde_json::json!({"stop": false, "duration": 7200}));
+
+        let activation = SceneActivation::builder()
+            .duration(2.0)
+            .ignore(vec![crate::SceneIgnore::Power, crate::SceneIgnore::Kelvin])
+            .overrides(State::builder().brightness(0.3).build())
+            .build();
+        let (method, path, _) = Operation::ActivateScene(activation.clone()).to_request("scene_id:abc");
+        assert_eq!((method, path.as_str()), (Method::PUT, "/v1/scenes/scene_id:abc/activate"));
+        assert_eq!(body(Operation::ActivateScene(activation)), serde_json::json!({"duration": 2.0, "ignore": ["power", "kelvin"], "overrides": {"brightness": 0.3}}));
+    }
+
+    #[test]
+    fn test_default_set_states() {
+        let recorder = Recorder::default();
+        let states = States {
+            states: Some(vec![
+                State { selector: Some("id:a".to_string()), power: Some("on".to_string()), ..Default::default() },
+                State { selector: Some("id:b".to_string()), brightness: Some(0.2), ..Default::default() },
+            ]),
+            defaults: Some(State { brightness: Some(0.5), duration: Some(1.0), ..Default::default() }),
+        };
+        let results = recorder.set_states(states).unwrap();
+        assert_eq!(results.results.unwrap().len(), 2);
+
+        let sent = recorder.sent.lock().unwrap();
+        assert_eq!(sent[0], ("id:a".to_string(), Operation::SetState(State { power: Some("on".to_string()), brightness: Some(0.5), duration: Some(1.0), ..Default::default() })));
+        assert_eq!(sent[1], ("id:b".to_string(), Operation::SetState(State { brightness: Some(0.2), duration: Some(1.0), ..Default::default() })));
+        assert!(matches!(recorder.list_scenes(), Err(LifxError::Unsupported(_))));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_light_over_transport() {
+        let recorder = Arc::new(Recorder::default());
+        let config = crate::LifxConfig { default_duration: Some(3.0), ..Default::default() }.with_transport(recorder.clone());
+
+        let results = crate::Light::set_state_by_selector(config.clone(), "id:a".to_string(), State::new()).unwrap();
+        assert_eq!(results.results.unwrap()[0].id, "id:a");
+        crate::Light::toggle_by_selector(config, "all".to_string(), Toggle::new()).unwrap();
+
+        let sent = recorder.sent.lock().unwrap();
+        assert_eq!(sent[0], ("id:a".to_string(), Operation::SetState(State { duration: Some(3.0), ..Default::default() })));
+        assert_eq!(sent[1], ("all".to_string(), Operation::Toggle(Toggle { duration: Some(3.0) })));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_async_light_over_transport() {
+        let recorder = Arc::new(Recorder::default());
+        let config = crate::LifxConfig::default().with_transport(recorder.clone());
+
+        crate::Light::async_breathe_effect_by_selector(config.clone(), "id:a".to_string(), BreatheEffect::new()).await.unwrap();
+        assert!(crate::Light::async_list_all(config.clone()).await.unwrap().is_empty());
+        assert!(matches!(crate::Scene::async_list(config).await, Err(LifxError::Unsupported(_))));
+        assert_eq!(recorder.sent.lock().unwrap()[0], ("id:a".to_string(), Operation::Breathe(BreatheEffect::new())));
+    }
+}
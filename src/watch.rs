@@ -0,0 +1,227 @@
+//! Change events for the lights matching a selector, by polling the light list.
+//!
+//! [watch] lists the selected lights every interval on a spawned tokio task, compares each poll
+//! with the one before it, and sends an [Event] for every change it finds over a channel, read
+//! with [Watch::recv]. The first poll reports every light as a [Event::NewLight], so a consumer
+//! starts from the full picture.
+//!
+//! Changes that come and go between two polls are missed, and a change shows up as late as one
+//! interval after it happens. The cloud API rate limits lists too, so keep the interval at a few
+//! seconds or more.
+//!
+//! Only compiled with `async`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! extern crate lifx_rs as lifx;
+//!
+//! use lifx::watch::Event;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let config = lifx::LifxConfig{
+//!        access_token: "xxx".to_string(),
+//!        api_endpoints: vec![format!("https://api.lifx.com")],
+//!        ..Default::default()
+//!     };
+//!
+//!     let mut watch = lifx::watch::watch(config, "group:Hallway", Duration::from_secs(10));
+//!     while let Some(event) = watch.recv().await {
+//!         match event {
+//!             Event::PowerChanged { light, .. } if light.power == "on" => println!("{} turned on", light.label),
+//!             Event::Disconnected(light) => println!("{} went offline", light.label),
+//!             Event::PollFailed(e) => println!("{}", e),
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::{diff_lights, Color, LifxConfig, LifxError, Light};
+
+/// How many events a Watch holds before the polling task waits for them to be read
+pub const EVENT_BUFFER: usize = 64;
+
+/// A change seen between two polls. Each carries the light as the latest poll reported it.
+#[derive(Debug)]
+pub enum Event {
+    /// A light that wasn't in the previous poll, including every light in the first one
+    NewLight(Light),
+    /// A light that was in the previous poll isn't any more, ex: it was moved out of the group
+    Removed(Light),
+    /// The light stopped reporting to the cloud
+    Disconnected(Light),
+    /// A disconnected light is reporting again
+    Reconnected(Light),
+    /// The light was turned on or off; `from` is the previous power, "on" or "off"
+    PowerChanged { light: Light, from: String },
+    /// The light's brightness changed; `from` is the previous brightness
+    BrightnessChanged { light: Light, from: f64 },
+    /// The light's hue, saturation or kelvin changed; `from` is the previous color
+    ColorChanged { light: Light, from: Color },
+    /// A poll failed. The next poll is compared with the last one that succeeded.
+    PollFailed(LifxError),
+}
+
+/// The events for the changes from `old` to `new`, two polls of the same selector, matching
+/// lights by id.
+///
+/// Added and removed lights come first, then each changed light's connection, power, brightness
+/// and color changes, in that order. A light that is disconnected in `new` reports only the
+/// disconnection, since the rest of what it reports is stale.
+pub fn events(old: &[Light], new: &[Light]) -> Vec<Event> {
+    let diff = diff_lights(old, new);
+    let mut events: Vec<Event> = diff.added.into_iter().map(Event::NewLight).collect();
+    events.extend(diff.removed.into_iter().map(Event::Removed));
+    for (previous, light) in diff.changed {
+        if !light.connected {
+            if previous.connected {
+                events.push(Event::Disconnected(light));
+            }
+            continue;
+        }
+        if !previous.connected {
+            events.push(Event::Reconnected(light.clone()));
+        }
+        if previous.power != light.power {
+            events.push(Event::PowerChanged { light: light.clone(), from: previous.power });
+        }
+        if previous.brightness != light.brightness {
+            events.push(Event::BrightnessChanged { light: light.clone(), from: previous.brightness });
+        }
+        if previous.color != light.color {
+            events.push(Event::ColorChanged { light, from: previous.color });
+        }
+    }
+    events
+}
+
+/// Starts polling the lights matching `selector` every `interval`, see the module docs. Must be
+/// called from within a tokio runtime.
+pub fn watch(config: LifxConfig, selector: impl Into<String>, interval: Duration) -> Watch {
+    let selector = selector.into();
+    let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let task = tokio::spawn(async move {
+        let mut previous: Vec<Light> = Vec::new();
+        loop {
+            ticks.tick().await;
+            let events = match Light::async_list_by_selector(config.clone(), selector.clone()).await {
+                Ok(lights) => {
+                    let events = events(&previous, &lights);
+                    previous = lights;
+                    events
+                }
+                Err(e) => vec![Event::PollFailed(e)],
+            };
+            for event in events {
+                // Nobody is listening any more
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Watch { events: receiver, task }
+}
+
+/// The events of a running watch, started by watch. Dropping it stops the polling.
+#[derive(Debug)]
+pub struct Watch {
+    events: mpsc::Receiver<Event>,
+    task: JoinHandle<()>,
+}
+
+impl Watch {
+    /// Waits for the next event, or returns None once the watch has been stopped and every event
+    /// already found has been read
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.events.recv().await
+    }
+
+    /// Returns the next event if one is waiting, without waiting for one
+    pub fn try_recv(&mut self) -> Option<Event> {
+        self.events.try_recv().ok()
+    }
+
+    /// Stops polling. A poll already in flight is dropped.
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+
+    /// Returns true until the watch is stopped
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixtures, MockTransport};
+
+    #[test]
+    fn test_events() {
+        let old = fixtures::lights();
+        let mut new = old.clone();
+        new.remove(1);
+        new[0].power = "off".to_string();
+        new[0].brightness = 0.25;
+        new[1].connected = false;
+        new[1].power = "on".to_string();
+        new.push(fixtures::light("d073d5000004", "Porch"));
+
+        let events = events(&old, &new);
+        assert_eq!(events.len(), 5, "{:?}", events);
+        assert!(matches!(&events[0], Event::NewLight(light) if light.label == "Porch"));
+        assert!(matches!(&events[1], Event::Removed(light) if light.label == "Ceiling"));
+        assert!(matches!(&events[2], Event::PowerChanged { light, from } if light.label == "Lamp" && from == "on"));
+        assert!(matches!(&events[3], Event::BrightnessChanged { light, .. } if light.brightness == 0.25));
+        assert!(matches!(&events[4], Event::Disconnected(light) if light.label == "Bedside"));
+
+        let mut recolored = new.clone();
+        recolored[1].connected = true;
+        recolored[2].color = Color::kelvin(2700);
+        let events = super::events(&new, &recolored);
+        assert_eq!(events.len(), 2, "{:?}", events);
+        assert!(matches!(&events[0], Event::Reconnected(light) if light.label == "Bedside"));
+        assert!(matches!(&events[1], Event::ColorChanged { light, .. } if light.label == "Porch"));
+        assert!(super::events(&new, &new).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        let mock = MockTransport::with_lights(fixtures::lights());
+        let mut watch = watch(mock.config(), "all", Duration::from_millis(20));
+        for _ in 0..3 {
+            assert!(matches!(watch.recv().await, Some(Event::NewLight(_))));
+        }
+
+        let mut lights = fixtures::lights();
+        lights[2].power = "on".to_string();
+        mock.set_lights(lights);
+        match watch.recv().await {
+            Some(Event::PowerChanged { light, from }) => assert_eq!((light.label.as_str(), from.as_str()), ("Bedside", "off")),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        watch.stop();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!watch.is_running());
+        assert!(watch.recv().await.is_none());
+    }
+}